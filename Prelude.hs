@@ -1,7 +1,6 @@
 module Prelude where
 
 data Bool = True | False
-    deriving(Eq, Ord)
 
 not b = case b of
     True -> False
@@ -61,6 +60,9 @@ instance Eq Double where
     (==) x y = primDoubleEQ x y
     (/=) x y = not (x == y)
 
+instance Eq Char where
+    (==) x y = primCharEQ x y
+
 instance Eq a => Eq [a] where
     (==) (x:xs) (y:ys) = (x == y) && (xs == ys)
     (==) [] [] = True
@@ -71,6 +73,9 @@ instance Eq a => Eq [a] where
 infixl 6 +, -
 infixl 7 *
 
+-- There is no arbitrary precision integer type so Integer is an alias for Int
+type Integer = Int
+
 class Num a where
     (+) :: a -> a -> a
     (-) :: a -> a -> a
@@ -89,6 +94,12 @@ instance Num Double where
     (*) x y = primDoubleMultiply x y
     fromInteger x = primIntToDouble x
 
+negate :: Num a => a -> a
+negate x = 0 - x
+
+subtract :: Num a => a -> a -> a
+subtract x y = y - x
+
 infixl 7 /
 
 class Fractional a where
@@ -282,40 +293,60 @@ instance Monad [] where
 class Enum a where
     succ :: a -> a
     pred :: a -> a
+    toEnum :: Int -> a
+    fromEnum :: a -> Int
     enumFrom :: a -> [a]
     enumFromThen :: a -> a -> [a]
     enumFromTo :: a -> a -> [a]
     enumFromThenTo :: a -> a -> a -> [a]
+    succ x = toEnum (fromEnum x + 1)
+    pred x = toEnum (fromEnum x - 1)
+    enumFrom x = map toEnum (enumFrom (fromEnum x))
+    enumFromThen x y = map toEnum (enumFromThen (fromEnum x) (fromEnum y))
+    enumFromTo x y = map toEnum (enumFromTo (fromEnum x) (fromEnum y))
+    enumFromThenTo x y z = map toEnum (enumFromThenTo (fromEnum x) (fromEnum y) (fromEnum z))
 
 instance Enum Int where
     succ x = x + 1
     pred x = x - 1
+    toEnum x = x
+    fromEnum x = x
     enumFrom x =
         let
             xs = x : enumFrom (x + 1)
         in xs
-    enumFromThen n step = n : enumFromThen (n + step) step
+    enumFromThen n m = n : enumFromThen m (m + m - n)
     enumFromTo start stop = case start <= stop of
         True -> start : enumFromTo (start + 1) stop
         False -> []
-    enumFromThenTo start step stop = case start <= stop of
-        True -> start : enumFromThenTo (start + step) step stop
-        False -> []
+    enumFromThenTo n m stop = case n <= m of
+        True -> case n <= stop of
+            True -> n : enumFromThenTo m (m + m - n) stop
+            False -> []
+        False -> case n >= stop of
+            True -> n : enumFromThenTo m (m + m - n) stop
+            False -> []
 
 instance Enum Double where
     succ x = x + 1
     pred x = x - 1
+    toEnum x = primIntToDouble x
+    fromEnum x = primDoubleToInt x
     enumFrom x =
         let
             xs = x : enumFrom (x + 1)
         in xs
-    enumFromThen n step = n : enumFromThen (n + step) step
+    enumFromThen n m = n : enumFromThen m (m + m - n)
     enumFromTo start stop = case start <= stop of
         True -> start : enumFromTo (start + 1) stop
         False -> []
-    enumFromThenTo start step stop = case start <= stop of
-        True -> start : enumFromThenTo (start + step) step stop
-        False -> []
+    enumFromThenTo n m stop = case n <= m of
+        True -> case n <= stop of
+            True -> n : enumFromThenTo m (m + m - n) stop
+            False -> []
+        False -> case n >= stop of
+            True -> n : enumFromThenTo m (m + m - n) stop
+            False -> []
 
 otherwise :: Bool
 otherwise = True
@@ -440,9 +471,133 @@ concat xs = case xs of
     y:ys -> y ++ concat ys
     [] -> []
 
+concatMap :: (a -> [b]) -> [a] -> [b]
+concatMap f xs = case xs of
+    y:ys -> f y ++ concatMap f ys
+    [] -> []
+
+
+type String = [Char]
+
+elem :: Eq a => a -> [a] -> Bool
+elem x ys = case ys of
+    y:ys2 -> (x == y) || elem x ys2
+    [] -> False
+
+span :: (a -> Bool) -> [a] -> ([a], [a])
+span p xs = case xs of
+    y:ys -> case p y of
+        True -> case span p ys of
+            (zs, rest) -> (y : zs, rest)
+        False -> ([], xs)
+    [] -> ([], [])
+
+isSpace :: Char -> Bool
+isSpace c = elem c " \t\n\r"
+
+isDigit :: Char -> Bool
+isDigit c = primCharLE '0' c && primCharLE c '9'
+
+isAlpha :: Char -> Bool
+isAlpha c = (primCharLE 'a' c && primCharLE c 'z') || (primCharLE 'A' c && primCharLE c 'Z')
+
+isAlphaNum :: Char -> Bool
+isAlphaNum c = isAlpha c || isDigit c
+
+digitToInt :: Char -> Int
+digitToInt c = case c of
+    '0' -> 0
+    '1' -> 1
+    '2' -> 2
+    '3' -> 3
+    '4' -> 4
+    '5' -> 5
+    '6' -> 6
+    '7' -> 7
+    '8' -> 8
+    '9' -> 9
+    _ -> error "digitToInt: not a digit"
+
+intToDigit :: Int -> Char
+intToDigit n = case n of
+    0 -> '0'
+    1 -> '1'
+    2 -> '2'
+    3 -> '3'
+    4 -> '4'
+    5 -> '5'
+    6 -> '6'
+    7 -> '7'
+    8 -> '8'
+    9 -> '9'
+    _ -> error "intToDigit: not a digit"
+
+
+type ShowS = String -> String
 
 class Show a where
-    show :: a -> [Char]
+    showsPrec :: Int -> a -> ShowS
+    show :: a -> String
+    showList :: [a] -> ShowS
+    showsPrec _ x s = show x ++ s
+    show x = showsPrec 0 x ""
+    showList xs s = showListWith (showsPrec 0) xs s
+
+shows :: Show a => a -> ShowS
+shows x s = showsPrec 0 x s
+
+showChar :: Char -> ShowS
+showChar c s = c : s
+
+showString :: String -> ShowS
+showString str s = str ++ s
+
+showParen :: Bool -> ShowS -> ShowS
+showParen b p s = case b of
+    True -> '(' : p (')' : s)
+    False -> p s
+
+showListWith :: (a -> ShowS) -> [a] -> ShowS
+showListWith showx xs s = case xs of
+    y:ys -> '[' : showx y (showListRest showx ys s)
+    [] -> '[' : ']' : s
+
+showListRest :: (a -> ShowS) -> [a] -> ShowS
+showListRest showx xs s = case xs of
+    y:ys -> ',' : showx y (showListRest showx ys s)
+    [] -> ']' : s
+
+showPositive :: Int -> ShowS
+showPositive n s = case primIntLT n 10 of
+    True -> intToDigit n : s
+    False -> showPositive (div n 10) (intToDigit (rem n 10) : s)
+
+showLitChar :: Char -> ShowS
+showLitChar c s = case c of
+    '\\' -> '\\' : '\\' : s
+    '\n' -> '\\' : 'n' : s
+    '\t' -> '\\' : 't' : s
+    _ -> c : s
+
+showLitString :: String -> ShowS
+showLitString cs s = case cs of
+    '"' : rest -> '\\' : '"' : showLitString rest s
+    c : rest -> showLitChar c (showLitString rest s)
+    [] -> s
+
+instance Show Int where
+    showsPrec d n s = case primIntLT n 0 of
+        True -> showParen (primIntGT d 6) (showChar '-' . showPositive (0 - n)) s
+        False -> showPositive n s
+
+instance Show Char where
+    showsPrec _ c s = case c of
+        '\'' -> '\'' : '\\' : '\'' : '\'' : s
+        _ -> '\'' : showLitChar c ('\'' : s)
+    showList cs s = '"' : showLitString cs ('"' : s)
+
+instance Show a => Show [a] where
+    showsPrec _ xs s = showList xs s
 
 instance Show Bool where
     show x = case x of
@@ -450,13 +605,145 @@ instance Show Bool where
         False -> "False"
 
 instance (Show a, Show b) => Show (a, b) where
-    show x = case x of
-        (y, z) -> "(" ++ show y ++ ", " ++ show z ++ ")"
+    showsPrec _ x s = case x of
+        (y, z) -> '(' : shows y (',' : shows z (')' : s))
 
 instance Show a => Show (Maybe a) where
-    show x = case x of
-        Just y -> "Just (" ++ show y ++ ")"
-        Nothing -> "Nothing"
+    showsPrec d x s = case x of
+        Just y -> showParen (primIntGT d 10) (showString "Just " . showsPrec 11 y) s
+        Nothing -> showString "Nothing" s
+
+
+type ReadS a = String -> [(a, String)]
+
+class Read a where
+    readsPrec :: Int -> ReadS a
+    readList :: ReadS [a]
+    readList s = readParen False (readListWith (readsPrec 0)) s
+
+reads :: Read a => ReadS a
+reads s = readsPrec 0 s
+
+read :: Read a => String -> a
+read s = case [x | (x, t) <- reads s, (rest, _) <- lex t, null rest] of
+    x : _ -> x
+    [] -> error "Prelude.read: no parse"
+
+readParen :: Bool -> ReadS a -> ReadS a
+readParen b g s = case b of
+    True -> readMandatoryParen g s
+    False -> readOptionalParen g s
+
+readOptionalParen :: ReadS a -> ReadS a
+readOptionalParen g s = g s ++ readMandatoryParen g s
+
+readMandatoryParen :: ReadS a -> ReadS a
+readMandatoryParen g s =
+    [(x, u) | (open, t) <- lex s, open == "(", (x, rest) <- readOptionalParen g t, (close, u) <- lex rest, close == ")"]
+
+readListWith :: ReadS a -> ReadS [a]
+readListWith g s = [(xs, u) | (open, t) <- lex s, open == "[", (xs, u) <- readListItems g t]
+
+readListItems :: ReadS a -> ReadS [a]
+readListItems g s =
+    [([], t) | (close, t) <- lex s, close == "]"] ++ [(x : xs, u) | (x, t) <- g s, (xs, u) <- readListRest g t]
+
+readListRest :: ReadS a -> ReadS [a]
+readListRest g s =
+    [([], t) | (close, t) <- lex s, close == "]"]
+        ++ [(x : xs, v) | (comma, t) <- lex s, comma == ",", (x, u) <- g t, (xs, v) <- readListRest g u]
+
+--Parsers used by derived Read instances
+readsValue :: a -> ReadS a
+readsValue x s = [(x, s)]
+
+readsToken :: ReadS a -> String -> ReadS a
+readsToken g token s = [(x, u) | (x, t) <- g s, (found, u) <- lex t, found == token]
+
+readsApply :: ReadS (a -> b) -> ReadS a -> ReadS b
+readsApply g h s = [(f x, u) | (f, t) <- g s, (x, u) <- h t]
+
+lex :: ReadS String
+lex s = case s of
+    c : cs -> lexToken c cs
+    [] -> [("", "")]
+
+lexToken :: Char -> String -> [(String, String)]
+lexToken c cs
+    | isSpace c = lex cs
+    | isDigit c = [span isDigit (c : cs)]
+    | isAlpha c || (c == '_') = [span isIdentifierChar (c : cs)]
+    | isSymbolChar c = [span isSymbolChar (c : cs)]
+    | (c == '"') || (c == '\'') = case lexQuoted c cs of
+        (body, rest) -> [(c : body, rest)]
+    | otherwise = [([c], cs)]
+
+isIdentifierChar :: Char -> Bool
+isIdentifierChar c = isAlphaNum c || (c == '_') || (c == '\'')
+
+isSymbolChar :: Char -> Bool
+isSymbolChar c = elem c "!#$%&*+./<=>?@\\^|-~:"
+
+lexQuoted :: Char -> String -> (String, String)
+lexQuoted quote s = case s of
+    '\\' : c : cs -> case lexQuoted quote cs of
+        (body, rest) -> ('\\' : c : body, rest)
+    c : cs -> case c == quote of
+        True -> ([c], cs)
+        False -> case lexQuoted quote cs of
+            (body, rest) -> (c : body, rest)
+    [] -> ([], [])
+
+readLitChar :: ReadS Char
+readLitChar s = case s of
+    '\\' : c : rest -> case c of
+        'n' -> [('\n', rest)]
+        't' -> [('\t', rest)]
+        _ -> [(c, rest)]
+    c : rest -> [(c, rest)]
+    [] -> []
+
+readNatural :: ReadS Int
+readNatural s = [(foldl (\n c -> n * 10 + digitToInt c) 0 token, t) | (token, t) <- lex s, isNatural token]
+
+isNatural :: String -> Bool
+isNatural token = case token of
+    c : _ -> isDigit c
+    [] -> False
+
+readStringBody :: ReadS String
+readStringBody s = case s of
+    '"' : rest -> [("", rest)]
+    _ -> [(c : cs, u) | (c, t) <- readLitChar s, (cs, u) <- readStringBody t]
+
+instance Read Int where
+    readsPrec d s = readParen False readNatural s
+        ++ readParen (primIntGT d 6) (\r -> [(0 - n, u) | (minus, t) <- lex r, minus == "-", (n, u) <- readNatural t]) s
+
+instance Read Char where
+    readsPrec _ s = readParen False (\r -> [(c, t) | ('\'' : body, t) <- lex r, (c, "'") <- readLitChar body]) s
+    readList s = readParen False (\r -> [(str, t) | ('"' : body, t) <- lex r, (str, "") <- readStringBody body]) s
+        ++ readParen False (readListWith (readsPrec 0)) s
+
+instance Read a => Read [a] where
+    readsPrec _ s = readList s
+
+instance (Read a, Read b) => Read (a, b) where
+    readsPrec _ s = readParen False (\r ->
+        [((x, y), w) | (open, t) <- lex r, open == "(", (x, u) <- reads t, (comma, v) <- lex u, comma == ",", (y, rest) <- reads v, (close, w) <- lex rest, close == ")"]) s
+
+
+class Bounded a where
+    minBound :: a
+    maxBound :: a
+
+instance Bounded Int where
+    minBound = primIntSubtract (0 - 9223372036854775807) 1
+    maxBound = 9223372036854775807
+
+instance (Bounded a, Bounded b) => Bounded (a, b) where
+    minBound = (minBound, minBound)
+    maxBound = (maxBound, maxBound)
 
 data RealWorld = RealWorld
 
@@ -11,6 +11,31 @@ use crate::{
     },
 };
 
+///The operations which exist as a primitive for both `Int` and `Double`, `primIntAdd` and so on
+pub const NUMERIC_PRIMITIVES: &[&str] = &[
+    "Add", "Subtract", "Multiply", "Divide", "Remainder", "EQ", "LT", "LE", "GT", "GE",
+];
+
+///Returns whether `name` is a primitive which is in scope in every module without being declared
+pub fn is_primitive(name: &str) -> bool {
+    let numeric = ["Int", "Double"].iter().any(|typename| {
+        name.strip_prefix("prim")
+            .and_then(|name| name.strip_prefix(typename))
+            .is_some_and(|op| NUMERIC_PRIMITIVES.contains(&op))
+    });
+    let tuple = name.len() > 2
+        && name.starts_with('(')
+        && name.ends_with(')')
+        && name[1..name.len() - 1].chars().all(|c| c == ',');
+    numeric
+        || tuple
+        || matches!(
+            name,
+            "primIntToDouble" | "primDoubleToInt" | "primCharEQ" | "primCharLE" | "[]" | ":" | "()"
+        )
+        || builtins().iter().any(|&(builtin, _)| builtin == name)
+}
+
 ///Returns an array of all the compiler primitves which exist (not including numeric primitives atm)
 pub fn builtins() -> Vec<(&'static str, Type<Name>)> {
     let var = Type::Generic(TypeVariable {
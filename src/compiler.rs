@@ -4,10 +4,17 @@ use {
             Expr::*,
             *,
         },
+        deriving::newtype_type,
+        diagnostics::Diagnostic,
         interner::*,
+        module::{
+            encode_binding_identifier,
+            instance_name,
+        },
         scoped_map::ScopedMap,
         typecheck::{
             find_specialized_instances,
+            same_instance_heads,
             DataTypes,
             TypeEnvironment,
             Types,
@@ -31,6 +38,8 @@ use crate::{
         rename_module,
         typ::*,
     },
+    search_path::SearchPath,
+    vm::VMError,
 };
 
 use self::Instruction::*;
@@ -57,6 +66,8 @@ pub enum Instruction {
     DoubleLE,
     DoubleGT,
     DoubleGE,
+    CharEQ,
+    CharLE,
     IntToDouble,
     DoubleToInt,
     Push(usize),
@@ -87,7 +98,7 @@ enum Var<'a> {
     Stack(usize),
     Global(usize),
     Constructor(u16, u16),
-    Class(&'a Type<Name>, &'a [Constraint<Name>], &'a TypeVariable),
+    Class(&'a Type<Name>, &'a [Constraint<Name>], &'a [TypeVariable]),
     Constraint(usize, &'a Type<Name>, &'a [Constraint<Name>]),
     Builtin(usize),
     Primitive(usize, Instruction),
@@ -120,6 +131,8 @@ static BINARY_PRIMITIVES: &'static [(&'static str, Instruction)] = &[
     ("primDoubleLE", DoubleLE),
     ("primDoubleGT", DoubleGT),
     ("primDoubleGE", DoubleGE),
+    ("primCharEQ", CharEQ),
+    ("primCharLE", CharLE),
 ];
 
 pub struct SuperCombinator {
@@ -133,8 +146,10 @@ pub struct Assembly {
     pub super_combinators: Vec<SuperCombinator>,
     pub instance_dictionaries: Vec<Vec<usize>>,
     pub classes: Vec<Class<Id>>,
-    pub instances: Vec<(Vec<Constraint<Name>>, Type<Name>)>,
+    pub instances: Vec<(Vec<Constraint<Name>>, Name, Vec<Type<Name>>)>,
     pub data_definitions: Vec<DataDefinition<Name>>,
+    pub newtypes: Vec<Newtype<Name>>,
+    pub type_synonyms: Vec<TypeSynonym<Name>>,
     pub offset: usize,
 }
 
@@ -150,7 +165,7 @@ impl Globals for Assembly {
             for decl in class.declarations.iter() {
                 if decl.name == name {
                     return Some(
-                        Var::Class(&decl.typ.value, &decl.typ.constraints, &class.variable)
+                        Var::Class(&decl.typ.value, &decl.typ.constraints, &class.variables)
                     );
                 }
             }
@@ -169,6 +184,14 @@ impl Globals for Assembly {
             });
         }
 
+        if self
+            .newtypes
+            .iter()
+            .any(|newtype| newtype.constructor_name == name)
+        {
+            return Some(Var::Newtype);
+        }
+
         self.find_constructor(name)
             .map(|(tag, arity)| Var::Constructor(tag, arity))
     }
@@ -188,7 +211,7 @@ fn find_global<'a>(module: &'a Module<Id>, offset: usize, name: Name) -> Option<
     for class in module.classes.iter() {
         for decl in class.declarations.iter() {
             if decl.name == name {
-                return Some(Var::Class(&decl.typ.value, &*decl.typ.constraints, &class.variable));
+                return Some(Var::Class(&decl.typ.value, &*decl.typ.constraints, &class.variables));
             }
         }
     }
@@ -210,7 +233,7 @@ fn find_global<'a>(module: &'a Module<Id>, offset: usize, name: Name) -> Option<
         })
         .map(|bind| {
             global_index -= 1;
-            let typ = bind.expression.get_type();
+            let typ = &bind.name.typ.value;
             let constraints = &bind.name.typ.constraints;
             if !constraints.is_empty() {
                 Var::Constraint(offset + global_index, typ, constraints)
@@ -270,7 +293,7 @@ impl Types for Module<Id> {
         name: Name,
     ) -> Option<(
         &'a [Constraint<Name>],
-        &'a TypeVariable,
+        &'a [TypeVariable],
         &'a [TypeDeclaration<Name>],
     )> {
         self.classes
@@ -279,7 +302,7 @@ impl Types for Module<Id> {
             .map(|class| {
                 (
                     class.constraints.as_ref(),
-                    &class.variable,
+                    class.variables.as_ref(),
                     class.declarations.as_ref(),
                 )
             })
@@ -288,20 +311,29 @@ impl Types for Module<Id> {
     fn find_instance<'a>(
         &'a self,
         classname: Name,
-        typ: &Type<Name>,
-    ) -> Option<(&'a [Constraint<Name>], &'a Type<Name>)> {
-        for instance in self.instances.iter() {
-            let Type::Constructor(ref y) = extract_applied_type(&instance.typ) else {
-                panic!();
-            };
-            let Type::Constructor(ref z) = extract_applied_type(typ) else {
-                panic!();
-            };
-            if classname == instance.classname && y.name == z.name {
-                return Some((instance.constraints.as_ref(), &instance.typ));
-            }
+        types: &[Type<Name>],
+    ) -> Option<(&'a [Constraint<Name>], &'a [Type<Name>])> {
+        self.instances
+            .iter()
+            .find(|instance| {
+                classname == instance.classname && same_instance_heads(&instance.types, types)
+            })
+            .map(|instance| (instance.constraints.as_ref(), instance.types.as_ref()))
+    }
+
+    fn each_global(&self, f: &mut dyn FnMut(&Name, &Qualified<Type<Name>, Name>)) {
+        for bind in self.bindings.iter() {
+            f(&bind.name.name, &bind.name.typ);
+        }
+        for decl in self.classes.iter().flat_map(|class| class.declarations.iter()) {
+            f(&decl.name, &decl.typ);
+        }
+        for ctor in self.data_definitions.iter().flat_map(|data| data.constructors.iter()) {
+            f(&ctor.name, &ctor.typ);
+        }
+        for newtype in self.newtypes.iter() {
+            f(&newtype.constructor_name, &newtype.constructor_type);
         }
-        None
     }
 }
 
@@ -337,7 +369,7 @@ impl Types for Assembly {
         name: Name,
     ) -> Option<(
         &'a [Constraint<Name>],
-        &'a TypeVariable,
+        &'a [TypeVariable],
         &'a [TypeDeclaration<Name>],
     )> {
         self.classes
@@ -346,7 +378,7 @@ impl Types for Assembly {
             .map(|class| {
                 (
                     class.constraints.as_ref(),
-                    &class.variable,
+                    class.variables.as_ref(),
                     class.declarations.as_ref(),
                 )
             })
@@ -354,26 +386,28 @@ impl Types for Assembly {
     fn find_instance<'a>(
         &'a self,
         classname: Name,
-        typ: &Type<Name>,
-    ) -> Option<(&'a [Constraint<Name>], &'a Type<Name>)> {
-        for &(ref constraints, ref op) in self.instances.iter() {
-            let &Type::Application(ref op, ref t) = op else {
-                continue;
-            };
-            let Type::Constructor(ref x) = extract_applied_type(op) else {
-                panic!();
-            };
-            let Type::Constructor(ref y) = extract_applied_type(t) else {
-                panic!();
-            };
-            let Type::Constructor(ref z) = extract_applied_type(typ) else {
-                panic!();
-            };
-            if classname.name == x.name && y.name == z.name {
-                return Some((constraints.as_ref(), t));
-            }
+        types: &[Type<Name>],
+    ) -> Option<(&'a [Constraint<Name>], &'a [Type<Name>])> {
+        self.instances
+            .iter()
+            .find(|&&(_, ref class, ref instance_types)| {
+                classname.name == class.name && same_instance_heads(instance_types, types)
+            })
+            .map(|&(ref constraints, _, ref instance_types)| {
+                (constraints.as_ref(), instance_types.as_ref())
+            })
+    }
+
+    fn each_global(&self, f: &mut dyn FnMut(&Name, &Qualified<Type<Name>, Name>)) {
+        for sc in self.super_combinators.iter() {
+            f(&sc.name, &sc.typ);
+        }
+        for decl in self.classes.iter().flat_map(|class| class.declarations.iter()) {
+            f(&decl.name, &decl.typ);
+        }
+        for ctor in self.data_definitions.iter().flat_map(|data| data.constructors.iter()) {
+            f(&ctor.name, &ctor.typ);
         }
-        None
     }
 }
 
@@ -386,6 +420,16 @@ impl DataTypes for Assembly {
         }
         None
     }
+    fn find_newtype<'a>(&'a self, name: Name) -> Option<&'a Newtype<Name>> {
+        self.newtypes
+            .iter()
+            .find(|newtype| extract_applied_type(newtype_type(newtype)).ctor().name == name)
+    }
+    fn find_type_synonym<'a>(&'a self, name: Name) -> Option<&'a TypeSynonym<Name>> {
+        self.type_synonyms
+            .iter()
+            .find(|synonym| synonym.name == name)
+    }
 }
 
 enum ArgList<'a> {
@@ -395,7 +439,7 @@ enum ArgList<'a> {
 
 pub struct Compiler<'a> {
     ///Hashmap containging class names mapped to the functions it contains
-    pub instance_dictionaries: Vec<(Vec<(Name, Type<Name>)>, Vec<usize>)>,
+    pub instance_dictionaries: Vec<(Vec<(Name, Vec<Type<Name>>)>, Vec<usize>)>,
     pub stack_size: usize,
     ///Array of all the assemblies which can be used to lookup functions in
     pub assemblies: Vec<&'a Assembly>,
@@ -470,14 +514,11 @@ impl<'a> Compiler<'a> {
             instances: module
                 .instances
                 .iter()
-                .map(|x| {
-                    (
-                        x.constraints.clone(),
-                        Type::new_op(x.classname, vec![x.typ.clone()]),
-                    )
-                })
+                .map(|x| (x.constraints.clone(), x.classname, x.types.clone()))
                 .collect(),
             data_definitions,
+            newtypes: module.newtypes.clone(),
+            type_synonyms: module.type_synonyms.clone(),
         }
     }
 
@@ -599,7 +640,7 @@ impl<'a> Compiler<'a> {
     fn find_class(
         &self,
         name: Name,
-    ) -> Option<(&[Constraint<Name>], &TypeVariable, &[TypeDeclaration<Name>])> {
+    ) -> Option<(&[Constraint<Name>], &[TypeVariable], &[TypeDeclaration<Name>])> {
         self.module.and_then(|m| m.find_class(name)).or_else(|| {
             for types in self.assemblies.iter() {
                 match types.find_class(name) {
@@ -611,6 +652,21 @@ impl<'a> Compiler<'a> {
         })
     }
 
+    fn find_instance(
+        &self,
+        classname: Name,
+        types: &[Type<Name>],
+    ) -> Option<(&[Constraint<Name>], &[Type<Name>])> {
+        self.module
+            .and_then(|m| m.find_instance(classname, types))
+            .or_else(|| {
+                self.assemblies
+                    .iter()
+                    .filter_map(|assembly| assembly.find_instance(classname, types))
+                    .next()
+            })
+    }
+
     fn new_stack_var(&mut self, identifier: Name) {
         self.variables
             .insert(identifier, Var::Stack(self.stack_size));
@@ -895,12 +951,16 @@ impl<'a> Compiler<'a> {
         name: Name,
         function_type: &Type<Name>,
         constraints: &[Constraint<Name>],
-        var: &TypeVariable,
+        vars: &[TypeVariable],
     ) {
-        if let Some(typename) = try_find_instance_type(var, function_type, actual_type) {
+        let typenames: Option<Vec<&str>> = vars
+            .iter()
+            .map(|var| try_find_instance_type(var, function_type, actual_type))
+            .collect();
+        if let Some(typenames) = typenames {
             //We should be able to retrieve the instance directly
             let mut b = "#".to_string();
-            b.push_str(typename);
+            b.push_str(&typenames.join(","));
             b.push_str(name.as_ref());
             let instance_fn_name = Name {
                 name: intern(b.as_ref()),
@@ -946,13 +1006,11 @@ impl<'a> Compiler<'a> {
         match self.find("$dict".into()) {
             Some(Var::Stack(_)) => {
                 //Push dictionary or member of dictionary
-                match self.push_dictionary_member(constraints, name) {
+                let dictionary_key =
+                    find_specialized_instances(function_type, actual_type, constraints);
+                match self.push_dictionary_member(&dictionary_key, name) {
                     Some(index) => instructions.push(PushDictionaryMember(index)),
-                    None => {
-                        let dictionary_key =
-                            find_specialized_instances(function_type, actual_type, constraints);
-                        self.push_dictionary(constraints, &*dictionary_key, instructions);
-                    }
+                    None => self.push_dictionary(constraints, &*dictionary_key, instructions),
                 }
             }
             _ => {
@@ -968,13 +1026,55 @@ impl<'a> Compiler<'a> {
     fn push_dictionary(
         &mut self,
         context: &[Constraint<Name>],
-        constraints: &[(Name, Type<Name>)],
+        constraints: &[(Name, Vec<Type<Name>>)],
         instructions: &mut Vec<Instruction>,
     ) {
         debug!("Push dictionary {:?} ==> {:?}", context, constraints);
-        for &(ref class, ref typ) in constraints.iter() {
-            self.fold_dictionary(*class, typ, instructions);
-            instructions.push(ConstructDictionary(constraints.len()));
+        for &(ref class, ref types) in constraints.iter() {
+            match **types {
+                [ref typ] => self.fold_dictionary(*class, typ, instructions),
+                _ => self.push_multi_dictionary(*class, types, instructions),
+            }
+        }
+        instructions.push(ConstructDictionary(constraints.len()));
+    }
+
+    ///Writes instructions which pushes the dictionary of a class with several parameters.
+    ///Either all types are known, in which case the dictionary of the instance is pushed, or
+    ///they are all variables which must have a constraint in the current context
+    fn push_multi_dictionary(
+        &mut self,
+        class: Name,
+        types: &[Type<Name>],
+        instructions: &mut Vec<Instruction>,
+    ) {
+        let variables: Option<Vec<&TypeVariable>> = types
+            .iter()
+            .map(|typ| match *typ {
+                Type::Variable(ref var) => Some(var),
+                _ => None,
+            })
+            .collect();
+        match variables {
+            Some(variables) => {
+                let mut index = 0;
+                for constraint in self.context.iter() {
+                    if constraint.class == class
+                        && constraint.variables.iter().eq(variables.iter().cloned())
+                    {
+                        let num_class_functions = self.num_class_functions(class);
+                        instructions.push(PushDictionaryRange(index, num_class_functions));
+                        return;
+                    }
+                    index += self.num_class_functions(constraint.class);
+                }
+                //The typechecker adds every constraint on the variables of a binding to its context
+                panic!("No dictionary for {} {:?} in the context of the binding", class, types);
+            }
+            None => {
+                let index = self.find_dictionary_index(&[(class, types.to_owned())]);
+                instructions.push(PushDictionary(index));
+            }
         }
     }
 
@@ -990,7 +1090,17 @@ impl<'a> Compiler<'a> {
                 //Simple
                 debug!("Simple for {:?}", ctor);
                 //Push static dictionary to the top of the stack
-                let index = self.find_dictionary_index(&[(class.clone(), typ.clone())]);
+                let index = self.find_dictionary_index(&[(class.clone(), vec![typ.clone()])]);
+                instructions.push(PushDictionary(index));
+            }
+            Type::Application(..)
+                if matches!(
+                    self.find_instance(class, ::std::slice::from_ref(typ)),
+                    Some((constraints, _)) if constraints.is_empty()
+                ) =>
+            {
+                //The instance does not need the dictionaries of the type's arguments
+                let index = self.find_dictionary_index(&[(class, vec![typ.clone()])]);
                 instructions.push(PushDictionary(index));
             }
             Type::Application(ref lhs, ref rhs) => {
@@ -1010,15 +1120,11 @@ impl<'a> Compiler<'a> {
                         has_constraint = true;
                         break;
                     }
-                    let (_, _, decls) = self.find_class(constraint.class).unwrap();
-                    index += decls.len();
+                    index += self.num_class_functions(constraint.class);
                 }
                 if has_constraint {
                     //Found the variable in the constraints
-                    let num_class_functions = self
-                        .find_class(class)
-                        .map(|(_, _, decls)| decls.len())
-                        .unwrap();
+                    let num_class_functions = self.num_class_functions(class);
                     debug!(
                         "Use previous dict for {:?} at {:?}..{:?}",
                         var, index, num_class_functions
@@ -1033,9 +1139,11 @@ impl<'a> Compiler<'a> {
     }
 
     ///Lookup which index in the instance dictionary that holds the function called 'name'
+    ///The function must belong to the dictionary of a constraint in the current context on the
+    ///same type variables as 'constraints'
     fn push_dictionary_member(
         &self,
-        constraints: &[Constraint<Name>],
+        constraints: &[(Name, Vec<Type<Name>>)],
         name: Name,
     ) -> Option<usize> {
         assert!(
@@ -1043,8 +1151,39 @@ impl<'a> Compiler<'a> {
             "Attempted to push dictionary member '{:?}' with no constraints",
             name
         );
+        let is_variable = |typ: &Type<Name>| matches!(*typ, Type::Variable(_));
+        if !constraints
+            .iter()
+            .all(|&(_, ref types)| types.iter().all(is_variable))
+        {
+            return None;
+        }
+        let same_variables = |c: &Constraint<Name>| {
+            constraints.iter().any(|&(_, ref types)| {
+                types.len() == c.variables.len()
+                    && types.iter().zip(c.variables.iter()).all(|(typ, var)| match *typ {
+                        Type::Variable(ref v) => v == var,
+                        _ => false,
+                    })
+            })
+        };
+        //Prefer the dictionary for the same variables but fall back to any dictionary of the class
+        //if the variable was never unified with one from the context
+        self.find_context_member(name, &same_variables)
+            .or_else(|| self.find_context_member(name, &|_| true))
+    }
+
+    fn find_context_member(
+        &self,
+        name: Name,
+        filter: &dyn Fn(&Constraint<Name>) -> bool,
+    ) -> Option<usize> {
         let mut ii = 0;
-        for c in constraints.iter() {
+        for c in self.context.iter() {
+            if !filter(c) {
+                ii += self.num_class_functions(c.class);
+                continue;
+            }
             let result =
                 self.walk_classes(c.class, &mut |declarations| -> Option<usize> {
                     for decl in declarations.iter() {
@@ -1062,6 +1201,17 @@ impl<'a> Compiler<'a> {
         None
     }
 
+    ///Returns the number of functions in the dictionary of 'class', including the functions of
+    ///its super classes
+    fn num_class_functions(&self, class: Name) -> usize {
+        let mut count = 0;
+        self.walk_classes(class, &mut |declarations| -> Option<()> {
+            count += declarations.len();
+            None
+        });
+        count
+    }
+
     ///Walks through the class and all of its super classes, calling 'f' on each of them
     ///Returning Some(..) from the function quits and returns that value
     fn walk_classes<T>(
@@ -1082,7 +1232,7 @@ impl<'a> Compiler<'a> {
 
     ///Find the index of the instance dictionary for the constraints and types in 'constraints'
     ///Returns the index
-    fn find_dictionary_index(&mut self, constraints: &[(Name, Type<Name>)]) -> usize {
+    fn find_dictionary_index(&mut self, constraints: &[(Name, Vec<Type<Name>>)]) -> usize {
         //Check if the dictionary already exist
         let dict_len = self.instance_dictionaries.len();
         for ii in 0..dict_len {
@@ -1102,20 +1252,17 @@ impl<'a> Compiler<'a> {
         dict_len
     }
 
-    fn add_class(&self, constraints: &[(Name, Type<Name>)], function_indexes: &mut Vec<usize>) {
-        for &(ref class_name, ref typ) in constraints.iter() {
+    fn add_class(
+        &self,
+        constraints: &[(Name, Vec<Type<Name>>)],
+        function_indexes: &mut Vec<usize>,
+    ) {
+        for &(ref class_name, ref types) in constraints.iter() {
+            let instance = instance_name(types);
             self.walk_classes(*class_name, &mut |declarations| -> Option<()> {
                 for decl in declarations.iter() {
-                    let x = match extract_applied_type(typ) {
-                        &Type::Constructor(ref x) => x,
-                        _ => panic!("{:?}", typ),
-                    };
-                    let mut b = "#".to_string();
-                    b.push_str(x.name.as_ref());
-                    b.push_str(decl.name.as_ref());
-                    let f = intern(b.as_ref());
                     let name = Name {
-                        name: f,
+                        name: encode_binding_identifier(instance, decl.name.name),
                         uid: decl.name.uid,
                     };
                     match self.find(name) {
@@ -1125,7 +1272,7 @@ impl<'a> Compiler<'a> {
                         Some(Var::Constraint(index, _, _)) => {
                             function_indexes.push(index as usize); //TODO this is not really correct since this function requires a dictionary
                         }
-                        var => panic!("Did not find function {:?} {:?}", name, var),
+                        var => panic!("Did not find function {} {:?}", name, var),
                     }
                 }
                 None
@@ -1145,6 +1292,13 @@ impl<'a> Compiler<'a> {
     ) -> usize {
         debug!("Pattern {:?} at {:?}", pattern, stack_size);
         match pattern {
+            &Pattern::Constructor(ref name, ref patterns)
+                if matches!(self.find(name.name), Some(Var::Newtype)) =>
+            {
+                //Newtypes are represented by the wrapped value so it is bound directly
+                self.new_var_at(patterns[0].name, stack_size);
+                0
+            }
             &Pattern::Constructor(ref name, ref patterns) => {
                 instructions.push(Push(stack_size));
                 match self.find_constructor(name.name) {
@@ -1171,6 +1325,22 @@ impl<'a> Compiler<'a> {
                 instructions.push(JumpFalse(0));
                 0
             }
+            &Pattern::Fractional(number) => {
+                instructions.push(Push(stack_size));
+                instructions.push(Eval);
+                instructions.push(PushFloat(number));
+                instructions.push(DoubleEQ);
+                instructions.push(JumpFalse(0));
+                0
+            }
+            &Pattern::Char(c) => {
+                instructions.push(Push(stack_size));
+                instructions.push(Eval);
+                instructions.push(PushChar(c));
+                instructions.push(CharEQ);
+                instructions.push(JumpFalse(0));
+                0
+            }
             &Pattern::Identifier(ref ident) => {
                 self.new_var_at(ident.name.clone(), stack_size);
                 0
@@ -1240,20 +1410,21 @@ pub fn compile_with_type_env<'a>(
 pub fn compile_string(module: &str) -> Result<Vec<Assembly>, ::std::string::String> {
     use crate::typecheck::typecheck_string;
     let modules = typecheck_string(module)?;
-    compile_module_(modules)
+    Ok(compile_module_(modules))
 }
 
 ///Takes a module name and does everything needed up to and including compiling the module
-///and its imported modules
-pub fn compile_module(module: &str) -> Result<Vec<Assembly>, ::std::string::String> {
+///and its imported modules. The warnings found in the modules are returned along with them
+pub fn compile_module(
+    search_path: &SearchPath,
+    module: &str,
+) -> Result<(Vec<Assembly>, Vec<Diagnostic>), VMError> {
     use crate::typecheck::typecheck_module;
-    let modules = typecheck_module(module)?;
-    compile_module_(modules)
+    let (modules, warnings) = typecheck_module(search_path, module)?;
+    Ok((compile_module_(modules), warnings))
 }
 
-fn compile_module_(
-    modules: Vec<crate::module::Module<Name>>,
-) -> Result<Vec<Assembly>, ::std::string::String> {
+fn compile_module_(modules: Vec<crate::module::Module<Name>>) -> Vec<Assembly> {
     let core_modules: Vec<Module<Id<Name>>> = translate_modules(modules)
         .into_iter()
         .map(|module| do_lambda_lift(module))
@@ -1269,7 +1440,7 @@ fn compile_module_(
         };
         assemblies.push(x);
     }
-    Ok(assemblies)
+    assemblies
 }
 
 #[cfg(test)]
@@ -1312,7 +1483,7 @@ mod tests {
     #[test]
     fn add_double() {
         let file = r"add x y = primDoubleAdd x y
-main = add 2. 3.";
+main = add 2.0 3.0";
         let assembly = compile(file);
 
         assert_eq!(
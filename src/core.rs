@@ -9,6 +9,7 @@ pub use crate::{
         },
         Newtype,
         TypeDeclaration,
+        TypeSynonym,
     },
     renamer::Name,
     types::{
@@ -33,6 +34,7 @@ pub struct Module<Ident> {
     pub classes: Vec<Class<Ident>>,
     pub data_definitions: Vec<DataDefinition<Name>>,
     pub newtypes: Vec<Newtype<Name>>,
+    pub type_synonyms: Vec<TypeSynonym<Name>>,
     pub instances: Vec<Instance<Ident>>,
     pub bindings: Vec<Binding<Ident>>,
 }
@@ -43,6 +45,7 @@ impl Module<Id> {
             classes: vec![],
             data_definitions: vec![],
             newtypes: vec![],
+            type_synonyms: vec![],
             instances: vec![],
             bindings: vec![Binding {
                 name: Id::new("main".into(), expr.get_type().clone(), vec![]),
@@ -56,7 +59,7 @@ impl Module<Id> {
 pub struct Class<Ident> {
     pub constraints: Vec<Constraint<Name>>,
     pub name: Name,
-    pub variable: TypeVariable,
+    pub variables: Vec<TypeVariable>,
     pub declarations: Vec<module::TypeDeclaration<Name>>,
     pub bindings: Vec<Binding<Ident>>,
 }
@@ -65,7 +68,7 @@ pub struct Class<Ident> {
 pub struct Instance<Ident = InternedStr> {
     pub bindings: Vec<Binding<Ident>>,
     pub constraints: Vec<Constraint<Name>>,
-    pub typ: TcType,
+    pub types: Vec<TcType>,
     pub classname: Name,
 }
 
@@ -86,6 +89,8 @@ pub enum Pattern<Ident> {
     Constructor(Ident, Vec<Ident>),
     Identifier(Ident),
     Number(isize),
+    Fractional(f64),
+    Char(char),
     WildCard,
 }
 
@@ -133,6 +138,8 @@ impl<T: fmt::Display> fmt::Display for Pattern<T> {
         match *self {
             Self::Identifier(ref s) => write!(f, "{}", s),
             Self::Number(ref i) => write!(f, "{}", i),
+            Self::Fractional(ref v) => write!(f, "{}", v),
+            Self::Char(ref c) => write!(f, "'{}'", c),
             Self::Constructor(ref name, ref patterns) => {
                 write!(f, "({} ", name)?;
                 for p in patterns.iter() {
@@ -177,7 +184,7 @@ impl<Ident: Typed> Typed for Pattern<Ident> {
     fn get_type<'a>(&'a self) -> &'a Type<Ident::Id> {
         match *self {
             Self::Identifier(ref name) | Self::Constructor(ref name, _) => name.get_type(),
-            Self::Number(_) | Self::WildCard => panic!(),
+            Self::Number(_) | Self::Fractional(_) | Self::Char(_) | Self::WildCard => panic!(),
         }
     }
 }
@@ -248,6 +255,16 @@ pub mod ref_ {
     }
 
     pub fn walk_module<V: Visitor<Ident>, Ident>(visitor: &mut V, module: &Module<Ident>) {
+        for class in module.classes.iter() {
+            for bind in class.bindings.iter() {
+                visitor.visit_binding(bind);
+            }
+        }
+        for instance in module.instances.iter() {
+            for bind in instance.bindings.iter() {
+                visitor.visit_binding(bind);
+            }
+        }
         for bind in module.bindings.iter() {
             visitor.visit_binding(bind);
         }
@@ -308,6 +325,16 @@ pub mod mutable {
     }
 
     pub fn walk_module<Ident, V: Visitor<Ident>>(visitor: &mut V, module: &mut Module<Ident>) {
+        for class in module.classes.iter_mut() {
+            for bind in class.bindings.iter_mut() {
+                visitor.visit_binding(bind);
+            }
+        }
+        for instance in module.instances.iter_mut() {
+            for bind in instance.bindings.iter_mut() {
+                visitor.visit_binding(bind);
+            }
+        }
         for bind in module.bindings.iter_mut() {
             visitor.visit_binding(bind);
         }
@@ -378,12 +405,22 @@ pub mod result {
         visitor: &mut V,
         mut module: Module<Ident>,
     ) -> Module<Ident> {
-        let mut bindings = vec![];
-        ::std::mem::swap(&mut module.bindings, &mut bindings);
-        module.bindings = bindings
-            .into_iter()
-            .map(|bind| visitor.visit_binding(bind))
-            .collect();
+        fn walk_bindings<V: Visitor<Ident>, Ident>(
+            visitor: &mut V,
+            bindings: &mut Vec<Binding<Ident>>,
+        ) {
+            *bindings = ::std::mem::take(bindings)
+                .into_iter()
+                .map(|bind| visitor.visit_binding(bind))
+                .collect();
+        }
+        for class in module.classes.iter_mut() {
+            walk_bindings(visitor, &mut class.bindings);
+        }
+        for instance in module.instances.iter_mut() {
+            walk_bindings(visitor, &mut instance.bindings);
+        }
+        walk_bindings(visitor, &mut module.bindings);
         module
     }
 
@@ -449,12 +486,20 @@ pub mod translate {
                 *,
             },
             deriving::*,
+            lexer::{
+                Located,
+                Location,
+            },
             module,
             renamer::{
                 typ::*,
                 NameSupply,
             },
             typecheck::TcType,
+            types::{
+                extract_applied_type,
+                try_get_function,
+            },
         },
         std::collections::HashMap,
     };
@@ -462,19 +507,37 @@ pub mod translate {
     struct Translator<'a> {
         name_supply: NameSupply,
         functions_in_class:
-            &'a mut (dyn FnMut(Name) -> (&'a TypeVariable, &'a [TypeDeclaration<Name>]) + 'a),
+            &'a mut (dyn FnMut(Name) -> (&'a [TypeVariable], &'a [TypeDeclaration<Name>]) + 'a),
+        ///The data types of all modules being translated, used to resolve record fields
+        data_definitions: Vec<DataDefinition<Name>>,
+        ///The newtypes and fixity declarations of all modules being translated, used to derive
+        ///instances for types declared in other modules
+        newtypes: Vec<module::Newtype<Name>>,
+        fixity_declarations: Vec<module::FixityDeclaration<Name>>,
     }
 
-    #[derive(Debug)]
-    struct Equation<'a>(
-        &'a [(Id<Name>, Pattern<Id<Name>>)],
-        (&'a [Binding<Id<Name>>], &'a module::Match<Name>),
-    );
+    ///An equation or alternative which is compiled by the match compiler, a row in the
+    ///clause matrix
+    #[derive(Clone)]
+    struct Row {
+        ///The patterns which are left to match, each paired with the variable it is matched against
+        patterns: Vec<(Id<Name>, module::Pattern<Name>)>,
+        ///The variables bound by the patterns which have already matched, as well as the
+        ///bindings which lazily match irrefutable patterns
+        bindings: Vec<Binding<Id<Name>>>,
+        ///Variables which bang patterns require to be evaluated
+        strict: Vec<Id<Name>>,
+        where_bindings: Vec<Binding<Id<Name>>>,
+        matches: module::Match<Name>,
+    }
 
     pub fn translate_expr(expr: module::TypedExpr<Name>) -> Expr<Id<Name>> {
         let mut translator = Translator {
             name_supply: NameSupply::new(),
             functions_in_class: &mut |_| panic!(),
+            data_definitions: vec![],
+            newtypes: vec![],
+            fixity_declarations: vec![],
         };
         translator.translate_expr(expr)
     }
@@ -484,16 +547,31 @@ pub mod translate {
         for class in modules.iter().flat_map(|m| m.classes.iter()) {
             map.insert(
                 class.name.clone(),
-                (class.variable.clone(), class.declarations.clone()),
+                (class.variables.clone(), class.declarations.clone()),
             );
         }
+        let data_definitions = modules
+            .iter()
+            .flat_map(|m| m.data_definitions.iter().cloned())
+            .collect();
+        let newtypes = modules
+            .iter()
+            .flat_map(|m| m.newtypes.iter().cloned())
+            .collect();
+        let fixity_declarations = modules
+            .iter()
+            .flat_map(|m| m.fixity_declarations.iter().cloned())
+            .collect();
         let mut translator =
             Translator {
                 name_supply: NameSupply::new(),
                 functions_in_class: &mut |name| {
-                    let &(ref var, ref decls) = map.get(&name).unwrap();
-                    (var, decls.as_ref())
+                    let &(ref vars, ref decls) = map.get(&name).unwrap();
+                    (vars.as_ref(), decls.as_ref())
                 },
+                data_definitions,
+                newtypes,
+                fixity_declarations,
             };
         modules
             .into_iter()
@@ -509,14 +587,19 @@ pub mod translate {
     ) -> Module<Id<Name>> {
         let module::Module {
             name: _name,
+            exports: _exports,
             imports: _imports,
             bindings,
             type_declarations: _type_declarations,
             newtypes,
             classes,
             instances,
+            deriving_instances,
             data_definitions,
+            type_synonyms,
             fixity_declarations: _fixity_declarations,
+            default_types: _default_types,
+            extensions: _extensions,
         } = module;
 
         let mut new_instances: Vec<Instance<Id<Name>>> = vec![];
@@ -527,14 +610,15 @@ pub mod translate {
                 let module::Class {
                     constraints,
                     name,
-                    variable,
+                    variables,
                     declarations,
                     bindings,
+                    location: _,
                 } = class;
                 Class {
                     constraints,
                     name,
-                    variable,
+                    variables,
                     declarations,
                     bindings: translator.translate_bindings(bindings),
                 }
@@ -544,9 +628,10 @@ pub mod translate {
         for instance in instances.into_iter() {
             let module::Instance {
                 classname,
-                typ,
+                types,
                 constraints,
                 bindings,
+                location: _,
             } = instance;
             let bs: Vec<Binding<Id<Name>>> = translator
                 .translate_bindings(bindings)
@@ -554,7 +639,7 @@ pub mod translate {
                 .collect();
             new_instances.push(Instance {
                 constraints,
-                typ,
+                types,
                 classname,
                 bindings: bs,
             });
@@ -564,11 +649,14 @@ pub mod translate {
             .into_iter()
             .collect();
         for data in data_definitions.iter() {
-            generate_deriving(&mut new_instances, data);
+            generate_deriving(&mut new_instances, data, &translator.fixity_declarations);
+        }
+        for instance in deriving_instances.iter() {
+            new_instances.push(translator.translate_deriving_instance(instance));
         }
         for instance in new_instances.iter_mut() {
-            let (class_var, class_decls) = (translator.functions_in_class)(instance.classname);
-            let defaults = create_default_stubs(class_var, class_decls, instance);
+            let (class_vars, class_decls) = (translator.functions_in_class)(instance.classname);
+            let defaults = create_default_stubs(class_vars, class_decls, instance);
             let mut temp = vec![];
             ::std::mem::swap(&mut temp, &mut instance.bindings);
             let vec: Vec<Binding<Id<Name>>> =
@@ -579,6 +667,7 @@ pub mod translate {
             classes: classes2,
             data_definitions,
             newtypes,
+            type_synonyms,
             bindings: bs,
             instances: new_instances,
         }
@@ -586,7 +675,7 @@ pub mod translate {
 
     ///Creates stub functions for each undeclared function in the instance
     fn create_default_stubs(
-        class_var: &TypeVariable,
+        class_vars: &[TypeVariable],
         class_decls: &[TypeDeclaration<Name>],
         instance: &Instance<Id<Name>>,
     ) -> Vec<Binding<Id<Name>>> {
@@ -601,19 +690,28 @@ pub mod translate {
             })
             .map(|decl| {
                 debug!(
-                    "Create default function for {} ({}) {}",
-                    instance.classname, instance.typ, decl.name
+                    "Create default function for {} ({:?}) {}",
+                    instance.classname, instance.types, decl.name
                 );
                 //The stub functions will naturally have the same type as the function in the class but with the variable replaced
                 //with the instance's type
                 let mut typ = decl.typ.clone();
-                crate::typecheck::replace_var(&mut typ.value, class_var, &instance.typ);
+                for (class_var, instance_type) in class_vars.iter().zip(instance.types.iter()) {
+                    crate::typecheck::replace_var(&mut typ.value, class_var, instance_type);
+                }
                 {
                     let context = ::std::mem::replace(&mut typ.constraints, vec![]);
-                    //Remove all constraints which refer to the class's variable
-                    let vec_context: Vec<Constraint<Name>> = context
-                        .into_iter()
-                        .filter(|c| c.variables[0] != *class_var)
+                    //Remove all constraints which refer to the class's variable and add the
+                    //constraints of the instance since the default needs the instance's dictionary
+                    let vec_context: Vec<Constraint<Name>> = instance
+                        .constraints
+                        .iter()
+                        .cloned()
+                        .chain(
+                            context
+                                .into_iter()
+                                .filter(|c| c.variables.iter().all(|var| !class_vars.contains(var))),
+                        )
                         .collect();
                     typ.constraints = vec_context;
                 }
@@ -623,7 +721,7 @@ pub mod translate {
                 } = typ;
                 let default_name =
                     module::encode_binding_identifier(instance.classname.name, decl.name.name);
-                let typ_name = module::extract_applied_type(&instance.typ).ctor().name.name;
+                let typ_name = module::instance_name(&instance.types);
                 let instance_fn_name = module::encode_binding_identifier(typ_name, decl.name.name);
 
                 //Example stub for undeclared (/=)
@@ -650,6 +748,34 @@ pub mod translate {
             .collect()
     }
     impl<'a> Translator<'a> {
+        ///Generates the instance of a standalone deriving declaration or of a class in the
+        ///deriving clause of a newtype
+        fn translate_deriving_instance(
+            &mut self,
+            instance: &module::DerivingInstance<Name>,
+        ) -> Instance<Id<Name>> {
+            let name = extract_applied_type(&instance.types[0]).ctor().name;
+            if let Some(data) = self
+                .data_definitions
+                .iter()
+                .find(|data| extract_applied_type(&data.typ.value).ctor().name == name)
+            {
+                return derive_instance(instance.classname, data, &self.fixity_declarations);
+            }
+            let newtype = self
+                .newtypes
+                .iter()
+                .find(|newtype| extract_applied_type(newtype_type(newtype)).ctor().name == name)
+                .unwrap_or_else(|| panic!("Deriving an instance for the unknown type {}", name));
+            if is_newtype_deriving(instance.classname) {
+                let (class_vars, class_decls) = (self.functions_in_class)(instance.classname);
+                derive_newtype_instance(instance, newtype, class_vars, class_decls)
+            } else {
+                let data = newtype_data_definition(newtype);
+                derive_instance(instance.classname, &data, &self.fixity_declarations)
+            }
+        }
+
         fn translate_match(&mut self, matches: module::Match<Name>) -> Expr<Id<Name>> {
             match matches {
                 module::Match::Simple(e) => self.translate_expr(e),
@@ -690,11 +816,14 @@ pub mod translate {
         }
 
         fn translate_expr_rest(&mut self, input_expr: module::TypedExpr<Name>) -> Expr<Id<Name>> {
+            if missing_constructor_arguments(&input_expr) {
+                return self.translate_partial_constructor(input_expr);
+            }
             let module::TypedExpr { typ, expr, .. } = input_expr;
             match expr {
                 module::Expr::Identifier(s) => Identifier(Id::new(s, typ, vec![])),
                 module::Expr::Apply(func, arg) => Apply(
-                    Box::new(self.translate_expr(*func)),
+                    Box::new(self.translate_function(*func)),
                     Box::new(self.translate_expr(*arg)),
                 ),
                 module::Expr::OpApply(lhs, op, rhs) => {
@@ -775,7 +904,258 @@ pub mod translate {
                 }
                 module::Expr::TypeSig(expr, _) => self.translate_expr(*expr),
                 module::Expr::Paren(expr) => self.translate_expr(*expr),
+                module::Expr::ListComprehension(expr, qualifiers) => {
+                    self.translate_list_comprehension(typ, *expr, qualifiers)
+                }
+                module::Expr::Record(name, fields) => self.translate_record(typ, name, fields),
+                module::Expr::RecordUpdate(record, fields) => {
+                    self.translate_record_update(typ, *record, fields)
+                }
+                module::Expr::LeftSection(lhs, op) => {
+                    //(e op) = (op) e
+                    let l = self.translate_expr(*lhs);
+                    let func_type = function_type_(l.get_type().clone(), typ);
+                    Apply(Identifier(Id::new(op, func_type, vec![])).into(), l.into())
+                }
+                module::Expr::RightSection(op, rhs) => {
+                    //(op e) = \x -> x op e
+                    let arg_type = typ.appl().appr().clone();
+                    let result_type = typ.appr().clone();
+                    let arg = Id::new(self.name_supply.from_str("x"), arg_type.clone(), vec![]);
+                    let r = self.translate_expr(*rhs);
+                    let func_type =
+                        function_type_(arg_type, function_type_(r.get_type().clone(), result_type));
+                    let body = apply(
+                        Identifier(Id::new(op, func_type, vec![])),
+                        vec![Identifier(arg.clone()), r].into_iter(),
+                    );
+                    self.section_lambda(typ, vec![arg], body)
+                }
+                module::Expr::TupleSection(elements) => self.translate_tuple_section(typ, elements),
+                module::Expr::Hole(name) => {
+                    panic!("Typed hole {} should have been reported by the typechecker", name)
+                }
+                module::Expr::ArithmeticSequence(from, then, to) => {
+                    let function = match (&then, &to) {
+                        (&None, &None) => "enumFrom",
+                        (&Some(_), &None) => "enumFromThen",
+                        (&None, &Some(_)) => "enumFromTo",
+                        (&Some(_), &Some(_)) => "enumFromThenTo",
+                    };
+                    let args: Vec<_> = Some(from)
+                        .into_iter()
+                        .chain(then)
+                        .chain(to)
+                        .map(|e| self.translate_expr(*e))
+                        .collect();
+                    let element_type = typ.appr().clone();
+                    let c = match element_type {
+                        Type::Variable(ref var) => vec![Constraint {
+                            class: "Enum".into(),
+                            variables: vec![var.clone()],
+                        }],
+                        _ => vec![],
+                    };
+                    let function_type = args
+                        .iter()
+                        .rev()
+                        .fold(typ, |result, _| function_type_(element_type.clone(), result));
+                    apply(
+                        Identifier(Id::new(function.into(), function_type, c)),
+                        args.into_iter(),
+                    )
+                }
+            }
+        }
+        ///Translates a list comprehension one qualifier at a time
+        ///[e | ] = [e]
+        ///[e | b, Q] = if b then [e | Q] else []
+        ///[e | let decls, Q] = let decls in [e | Q]
+        ///[e | p <- l, Q] =
+        ///    let ok p = [e | Q]
+        ///        ok _ = []
+        ///    in concatMap ok l
+        fn translate_list_comprehension(
+            &mut self,
+            typ: TcType,
+            expr: module::TypedExpr<Name>,
+            mut qualifiers: Vec<module::Qualifier<Name>>,
+        ) -> Expr<Id<Name>> {
+            if qualifiers.is_empty() {
+                let element = self.translate_expr(expr);
+                let cons_type = function_type_(
+                    element.get_type().clone(),
+                    function_type_(typ.clone(), typ.clone()),
+                );
+                let cons = Identifier(Id::new(":".into(), cons_type, vec![]));
+                return apply(cons, vec![element, nil(typ)].into_iter());
+            }
+            let location = expr.location;
+            let qualifier = qualifiers.remove(0);
+            let rest = module::TypedExpr {
+                expr: module::Expr::ListComprehension(expr.into(), qualifiers),
+                typ: typ.clone(),
+                location,
+            };
+            match qualifier {
+                module::Qualifier::Guard(predicate) => Case(
+                    Box::new(self.translate_expr(predicate)),
+                    vec![
+                        Alternative {
+                            pattern: bool_pattern("True"),
+                            expression: self.translate_expr(rest),
+                        },
+                        Alternative {
+                            pattern: bool_pattern("False"),
+                            expression: nil(typ),
+                        },
+                    ],
+                ),
+                module::Qualifier::Let(bindings) => {
+                    Let(self.translate_bindings(bindings), self.translate_expr(rest).into())
+                }
+                module::Qualifier::Generator(pattern, list) => {
+                    let element_type = list.typ.appr().clone();
+                    let func_type = function_type_(element_type.clone(), typ.clone());
+                    let refutable = refutable(&pattern.node);
+                    let mut alts = vec![module::Alternative {
+                        pattern,
+                        matches: module::Match::Simple(rest),
+                        where_bindings: None,
+                    }];
+                    if refutable {
+                        alts.push(module::Alternative {
+                            pattern: Located {
+                                location,
+                                node: module::Pattern::WildCard,
+                            },
+                            matches: module::Match::Simple(module::TypedExpr {
+                                expr: module::Expr::Identifier("[]".into()),
+                                typ: typ.clone(),
+                                location,
+                            }),
+                            where_bindings: None,
+                        });
+                    }
+                    let arg = Id::new(self.name_supply.from_str("x"), element_type.clone(), vec![]);
+                    let scrutinee = module::TypedExpr {
+                        expr: module::Expr::Identifier(arg.name),
+                        typ: element_type,
+                        location,
+                    };
+                    let func = Lambda(arg, Box::new(self.translate_case(scrutinee, alts)));
+                    let func_ident = Id::new(self.name_supply.from_str("#ok"), func_type.clone(), vec![]);
+                    let bind = Binding {
+                        name: func_ident.clone(),
+                        expression: func,
+                    };
+
+                    let concat_map_type =
+                        function_type_(func_type, function_type_(list.typ.clone(), typ));
+                    let concat_map = Identifier(Id::new("concatMap".into(), concat_map_type, vec![]));
+                    let list = self.translate_expr(list);
+                    Let(
+                        vec![bind],
+                        Box::new(apply(concat_map, vec![Identifier(func_ident), list].into_iter())),
+                    )
+                }
+            }
+        }
+        ///Translates
+        ///(e1, , e3) = \x -> (,,) e1 x e3
+        fn translate_tuple_section(
+            &mut self,
+            typ: TcType,
+            elements: Vec<Option<module::TypedExpr<Name>>>,
+        ) -> Expr<Id<Name>> {
+            let mut tuple_type = &typ;
+            let mut args = vec![];
+            let mut arguments = vec![];
+            for element in elements.into_iter() {
+                match element {
+                    Some(e) => arguments.push(self.translate_expr(e)),
+                    None => {
+                        let arg_type = tuple_type.appl().appr().clone();
+                        tuple_type = tuple_type.appr();
+                        let arg = Id::new(self.name_supply.from_str("x"), arg_type, vec![]);
+                        arguments.push(Identifier(arg.clone()));
+                        args.push(arg);
+                    }
+                }
+            }
+            let func_type = arguments
+                .iter()
+                .rev()
+                .fold(tuple_type.clone(), |result, arg| {
+                    function_type_(arg.get_type().clone(), result)
+                });
+            let name = Name {
+                name: intern(tuple_name(arguments.len()).as_ref()),
+                uid: 0,
+            };
+            let body = apply(
+                Identifier(Id::new(name, func_type, vec![])),
+                arguments.into_iter(),
+            );
+            self.section_lambda(typ, args, body)
+        }
+        ///Translates a constructor which is applied to fewer arguments than it has fields
+        ///C e1 = \x -> C e1 x
+        fn translate_partial_constructor(
+            &mut self,
+            input_expr: module::TypedExpr<Name>,
+        ) -> Expr<Id<Name>> {
+            let typ = input_expr.typ.clone();
+            let mut args = vec![];
+            {
+                let mut arg_type = &typ;
+                while let Some((arg, result)) = try_get_function(arg_type) {
+                    args.push(Id::new(self.name_supply.from_str("x"), arg.clone(), vec![]));
+                    arg_type = result;
+                }
             }
+            let body = apply(
+                self.translate_function(input_expr),
+                args.iter().map(|arg| Identifier(arg.clone())),
+            );
+            self.section_lambda(typ, args, body)
+        }
+        ///Translates the function of an application. A constructor in it is applied to the
+        ///arguments of the enclosing applications so it must not be expanded into a lambda
+        fn translate_function(&mut self, input_expr: module::TypedExpr<Name>) -> Expr<Id<Name>> {
+            match input_expr.expr {
+                module::Expr::Apply(..) | module::Expr::Identifier(_) => {
+                    let module::TypedExpr { typ, expr, .. } = input_expr;
+                    match expr {
+                        module::Expr::Apply(func, arg) => Apply(
+                            Box::new(self.translate_function(*func)),
+                            Box::new(self.translate_expr(*arg)),
+                        ),
+                        module::Expr::Identifier(s) => Identifier(Id::new(s, typ, vec![])),
+                        _ => unreachable!(),
+                    }
+                }
+                _ => self.translate_expr(input_expr),
+            }
+        }
+        ///Creates a lambda taking `args` which is bound by a let binding so that it can be lifted
+        ///let #section = \args -> body in #section
+        fn section_lambda(
+            &mut self,
+            typ: TcType,
+            args: Vec<Id<Name>>,
+            body: Expr<Id<Name>>,
+        ) -> Expr<Id<Name>> {
+            let lambda = args
+                .into_iter()
+                .rev()
+                .fold(body, |body, arg| Lambda(arg, body.into()));
+            let id = Id::new(self.name_supply.from_str("#section"), typ, vec![]);
+            let bind = Binding {
+                name: id.clone(),
+                expression: lambda,
+            };
+            Let(vec![bind], Box::new(Identifier(id)))
         }
         ///Translates
         ///do { expr; stmts } = expr >> do { stmts; }
@@ -884,104 +1264,98 @@ pub mod translate {
             result
         }
 
-        fn unwrap_pattern(
-            &mut self,
-            uid: usize,
-            id: Id<Name>,
-            pattern: module::Pattern<Name>,
-            result: &mut Vec<(Id<Name>, Pattern<Id<Name>>)>,
-        ) {
-            match pattern {
-                module::Pattern::Constructor(ctor_name, mut patterns) => {
-                    let index = result.len();
-                    let mut name = id.name.name.to_string();
-                    let base_length = name.len();
-                    result.push((id, Pattern::Number(0))); //Dummy
-                    for (i, p) in patterns.iter_mut().enumerate() {
-                        let x = match *p {
-                            module::Pattern::Constructor(..) | module::Pattern::Number(..) => {
-                                //HACK, by making the variable have the same uid as
-                                //the index the newly generated pattern will be recognized
-                                //as the same since their binding variable are the same
-                                name.truncate(base_length);
-                                name.push('_');
-                                name.push_str(&*i.to_string());
-
-                                let n = Name {
-                                    name: intern(name.as_ref()),
-                                    uid,
-                                };
-                                Some(module::Pattern::Identifier(n))
-                            }
-                            _ => None,
-                        };
-                        match x {
-                            Some(mut x) => {
-                                ::std::mem::swap(p, &mut x);
-                                let id = match *p {
-                                    module::Pattern::Identifier(ref n) => {
-                                        Id::new(n.clone(), "a".into(), vec![])
-                                    }
-                                    _ => panic!(),
-                                };
-                                self.unwrap_pattern(uid, id, x, result);
-                            }
-                            None => (),
-                        }
-                    }
-                    result[index].1 =
-                        self.translate_pattern(module::Pattern::Constructor(ctor_name, patterns));
-                }
-                _ => result.push((id, self.translate_pattern(pattern))),
-            }
-        }
-        ///Translates a pattern list of patterns into a list of patterns which are not nested.
-        ///The first argument of each tuple is the identifier that is expected to be passed to the case.
-        fn unwrap_patterns(
+        ///Creates the binding `variable = case id of pattern -> variable` which matches the
+        ///irrefutable `pattern` only once `variable` is demanded
+        fn lazy_binding(
             &mut self,
-            uid: usize,
-            arg_ids: &[Id<Name>],
-            arguments: &[module::Pattern<Name>],
-        ) -> Vec<(Id<Name>, Pattern<Id<Name>>)> {
-            let mut result = vec![];
-            for (p, id) in arguments.iter().zip(arg_ids.iter()) {
-                self.unwrap_pattern(uid, id.clone(), p.clone(), &mut result);
+            id: &Id<Name>,
+            pattern: &module::Pattern<Name>,
+            variable: Name,
+        ) -> Binding<Id<Name>> {
+            let location = Location::eof();
+            let scrutinee = module::TypedExpr {
+                expr: module::Expr::Identifier(id.name),
+                typ: id.typ.value.clone(),
+                location,
+            };
+            let alternative = module::Alternative {
+                pattern: Located {
+                    location,
+                    node: pattern.clone(),
+                },
+                matches: module::Match::Simple(module::TypedExpr::with_location(
+                    module::Expr::Identifier(variable),
+                    location,
+                )),
+                where_bindings: None,
+            };
+            let fallback = error("a".into(), "Irrefutable pattern failed");
+            let expression = self.translate_alternatives(scrutinee, vec![alternative], &fallback);
+            Binding {
+                name: Id::new(variable, "a".into(), vec![]),
+                expression,
             }
-            result
         }
 
         ///Translates a case expression into the core language.
-        ///Since the core language do not have nested patterns the patterns are unwrapped into
-        ///multiple case expressions.
+        ///Since the core language do not have nested patterns the alternatives are compiled
+        ///into a decision tree of case expressions.
         fn translate_case(
             &mut self,
             expr: module::TypedExpr<Name>,
             alts: Vec<module::Alternative<Name>>,
         ) -> Expr<Id<Name>> {
-            let mut vec = vec![];
-            let dummy_var = &[Id::new(self.name_supply.anonymous(), "a".into(), vec![])];
-            let uid = self.name_supply.next_id();
-            for module::Alternative {
-                pattern,
-                matches,
-                where_bindings,
-            } in alts.into_iter()
-            {
-                let bindings = where_bindings.map_or(vec![], |bs| self.translate_bindings(bs));
-                vec.push((
-                    self.unwrap_patterns(uid, dummy_var, &[pattern.node]),
-                    bindings,
-                    matches,
-                ));
-            }
-            let mut x = self.translate_equations_(vec);
-            match x {
-                Case(ref mut body, _) => {
-                    **body = self.translate_expr(expr);
+            let fallback = error("a".into(), "Non-exhaustive patterns in case");
+            self.translate_alternatives(expr, alts, &fallback)
+        }
+
+        ///Translates the alternatives of a case expression, evaluating `fallback` if none of
+        ///them match
+        fn translate_alternatives(
+            &mut self,
+            expr: module::TypedExpr<Name>,
+            alts: Vec<module::Alternative<Name>>,
+            fallback: &Expr<Id<Name>>,
+        ) -> Expr<Id<Name>> {
+            let dummy_var = Id::new(self.name_supply.anonymous(), expr.typ.clone(), vec![]);
+            let rows = alts
+                .into_iter()
+                .map(|alt| {
+                    let where_bindings = alt
+                        .where_bindings
+                        .map_or(vec![], |bs| self.translate_bindings(bs));
+                    Row {
+                        patterns: vec![(dummy_var.clone(), alt.pattern.node)],
+                        bindings: vec![],
+                        strict: vec![],
+                        where_bindings,
+                        matches: alt.matches,
+                    }
+                })
+                .collect();
+            let mut x = self.compile_rows(rows, fallback);
+            let expr = self.translate_expr(expr);
+            //The expression can be matched directly unless the patterns refer to the matched
+            //value, such as with as-patterns or irrefutable patterns
+            let mut occurrences = Occurrences {
+                name: dummy_var.name,
+                count: 0,
+            };
+            ref_::Visitor::visit_expr(&mut occurrences, &x);
+            let direct = occurrences.count == 1
+                && matches!(x, Case(ref body, _) if **body == Identifier(dummy_var.clone()));
+            if direct {
+                if let Case(ref mut body, _) = x {
+                    **body = expr;
                 }
-                _ => panic!("Not case"),
+                x
+            } else {
+                let bind = Binding {
+                    name: dummy_var,
+                    expression: expr,
+                };
+                Let(vec![bind], Box::new(x))
             }
-            x
         }
         ///Translates a binding group such as
         ///map f (x:xs) = e1
@@ -1049,10 +1423,7 @@ pub mod translate {
                     };
                 }
             }
-            //First we flatten all the patterns that occur in each equation
-            //(2:xs) -> [(x:xs), 2]
-            let uid = self.name_supply.next_id();
-            let equations: Vec<_> = bindings
+            let rows = bindings
                 .into_iter()
                 .map(|bind| {
                     let module::Binding {
@@ -1061,16 +1432,18 @@ pub mod translate {
                         where_bindings,
                         ..
                     } = bind;
-                    let where_bindings_binds =
-                        where_bindings.map_or(vec![], |bs| self.translate_bindings(bs));
-                    (
-                        self.unwrap_patterns(uid, arg_ids.as_ref(), &*arguments),
-                        where_bindings_binds,
+                    Row {
+                        patterns: arg_ids.iter().cloned().zip(arguments).collect(),
+                        bindings: vec![],
+                        strict: vec![],
+                        where_bindings: where_bindings
+                            .map_or(vec![], |bs| self.translate_bindings(bs)),
                         matches,
-                    )
+                    }
                 })
                 .collect();
-            let mut expr = self.translate_equations_(equations);
+            let message = format!("Non-exhaustive patterns in function {}", name.as_ref());
+            let mut expr = self.compile_rows(rows, &error("a".into(), &message));
             expr = make_lambda(arg_ids.into_iter(), expr);
             debug!("Desugared {} :: {}\n {}", name.name, name.typ, expr);
             Binding {
@@ -1078,24 +1451,6 @@ pub mod translate {
                 expression: expr,
             }
         }
-        fn translate_equations_(
-            &mut self,
-            equations: Vec<(
-                Vec<(Id<Name>, Pattern<Id<Name>>)>,
-                Vec<Binding<Id<Name>>>,
-                module::Match<Name>,
-            )>,
-        ) -> Expr<Id<Name>> {
-            let mut eqs: Vec<Equation> = vec![];
-            for &(ref ps, ref bs, ref e) in equations.iter() {
-                eqs.push(Equation(ps.as_ref(), (bs.as_ref(), e)));
-            }
-            for e in eqs.iter() {
-                debug!("{:?}", e);
-            }
-            self.translate_equations(eqs.as_ref())
-        }
-
         ///Translates a list of guards, if no guards matches then the result argument will be the result
         fn translate_guards(
             &mut self,
@@ -1103,175 +1458,310 @@ pub mod translate {
             guards: &[module::Guard<Name>],
         ) -> Expr<Id<Name>> {
             for guard in guards.iter().rev() {
-                let predicate = Box::new(self.translate_expr(guard.predicate.clone()));
-                result = Case(
-                    predicate,
+                let qualifiers = guard.qualifiers.clone();
+                let expression = guard.expression.clone();
+                result = self.share_fallback(
+                    |_| result,
+                    |this, fallback| this.translate_qualifiers(qualifiers, expression, fallback),
+                );
+            }
+            result
+        }
+
+        ///Translates the qualifiers of a guard followed by its `expression`.
+        ///Every qualifier which fails, a false predicate or a pattern which does not match,
+        ///evaluates `fallback` instead
+        fn translate_qualifiers(
+            &mut self,
+            mut qualifiers: Vec<module::Qualifier<Name>>,
+            expression: module::TypedExpr<Name>,
+            fallback: &Expr<Id<Name>>,
+        ) -> Expr<Id<Name>> {
+            if qualifiers.is_empty() {
+                return self.translate_expr(expression);
+            }
+            match qualifiers.remove(0) {
+                module::Qualifier::Guard(predicate) => Case(
+                    Box::new(self.translate_expr(predicate)),
                     vec![
                         Alternative {
                             pattern: bool_pattern("True"),
-                            expression: self.translate_expr(guard.expression.clone()),
+                            expression: self.translate_qualifiers(qualifiers, expression, fallback),
                         },
                         Alternative {
                             pattern: bool_pattern("False"),
-                            expression: result,
+                            expression: fallback.clone(),
                         },
                     ],
-                );
+                ),
+                module::Qualifier::Let(bindings) => {
+                    let bindings = self.translate_bindings(bindings);
+                    make_let(bindings, self.translate_qualifiers(qualifiers, expression, fallback))
+                }
+                module::Qualifier::Generator(pattern, expr) => {
+                    //The rest of the qualifiers become a guard of an alternative which matches
+                    //the pattern so they fall through to `fallback` as well
+                    let alt = module::Alternative {
+                        pattern,
+                        matches: module::Match::Guards(vec![module::Guard {
+                            qualifiers,
+                            expression,
+                        }]),
+                        where_bindings: None,
+                    };
+                    self.translate_alternatives(expr, vec![alt], fallback)
+                }
             }
-            result
         }
 
-        fn translate_equations(&mut self, equations: &[Equation]) -> Expr<Id<Name>> {
-            ///Returns true if the two patterns would match for the same values
-            fn matching<T: PartialEq>(lhs: &(T, Pattern<T>), rhs: &(T, Pattern<T>)) -> bool {
-                lhs.0 == rhs.0
-                    && match (&lhs.1, &rhs.1) {
-                        (&Pattern::Constructor(ref l, _), &Pattern::Constructor(ref r, _)) => {
-                            *l == *r
-                        }
-                        (&Pattern::Constructor(..), &Pattern::Number(..))
-                        | (&Pattern::Number(..), &Pattern::Constructor(..)) => false,
-                        _ => true,
-                    }
-            }
-            debug!("In {:?}", equations);
-            let &Equation(ps, (where_bindings_bindings, e)) = &equations[0];
-            if ps.is_empty() {
-                assert_eq!(equations.len(), 1); //Otherwise multiple matches for this group
-                let bindings = where_bindings_bindings.iter().map(|x| x.clone()).collect();
-                return make_let(bindings, self.translate_match((*e).clone()));
+        ///Compiles the rows into a decision tree of case expressions which evaluates the guards
+        ///or right hand side of the first matching row, or `fallback` if no row matches.
+        ///Patterns are tested from left to right, starting with the first row. The variable
+        ///tested by the first row is tested by a single case expression for all of the rows so
+        ///that no variable is tested twice on the same path
+        fn compile_rows(&mut self, mut rows: Vec<Row>, fallback: &Expr<Id<Name>>) -> Expr<Id<Name>> {
+            if rows.is_empty() {
+                return fallback.clone();
             }
-            if ps.len() == 1 {
-                let mut alts: Vec<Alternative<Id<Name>>> = vec![];
-                for (i, &Equation(ps, (where_bindings_bindings, m))) in equations.iter().enumerate()
-                {
-                    let bindings = where_bindings_bindings.iter().map(|x| x.clone()).collect();
-                    match *m {
-                        module::Match::Simple(ref e) => {
-                            let pattern =
-                                ps.first().map(|x| x.1.clone()).unwrap_or(Pattern::WildCard);
-
-                            alts.push(Alternative {
-                                pattern,
-                                expression: make_let(bindings, self.translate_expr((*e).clone())),
-                            });
-                        }
-                        module::Match::Guards(ref guards) => {
-                            let fallthrough = if equations.len() == i + 1 {
-                                unmatched_guard()
-                            } else {
-                                self.translate_equations(&equations[i + 1..])
-                            };
-                            alts.push(Alternative {
-                                pattern: ps[0].1.clone(),
-                                expression: make_let(
-                                    bindings,
-                                    self.translate_guards(fallthrough, guards),
-                                ),
-                            });
-                        }
-                    }
-                }
-                let body = Box::new(Identifier(ps[0].0.clone()));
-                return Case(body, alts);
+            while !rows[0].patterns.is_empty() && !self.simplify_pattern(&mut rows[0], 0) {}
+            if rows[0].patterns.is_empty() {
+                let rest = rows.split_off(1);
+                let row = rows.pop().unwrap();
+                return self.with_fallback(rest, fallback, |this, fallback| {
+                    this.translate_row(row, fallback)
+                });
             }
+            let id = rows[0].patterns[0].0.clone();
+            self.compile_group(id, rows, fallback)
+        }
 
-            let mut last_index = 0;
-            let mut vec: Vec<Equation> = vec![];
-            let mut alts: Vec<Alternative<Id<Name>>> = vec![];
-            let mut visited = vec![];
+        ///Removes variables, as-patterns, bang patterns and irrefutable patterns from the pattern
+        ///at `index`, adding what they bind to the row.
+        ///Returns true if the remaining pattern does a test, otherwise it is removed from the row
+        fn simplify_pattern(&mut self, row: &mut Row, index: usize) -> bool {
             loop {
-                //Find the first pattern which does a test and is not already used
-                let mut pattern_test = None;
-                while last_index < equations.len() {
-                    let &Equation(ps, _) = &equations[last_index];
-                    if let Some(head) = ps.first() {
-                        match head.1 {
-                            Pattern::Constructor(..) | Pattern::Number(..) => {
-                                if visited.iter().find(|x| matching(**x, &head)).is_none() {
-                                    pattern_test = Some(head);
-                                    visited.push(&head);
-                                    last_index += 1;
-                                    break;
-                                }
+                let id = row.patterns[index].0.clone();
+                let pattern = ::std::mem::replace(
+                    &mut row.patterns[index].1,
+                    module::Pattern::WildCard,
+                );
+                row.patterns[index].1 = match pattern {
+                    module::Pattern::Identifier(name) => {
+                        bind_variable(row, name, &id);
+                        module::Pattern::WildCard
+                    }
+                    module::Pattern::As(name, pattern) => {
+                        bind_variable(row, name, &id);
+                        *pattern
+                    }
+                    module::Pattern::Bang(pattern) => {
+                        row.strict.push(id);
+                        *pattern
+                    }
+                    module::Pattern::Irrefutable(pattern) => {
+                        if !refutable(&pattern) {
+                            *pattern
+                        } else {
+                            let mut variables = PatternVariables(vec![]);
+                            module::Visitor::visit_pattern(&mut variables, &pattern);
+                            for variable in variables.0 {
+                                let binding = self.lazy_binding(&id, &pattern, variable);
+                                row.bindings.push(binding);
                             }
-                            _ => (),
+                            module::Pattern::WildCard
                         }
                     }
-                    last_index += 1;
+                    module::Pattern::Record(name, fields) => self.record_pattern(name, fields),
+                    module::Pattern::String(s) => string_pattern(s.as_ref()),
+                    module::Pattern::WildCard => {
+                        row.patterns.remove(index);
+                        return false;
+                    }
+                    pattern => {
+                        row.patterns[index].1 = pattern;
+                        return true;
+                    }
+                };
+            }
+        }
+
+        ///Calls `f` with an expression which matches the `rest` of the rows, or which is
+        ///`fallback` if there are no rows left.
+        ///The rows are only compiled once, if `f` refers to them more than once they are shared
+        ///through a let binding
+        fn with_fallback<F>(
+            &mut self,
+            rest: Vec<Row>,
+            fallback: &Expr<Id<Name>>,
+            f: F,
+        ) -> Expr<Id<Name>>
+        where
+            F: FnOnce(&mut Self, &Expr<Id<Name>>) -> Expr<Id<Name>>,
+        {
+            if rest.is_empty() {
+                return f(self, fallback);
+            }
+            self.share_fallback(|this| this.compile_rows(rest, fallback), f)
+        }
+
+        ///Calls `f` with an expression which evaluates the expression created by `fallback`.
+        ///`fallback` is only created if `f` refers to it and if it is referred to more than once
+        ///it is shared through a let binding, unless it is a literal or variable which are
+        ///cheaper to copy
+        fn share_fallback<F, G>(&mut self, fallback: G, f: F) -> Expr<Id<Name>>
+        where
+            F: FnOnce(&mut Self, &Expr<Id<Name>>) -> Expr<Id<Name>>,
+            G: FnOnce(&mut Self) -> Expr<Id<Name>>,
+        {
+            let fail = Id::new(self.name_supply.from_str("fail"), "a".into(), vec![]);
+            let mut expr = f(self, &Identifier(fail.clone()));
+            let mut occurrences = Occurrences {
+                name: fail.name,
+                count: 0,
+            };
+            ref_::Visitor::visit_expr(&mut occurrences, &expr);
+            if occurrences.count == 0 {
+                return expr;
+            }
+            let fallback = fallback(self);
+            if occurrences.count == 1 || matches!(fallback, Literal(_) | Identifier(_)) {
+                let mut substitute = Substitute {
+                    name: fail.name,
+                    expr: fallback,
+                };
+                mutable::Visitor::visit_expr(&mut substitute, &mut expr);
+                expr
+            } else {
+                let bind = Binding {
+                    name: fail,
+                    expression: fallback,
+                };
+                Let(vec![bind], Box::new(expr))
+            }
+        }
+
+        ///Translates the guards or right hand side of a row which has matched all its patterns.
+        ///If none of the guards are true `fallback` is evaluated
+        fn translate_row(&mut self, row: Row, fallback: &Expr<Id<Name>>) -> Expr<Id<Name>> {
+            let Row {
+                mut bindings,
+                strict,
+                where_bindings,
+                matches,
+                ..
+            } = row;
+            let expr = match force_variables(&strict, matches) {
+                module::Match::Simple(e) => self.translate_expr(e),
+                module::Match::Guards(ref guards) => {
+                    self.translate_guards(fallback.clone(), guards)
                 }
-                if let Some(pattern_test) = pattern_test {
-                    vec.clear();
-                    let mut variable_bindings = vec![];
-                    //Gather all patterns which matches the pattern
-                    for &Equation(patterns, expr) in equations.iter() {
-                        if !patterns.is_empty() && matching(pattern_test, &patterns[0]) {
-                            vec.push(Equation(&patterns[1..], expr));
-                            //If the patter_test is a constructor we need to add the variables
-                            //of the other patterns in a let binding to make sure that all names exist
-                            match (&patterns[0].1, &pattern_test.1) {
-                                (
-                                    &Pattern::Constructor(_, ref l_vars),
-                                    &Pattern::Constructor(_, ref r_vars),
-                                ) => {
-                                    for (l_var, r_var) in l_vars.iter().zip(r_vars.iter()) {
-                                        if l_var != r_var {
-                                            variable_bindings.push(Binding {
-                                                name: l_var.clone(),
-                                                expression: Identifier(r_var.clone()),
-                                            });
-                                        }
-                                    }
-                                }
-                                _ => (),
+            };
+            bindings.extend(where_bindings);
+            make_let(bindings, expr)
+        }
+
+        ///Compiles the rows into a case expression on `id`.
+        ///Each alternative matches the rows with the same constructor or literal as well as the
+        ///rows which match any value of `id`. If the alternatives do not cover every value the
+        ///last alternative matches only the rows which match any value
+        fn compile_group(
+            &mut self,
+            id: Id<Name>,
+            rows: Vec<Row>,
+            fallback: &Expr<Id<Name>>,
+        ) -> Expr<Id<Name>> {
+            let mut groups: Vec<(Pattern<Id<Name>>, Vec<Row>)> = vec![];
+            //The rows which match any value of `id`
+            let mut defaults: Vec<Row> = vec![];
+            for mut row in rows {
+                let index = row.patterns.iter().position(|(other, _)| other.name == id.name);
+                let index = match index {
+                    Some(index) if self.simplify_pattern(&mut row, index) => index,
+                    _ => {
+                        for (_, group) in groups.iter_mut() {
+                            group.push(row.clone());
+                        }
+                        defaults.push(row);
+                        continue;
+                    }
+                };
+                let (_, pattern) = row.patterns.remove(index);
+                match pattern {
+                    module::Pattern::Constructor(name, arguments) => {
+                        let group = groups.iter().position(|(p, _)| {
+                            matches!(*p, Pattern::Constructor(ref c, _) if c.name == name)
+                        });
+                        let group = group.unwrap_or_else(|| {
+                            //Reuse the name of a variable pattern so it does not need to be bound
+                            let ids = arguments
+                                .iter()
+                                .map(|argument| {
+                                    let name = match *argument {
+                                        module::Pattern::Identifier(name) => name,
+                                        _ => self.name_supply.anonymous(),
+                                    };
+                                    Id::new(name, "a".into(), vec![])
+                                })
+                                .collect();
+                            let ctor = Id::new(name, "a".into(), vec![]);
+                            groups.push((Pattern::Constructor(ctor, ids), defaults.clone()));
+                            groups.len() - 1
+                        });
+                        if let Pattern::Constructor(_, ref ids) = groups[group].0 {
+                            let arguments = ids.iter().cloned().zip(arguments);
+                            row.patterns.splice(index..index, arguments);
+                        }
+                        groups[group].1.push(row);
+                    }
+                    pattern => {
+                        let pattern = self.translate_pattern(pattern);
+                        match groups.iter_mut().find(|(p, _)| *p == pattern) {
+                            Some((_, group)) => group.push(row),
+                            None => {
+                                let mut group = defaults.clone();
+                                group.push(row);
+                                groups.push((pattern, group));
                             }
-                        } else if patterns.is_empty() {
-                            vec.push(Equation(patterns, expr));
                         }
                     }
-                    //For all the pattern that match the pattern we need to generate new case expressions
-                    let e = make_let(variable_bindings, self.translate_equations(vec.as_ref()));
-
-                    let arg_id = &ps[0].0;
-                    let bs = needed_variables(arg_id, equations);
-                    alts.push(Alternative {
-                        pattern: pattern_test.1.clone(),
-                        expression: make_let(bs, e),
-                    });
-                } else {
-                    break;
                 }
             }
-            if alts.is_empty() {
-                for &Equation(patterns, expr) in equations.iter() {
-                    vec.push(Equation(&patterns[1..], expr));
-                }
-                let &Equation(ps, _) = &equations[0];
-                let arg_id = &ps[0].0;
-                let bs = needed_variables(arg_id, equations);
-                make_let(bs, self.translate_equations(vec.as_ref()))
-            } else {
-                let defaults: Vec<Equation> = equations
-                    .iter()
-                    .filter(|&&Equation(ps, _)| {
-                        !ps.is_empty()
-                            && matches!(ps[0].1, Pattern::WildCard | Pattern::Identifier(..))
-                    })
-                    .map(|&Equation(ps, e)| Equation(&ps[1..], e))
-                    .collect();
-                if !defaults.is_empty() {
-                    let arg_id = &ps[0].0;
-                    let bs = needed_variables(arg_id, equations);
-                    let e = make_let(bs, self.translate_equations(defaults.as_ref()));
-                    alts.push(Alternative {
-                        pattern: Pattern::WildCard,
-                        expression: e,
-                    });
-                }
-                let &Equation(ps, _) = &equations[0];
-                let body = Box::new(Identifier(ps[0].0.clone()));
-                Case(body, alts)
+            let complete = self.is_complete(&groups);
+            let mut alts: Vec<_> = groups
+                .into_iter()
+                .map(|(pattern, rows)| Alternative {
+                    pattern,
+                    expression: self.compile_rows(rows, fallback),
+                })
+                .collect();
+            if !complete {
+                alts.push(Alternative {
+                    pattern: Pattern::WildCard,
+                    expression: self.compile_rows(defaults, fallback),
+                });
             }
+            Case(Box::new(Identifier(id)), alts)
+        }
+
+        ///Returns true if the constructor patterns in `groups` match every value of their type
+        fn is_complete(&self, groups: &[(Pattern<Id<Name>>, Vec<Row>)]) -> bool {
+            let name = match groups.first() {
+                Some(&(Pattern::Constructor(ref ctor, _), _)) => ctor.name,
+                _ => return false,
+            };
+            let count = match name.as_ref() {
+                "[]" | ":" => 2,
+                s if s.starts_with('(') => 1,
+                _ => match self
+                    .data_definitions
+                    .iter()
+                    .find(|data| data.constructors.iter().any(|ctor| ctor.name == name))
+                {
+                    Some(data) => data.constructors.len(),
+                    None => return false,
+                },
+            };
+            groups.len() == count
         }
 
         fn translate_pattern(&mut self, pattern: module::Pattern<Name>) -> Pattern<Id<Name>> {
@@ -1280,6 +1770,8 @@ pub mod translate {
                     Pattern::Identifier(Id::new(i, "a".into(), vec![]))
                 }
                 module::Pattern::Number(n) => Pattern::Number(n),
+                module::Pattern::Fractional(f) => Pattern::Fractional(f),
+                module::Pattern::Char(c) => Pattern::Char(c),
                 module::Pattern::Constructor(name, patterns) => {
                     let ps = patterns
                         .into_iter()
@@ -1298,8 +1790,142 @@ pub mod translate {
                         .collect();
                     Pattern::Constructor(Id::new(name, "a".into(), vec![]), ps)
                 }
+                module::Pattern::Record(name, fields) => {
+                    let pattern = self.record_pattern(name, fields);
+                    self.translate_pattern(pattern)
+                }
                 module::Pattern::WildCard => Pattern::WildCard,
+                module::Pattern::String(..)
+                | module::Pattern::As(..)
+                | module::Pattern::Irrefutable(..)
+                | module::Pattern::Bang(..) => panic!("Nested pattern"),
+            }
+        }
+
+        fn find_constructor(&self, name: Name) -> &module::Constructor<Name> {
+            self.data_definitions
+                .iter()
+                .flat_map(|data| data.constructors.iter())
+                .find(|ctor| ctor.name == name)
+                .unwrap_or_else(|| panic!("Could not find constructor {:?}", name))
+        }
+
+        ///Turns C { field = p } into a constructor pattern with wildcards for the fields that
+        ///are not matched
+        fn record_pattern(
+            &self,
+            name: Name,
+            fields: Vec<(Name, module::Pattern<Name>)>,
+        ) -> module::Pattern<Name> {
+            let ctor = self.find_constructor(name);
+            let mut patterns: Vec<module::Pattern<Name>> = (0..ctor.arity)
+                .map(|_| module::Pattern::WildCard)
+                .collect();
+            for (field, pattern) in fields.into_iter() {
+                let index = ctor.fields.iter().position(|f| *f == field).unwrap();
+                patterns[index] = pattern;
+            }
+            module::Pattern::Constructor(name, patterns)
+        }
+
+        ///Translates
+        ///C { f2 = e } = C (error "Missing field") e
+        fn translate_record(
+            &mut self,
+            typ: TcType,
+            name: Name,
+            fields: Vec<(Name, module::TypedExpr<Name>)>,
+        ) -> Expr<Id<Name>> {
+            let ctor = self.find_constructor(name).clone();
+            let mut arguments: Vec<Option<Expr<Id<Name>>>> = (0..ctor.arity).map(|_| None).collect();
+            for (field, value) in fields.into_iter() {
+                let index = ctor.fields.iter().position(|f| *f == field).unwrap();
+                arguments[index] = Some(self.translate_expr(value));
+            }
+            let arguments: Vec<Expr<Id<Name>>> = arguments
+                .into_iter()
+                .zip(lambda_iterator(&ctor.typ.value))
+                .map(|(argument, arg_type)| {
+                    argument.unwrap_or_else(|| {
+                        error(arg_type.clone(), "Missing field in record construction")
+                    })
+                })
+                .collect();
+            let func_type = arguments
+                .iter()
+                .rev()
+                .fold(typ, |result, arg| function_type_(arg.get_type().clone(), result));
+            apply(Identifier(Id::new(name, func_type, vec![])), arguments.into_iter())
+        }
+
+        ///Translates
+        ///e { f2 = x } = case e of { C1 a b -> C1 a x; C2 a b c -> C2 a x c; _ -> error "..." }
+        ///where C1 and C2 are all the constructors which have every updated field
+        fn translate_record_update(
+            &mut self,
+            typ: TcType,
+            record: module::TypedExpr<Name>,
+            fields: Vec<(Name, module::TypedExpr<Name>)>,
+        ) -> Expr<Id<Name>> {
+            let (constructors, all_constructors) = {
+                let data = fields.first().and_then(|(field, _)| {
+                    self.data_definitions.iter().find(|data| {
+                        data.constructors
+                            .iter()
+                            .any(|ctor| ctor.fields.contains(field))
+                    })
+                });
+                let data = match data {
+                    Some(data) => data,
+                    //Unknown fields are reported by the typechecker and an update without any
+                    //fields leaves the record as it is
+                    None => return self.translate_expr(record),
+                };
+                let constructors: Vec<module::Constructor<Name>> = data
+                    .constructors
+                    .iter()
+                    .filter(|ctor| fields.iter().all(|&(ref f, _)| ctor.fields.contains(f)))
+                    .cloned()
+                    .collect();
+                let all_constructors = constructors.len() == data.constructors.len();
+                (constructors, all_constructors)
+            };
+            let mut alts = vec![];
+            for ctor in constructors.into_iter() {
+                let ids: Vec<Id<Name>> = ctor
+                    .fields
+                    .iter()
+                    .map(|_| Id::new(self.name_supply.from_str("#field"), "a".into(), vec![]))
+                    .collect();
+                let arguments: Vec<Expr<Id<Name>>> = ids
+                    .iter()
+                    .zip(ctor.fields.iter())
+                    .map(|(id, field)| match fields.iter().find(|&&(ref f, _)| f == field) {
+                        Some(&(_, ref value)) => self.translate_expr(value.clone()),
+                        None => Identifier(id.clone()),
+                    })
+                    .collect();
+                let func_type = arguments
+                    .iter()
+                    .rev()
+                    .fold(typ.clone(), |result, arg| {
+                        function_type_(arg.get_type().clone(), result)
+                    });
+                alts.push(Alternative {
+                    pattern: Pattern::Constructor(Id::new(ctor.name, "a".into(), vec![]), ids),
+                    expression: apply(
+                        Identifier(Id::new(ctor.name, func_type, vec![])),
+                        arguments.into_iter(),
+                    ),
+                });
+            }
+            if !all_constructors {
+                alts.push(Alternative {
+                    pattern: Pattern::WildCard,
+                    expression: error(typ, "No match in record update"),
+                });
             }
+            Case(Box::new(self.translate_expr(record)), alts)
         }
     }
 
@@ -1337,6 +1963,23 @@ pub mod translate {
     fn lambda_iterator<'a, Id: AsRef<str>>(typ: &'a Type<Id>) -> LambdaIterator<'a, Id> {
         LambdaIterator { typ }
     }
+    ///Tests if the expression is a constructor which is applied to fewer arguments than it has
+    ///fields. Since a constructor is never a function itself any argument left in the type of
+    ///the expression is a missing field
+    fn missing_constructor_arguments(expr: &module::TypedExpr<Name>) -> bool {
+        let mut func = expr;
+        while let module::Expr::Apply(ref f, _) = func.expr {
+            func = f;
+        }
+        match func.expr {
+            module::Expr::Identifier(ref name) => {
+                let is_constructor = name.name.starts_with(':')
+                    || name.name.chars().next().is_some_and(|c| c.is_uppercase());
+                is_constructor && try_get_function(&expr.typ).is_some()
+            }
+            _ => false,
+        }
+    }
     ///Tests that the binding has no patterns for its arguments
     fn simple_binding(binding: &module::Binding<Name>) -> bool {
         binding.arguments.iter().all(|arg| {
@@ -1347,6 +1990,138 @@ pub mod translate {
         })
     }
 
+    ///Returns true if matching `pattern` can fail
+    fn refutable(pattern: &module::Pattern<Name>) -> bool {
+        match *pattern {
+            module::Pattern::Identifier(..)
+            | module::Pattern::WildCard
+            | module::Pattern::Irrefutable(..) => false,
+            module::Pattern::As(_, ref pattern) | module::Pattern::Bang(ref pattern) => {
+                refutable(pattern)
+            }
+            _ => true,
+        }
+    }
+
+    ///Turns a string pattern into the equivalent list of character patterns
+    fn string_pattern(s: &str) -> module::Pattern<Name> {
+        let nil = module::Pattern::Constructor("[]".into(), vec![]);
+        s.chars().rev().fold(nil, |tail, c| {
+            module::Pattern::Constructor(":".into(), vec![module::Pattern::Char(c), tail])
+        })
+    }
+
+    ///Collects the variables which are bound by a pattern
+    struct PatternVariables(Vec<Name>);
+    impl module::Visitor<Name> for PatternVariables {
+        fn visit_pattern(&mut self, pattern: &module::Pattern<Name>) {
+            match *pattern {
+                module::Pattern::Identifier(name) | module::Pattern::As(name, _) => {
+                    self.0.push(name)
+                }
+                _ => (),
+            }
+            module::walk_pattern(self, pattern)
+        }
+    }
+
+    ///Counts how many times the variable `name` is referred to
+    struct Occurrences {
+        name: Name,
+        count: usize,
+    }
+    impl ref_::Visitor<Id<Name>> for Occurrences {
+        fn visit_expr(&mut self, expr: &Expr<Id<Name>>) {
+            if let Identifier(ref id) = *expr {
+                if id.name == self.name {
+                    self.count += 1;
+                }
+            }
+            ref_::walk_expr(self, expr)
+        }
+    }
+
+    ///Replaces every occurrence of the variable `name` with `expr`
+    struct Substitute {
+        name: Name,
+        expr: Expr<Id<Name>>,
+    }
+    impl mutable::Visitor<Id<Name>> for Substitute {
+        fn visit_expr(&mut self, expr: &mut Expr<Id<Name>>) {
+            match *expr {
+                Identifier(ref id) if id.name == self.name => *expr = self.expr.clone(),
+                _ => mutable::walk_expr(self, expr),
+            }
+        }
+    }
+
+    ///Binds the variable `name` of a pattern to the matched variable `id`
+    fn bind_variable(row: &mut Row, name: Name, id: &Id<Name>) {
+        if name != id.name {
+            row.bindings.push(Binding {
+                name: Id::new(name, "a".into(), vec![]),
+                expression: Identifier(id.clone()),
+            });
+        }
+    }
+
+    ///Makes `matches` evaluate each of the `strict` variables with `seq` before its guards or
+    ///right hand side
+    fn force_variables(strict: &[Id<Name>], matches: module::Match<Name>) -> module::Match<Name> {
+        if strict.is_empty() {
+            return matches;
+        }
+        let force = |expr: module::TypedExpr<Name>| {
+            strict.iter().rev().fold(expr, |expr, id| {
+                let location = expr.location;
+                let typ = expr.typ.clone();
+                let variable_type = id.typ.value.clone();
+                let seq = module::TypedExpr {
+                    expr: module::Expr::Identifier("seq".into()),
+                    typ: function_type_(
+                        variable_type.clone(),
+                        function_type_(typ.clone(), typ.clone()),
+                    ),
+                    location,
+                };
+                let variable = module::TypedExpr {
+                    expr: module::Expr::Identifier(id.name),
+                    typ: variable_type,
+                    location,
+                };
+                let seq_variable = module::TypedExpr {
+                    expr: module::Expr::Apply(seq.into(), variable.into()),
+                    typ: function_type_(typ.clone(), typ.clone()),
+                    location,
+                };
+                module::TypedExpr {
+                    expr: module::Expr::Apply(seq_variable.into(), expr.into()),
+                    typ,
+                    location,
+                }
+            })
+        };
+        match matches {
+            module::Match::Simple(expr) => module::Match::Simple(force(expr)),
+            module::Match::Guards(mut guards) => {
+                //Let bindings are lazy so the variables are forced by the first qualifier which
+                //is evaluated or by the expression if there is no such qualifier
+                let guard = &mut guards[0];
+                let forced = guard
+                    .qualifiers
+                    .iter_mut()
+                    .find_map(|qualifier| match *qualifier {
+                        module::Qualifier::Guard(ref mut e)
+                        | module::Qualifier::Generator(_, ref mut e) => Some(e),
+                        module::Qualifier::Let(_) => None,
+                    })
+                    .unwrap_or(&mut guard.expression);
+                *forced = force(forced.clone());
+                module::Match::Guards(guards)
+            }
+        }
+    }
+
     ///Creates a lambda from an iterator of its arguments and body
     fn make_lambda<T, I: Iterator<Item = T>>(mut iter: I, body: Expr<T>) -> Expr<T> {
         match iter.next() {
@@ -1370,34 +2145,9 @@ pub mod translate {
         }
     }
 
-    ///Takes a id of the variable passed to the case and returns a vector
-    ///of bindings which need to be added to make sure no variables are missing
-    fn needed_variables(arg_id: &Id<Name>, equations: &[Equation]) -> Vec<Binding<Id<Name>>> {
-        equations
-            .iter()
-            .filter(|&&Equation(ps, _)| {
-                !ps.is_empty() && matches!(ps[0].1, Pattern::WildCard | Pattern::Identifier(..))
-            })
-            .map(|eq| {
-                let &Equation(ps, _) = eq;
-                let other_id = match ps[0].1 {
-                    Pattern::Identifier(ref name) => name.clone(),
-                    Pattern::WildCard => Id::new(
-                        Name {
-                            name: intern("_"),
-                            uid: usize::max_value(),
-                        },
-                        "a".into(),
-                        vec![],
-                    ),
-                    _ => panic!(),
-                };
-                Binding {
-                    name: other_id,
-                    expression: Identifier(arg_id.clone()),
-                }
-            })
-            .collect()
+    ///Creates an empty list with the type 'typ'
+    fn nil(typ: TcType) -> Expr<Id<Name>> {
+        Identifier(Id::new("[]".into(), typ, vec![]))
     }
     ///Creates a string literal expressions from a &str
     fn string(s: &str) -> Expr<Id<Name>> {
@@ -1415,4 +2165,72 @@ pub mod translate {
         ));
         Apply(error_ident.into(), Box::new(string("Unmatched guard")))
     }
+    ///Creates an expression of type `typ` which reports `message` as an error when executed
+    fn error(typ: TcType, message: &str) -> Expr<Id<Name>> {
+        let error_ident = Identifier(Id::new(
+            "error".into(),
+            function_type_(list_type(char_type()), typ),
+            vec![],
+        ));
+        Apply(error_ident.into(), Box::new(string(message)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{
+            ref_::*,
+            translate::translate_modules,
+            Expr::*,
+            *,
+        },
+        renamer::Name,
+        typecheck::typecheck_string,
+    };
+
+    ///Collects the variables which are tested by case expressions
+    struct Scrutinees(Vec<Name>);
+
+    impl Visitor<Id<Name>> for Scrutinees {
+        fn visit_expr(&mut self, expr: &Expr<Id<Name>>) {
+            if let Case(ref scrutinee, _) = *expr {
+                if let Identifier(ref id) = **scrutinee {
+                    self.0.push(id.name);
+                }
+            }
+            walk_expr(self, expr)
+        }
+    }
+
+    #[test]
+    fn decision_tree_tests_each_argument_once() {
+        let modules = typecheck_string(
+            r"
+import Prelude
+zipLength :: [Int] -> [Int] -> Int
+zipLength [] _ = 0
+zipLength _ [] = 0
+zipLength (x:xs) (y:ys) = 1 + zipLength xs ys
+",
+        )
+        .unwrap();
+        let modules = translate_modules(modules);
+        let bind = modules
+            .last()
+            .unwrap()
+            .bindings
+            .iter()
+            .find(|bind| bind.name.name.as_ref() == "zipLength")
+            .unwrap();
+        let mut arguments = vec![];
+        let mut body = &bind.expression;
+        while let Lambda(ref argument, ref rest) = *body {
+            arguments.push(argument.name);
+            body = rest;
+        }
+        let mut scrutinees = Scrutinees(vec![]);
+        scrutinees.visit_expr(body);
+        assert_eq!(scrutinees.0, arguments, "{}", body);
+    }
 }
@@ -3,49 +3,252 @@ use crate::{
         Expr::*,
         *,
     },
-    interner::{
-        intern,
-        InternedStr,
+    interner::intern,
+    module::{
+        self,
+        encode_binding_identifier,
+        instance_name,
+        Constructor,
+        DerivingInstance,
+        FixityDeclaration,
+        Newtype,
+        TypeDeclaration,
     },
-    module::encode_binding_identifier,
     renamer::{
         name,
         typ::*,
         NameSupply,
     },
+    types::{
+        try_get_function,
+        Kind,
+        TypePrinter,
+    },
 };
+use std::string::String;
+
+///The classes which instances can be derived for
+pub const DERIVABLE_CLASSES: &[&str] = &["Eq", "Ord", "Show", "Read", "Enum", "Bounded", "Functor"];
+
+///Checks that an instance of `class` can be derived for `data`.
+///Returns the reason if it can't
+pub fn check_deriving(class: Name, data: &DataDefinition<Name>) -> Result<(), String> {
+    match class.as_ref() {
+        "Eq" | "Ord" | "Show" | "Read" => Ok(()),
+        "Enum" => {
+            if is_enumeration(data) {
+                Ok(())
+            } else {
+                Err("all of its constructors must be nullary".to_string())
+            }
+        }
+        "Bounded" => {
+            if is_enumeration(data) || data.constructors.len() == 1 {
+                Ok(())
+            } else {
+                Err("it must have a single constructor or only nullary constructors".to_string())
+            }
+        }
+        "Functor" => {
+            let var = match data.typ.value {
+                Type::Application(_, ref param) => param.var(),
+                _ => return Err("it must have a type parameter".to_string()),
+            };
+            if var.kind != Kind::Star {
+                return Err(format!("its last type parameter must have kind {}", Kind::Star));
+            }
+            let mut parameters = vec![];
+            let mut head = &data.typ.value;
+            while let Type::Application(ref lhs, ref param) = *head {
+                parameters.push(param.var().clone());
+                head = lhs;
+            }
+            let mut printer = TypePrinter::with_declared_names(&parameters);
+            for constructor in data.constructors.iter() {
+                let fields = ArgIterator {
+                    typ: &constructor.typ.value,
+                };
+                for field in fields {
+                    if !can_fmap(var, field) {
+                        return Err(format!(
+                            "{} can't be mapped over in the field {}",
+                            printer.variable(var),
+                            printer.print(field)
+                        ));
+                    }
+                }
+            }
+            Ok(())
+        }
+        _ => Err(format!(
+            "only instances of {} can be derived",
+            DERIVABLE_CLASSES.join(", ")
+        )),
+    }
+}
+
+///Returns the type which a derived instance of `class` is declared for.
+///This is the type of `data` except for Functor where the last type parameter is left out
+pub fn derived_instance_type(class: Name, data: &DataDefinition<Name>) -> Type<Name> {
+    match (class.as_ref(), &data.typ.value) {
+        ("Functor", &Type::Application(ref lhs, _)) => (**lhs).clone(),
+        (_, typ) => typ.clone(),
+    }
+}
+
+///Returns the constraints of a derived instance of `class`.
+///Each type parameter of `data` is required to have an instance of `class` as well unless the
+///instance does not depend on the parameters
+pub fn derived_constraints(class: Name, data: &DataDefinition<Name>) -> Vec<Constraint<Name>> {
+    fn make_constraints(result: &mut Vec<Constraint<Name>>, class: Name, typ: &Type<Name>) {
+        if let Type::Application(ref f, ref param) = *typ {
+            make_constraints(result, class, f);
+            result.push(Constraint {
+                class,
+                variables: vec![param.var().clone()],
+            });
+        }
+    }
+    let mut constraints = vec![];
+    match class.as_ref() {
+        "Enum" | "Functor" => (),
+        _ => make_constraints(&mut constraints, class, &data.typ.value),
+    }
+    constraints
+}
+
+pub fn generate_deriving(
+    instances: &mut Vec<Instance<Id<Name>>>,
+    data: &DataDefinition<Name>,
+    fixity_declarations: &[FixityDeclaration<Name>],
+) {
+    for &class in data.deriving.iter() {
+        //Classes which can't be derived have already been reported by the typechecker
+        if check_deriving(class, data).is_err() {
+            continue;
+        }
+        instances.push(derive_instance(class, data, fixity_declarations));
+    }
+}
 
-pub fn generate_deriving(instances: &mut Vec<Instance<Id<Name>>>, data: &DataDefinition<Name>) {
+///Generates the instance of `class` for `data`
+pub fn derive_instance(
+    class: Name,
+    data: &DataDefinition<Name>,
+    fixity_declarations: &[FixityDeclaration<Name>],
+) -> Instance<Id<Name>> {
     let mut gen =
         DerivingGen {
             name_supply: NameSupply::new(),
         };
-    for deriving in data.deriving.iter() {
-        match deriving.as_ref() {
-            "Eq" => {
-                let mut bindings = vec![];
-                bindings.push(gen.generate_eq(data));
-                instances.push(Instance {
-                    constraints: vec![],
-                    typ: data.typ.value.clone(),
-                    classname: "Eq".into(),
-                    bindings,
-                });
+    let bindings = match class.as_ref() {
+        "Eq" => vec![gen.generate_eq(data)],
+        "Ord" => {
+            let b = gen.generate_ord(data);
+            debug!("Generated Ord {:?} ->>\n{:?}", data.typ, b);
+            vec![b]
+        }
+        "Show" => vec![gen.generate_show(data, fixity_declarations)],
+        "Read" => vec![gen.generate_read(data, fixity_declarations)],
+        "Enum" => gen.generate_enum(data),
+        "Bounded" => gen.generate_bounded(data),
+        "Functor" => vec![gen.generate_functor(data)],
+        _ => unreachable!(),
+    };
+    Instance {
+        constraints: derived_constraints(class, data),
+        types: vec![derived_instance_type(class, data)],
+        classname: class,
+        bindings,
+    }
+}
+
+///Returns whether deriving `class` for a newtype reuses the instance of the wrapped type.
+///Show, Read and Functor are instead derived from the structure of the newtype since the
+///constructor is part of what they do
+pub fn is_newtype_deriving(class: Name) -> bool {
+    !["Show", "Read", "Functor"].contains(&class.as_ref())
+}
+
+///Returns `newtype` as a data type with a single constructor
+pub fn newtype_data_definition(newtype: &Newtype<Name>) -> DataDefinition<Name> {
+    DataDefinition {
+        constructors: vec![Constructor {
+            name: newtype.constructor_name,
+            typ: newtype.constructor_type.clone(),
+            tag: 0,
+            arity: 1,
+            fields: vec![],
+        }],
+        typ: module::qualified(vec![], newtype_type(newtype).clone()),
+        parameters: Default::default(),
+        deriving: newtype.deriving.clone(),
+        location: newtype.location,
+    }
+}
+
+///Returns the type which `newtype` wraps
+pub fn newtype_wrapped_type(newtype: &Newtype<Name>) -> &Type<Name> {
+    try_get_function(&newtype.constructor_type.value)
+        .expect("newtype constructor takes one argument")
+        .0
+}
+
+///Returns the type declared by `newtype`
+pub fn newtype_type(newtype: &Newtype<Name>) -> &Type<Name> {
+    try_get_function(&newtype.constructor_type.value)
+        .expect("newtype constructor takes one argument")
+        .1
+}
+
+///Generates an instance for a newtype which reuses the instance of the type it wraps.
+///Since a newtype is represented by the value it wraps, each function of the class is bound
+///to the same function of the wrapped type
+pub fn derive_newtype_instance(
+    instance: &DerivingInstance<Name>,
+    newtype: &Newtype<Name>,
+    class_vars: &[TypeVariable],
+    class_decls: &[TypeDeclaration<Name>],
+) -> Instance<Id<Name>> {
+    let wrapped = newtype_wrapped_type(newtype);
+    let type_name = instance_name(&instance.types);
+    let bindings = class_decls
+        .iter()
+        .map(|decl| {
+            let mut typ = decl.typ.value.clone();
+            let mut wrapped_typ = decl.typ.value.clone();
+            for (class_var, instance_type) in class_vars.iter().zip(instance.types.iter()) {
+                crate::typecheck::replace_var(&mut typ, class_var, instance_type);
+                crate::typecheck::replace_var(&mut wrapped_typ, class_var, wrapped);
             }
-            "Ord" => {
-                let mut bindings = vec![];
-                let b = gen.generate_ord(data);
-                debug!("Generated Ord {:?} ->>\n{:?}", data.typ, b);
-                bindings.push(b);
-                instances.push(Instance {
-                    constraints: vec![],
-                    typ: data.typ.value.clone(),
-                    classname: "Ord".into(),
-                    bindings,
-                });
+            let constraints: Vec<Constraint<Name>> = instance
+                .constraints
+                .iter()
+                .cloned()
+                .chain(
+                    decl.typ
+                        .constraints
+                        .iter()
+                        .filter(|c| c.variables.iter().all(|var| !class_vars.contains(var)))
+                        .cloned(),
+                )
+                .collect();
+            let binding_name = Name {
+                name: encode_binding_identifier(type_name, decl.name.name),
+                uid: decl.name.uid,
+            };
+            //#Agecompare = compare :: Int -> Int -> Ordering
+            Binding {
+                name: Id::new(binding_name, typ, constraints.clone()),
+                expression: Identifier(Id::new(decl.name, wrapped_typ, constraints)),
             }
-            x => panic!("Cannot generate instance for class {:?}", x),
-        }
+        })
+        .collect();
+    Instance {
+        constraints: instance.constraints.clone(),
+        types: instance.types.clone(),
+        classname: instance.classname,
+        bindings,
     }
 }
 
@@ -54,7 +257,7 @@ struct DerivingGen {
 }
 impl DerivingGen {
     fn generate_eq(&mut self, data: &DataDefinition<Name>) -> Binding<Id<Name>> {
-        self.make_binop("Eq", "==", data, &mut |this, id_l, id_r| {
+        self.make_binop("Eq", "==", data, bool_type(), &mut |this, id_l, id_r| {
             let alts =
                 this.match_same_constructors(data, &id_r, &mut |this, l, r| this.eq_fields(l, r));
             Case(Identifier(id_l.clone()).into(), alts)
@@ -82,7 +285,8 @@ impl DerivingGen {
     }
 
     fn generate_ord(&mut self, data: &DataDefinition<Name>) -> Binding<Id<Name>> {
-        self.make_binop("Ord", "compare", data, &mut |this, id_l, id_r| {
+        let ordering = Type::new_op(name("Ordering"), vec![]);
+        self.make_binop("Ord", "compare", data, ordering, &mut |this, id_l, id_r| {
             //We first compare the tags of the arguments since this would otherwise the last of the alternatives
             let when_eq = {
                 let alts = this
@@ -114,6 +318,384 @@ impl DerivingGen {
         }
     }
 
+    ///Generates `showsPrec` which shows each constructor the same way as it is written in an
+    ///expression, surrounding it with parentheses if the precedence is higher than that of
+    ///the constructor's application
+    fn generate_show(
+        &mut self,
+        data: &DataDefinition<Name>,
+        fixity_declarations: &[FixityDeclaration<Name>],
+    ) -> Binding<Id<Name>> {
+        let precedence = Id::new(self.name_supply.anonymous(), int_type(), vec![]);
+        let value = Id::new(self.name_supply.anonymous(), data.typ.value.clone(), vec![]);
+        let rest = Id::new(self.name_supply.anonymous(), string_type(), vec![]);
+        let alts = data
+            .constructors
+            .iter()
+            .map(|constructor| {
+                let args = self.constructor_arguments(constructor);
+                let shows =
+                    self.show_constructor(constructor, &args, &precedence, fixity_declarations);
+                Alternative {
+                    pattern: Pattern::Constructor(constructor_id(constructor), args),
+                    expression: Apply(shows.into(), Identifier(rest.clone()).into()),
+                }
+            })
+            .collect();
+        let body = Case(Identifier(value.clone()).into(), alts);
+        self.make_binding(
+            "Show",
+            "showsPrec",
+            data,
+            vec![precedence, value, rest],
+            string_type(),
+            body,
+        )
+    }
+
+    ///Returns an expression of type `ShowS` which shows `constructor` applied to `args`
+    fn show_constructor(
+        &mut self,
+        constructor: &Constructor<Name>,
+        args: &[Id<Name>],
+        precedence: &Id<Name>,
+        fixity_declarations: &[FixityDeclaration<Name>],
+    ) -> Expr<Id<Name>> {
+        let ctor_name = constructor.name.as_ref();
+        if args.is_empty() {
+            return show_string(&prefix_name(ctor_name));
+        }
+        let (ctor_precedence, parts) = if !constructor.fields.is_empty() {
+            //C {field1 = x, field2 = y}
+            let mut parts = vec![];
+            for (i, (field, arg)) in constructor.fields.iter().zip(args).enumerate() {
+                let separator = if i == 0 {
+                    format!("{} {{", prefix_name(ctor_name))
+                } else {
+                    ", ".to_string()
+                };
+                let field = prefix_name(field.as_ref());
+                parts.push(show_string(&format!("{}{} = ", separator, field)));
+                parts.push(shows_prec(0, arg));
+            }
+            parts.push(show_string("}"));
+            (10, parts)
+        } else if is_operator(ctor_name) && args.len() == 2 {
+            //x :+ y
+            let p = fixity(constructor.name, fixity_declarations);
+            let parts = vec![
+                shows_prec(p + 1, &args[0]),
+                show_string(&format!(" {} ", ctor_name)),
+                shows_prec(p + 1, &args[1]),
+            ];
+            (p, parts)
+        } else {
+            //C x y
+            let mut parts = vec![show_string(&format!("{} ", prefix_name(ctor_name)))];
+            for (i, arg) in args.iter().enumerate() {
+                if i != 0 {
+                    parts.push(show_string(" "));
+                }
+                parts.push(shows_prec(11, arg));
+            }
+            (10, parts)
+        };
+        let shows = parts
+            .into_iter()
+            .rev()
+            .fold(None, |acc, part| match acc {
+                Some(acc) => Some(apply(global(".", compose_type(shows_type())), vec![part, acc])),
+                None => Some(part),
+            })
+            .unwrap();
+        let paren_type = function_type_(bool_type(), function_type_(shows_type(), shows_type()));
+        apply(
+            global("showParen", paren_type),
+            vec![greater_than(precedence, ctor_precedence), shows],
+        )
+    }
+
+    ///Generates `readsPrec` which reads each constructor in the format produced by the derived
+    ///Show instance
+    fn generate_read(
+        &mut self,
+        data: &DataDefinition<Name>,
+        fixity_declarations: &[FixityDeclaration<Name>],
+    ) -> Binding<Id<Name>> {
+        let precedence = Id::new(self.name_supply.anonymous(), int_type(), vec![]);
+        let input = Id::new(self.name_supply.anonymous(), string_type(), vec![]);
+        let result_type = reads_result(data.typ.value.clone());
+        let append_type =
+            function_type_(result_type.clone(), function_type_(result_type.clone(), result_type));
+        let body = data
+            .constructors
+            .iter()
+            .rev()
+            .map(|constructor| {
+                let (parenthesized, reads) =
+                    self.read_constructor(constructor, &precedence, fixity_declarations);
+                let paren_type = function_type_(
+                    bool_type(),
+                    function_type_(reads.get_type().clone(), reads.get_type().clone()),
+                );
+                let reads = apply(global("readParen", paren_type), vec![parenthesized, reads]);
+                apply(reads, vec![Identifier(input.clone())])
+            })
+            .fold(None, |acc, alt| match acc {
+                Some(acc) => Some(apply(global("++", append_type.clone()), vec![alt, acc])),
+                None => Some(alt),
+            })
+            .unwrap();
+        self.make_binding(
+            "Read",
+            "readsPrec",
+            data,
+            vec![precedence, input],
+            reads_result(data.typ.value.clone()),
+            body,
+        )
+    }
+
+    ///Returns an expression of type `ReadS T` which reads `constructor` as well as a boolean
+    ///expression which is true if the value must be surrounded by parentheses
+    fn read_constructor(
+        &mut self,
+        constructor: &Constructor<Name>,
+        precedence: &Id<Name>,
+        fixity_declarations: &[FixityDeclaration<Name>],
+    ) -> (Expr<Id<Name>>, Expr<Id<Name>>) {
+        let ctor_name = constructor.name.as_ref();
+        let fields: Vec<&Type<Name>> = ArgIterator {
+            typ: &constructor.typ.value,
+        }
+        .collect();
+        let value_type =
+            function_type_(constructor.typ.value.clone(), reads_type(constructor.typ.value.clone()));
+        let ctor = self.constructor_function(constructor);
+        let mut reads = apply(global("readsValue", value_type), vec![ctor]);
+        if fields.is_empty() {
+            for token in prefix_tokens(ctor_name) {
+                reads = reads_token(reads, &token);
+            }
+            return (Identifier(id("False", bool_type())), reads);
+        }
+        let ctor_precedence = if !constructor.fields.is_empty() {
+            //C {field1 = x, field2 = y}
+            for token in prefix_tokens(ctor_name) {
+                reads = reads_token(reads, &token);
+            }
+            reads = reads_token(reads, "{");
+            for (i, (field, typ)) in constructor.fields.iter().zip(fields).enumerate() {
+                if i != 0 {
+                    reads = reads_token(reads, ",");
+                }
+                for token in prefix_tokens(field.as_ref()) {
+                    reads = reads_token(reads, &token);
+                }
+                reads = reads_token(reads, "=");
+                reads = reads_apply(reads, reads_prec(0, typ));
+            }
+            reads = reads_token(reads, "}");
+            10
+        } else if is_operator(ctor_name) && fields.len() == 2 {
+            //x :+ y
+            let p = fixity(constructor.name, fixity_declarations);
+            reads = reads_apply(reads, reads_prec(p + 1, fields[0]));
+            reads = reads_token(reads, ctor_name);
+            reads = reads_apply(reads, reads_prec(p + 1, fields[1]));
+            p
+        } else {
+            //C x y
+            for token in prefix_tokens(ctor_name) {
+                reads = reads_token(reads, &token);
+            }
+            for typ in fields {
+                reads = reads_apply(reads, reads_prec(11, typ));
+            }
+            10
+        };
+        (greater_than(precedence, ctor_precedence), reads)
+    }
+
+    ///Generates `fromEnum`, `toEnum`, `enumFrom` and `enumFromThen` for an enumeration.
+    ///`enumFrom` and `enumFromThen` stop at the last (or first) constructor instead of
+    ///producing an infinite list
+    fn generate_enum(&mut self, data: &DataDefinition<Name>) -> Vec<Binding<Id<Name>>> {
+        let typ = data.typ.value.clone();
+        let first = Identifier(constructor_id(&data.constructors[0]));
+        let last = Identifier(constructor_id(data.constructors.last().unwrap()));
+
+        let value = Id::new(self.name_supply.anonymous(), typ.clone(), vec![]);
+        let alts = data
+            .constructors
+            .iter()
+            .map(|constructor| Alternative {
+                pattern: Pattern::Constructor(constructor_id(constructor), vec![]),
+                expression: int(constructor.tag),
+            })
+            .collect();
+        let body = Case(Identifier(value.clone()).into(), alts);
+        let from_enum = self.make_binding("Enum", "fromEnum", data, vec![value], int_type(), body);
+
+        let index = Id::new(self.name_supply.anonymous(), int_type(), vec![]);
+        let mut alts: Vec<_> = data
+            .constructors
+            .iter()
+            .map(|constructor| Alternative {
+                pattern: Pattern::Number(constructor.tag),
+                expression: Identifier(constructor_id(constructor)),
+            })
+            .collect();
+        let error = apply(
+            global("error", function_type_(string_type(), typ.clone())),
+            vec![string("toEnum: bad argument")],
+        );
+        alts.push(Alternative {
+            pattern: Pattern::WildCard,
+            expression: error,
+        });
+        let body = Case(Identifier(index.clone()).into(), alts);
+        let to_enum = self.make_binding("Enum", "toEnum", data, vec![index], typ.clone(), body);
+
+        let enum_type = |n| (0..n).fold(list_type(typ.clone()), |acc, _| function_type_(typ.clone(), acc));
+        let start = Id::new(self.name_supply.anonymous(), typ.clone(), vec![]);
+        let enum_from = self.make_binding(
+            "Enum",
+            "enumFrom",
+            data,
+            vec![start.clone()],
+            list_type(typ.clone()),
+            apply(
+                global("enumFromTo", enum_type(2)),
+                vec![Identifier(start.clone()), last.clone()],
+            ),
+        );
+
+        let next = Id::new(self.name_supply.anonymous(), typ.clone(), vec![]);
+        let from_enum_id = || global("fromEnum", function_type_(typ.clone(), int_type()));
+        let decreasing = apply(
+            global("primIntLT", function_type_(int_type(), function_type_(int_type(), bool_type()))),
+            vec![
+                apply(from_enum_id(), vec![Identifier(next.clone())]),
+                apply(from_enum_id(), vec![Identifier(start.clone())]),
+            ],
+        );
+        let stop = Case(
+            decreasing.into(),
+            vec![
+                Alternative {
+                    pattern: Pattern::Constructor(id("True", bool_type()), vec![]),
+                    expression: first,
+                },
+                Alternative {
+                    pattern: Pattern::Constructor(id("False", bool_type()), vec![]),
+                    expression: last,
+                },
+            ],
+        );
+        let enum_from_then = self.make_binding(
+            "Enum",
+            "enumFromThen",
+            data,
+            vec![start.clone(), next.clone()],
+            list_type(typ.clone()),
+            apply(
+                global("enumFromThenTo", enum_type(3)),
+                vec![Identifier(start), Identifier(next), stop],
+            ),
+        );
+        vec![from_enum, to_enum, enum_from, enum_from_then]
+    }
+
+    ///Generates `minBound` and `maxBound`.
+    ///For enumerations these are the first and last constructor, otherwise the only
+    ///constructor is applied to the bounds of its fields
+    fn generate_bounded(&mut self, data: &DataDefinition<Name>) -> Vec<Binding<Id<Name>>> {
+        let typ = data.typ.value.clone();
+        let bound = |this: &mut Self, funcname: &str, constructor: &Constructor<Name>| {
+            let fields = ArgIterator {
+                typ: &constructor.typ.value,
+            }
+            .map(|field| global(funcname, field.clone()))
+            .collect();
+            let expr = apply(Identifier(constructor_id(constructor)), fields);
+            this.make_binding("Bounded", funcname, data, vec![], typ.clone(), expr)
+        };
+        let first = &data.constructors[0];
+        let last = data.constructors.last().unwrap();
+        vec![bound(self, "minBound", first), bound(self, "maxBound", last)]
+    }
+
+    ///Generates `fmap` which applies the function to every field which has the type of the last
+    ///type parameter, mapping over any fields which contain that type
+    fn generate_functor(&mut self, data: &DataDefinition<Name>) -> Binding<Id<Name>> {
+        let var = data.typ.value.appr().var().clone();
+        let mapped_var = TypeVariable::new(intern("#b"));
+        let mut mapped_type = data.typ.value.clone();
+        replace_var(&mut mapped_type, &var, &mapped_var);
+        let function = Id::new(
+            self.name_supply.anonymous(),
+            function_type_(Type::Variable(var.clone()), Type::Variable(mapped_var.clone())),
+            vec![],
+        );
+        let value = Id::new(self.name_supply.anonymous(), data.typ.value.clone(), vec![]);
+        let alts = data
+            .constructors
+            .iter()
+            .map(|constructor| {
+                let args = self.constructor_arguments(constructor);
+                let mut ctor_type = constructor.typ.value.clone();
+                replace_var(&mut ctor_type, &var, &mapped_var);
+                let fields = args
+                    .iter()
+                    .map(|arg| match fmap_field(&function, &var, &mapped_var, arg.get_type()) {
+                        Some(mapper) => apply(mapper, vec![Identifier(arg.clone())]),
+                        None => Identifier(arg.clone()),
+                    })
+                    .collect();
+                let ctor = Id::new(constructor.name, ctor_type, vec![]);
+                Alternative {
+                    pattern: Pattern::Constructor(constructor_id(constructor), args),
+                    expression: apply(Identifier(ctor), fields),
+                }
+            })
+            .collect();
+        let body = Case(Identifier(value.clone()).into(), alts);
+        self.make_binding("Functor", "fmap", data, vec![function, value], mapped_type, body)
+    }
+
+    ///Returns `constructor` as a function value.
+    ///Constructors can't be partially applied so a lambda which applies the constructor is
+    ///bound by a let if it has any fields
+    fn constructor_function(&mut self, constructor: &Constructor<Name>) -> Expr<Id<Name>> {
+        if constructor.arity == 0 {
+            return Identifier(constructor_id(constructor));
+        }
+        let args = self.constructor_arguments(constructor);
+        let typ = constructor.typ.value.clone();
+        let body = apply(
+            Identifier(Id::new(constructor.name, typ.clone(), vec![])),
+            args.iter().cloned().map(Identifier).collect(),
+        );
+        let return_type = body.get_type().clone();
+        let (_, body) = make_lambda(args, return_type, body);
+        let id = Id::new(self.name_supply.from_str("#constructor"), typ, vec![]);
+        let bind = Binding {
+            name: id.clone(),
+            expression: body,
+        };
+        Let(vec![bind], Identifier(id).into())
+    }
+
+    ///Returns an identifier for each field of `constructor`
+    fn constructor_arguments(&mut self, constructor: &Constructor<Name>) -> Vec<Id<Name>> {
+        ArgIterator {
+            typ: &constructor.typ.value,
+        }
+        .map(|arg| Id::new(self.name_supply.anonymous(), arg.clone(), vec![]))
+        .collect()
+    }
+
     ///Creates a binary function binding with the name 'funcname' which is a function in an instance for 'data'
     ///This function takes two parameters of the type of 'data'
     fn make_binop(
@@ -121,49 +703,41 @@ impl DerivingGen {
         class: &str,
         funcname: &str,
         data: &DataDefinition<Name>,
+        return_type: Type<Name>,
         func: &mut dyn FnMut(&mut DerivingGen, Id<Name>, Id<Name>) -> Expr<Id<Name>>,
     ) -> Binding<Id<Name>> {
         let arg_l = self.name_supply.anonymous();
         let arg_r = self.name_supply.anonymous();
-        let mut id_r = Id::new(arg_r, data.typ.value.clone(), data.typ.constraints.clone());
-        let mut id_l = Id::new(arg_l, data.typ.value.clone(), data.typ.constraints.clone());
+        let id_r = Id::new(arg_r, data.typ.value.clone(), data.typ.constraints.clone());
+        let id_l = Id::new(arg_l, data.typ.value.clone(), data.typ.constraints.clone());
         let expr = func(self, id_l.clone(), id_r.clone());
-        id_r.typ.value = function_type_(data.typ.value.clone(), bool_type());
-        id_l.typ.value = function_type_(
-            data.typ.value.clone(),
-            function_type_(data.typ.value.clone(), bool_type()),
-        );
-        let lambda_expr = Lambda(id_l, Box::new(Lambda(id_r, expr.into()))); //TODO types
+        self.make_binding(class, funcname, data, vec![id_l, id_r], return_type, expr)
+    }
+
+    ///Creates the binding of the function 'funcname' in the derived instance of 'class' for 'data'.
+    ///The binding is a lambda taking `args` and returning `body` which has the type `return_type`
+    fn make_binding(
+        &mut self,
+        class: &str,
+        funcname: &str,
+        data: &DataDefinition<Name>,
+        args: Vec<Id<Name>>,
+        return_type: Type<Name>,
+        body: Expr<Id<Name>>,
+    ) -> Binding<Id<Name>> {
+        let (typ, expr) = make_lambda(args, return_type, body);
         let data_name = extract_applied_type(&data.typ.value).ctor().name;
-        let name = encode_binding_identifier(data_name.name, intern(funcname));
-        //Create a constraint for each type parameter
-        fn make_constraints(
-            mut result: Vec<Constraint<Name>>,
-            class: InternedStr,
-            typ: &Type<Name>,
-        ) -> Vec<Constraint<Name>> {
-            match typ {
-                &Type::Application(ref f, ref param) => {
-                    result.push(Constraint {
-                        class: Name {
-                            name: class,
-                            uid: 0,
-                        },
-                        variables: vec![param.var().clone()],
-                    });
-                    make_constraints(result, class, f)
-                }
-                _ => result,
-            }
-        }
-        let constraints = make_constraints(vec![], intern(class), &data.typ.value);
+        let binding_name = encode_binding_identifier(data_name.name, intern(funcname));
         Binding {
             name: Id::new(
-                Name { name, uid: 0 },
-                lambda_expr.get_type().clone(),
-                constraints,
+                Name {
+                    name: binding_name,
+                    uid: 0,
+                },
+                typ,
+                derived_constraints(name(class), data),
             ),
-            expression: lambda_expr,
+            expression: expr,
         }
     }
 
@@ -255,10 +829,262 @@ impl DerivingGen {
     }
 }
 
+///Wraps `body` in a lambda for each argument, returning the type of the resulting function
+fn make_lambda(
+    args: Vec<Id<Name>>,
+    return_type: Type<Name>,
+    body: Expr<Id<Name>>,
+) -> (Type<Name>, Expr<Id<Name>>) {
+    let mut typ = return_type;
+    let mut expr = body;
+    for mut arg in args.into_iter().rev() {
+        typ = function_type_(arg.typ.value.clone(), typ);
+        //The type of a lambda is stored in its argument
+        arg.typ.value = typ.clone();
+        expr = Lambda(arg, expr.into());
+    }
+    (typ, expr)
+}
+
 fn id(s: &str, typ: Type<Name>) -> Id<Name> {
     Id::new(s.into(), typ, vec![])
 }
 
+fn global(s: &str, typ: Type<Name>) -> Expr<Id<Name>> {
+    Identifier(Id::new(name(s), typ, vec![]))
+}
+
+fn apply(func: Expr<Id<Name>>, args: Vec<Expr<Id<Name>>>) -> Expr<Id<Name>> {
+    args.into_iter()
+        .fold(func, |func, arg| Apply(func.into(), arg.into()))
+}
+
+fn int(i: isize) -> Expr<Id<Name>> {
+    Literal(LiteralData {
+        typ: int_type(),
+        value: module::LiteralData::Integral(i),
+    })
+}
+
+fn string(s: &str) -> Expr<Id<Name>> {
+    Literal(LiteralData {
+        typ: string_type(),
+        value: module::LiteralData::String(intern(s)),
+    })
+}
+
+fn string_type() -> Type<Name> {
+    list_type(char_type())
+}
+
+///Returns the type `ShowS`
+fn shows_type() -> Type<Name> {
+    function_type_(string_type(), string_type())
+}
+
+///Returns the type of `(.)` when composing two functions of type `typ`
+fn compose_type(typ: Type<Name>) -> Type<Name> {
+    function_type_(typ.clone(), function_type_(typ.clone(), typ))
+}
+
+///Returns the type `[(typ, String)]`
+fn reads_result(typ: Type<Name>) -> Type<Name> {
+    list_type(Type::new_op(name("(,)"), vec![typ, string_type()]))
+}
+
+///Returns the type `ReadS typ`
+fn reads_type(typ: Type<Name>) -> Type<Name> {
+    function_type_(string_type(), reads_result(typ))
+}
+
+///Returns the type which is read by the parser `reads`
+fn read_value_type(reads: &Expr<Id<Name>>) -> Type<Name> {
+    reads.get_type().appr().appr().appl().appr().clone()
+}
+
+///showString s
+fn show_string(s: &str) -> Expr<Id<Name>> {
+    apply(
+        global("showString", function_type_(string_type(), shows_type())),
+        vec![string(s)],
+    )
+}
+
+///showsPrec precedence arg
+fn shows_prec(precedence: isize, arg: &Id<Name>) -> Expr<Id<Name>> {
+    let typ = function_type_(int_type(), function_type_(arg.get_type().clone(), shows_type()));
+    apply(
+        global("showsPrec", typ),
+        vec![int(precedence), Identifier(arg.clone())],
+    )
+}
+
+///readsPrec precedence
+fn reads_prec(precedence: isize, typ: &Type<Name>) -> Expr<Id<Name>> {
+    let typ = function_type_(int_type(), reads_type(typ.clone()));
+    apply(global("readsPrec", typ), vec![int(precedence)])
+}
+
+///readsToken reads token
+fn reads_token(reads: Expr<Id<Name>>, token: &str) -> Expr<Id<Name>> {
+    let reads_type = reads.get_type().clone();
+    let typ = function_type_(
+        reads_type.clone(),
+        function_type_(string_type(), reads_type),
+    );
+    apply(global("readsToken", typ), vec![reads, string(token)])
+}
+
+///readsApply reads_function reads_argument
+fn reads_apply(reads_function: Expr<Id<Name>>, reads_argument: Expr<Id<Name>>) -> Expr<Id<Name>> {
+    let result = read_value_type(&reads_function).appr().clone();
+    let typ = function_type_(
+        reads_function.get_type().clone(),
+        function_type_(reads_argument.get_type().clone(), reads_type(result)),
+    );
+    apply(global("readsApply", typ), vec![reads_function, reads_argument])
+}
+
+///primIntGT precedence n
+fn greater_than(precedence: &Id<Name>, n: isize) -> Expr<Id<Name>> {
+    let typ = function_type_(int_type(), function_type_(int_type(), bool_type()));
+    apply(
+        global("primIntGT", typ),
+        vec![Identifier(precedence.clone()), int(n)],
+    )
+}
+
+///Returns an expression which maps a field of type `typ` by applying `function` to every
+///value of the type `var` in it, or None if the field does not contain `var`
+fn fmap_field(
+    function: &Id<Name>,
+    var: &TypeVariable,
+    mapped_var: &TypeVariable,
+    typ: &Type<Name>,
+) -> Option<Expr<Id<Name>>> {
+    if !mentions(var, typ) {
+        return None;
+    }
+    let mut mapped_type = typ.clone();
+    replace_var(&mut mapped_type, var, mapped_var);
+    match *typ {
+        Type::Variable(_) => Some(Identifier(function.clone())),
+        Type::Application(ref lhs, ref result) => {
+            let inner = fmap_field(function, var, mapped_var, result).unwrap();
+            let inner_type = inner.get_type().clone();
+            let (name, typ) = if is_function(lhs) {
+                //(.) f
+                (".", function_type_(inner_type, function_type_(typ.clone(), mapped_type)))
+            } else {
+                //fmap f
+                ("fmap", function_type_(inner_type, function_type_(typ.clone(), mapped_type)))
+            };
+            Some(apply(global(name, typ), vec![inner]))
+        }
+        _ => unreachable!(),
+    }
+}
+
+///Returns true if the values of `var` in `typ` can be mapped over by `fmap_field`
+fn can_fmap(var: &TypeVariable, typ: &Type<Name>) -> bool {
+    if !mentions(var, typ) {
+        return true;
+    }
+    match *typ {
+        Type::Variable(ref v) => v == var,
+        Type::Application(ref lhs, ref result) => {
+            let head = extract_applied_type(lhs);
+            //Tuples and type variables have no Functor instance which can be used
+            let has_functor = match *head {
+                Type::Constructor(ref ctor) => !ctor.name.as_ref().starts_with('('),
+                _ => false,
+            };
+            has_functor && !mentions(var, lhs) && can_fmap(var, result)
+        }
+        _ => false,
+    }
+}
+
+///Returns true if `typ` is `(->) a`
+fn is_function(typ: &Type<Name>) -> bool {
+    match *typ {
+        Type::Application(ref f, _) => match **f {
+            Type::Constructor(ref ctor) => ctor.name.as_ref() == "->",
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+///Returns true if the type variable `var` appears in `typ`
+fn mentions(var: &TypeVariable, typ: &Type<Name>) -> bool {
+    match *typ {
+        Type::Variable(ref v) => v.id == var.id,
+        Type::Application(ref lhs, ref rhs) => mentions(var, lhs) || mentions(var, rhs),
+        _ => false,
+    }
+}
+
+///Replaces every occurence of the type variable `var` in `typ` with `replacement`
+fn replace_var(typ: &mut Type<Name>, var: &TypeVariable, replacement: &TypeVariable) {
+    match *typ {
+        Type::Variable(ref mut v) => {
+            if v.id == var.id {
+                *v = replacement.clone();
+            }
+        }
+        Type::Application(ref mut lhs, ref mut rhs) => {
+            replace_var(lhs, var, replacement);
+            replace_var(rhs, var, replacement);
+        }
+        _ => (),
+    }
+}
+
+fn is_enumeration(data: &DataDefinition<Name>) -> bool {
+    data.constructors.iter().all(|constructor| constructor.arity == 0)
+}
+
+fn is_operator(name: &str) -> bool {
+    !name.starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == '(' || c == '[')
+}
+
+///Returns the name as it is written when it is used as a prefix function
+fn prefix_name(name: &str) -> String {
+    if is_operator(name) {
+        format!("({})", name)
+    } else {
+        name.to_string()
+    }
+}
+
+///Returns the tokens of `name` when it is used as a prefix function
+fn prefix_tokens(name: &str) -> Vec<String> {
+    if is_operator(name) {
+        vec!["(".to_string(), name.to_string(), ")".to_string()]
+    } else {
+        vec![name.to_string()]
+    }
+}
+
+///Returns the precedence of the operator `name`, operators without a fixity declaration
+///have precedence 9
+fn fixity(name: Name, fixity_declarations: &[FixityDeclaration<Name>]) -> isize {
+    fixity_declarations
+        .iter()
+        .find(|fixity| fixity.operators.contains(&name))
+        .map_or(9, |fixity| fixity.precedence)
+}
+
+///Returns an identifier for `constructor` with the type of the value it constructs
+fn constructor_id(constructor: &Constructor<Name>) -> Id<Name> {
+    let mut iter = ArgIterator {
+        typ: &constructor.typ.value,
+    };
+    iter.by_ref().count();
+    Id::new(constructor.name, iter.typ.clone(), vec![])
+}
+
 fn compare_tags(lhs: Expr<Id<Name>>, rhs: Expr<Id<Name>>) -> Expr<Id<Name>> {
     let var: Type<_> = "a".into();
     let typ = function_type_(
@@ -299,7 +1125,6 @@ struct ArgIterator<'a> {
 impl<'a> Iterator for ArgIterator<'a> {
     type Item = &'a Type<Name>;
     fn next(&mut self) -> Option<&'a Type<Name>> {
-        use crate::types::try_get_function;
         if let Some((arg, rest)) = try_get_function(self.typ) {
             self.typ = rest;
             Some(arg)
@@ -0,0 +1,205 @@
+use {
+    crate::lexer::Location,
+    std::fmt,
+};
+
+///How severe a reported diagnostic is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+///The region of the source code a diagnostic refers to, `end` is exclusive
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    pub fn new(start: Location, end: Location) -> Self {
+        Self { start, end }
+    }
+
+    ///The span of errors which can't be attributed to a position in the source code
+    pub fn unknown() -> Self {
+        Self::from(Location::eof())
+    }
+
+    pub fn is_known(&self) -> bool {
+        self.start.row >= 0
+    }
+}
+
+impl From<Location> for Span {
+    fn from(location: Location) -> Self {
+        Self::new(location, location)
+    }
+}
+
+///An error or warning found by one of the compiler passes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+    pub notes: Vec<String>,
+    ///The name of the file the diagnostic was found in, if it is known
+    pub file: Option<String>,
+    ///The line of source code which `span` starts at, set by `with_source`
+    source_line: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, span: Span, message: String) -> Self {
+        Self {
+            severity,
+            span,
+            message,
+            notes: vec![],
+            file: None,
+            source_line: None,
+        }
+    }
+
+    pub fn error(span: Span, message: String) -> Self {
+        Self::new(Severity::Error, span, message)
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    ///Attaches the name of the file and the offending line of `source` to the diagnostic so that
+    ///it can be displayed along with the message
+    pub fn with_source(mut self, file: &str, source: &str) -> Self {
+        self.file = Some(file.to_string());
+        if self.span.is_known() {
+            self.source_line = source
+                .lines()
+                .nth(self.span.start.row as usize)
+                .map(|line| line.to_string());
+        }
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line_number = (self.span.start.row + 1).to_string();
+        let gutter = match self.source_line {
+            Some(_) => " ".repeat(line_number.len()),
+            None => " ".to_string(),
+        };
+        write!(f, "{}: {}", self.severity, self.message)?;
+        match (&self.file, self.span.is_known()) {
+            (Some(file), true) => write!(
+                f,
+                "\n{}--> {}:{}:{}",
+                gutter, file, line_number, self.span.start.column
+            )?,
+            (Some(file), false) => write!(f, "\n{}--> {}", gutter, file)?,
+            (None, true) => write!(f, "\n{}--> {}:{}", gutter, line_number, self.span.start.column)?,
+            (None, false) => (),
+        }
+        if let Some(ref line) = self.source_line {
+            //Columns start at 1 and a span which ends on a later line is underlined to the end of
+            //the line
+            let start = (self.span.start.column.max(1) - 1) as usize;
+            let end = if self.span.end.row == self.span.start.row {
+                (self.span.end.column.max(1) - 1) as usize
+            } else {
+                line.chars().count()
+            };
+            //Keep any tabs so that the carets line up with the source line
+            let indent: String = line
+                .chars()
+                .take(start)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            write!(
+                f,
+                "\n{} |\n{} | {}\n{} | {}{}",
+                gutter,
+                line_number,
+                line,
+                gutter,
+                indent,
+                "^".repeat(end.saturating_sub(start).max(1))
+            )?;
+        }
+        for note in self.notes.iter() {
+            write!(f, "\n{} = note: {}", gutter, note)?;
+        }
+        Ok(())
+    }
+}
+
+///A file which has been read by the compiler, kept so that its diagnostics can display its lines
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceFile {
+    pub path: String,
+    pub contents: String,
+}
+
+///Implemented by the errors of the compiler passes so that they can be reported in the same way
+pub trait ToDiagnostics {
+    fn diagnostics(&self) -> Vec<Diagnostic>;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        diagnostics::*,
+        lexer::Location,
+    };
+
+    fn location(row: isize, column: isize) -> Location {
+        Location {
+            row,
+            column,
+            absolute: 0,
+        }
+    }
+
+    #[test]
+    fn render_snippet() {
+        let source = "module Main where\nmain = foo bar\n";
+        let diagnostic = Diagnostic::error(
+            Span::new(location(1, 8), location(1, 11)),
+            "foo is not defined".to_string(),
+        )
+        .with_note("did you mean `fst`?".to_string())
+        .with_source("Main.hs", source);
+        assert_eq!(
+            diagnostic.to_string(),
+            "error: foo is not defined
+ --> Main.hs:2:8
+  |
+2 | main = foo bar
+  |        ^^^
+  = note: did you mean `fst`?"
+        );
+    }
+
+    #[test]
+    fn render_unknown_location() {
+        let diagnostic = Diagnostic::error(Span::unknown(), "Module Data.Foo is not defined".into())
+            .with_source("Main.hs", "import Data.Foo");
+        assert_eq!(
+            diagnostic.to_string(),
+            "error: Module Data.Foo is not defined\n --> Main.hs"
+        );
+    }
+}
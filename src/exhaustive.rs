@@ -0,0 +1,601 @@
+//!Checks pattern matches for missing and unreachable alternatives.
+//!The check is done on the typechecked module using the usefulness algorithm described in
+//!"Warnings for pattern matching" by Luc Maranget
+use {
+    crate::{
+        interner::InternedStr,
+        lexer::{
+            Located,
+            Location,
+        },
+        module::*,
+        renamer::{
+            name,
+            Name,
+        },
+    },
+    std::fmt,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+    ///The match does not handle every value, the string shows an example of an unmatched value
+    NonExhaustive(String),
+    ///The alternative can never be reached as the alternatives before it match everything it does
+    Redundant,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::NonExhaustive(ref example) => write!(
+                f,
+                "Pattern match is non-exhaustive, `{}` is not matched",
+                example
+            ),
+            Warning::Redundant => write!(f, "Pattern match is redundant"),
+        }
+    }
+}
+
+///Checks every binding, case expression and lambda in `module`.
+///`modules` are all the modules in scope which are searched for the constructors of each type
+pub fn check_module(module: &Module<Name>, modules: &[Module<Name>]) -> Vec<Located<Warning>> {
+    let selectors = module
+        .data_definitions
+        .iter()
+        .flat_map(|data| data.constructors.iter())
+        .flat_map(|ctor| ctor.fields.iter().cloned())
+        .collect();
+    let mut checker = Checker {
+        modules,
+        selectors,
+        warnings: vec![],
+    };
+    checker.check_bindings(&module.bindings);
+    for instance in module.instances.iter() {
+        checker.check_bindings(&instance.bindings);
+    }
+    for class in module.classes.iter() {
+        checker.check_bindings(&class.bindings);
+    }
+    let bindings = module
+        .bindings
+        .iter()
+        .chain(module.instances.iter().flat_map(|i| i.bindings.iter()))
+        .chain(module.classes.iter().flat_map(|c| c.bindings.iter()));
+    for binding in bindings {
+        checker.visit_binding(binding);
+    }
+    checker.warnings
+}
+
+///A pattern with everything which always matches (variables, `~p`) turned into wildcards
+#[derive(Clone, Debug, PartialEq)]
+enum Pat {
+    WildCard,
+    Constructor(Name, Vec<Pat>),
+    Literal(LiteralData),
+}
+
+impl Pat {
+    fn is_atomic(&self) -> bool {
+        match *self {
+            Pat::Constructor(ref name, ref args) => args.is_empty() || name.name.starts_with('('),
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Pat::WildCard => write!(f, "_"),
+            Pat::Literal(LiteralData::Integral(i)) => write!(f, "{}", i),
+            Pat::Literal(LiteralData::Fractional(d)) => write!(f, "{}", d),
+            Pat::Literal(LiteralData::Char(c)) => write!(f, "{:?}", c),
+            Pat::Literal(LiteralData::String(s)) => write!(f, "{:?}", s.as_ref()),
+            Pat::Constructor(ref name, ref args) if name.name.starts_with('(') => {
+                write!(f, "(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Pat::Constructor(ref name, ref args) if *name.name == *":" => {
+                write_argument(f, &args[0])?;
+                write!(f, " : ")?;
+                write_argument(f, &args[1])
+            }
+            Pat::Constructor(ref name, ref args) => {
+                write!(f, "{}", name.as_ref())?;
+                for arg in args.iter() {
+                    write!(f, " ")?;
+                    write_argument(f, arg)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn write_argument(f: &mut fmt::Formatter, pattern: &Pat) -> fmt::Result {
+    if pattern.is_atomic() {
+        write!(f, "{}", pattern)
+    } else {
+        write!(f, "({})", pattern)
+    }
+}
+
+struct Checker<'a> {
+    modules: &'a [Module<Name>],
+    ///The field selectors of the checked module. They are generated from the data definitions
+    ///and fail on the constructors which do not have the field so they are never checked
+    selectors: Vec<Name>,
+    warnings: Vec<Located<Warning>>,
+}
+
+impl<'a> Checker<'a> {
+    ///Checks each group of bindings which define the same function
+    fn check_bindings(&mut self, bindings: &[Binding<Name>]) {
+        for group in bindings.chunk_by(|l, r| l.name == r.name) {
+            if group[0].arguments.is_empty() || self.selectors.contains(&group[0].name) {
+                continue;
+            }
+            let rows = group
+                .iter()
+                .map(|binding| {
+                    let patterns = binding.arguments.iter().map(|p| self.simplify(p)).collect();
+                    (
+                        patterns,
+                        can_fail(&binding.matches),
+                        *binding.matches.location(),
+                    )
+                })
+                .collect();
+            if let Some(example) = self.check_rows(rows) {
+                let mut example_string = group[0].name.as_ref().to_string();
+                for pattern in example.iter() {
+                    example_string.push(' ');
+                    if pattern.is_atomic() {
+                        example_string.push_str(&pattern.to_string());
+                    } else {
+                        example_string.push_str(&format!("({})", pattern));
+                    }
+                }
+                self.warn(
+                    *group[0].matches.location(),
+                    Warning::NonExhaustive(example_string),
+                );
+            }
+        }
+    }
+
+    fn check_qualifiers(&mut self, qualifiers: &[Qualifier<Name>]) {
+        for qualifier in qualifiers.iter() {
+            if let Qualifier::Let(ref bindings) = *qualifier {
+                self.check_bindings(bindings);
+            }
+        }
+    }
+
+    fn check_guards(&mut self, matches: &Match<Name>) {
+        if let Match::Guards(ref guards) = *matches {
+            for guard in guards.iter() {
+                self.check_qualifiers(&guard.qualifiers);
+            }
+        }
+    }
+
+    fn check_case(&mut self, location: Location, alternatives: &[Alternative<Name>]) {
+        let rows = alternatives
+            .iter()
+            .map(|alt| {
+                (
+                    vec![self.simplify(&alt.pattern.node)],
+                    can_fail(&alt.matches),
+                    alt.pattern.location,
+                )
+            })
+            .collect();
+        if let Some(example) = self.check_rows(rows) {
+            self.warn(location, Warning::NonExhaustive(example[0].to_string()));
+        }
+    }
+
+    ///Reports every redundant row and returns an example of a value which no row matches.
+    ///Rows whose guards can fail do not count towards the values matched
+    fn check_rows(&mut self, rows: Vec<(Vec<Pat>, bool, Location)>) -> Option<Vec<Pat>> {
+        let width = rows[0].0.len();
+        let mut matrix = vec![];
+        for (row, can_fail, location) in rows {
+            if !self.useful(&matrix, &row) {
+                self.warn(location, Warning::Redundant);
+            } else if !can_fail {
+                matrix.push(row);
+            }
+        }
+        self.missing(&matrix, width)
+    }
+
+    fn warn(&mut self, location: Location, warning: Warning) {
+        self.warnings.push(Located {
+            location,
+            node: warning,
+        });
+    }
+
+    fn simplify(&self, pattern: &Pattern<Name>) -> Pat {
+        match *pattern {
+            Pattern::Identifier(_) | Pattern::WildCard | Pattern::Irrefutable(_) => Pat::WildCard,
+            Pattern::As(_, ref pattern) | Pattern::Bang(ref pattern) => self.simplify(pattern),
+            Pattern::Number(i) => Pat::Literal(LiteralData::Integral(i)),
+            Pattern::Fractional(d) => Pat::Literal(LiteralData::Fractional(d)),
+            Pattern::Char(c) => Pat::Literal(LiteralData::Char(c)),
+            Pattern::String(s) => {
+                s.chars()
+                    .rev()
+                    .fold(Pat::Constructor(name("[]"), vec![]), |tail, c| {
+                        Pat::Constructor(name(":"), vec![Pat::Literal(LiteralData::Char(c)), tail])
+                    })
+            }
+            Pattern::Constructor(ref name, ref patterns) => {
+                Pat::Constructor(*name, patterns.iter().map(|p| self.simplify(p)).collect())
+            }
+            Pattern::Record(ref name, ref fields) => {
+                let constructor = self
+                    .modules
+                    .iter()
+                    .flat_map(|module| module.data_definitions.iter())
+                    .flat_map(|data| data.constructors.iter())
+                    .find(|constructor| constructor.name == *name);
+                let mut arguments = vec![Pat::WildCard; self.arity(name)];
+                if let Some(constructor) = constructor {
+                    for (field, pattern) in fields.iter() {
+                        if let Some(i) = constructor.fields.iter().position(|f| f == field) {
+                            arguments[i] = self.simplify(pattern);
+                        }
+                    }
+                }
+                Pat::Constructor(*name, arguments)
+            }
+        }
+    }
+
+    fn arity(&self, constructor: &Name) -> usize {
+        self.signature(constructor)
+            .into_iter()
+            .find(|(name, _)| name == constructor)
+            .map_or(0, |(_, arity)| arity)
+    }
+
+    ///Returns all the constructors (and their arities) of the type which `constructor` belongs to
+    fn signature(&self, constructor: &Name) -> Vec<(Name, usize)> {
+        for module in self.modules.iter() {
+            for data in module.data_definitions.iter() {
+                if data.constructors.iter().any(|c| c.name == *constructor) {
+                    return data
+                        .constructors
+                        .iter()
+                        .map(|c| (c.name, c.arity as usize))
+                        .collect();
+                }
+            }
+            if module
+                .newtypes
+                .iter()
+                .any(|newtype| newtype.constructor_name == *constructor)
+            {
+                return vec![(*constructor, 1)];
+            }
+        }
+        let s: &str = constructor.name.as_ref();
+        match s {
+            "[]" | ":" => vec![(name("[]"), 0), (name(":"), 2)],
+            "()" => vec![(*constructor, 0)],
+            _ if s.starts_with('(') => vec![(*constructor, s.len() - 1)],
+            _ => vec![],
+        }
+    }
+
+    ///Returns the constructors which appear at the head of the first column of `matrix`
+    fn head_constructors(&self, matrix: &[Vec<Pat>]) -> Vec<Name> {
+        let mut constructors = vec![];
+        for row in matrix.iter() {
+            if let Pat::Constructor(ref name, _) = row[0] {
+                if !constructors.contains(name) {
+                    constructors.push(*name);
+                }
+            }
+        }
+        constructors
+    }
+
+    ///Returns the signature of the type in the first column if every one of its constructors
+    ///appear in that column
+    fn complete_signature(&self, matrix: &[Vec<Pat>]) -> Option<Vec<(Name, usize)>> {
+        let heads = self.head_constructors(matrix);
+        let signature = self.signature(heads.first()?);
+        if !signature.is_empty() && signature.iter().all(|(c, _)| heads.contains(c)) {
+            Some(signature)
+        } else {
+            None
+        }
+    }
+
+    ///Returns true if `row` matches a value which no row in `matrix` matches
+    fn useful(&self, matrix: &[Vec<Pat>], row: &[Pat]) -> bool {
+        let (head, rest) = match row.split_first() {
+            Some(x) => x,
+            None => return matrix.is_empty(),
+        };
+        match *head {
+            Pat::Constructor(ref name, ref args) => {
+                let mut row = args.clone();
+                row.extend(rest.iter().cloned());
+                self.useful(&specialize(matrix, name, args.len()), &row)
+            }
+            Pat::Literal(ref literal) => self.useful(&specialize_literal(matrix, literal), rest),
+            Pat::WildCard => match self.complete_signature(matrix) {
+                Some(signature) => signature.iter().any(|&(ref name, arity)| {
+                    let mut row = vec![Pat::WildCard; arity];
+                    row.extend(rest.iter().cloned());
+                    self.useful(&specialize(matrix, name, arity), &row)
+                }),
+                None => self.useful(&default_matrix(matrix), rest),
+            },
+        }
+    }
+
+    ///Returns `width` patterns which match a value that no row in `matrix` matches
+    fn missing(&self, matrix: &[Vec<Pat>], width: usize) -> Option<Vec<Pat>> {
+        if width == 0 {
+            return if matrix.is_empty() {
+                Some(vec![])
+            } else {
+                None
+            };
+        }
+        match self.complete_signature(matrix) {
+            Some(signature) => signature.iter().find_map(|&(ref name, arity)| {
+                let mut example =
+                    self.missing(&specialize(matrix, name, arity), arity + width - 1)?;
+                let rest = example.split_off(arity);
+                let mut result = vec![Pat::Constructor(*name, example)];
+                result.extend(rest);
+                Some(result)
+            }),
+            None => {
+                let rest = self.missing(&default_matrix(matrix), width - 1)?;
+                let heads = self.head_constructors(matrix);
+                let head = heads
+                    .first()
+                    .and_then(|first| {
+                        self.signature(first)
+                            .into_iter()
+                            .find(|(c, _)| !heads.contains(c))
+                    })
+                    .map_or(Pat::WildCard, |(name, arity)| {
+                        Pat::Constructor(name, vec![Pat::WildCard; arity])
+                    });
+                let mut result = vec![head];
+                result.extend(rest);
+                Some(result)
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<Name> for Checker<'a> {
+    fn visit_expr(&mut self, expr: &TypedExpr<Name>) {
+        match expr.expr {
+            Expr::Case(_, ref alternatives) => self.check_case(expr.location, alternatives),
+            Expr::Lambda(ref pattern, _) => {
+                let row = (vec![self.simplify(pattern)], false, expr.location);
+                if let Some(example) = self.check_rows(vec![row]) {
+                    self.warn(
+                        expr.location,
+                        Warning::NonExhaustive(example[0].to_string()),
+                    );
+                }
+            }
+            Expr::Let(ref bindings, _) => self.check_bindings(bindings),
+            Expr::Do(ref bindings, _) => {
+                for binding in bindings.iter() {
+                    if let DoBinding::DoLet(ref bindings) = *binding {
+                        self.check_bindings(bindings);
+                    }
+                }
+            }
+            Expr::ListComprehension(_, ref qualifiers) => self.check_qualifiers(qualifiers),
+            _ => (),
+        }
+        walk_expr(self, expr)
+    }
+    fn visit_alternative(&mut self, alt: &Alternative<Name>) {
+        if let Some(ref bindings) = alt.where_bindings {
+            self.check_bindings(bindings);
+        }
+        self.check_guards(&alt.matches);
+        walk_alternative(self, alt)
+    }
+    fn visit_binding(&mut self, binding: &Binding<Name>) {
+        if let Some(ref bindings) = binding.where_bindings {
+            self.check_bindings(bindings);
+        }
+        self.check_guards(&binding.matches);
+        walk_binding(self, binding)
+    }
+}
+
+///Returns true if the guards of `matches` may all be false
+fn can_fail(matches: &Match<Name>) -> bool {
+    match *matches {
+        Match::Simple(_) => false,
+        Match::Guards(ref guards) => !guards.iter().any(|guard| {
+            guard.qualifiers.iter().all(|qualifier| match *qualifier {
+                Qualifier::Guard(TypedExpr {
+                    expr: Expr::Identifier(ref name),
+                    ..
+                }) => {
+                    let always: [InternedStr; 2] = ["otherwise".into(), "True".into()];
+                    always.contains(&name.name)
+                }
+                Qualifier::Generator(ref pattern, _) => matches!(
+                    pattern.node,
+                    Pattern::Identifier(_) | Pattern::WildCard | Pattern::Irrefutable(_)
+                ),
+                Qualifier::Let(_) => true,
+                _ => false,
+            })
+        }),
+    }
+}
+
+///Keeps the rows which match the constructor `name`, replacing the first pattern with the
+///`arity` arguments of the constructor
+fn specialize(matrix: &[Vec<Pat>], name: &Name, arity: usize) -> Vec<Vec<Pat>> {
+    matrix
+        .iter()
+        .filter_map(|row| {
+            let mut result = match row[0] {
+                Pat::Constructor(ref other, ref args) if other == name => args.clone(),
+                Pat::WildCard => vec![Pat::WildCard; arity],
+                _ => return None,
+            };
+            result.extend(row[1..].iter().cloned());
+            Some(result)
+        })
+        .collect()
+}
+
+///Keeps the rows which match `literal`, removing the first pattern
+fn specialize_literal(matrix: &[Vec<Pat>], literal: &LiteralData) -> Vec<Vec<Pat>> {
+    matrix
+        .iter()
+        .filter(|row| match row[0] {
+            Pat::Literal(ref other) => other == literal,
+            Pat::WildCard => true,
+            _ => false,
+        })
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
+
+///Keeps the rows which start with a wildcard, removing the wildcard
+fn default_matrix(matrix: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    matrix
+        .iter()
+        .filter(|row| row[0] == Pat::WildCard)
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::typecheck::typecheck_string,
+    };
+
+    fn warnings(source: &str) -> Vec<String> {
+        let modules = typecheck_string(source).unwrap();
+        let module = modules.last().unwrap();
+        check_module(module, &modules)
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn exhaustive_matches() {
+        let source = r"
+data Maybe a = Just a | Nothing
+data Pair a b = Pair a b
+
+fromMaybe x (Just y) = y
+fromMaybe x Nothing = x
+
+zipWith f (x:xs) (y:ys) = f x y : zipWith f xs ys
+zipWith f _ _ = []
+
+first p = case p of
+    Pair x _ -> x
+";
+        assert_eq!(warnings(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn partial_field_selectors() {
+        let source = r"
+data S = A { f1, f2 :: Int } | B { f1 :: Int }
+";
+        assert_eq!(warnings(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn non_exhaustive_example() {
+        let source = r"
+data Maybe a = Just a | Nothing
+
+test (Just x) [] = x
+test Nothing (y:ys) = y
+";
+        assert_eq!(
+            warnings(source),
+            ["3:20: Pattern match is non-exhaustive, `test (Just _) (_ : _)` is not matched"]
+        );
+    }
+
+    #[test]
+    fn non_exhaustive_case_and_literals() {
+        let source = r"
+data Maybe a = Just a | Nothing
+
+test x = case x of
+    Just 1 -> 1
+    Nothing -> 0
+";
+        assert_eq!(
+            warnings(source),
+            ["3:10: Pattern match is non-exhaustive, `Just _` is not matched"]
+        );
+    }
+
+    #[test]
+    fn guards_do_not_cover() {
+        let source = r"
+test x [] | x = 1
+test x (_:_) = 2
+";
+        assert_eq!(
+            warnings(source),
+            ["1:13: Pattern match is non-exhaustive, `test _ []` is not matched"]
+        );
+    }
+
+    #[test]
+    fn redundant_alternatives() {
+        let source = r"
+data Maybe a = Just a | Nothing
+
+test x = case x of
+    Just _ -> 1
+    Nothing -> 0
+    Just 2 -> 2
+
+f _ = 1
+f 2 = 2
+";
+        assert_eq!(
+            warnings(source),
+            [
+                "9:7: Pattern match is redundant",
+                "6:5: Pattern match is redundant",
+            ]
+        );
+    }
+}
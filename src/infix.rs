@@ -1,14 +1,82 @@
 use {
     crate::{
+        diagnostics::{
+            Diagnostic,
+            Span,
+            ToDiagnostics,
+        },
         interner::intern,
+        lexer::{
+            Located,
+            Location,
+        },
         module::*,
-        renamer::Name,
+        renamer::{
+            Errors,
+            Name,
+        },
+    },
+    std::{
+        collections::HashMap,
+        error,
+        fmt,
     },
-    std::collections::HashMap,
 };
 
+#[derive(Debug)]
+pub struct PrecedenceError(Errors<Located<Error>>);
+
+impl fmt::Display for PrecedenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.report_errors(f, "precedence")
+    }
+}
+
+impl error::Error for PrecedenceError {
+    fn description(&self) -> &str {
+        "precedence error"
+    }
+}
+
+impl ToDiagnostics for PrecedenceError {
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.0
+            .iter()
+            .map(|error| Diagnostic::error(Span::from(error.location), error.node.to_string()))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    ///The operator of a section and the operator in its operand which binds less tightly
+    Section(Name, Name),
+    ///Two operators with the same precedence but different associativity
+    MismatchedAssociativity(Name, Name),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Section(ref op, ref operand_op) => write!(
+                f,
+                "The operator {} in a section binds tighter than the operator {} in its operand",
+                op.name.as_ref(),
+                operand_op.name.as_ref()
+            ),
+            Self::MismatchedAssociativity(ref lhs, ref rhs) => write!(
+                f,
+                "The operators {} and {} have the same precedence but different associativity",
+                lhs.name.as_ref(),
+                rhs.name.as_ref()
+            ),
+        }
+    }
+}
+
 pub struct PrecedenceVisitor {
     precedence: HashMap<Name, (isize, Assoc)>,
+    errors: Errors<Located<Error>>,
 }
 
 impl MutVisitor<Name> for PrecedenceVisitor {
@@ -24,6 +92,12 @@ impl MutVisitor<Name> for PrecedenceVisitor {
                 temp = self.rewrite(temp.into());
                 ::std::mem::swap(&mut temp, expr);
             }
+            Expr::LeftSection(ref lhs, ref op) => {
+                self.check_section(&expr.location, lhs, op, Assoc::Left)
+            }
+            Expr::RightSection(ref op, ref rhs) => {
+                self.check_section(&expr.location, rhs, op, Assoc::Right)
+            }
             _ => (),
         }
     }
@@ -41,9 +115,15 @@ impl PrecedenceVisitor {
     pub fn new() -> Self {
         Self {
             precedence: [(":".into(), (5, Assoc::Right))].into_iter().collect(),
+            errors: Errors::new(),
         }
     }
 
+    ///Returns the errors found in the modules visited since the last call
+    pub fn take_errors<V>(&mut self, value: V) -> Result<V, PrecedenceError> {
+        self.errors.into_result(value).map_err(PrecedenceError)
+    }
+
     fn get_precedence(&self, name: &Name) -> (isize, Assoc) {
         self.precedence
             .get(name)
@@ -51,9 +131,32 @@ impl PrecedenceVisitor {
             .unwrap_or_else(|| (9, Assoc::Left))
     }
 
+    ///Checks that the operand of a section binds tighter than the operator of the section
+    ///(1 * 2 +) and (+ 1 * 2) are valid while (1 + 2 *) and (* 1 + 2) are not
+    fn check_section(
+        &mut self,
+        location: &Location,
+        operand: &TypedExpr<Name>,
+        op: &Name,
+        side: Assoc,
+    ) {
+        if let Expr::OpApply(_, ref operand_op, _) = operand.expr {
+            let (op_prec, op_assoc) = self.get_precedence(op);
+            let (operand_prec, operand_assoc) = self.get_precedence(operand_op);
+            let valid = operand_prec > op_prec
+                || (operand_prec == op_prec && op_assoc == side && operand_assoc == side);
+            if !valid {
+                self.errors.insert(Located {
+                    location: *location,
+                    node: Error::Section(*op, *operand_op),
+                });
+            }
+        }
+    }
+
     ///Takes a operator expression the is in the form (1 + (2 * (3 - 4))) and rewrites it using the
     ///operators real precedences
-    fn rewrite(&self, mut input: Box<TypedExpr<Name>>) -> TypedExpr<Name> {
+    fn rewrite(&mut self, mut input: Box<TypedExpr<Name>>) -> TypedExpr<Name> {
         //Takes the two expressions at the top of the stack and applies the operator at the top to them
         fn reduce(expr_stack: &mut Vec<Box<TypedExpr<Name>>>, op_stack: &mut Vec<Name>) {
             assert!(expr_stack.len() >= 2);
@@ -96,7 +199,18 @@ impl PrecedenceVisitor {
                                         op_stack.push(op);
                                         break;
                                     }
-                                    _ => panic!("Syntax error: mismatched associativity"),
+                                    _ => {
+                                        //Report the error and parse as if both associated
+                                        //to the left
+                                        self.errors.insert(Located {
+                                            location,
+                                            node: Error::MismatchedAssociativity(
+                                                *previous_op,
+                                                op,
+                                            ),
+                                        });
+                                        reduce(&mut expr_stack, &mut op_stack);
+                                    }
                                 }
                             } else {
                                 reduce(&mut expr_stack, &mut op_stack);
@@ -133,22 +247,19 @@ mod tests {
         infix::PrecedenceVisitor,
         interner::intern,
         module::*,
-        parser::*,
         renamer::tests::{
             rename_expr,
-            rename_modules,
+            rename_string,
         },
         typecheck::*,
     };
 
     #[test]
     fn operator_precedence() {
-        let m = parse_string(
+        let mut modules = rename_string(
             r"import Prelude
 test = 3 * 4 - 5 * 6",
-        )
-        .unwrap();
-        let mut modules = rename_modules(m);
+        );
         let mut v = PrecedenceVisitor::new();
         for module in modules.iter_mut() {
             v.visit_module(module);
@@ -164,12 +275,10 @@ test = 3 * 4 - 5 * 6",
     }
     #[test]
     fn operator_precedence_parens() {
-        let m = parse_string(
+        let mut modules = rename_string(
             r"import Prelude
 test = 3 * 4 * (5 - 6)",
-        )
-        .unwrap();
-        let mut modules = rename_modules(m);
+        );
         let mut v = PrecedenceVisitor::new();
         for module in modules.iter_mut() {
             v.visit_module(module);
@@ -184,6 +293,59 @@ test = 3 * 4 * (5 - 6)",
         );
     }
 
+    #[test]
+    fn section_precedence() {
+        let mut modules = rename_string(
+            r"import Prelude
+test = (3 * 4 +)",
+        );
+        let mut v = PrecedenceVisitor::new();
+        for module in modules.iter_mut() {
+            v.visit_module(module);
+        }
+        let expected = TypedExpr::new(Expr::LeftSection(
+            op_apply(number(3), intern("*"), number(4)).into(),
+            intern("+"),
+        ));
+        assert_eq!(
+            modules.last().unwrap().bindings[0].matches,
+            Match::Simple(rename_expr(expected))
+        );
+    }
+
+    #[test]
+    fn section_precedence_error() {
+        let mut modules = rename_string(
+            r"import Prelude
+test = (* 3 + 4)",
+        );
+        let mut v = PrecedenceVisitor::new();
+        for module in modules.iter_mut() {
+            v.visit_module(module);
+        }
+        let error = v.take_errors(()).unwrap_err().to_string();
+        assert!(
+            error.contains("The operator * in a section binds tighter than the operator +"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn section_precedence_error_location() {
+        let error = typecheck_string(
+            r"import Prelude
+test = (1 + 2 *) 3",
+        )
+        .unwrap_err();
+        assert!(error.contains("--> <input>:2:8"), "{}", error);
+        assert!(
+            error.contains("The operator * in a section binds tighter than the operator +"),
+            "{}",
+            error
+        );
+    }
+
     #[test]
     fn rewrite_operators() {
         let mut expr = rename_expr(op_apply(
@@ -23,6 +23,8 @@ pub fn do_lambda_lift(module: Module<TypeAndStr>) -> Module<Id> {
 
 struct FreeVariables {
     name_supply: NameSupply,
+    ///The constraints of the top level binding which is being abstracted
+    constraints: Vec<Constraint<Name>>,
 }
 
 fn each_pattern_variables(pattern: &Pattern<Id>, f: &mut dyn FnMut(&Name)) {
@@ -81,8 +83,25 @@ impl FreeVariables {
                 }
                 let mut free_vars2 = HashMap::new();
                 for bind in bindings.iter_mut() {
+                    if let Lambda(..) = bind.expression {
+                        //Functions are lifted to the top level so they need to take the
+                        //dictionaries of the enclosing binding as well
+                        for constraint in self.constraints.iter() {
+                            if !bind.name.typ.constraints.contains(constraint) {
+                                bind.name.typ.constraints.push(constraint.clone());
+                            }
+                        }
+                    }
                     free_vars2.clear();
+                    //Lambdas inside a polymorphic local binding need its dictionaries as well
+                    let outer_constraints = self.constraints.clone();
+                    for constraint in bind.name.typ.constraints.iter() {
+                        if !self.constraints.contains(constraint) {
+                            self.constraints.push(constraint.clone());
+                        }
+                    }
                     self.free_variables(variables, &mut free_vars2, &mut bind.expression);
+                    self.constraints = outer_constraints;
                     //free_vars2 is the free variables for this binding
                     for (k, v) in free_vars2.iter() {
                         free_vars.insert(k.clone(), v.clone());
@@ -128,21 +147,27 @@ impl FreeVariables {
                 value: Integral(0),
             });
             ::std::mem::swap(&mut temp, input_expr);
+            //The lambdas are created in reverse so that they take the variables in the same
+            //order as they are applied below
+            let variables: Vec<_> = free_vars.values().collect();
             let mut e = {
                 let mut rhs = temp;
                 let mut typ = rhs.get_type().clone();
-                for (_, var) in free_vars.iter() {
-                    rhs = Lambda(var.clone(), rhs.into());
+                for var in variables.iter().rev() {
+                    rhs = Lambda((*var).clone(), rhs.into());
                     typ = function_type_(var.get_type().clone(), typ);
                 }
-                let id = Id::new(self.name_supply.from_str("#sc"), typ.clone(), vec![]);
+                //The lifted binding needs the dictionaries of the enclosing binding to call
+                //class functions on its type variables
+                let constraints = self.constraints.clone();
+                let id = Id::new(self.name_supply.from_str("#sc"), typ.clone(), constraints);
                 let bind = Binding {
                     name: id.clone(),
                     expression: rhs,
                 };
                 Let(vec![bind], Identifier(id).into())
             };
-            for (_, var) in free_vars.iter() {
+            for var in variables {
                 e = Apply(e.into(), Identifier(var.clone()).into());
             }
             *input_expr = e
@@ -165,7 +190,8 @@ pub fn lift_lambdas<T>(mut module: Module<T>) -> Module<T> {
                     ::std::mem::swap(&mut bs, bindings);
                     for mut bind in bs.into_iter() {
                         let is_lambda = matches!(bind.expression, Lambda(..));
-                        walk_expr(self, &mut bind.expression);
+                        //Visit the binding itself so that nested sections are lifted as well
+                        self.visit_expr(&mut bind.expression);
                         if is_lambda {
                             self.out_lambdas.push(bind);
                         } else {
@@ -216,6 +242,7 @@ pub fn abstract_module(mut module: Module<TypeAndStr>) -> Module<TypeAndStr> {
     use crate::core::mutable::*;
     impl Visitor<TypeAndStr> for FreeVariables {
         fn visit_binding(&mut self, bind: &mut Binding<TypeAndStr>) {
+            self.constraints = bind.name.typ.constraints.clone();
             self.free_variables(
                 &mut <_>::default(),
                 &mut <_>::default(),
@@ -226,6 +253,7 @@ pub fn abstract_module(mut module: Module<TypeAndStr>) -> Module<TypeAndStr> {
     let mut this =
         FreeVariables {
             name_supply: NameSupply::new(),
+            constraints: vec![],
         };
     this.visit_module(&mut module);
     module
@@ -305,11 +333,12 @@ test2 x =
     fn get_let<'a>(expr: &'a Expr<Id>, args: &mut Vec<InternedStr>) -> &'a Expr<Id> {
         match expr {
             &Apply(ref f, ref arg) => {
+                let e = get_let(f, args);
                 match **arg {
                     Identifier(ref i) => args.push(i.name.name),
                     _ => panic!("Expected identifier as argument"),
                 }
-                get_let(f, args)
+                e
             }
             _ => expr,
         }
@@ -4,7 +4,6 @@ use {
         cell::RefCell,
         collections::VecDeque,
         fmt,
-        iter::Peekable,
         rc::Rc,
     },
 };
@@ -46,6 +45,7 @@ pub enum TokenEnum {
     TYPEDECL,
     DATA,
     NEWTYPE,
+    TYPE,
     LAMBDA,
     DO,
     IMPORT,
@@ -53,12 +53,14 @@ pub enum TokenEnum {
     INFIXR,
     INFIX,
     DERIVING,
+    DEFAULT,
     IF,
     THEN,
     ELSE,
+    DOTDOT,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug, Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
 pub struct Location {
     pub column: isize,
     pub row: isize,
@@ -147,29 +149,100 @@ fn name_or_keyword(tok: &str) -> TokenEnum {
         "->" => ARROW,
         "data" => DATA,
         "newtype" => NEWTYPE,
+        "type" => TYPE,
         "do" => DO,
         "import" => IMPORT,
         "infixl" => INFIXL,
         "infixr" => INFIXR,
         "infix" => INFIX,
         "deriving" => DERIVING,
+        "default" => DEFAULT,
         "if" => IF,
         "then" => THEN,
         "else" => ELSE,
         _ => NAME,
     }
 }
+///The names of the ASCII control characters which can be used in escapes such as `\NUL`
+static ASCII_ESCAPES: &[(&str, char)] = &[
+    ("NUL", '\x00'),
+    ("SOH", '\x01'),
+    ("STX", '\x02'),
+    ("ETX", '\x03'),
+    ("EOT", '\x04'),
+    ("ENQ", '\x05'),
+    ("ACK", '\x06'),
+    ("BEL", '\x07'),
+    ("BS", '\x08'),
+    ("HT", '\x09'),
+    ("LF", '\x0A'),
+    ("VT", '\x0B'),
+    ("FF", '\x0C'),
+    ("CR", '\x0D'),
+    ("SO", '\x0E'),
+    ("SI", '\x0F'),
+    ("DLE", '\x10'),
+    ("DC1", '\x11'),
+    ("DC2", '\x12'),
+    ("DC3", '\x13'),
+    ("DC4", '\x14'),
+    ("NAK", '\x15'),
+    ("SYN", '\x16'),
+    ("ETB", '\x17'),
+    ("CAN", '\x18'),
+    ("EM", '\x19'),
+    ("SUB", '\x1A'),
+    ("ESC", '\x1B'),
+    ("FS", '\x1C'),
+    ("GS", '\x1D'),
+    ("RS", '\x1E'),
+    ("US", '\x1F'),
+    ("SP", ' '),
+    ("DEL", '\x7F'),
+];
+
 ///Returns whether the character is a haskell operator
 fn is_operator(first_char: char) -> bool {
     matches!(
         first_char,
-        '+' | '-' | '*' | '/' | '.' | '$' | ':' | '=' | '<' | '>' | '|' | '&' | '!'
+        '+' | '-' | '*' | '/' | '.' | '$' | ':' | '=' | '<' | '>' | '|' | '&' | '!' | '#' | '%'
+            | '^' | '?' | '@' | '~'
     )
 }
 
+///Splits a qualified name such as `Data.Map.lookup` into its qualifier and the unqualified name
+///Returns None if the name is not qualified
+pub fn split_qualified(name: &str) -> Option<(&str, &str)> {
+    let mut end = None;
+    let mut start = 0;
+    loop {
+        let segment = &name[start..];
+        if !segment.starts_with(char::is_uppercase) {
+            break;
+        }
+        let len = segment
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(segment.len());
+        if segment[len..].starts_with('.') && len + 1 < segment.len() {
+            end = Some(start + len);
+            start += len + 1;
+        } else {
+            break;
+        }
+    }
+    end.map(|i| (&name[..i], &name[i + 1..]))
+}
+
+///Returns the name with any module qualifier removed
+pub fn unqualified(name: &str) -> &str {
+    split_qualified(name).map_or(name, |(_, name)| name)
+}
+
 pub struct Lexer<Stream: Iterator<Item = char>> {
     ///The input which the lexer processes
-    input: Peekable<Stream>,
+    input: Stream,
+    ///Characters which have been read from the input but not yet consumed
+    lookahead: VecDeque<char>,
     ///The current location of the lexer
     location: Location,
     ///All the current unprocessed tokens stored on a stack
@@ -182,19 +255,26 @@ pub struct Lexer<Stream: Iterator<Item = char>> {
     offset: usize,
     ///The string interner, cached here for efficency
     interner: Rc<RefCell<Interner>>,
+    ///The first error encountered while scanning the input
+    error: Option<Located<String>>,
+    ///The contents of the `{-# ... #-}` pragmas which have been skipped
+    pragmas: Vec<String>,
 }
 
 impl<Stream: Iterator<Item = char>> Lexer<Stream> {
     ///Constructs a new lexer with a default sized token buffer and the local string interner
     pub fn new(input: Stream) -> Self {
         Self {
-            input: input.peekable(),
+            input,
+            lookahead: VecDeque::new(),
             location: <_>::default(),
             unprocessed_tokens: vec![],
             tokens: VecDeque::with_capacity(20),
             indent_levels: vec![],
             offset: 0,
             interner: get_local_interner(),
+            error: None,
+            pragmas: vec![],
         }
     }
     ///Returns a new token with some special rules necessary for the parsing of the module declaration
@@ -257,39 +337,84 @@ impl<Stream: Iterator<Item = char>> Lexer<Stream> {
         self.offset += 1;
     }
 
+    ///Returns the position of the next token, which the lexer can be moved back to with `reset`
+    pub fn position(&self) -> usize {
+        self.tokens.len() - self.offset
+    }
+
+    ///Moves the lexer back to `position` so that the tokens after it are returned again
+    pub fn reset(&mut self, position: usize) {
+        self.offset = self.tokens.len() - position;
+    }
+
     ///Returns true if the lexer is still valid (it has not hit EOF)
     pub fn valid(&self) -> bool {
         self.offset > 0 || self.tokens.back().map(|x| x.token != EOF).unwrap_or(true)
     }
 
+    ///Returns the first error which was encountered while scanning the input
+    pub fn error(&self) -> Option<&Located<String>> {
+        self.error.as_ref()
+    }
+
+    ///Returns the pragmas which have been scanned so far, without the enclosing `{-#` and `#-}`
+    pub fn pragmas(&self) -> &[String] {
+        &self.pragmas
+    }
+
+    ///Records an error at `location` and returns an EOF token so that no more tokens are produced
+    fn error_token(&mut self, location: Location, message: String) -> Token {
+        if self.error.is_none() {
+            self.error = Some(Located {
+                location,
+                node: message,
+            });
+        }
+        Token::new(&self.interner, EOF, "", location)
+    }
+
     ///Peeks at the next character in the input
     fn peek_char(&mut self) -> Option<char> {
-        self.input.peek().map(|c| *c)
+        self.peek_char_at(0)
+    }
+
+    ///Peeks at the character 'n' characters ahead in the input
+    fn peek_char_at(&mut self, n: usize) -> Option<char> {
+        while self.lookahead.len() <= n {
+            let c = self.input.next()?;
+            self.lookahead.push_back(c);
+        }
+        Some(self.lookahead[n])
+    }
+
+    ///Takes the next character from the input without updating the position
+    fn next_char(&mut self) -> Option<char> {
+        self.lookahead.pop_front().or_else(|| self.input.next())
     }
 
     ///Reads a character from the input and increments the current position
     fn read_char(&mut self) -> Option<char> {
-        self.input.next().map(|c| {
+        self.next_char().map(|c| {
             self.location.absolute += 1;
             self.location.column += 1;
             if matches!(c, '\n' | '\r') {
                 self.location.column = 0;
                 self.location.row += 1;
                 //If this is a \n\r line ending skip the next char without increasing the location
-                if c == '\r' && self.input.peek() == Some(&'\n') {
-                    self.input.next();
+                if c == '\r' && self.peek_char() == Some('\n') {
+                    self.next_char();
                 }
             }
             c
         })
     }
 
-    ///Scans digits into a string
-    fn scan_digits(&mut self) -> String {
+    ///Scans digits of the radix `radix` into a string
+    fn scan_digits(&mut self, radix: u32) -> String {
         let mut result = String::new();
 
         while let Some(x) = self.peek_char() {
-            if !x.is_digit(10) {
+            if !x.is_digit(radix) {
                 break;
             }
             self.read_char();
@@ -298,37 +423,274 @@ impl<Stream: Iterator<Item = char>> Lexer<Stream> {
         result
     }
     ///Scans a number, float or isizeeger and returns the appropriate token
+    ///Integers written in hexadecimal, octal or binary are returned in decimal form
     fn scan_number(&mut self, c: char, location: Location) -> Token {
+        if c == '0' {
+            let radix = match self.peek_char() {
+                Some('x' | 'X') => 16,
+                Some('o' | 'O') => 8,
+                Some('b' | 'B') => 2,
+                _ => 10,
+            };
+            //`0x` which is not followed by a digit is the number 0 followed by the name `x`
+            let has_digits = self.peek_char_at(1).map_or(false, |x| x.is_digit(radix));
+            if radix != 10 && has_digits {
+                self.read_char();
+                let digits = self.scan_digits(radix);
+                return match isize::from_str_radix(&digits, radix) {
+                    Ok(number) => {
+                        Token::new(&self.interner, NUMBER, &number.to_string(), location)
+                    }
+                    Err(_) => self.error_token(
+                        location,
+                        format!("Integer literal `{}` is too large", digits),
+                    ),
+                };
+            }
+        }
         let mut number = c.to_string();
-        number.push_str(self.scan_digits().as_ref());
+        number.push_str(self.scan_digits(10).as_ref());
         let mut token = NUMBER;
-        match self.peek_char() {
-            Some('.') => {
-                self.input.next();
+        //The '.' only starts a fraction if a digit follows it, otherwise it is a token of its own
+        //as in the arithmetic sequence [1..] or the composition `f 1.g`
+        if self.peek_char() == Some('.')
+            && self.peek_char_at(1).is_some_and(|x| x.is_ascii_digit())
+        {
+            self.read_char();
+            token = FLOAT;
+            number.push('.');
+            number.push_str(self.scan_digits(10).as_ref());
+        }
+        if let Some(e @ ('e' | 'E')) = self.peek_char() {
+            //The exponent is only part of the number if it has digits, `2e` is `2` applied to `e`
+            let sign = match self.peek_char_at(1) {
+                Some(sign @ ('+' | '-')) => Some(sign),
+                _ => None,
+            };
+            let digit_offset = if sign.is_some() { 2 } else { 1 };
+            if self.peek_char_at(digit_offset).map_or(false, |x| x.is_digit(10)) {
+                self.read_char();
+                number.push(e);
+                if let Some(sign) = sign {
+                    self.read_char();
+                    number.push(sign);
+                }
+                number.push_str(self.scan_digits(10).as_ref());
                 token = FLOAT;
-                number.push('.');
-                number.push_str(self.scan_digits().as_ref());
             }
-            _ => (),
+        }
+        if token == NUMBER && number.parse::<isize>().is_err() {
+            return self.error_token(
+                location,
+                format!("Integer literal `{}` is too large", number),
+            );
         }
         Token::new(&self.interner, token, number.as_ref(), location)
     }
-    ///Scans an identifier or a keyword
-    fn scan_identifier(&mut self, c: char, start_location: Location) -> Token {
-        let mut result = c.to_string();
+    ///Scans an escape sequence whose leading `\` has already been read.
+    ///Returns `None` for the empty escape `\&` and for string gaps
+    fn scan_escape(&mut self, location: Location) -> Result<Option<char>, Token> {
+        let c = match self.read_char() {
+            Some(c) => c,
+            None => return Err(self.error_token(location, "Unexpected EOF in escape".into())),
+        };
+        let escaped = match c {
+            'a' => '\x07',
+            'b' => '\x08',
+            'f' => '\x0C',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'v' => '\x0B',
+            '\\' | '"' | '\'' => c,
+            '&' => return Ok(None),
+            '^' => match self.read_char() {
+                Some(x @ '@'..='_') => char::from(x as u8 - b'@'),
+                _ => return Err(self.error_token(location, "Invalid control escape".into())),
+            },
+            'x' | 'o' | '0'..='9' => {
+                let (radix, mut digits) = match c {
+                    'x' => (16, String::new()),
+                    'o' => (8, String::new()),
+                    _ => (10, c.to_string()),
+                };
+                digits.push_str(&self.scan_digits(radix));
+                match u32::from_str_radix(&digits, radix).ok().and_then(char::from_u32) {
+                    Some(x) => x,
+                    None => {
+                        return Err(self.error_token(location, "Numeric escape out of range".into()))
+                    }
+                }
+            }
+            _ if c.is_whitespace() => {
+                //A string gap, the whitespace up to the next `\` is ignored
+                loop {
+                    match self.read_char() {
+                        Some('\\') => return Ok(None),
+                        Some(x) if x.is_whitespace() => (),
+                        _ => return Err(self.error_token(location, "Invalid string gap".into())),
+                    }
+                }
+            }
+            _ => {
+                //Take the longest matching name so that `\SOH` is not read as `\SO` followed by `H`
+                let mut name = c.to_string();
+                let mut longest = None;
+                for n in 0..3 {
+                    let escape = ASCII_ESCAPES.iter().find(|&&(escape, _)| escape == name);
+                    if let Some(&(_, code)) = escape {
+                        longest = Some((n, code));
+                    }
+                    match self.peek_char_at(n) {
+                        Some(x) => name.push(x),
+                        None => break,
+                    }
+                }
+                match longest {
+                    Some((n, code)) => {
+                        for _ in 0..n {
+                            self.read_char();
+                        }
+                        code
+                    }
+                    None => {
+                        return Err(
+                            self.error_token(location, format!("Invalid escape `\\{}`", c))
+                        )
+                    }
+                }
+            }
+        };
+        Ok(Some(escaped))
+    }
+    ///Scans a string literal whose leading `"` has already been read
+    fn scan_string(&mut self, location: Location) -> Token {
+        let mut string = String::new();
+        loop {
+            match self.read_char() {
+                Some('"') => return Token::new(&self.interner, STRING, string.as_ref(), location),
+                Some('\\') => match self.scan_escape(self.location) {
+                    Ok(Some(x)) => string.push(x),
+                    Ok(None) => (),
+                    Err(token) => return token,
+                },
+                Some('\n' | '\r') | None => {
+                    return self.error_token(location, "Unterminated string literal".into())
+                }
+                Some(x) => string.push(x),
+            }
+        }
+    }
+    ///Scans a character literal whose leading `'` has already been read
+    fn scan_char(&mut self, location: Location) -> Token {
+        let c = match self.read_char() {
+            Some('\\') => {
+                let escape_location = self.location;
+                match self.scan_escape(escape_location) {
+                    Ok(Some(x)) => x,
+                    Ok(None) => {
+                        return self.error_token(escape_location, "Invalid character escape".into())
+                    }
+                    Err(token) => return token,
+                }
+            }
+            Some('\'') => return self.error_token(location, "Empty character literal".into()),
+            Some('\n' | '\r') | None => {
+                return self.error_token(location, "Unterminated character literal".into())
+            }
+            Some(x) => x,
+        };
+        match self.read_char() {
+            Some('\'') => Token::new(&self.interner, CHAR, &c.to_string(), location),
+            _ => self.error_token(location, "Character literals must contain one character".into()),
+        }
+    }
+    ///Returns true if the `-` which was just read starts a line comment.
+    ///A line comment is two or more dashes which are not part of an operator such as `-->`
+    fn is_line_comment(&mut self) -> bool {
+        let mut n = 0;
+        while self.peek_char_at(n) == Some('-') {
+            n += 1;
+        }
+        n > 0 && self.peek_char_at(n).map_or(true, |x| !is_operator(x))
+    }
+    ///Skips a possibly nested block comment whose leading `{` has already been read.
+    ///The contents of comments of the form `{-# ... #-}` are saved as pragmas
+    fn skip_block_comment(&mut self, location: Location) -> Result<(), Token> {
+        self.read_char();
+        let mut depth = 1;
+        let mut contents = String::new();
+        while depth > 0 {
+            match self.read_char() {
+                Some('{') if self.peek_char() == Some('-') => {
+                    self.read_char();
+                    depth += 1;
+                }
+                Some('-') if self.peek_char() == Some('}') => {
+                    self.read_char();
+                    depth -= 1;
+                }
+                Some(c) => contents.push(c),
+                None => return Err(self.error_token(location, "Unterminated block comment".into())),
+            }
+        }
+        if contents.len() >= 2 && contents.starts_with('#') && contents.ends_with('#') {
+            self.pragmas
+                .push(contents[1..contents.len() - 1].trim().to_string());
+        }
+        Ok(())
+    }
+    ///Scans the rest of an identifier into `result`
+    fn scan_identifier_chars(&mut self, result: &mut String) {
         while let Some(ch) = self.peek_char() {
-            if !ch.is_alphanumeric() && ch != '_' {
+            if !ch.is_alphanumeric() && ch != '_' && ch != '\'' {
                 break;
             }
             self.read_char();
             result.push(ch);
         }
-        Token::new(
-            &self.interner,
-            name_or_keyword(result.as_ref()),
-            result.as_ref(),
-            start_location,
-        )
+    }
+    ///Scans an identifier or a keyword
+    ///A name qualified by a module such as `Data.Map.lookup` or `M.+` is scanned as a single token
+    fn scan_identifier(&mut self, c: char, start_location: Location) -> Token {
+        let mut result = c.to_string();
+        let mut is_module = c.is_uppercase();
+        self.scan_identifier_chars(&mut result);
+        let token = loop {
+            if !is_module || self.peek_char() != Some('.') {
+                break NAME;
+            }
+            match self.peek_char_at(1) {
+                Some(x) if x.is_alphabetic() || x == '_' => {
+                    self.read_char();
+                    self.read_char();
+                    result.push('.');
+                    result.push(x);
+                    is_module = x.is_uppercase();
+                    self.scan_identifier_chars(&mut result);
+                }
+                //`M..` is not treated as a qualified operator so that `[False..]` still works
+                Some(x) if is_operator(x) && x != '.' => {
+                    self.read_char();
+                    result.push('.');
+                    while let Some(ch) = self.peek_char() {
+                        if !is_operator(ch) {
+                            break;
+                        }
+                        self.read_char();
+                        result.push(ch);
+                    }
+                    break OPERATOR;
+                }
+                _ => break NAME,
+            }
+        };
+        let token = if token == NAME && split_qualified(&result).is_none() {
+            name_or_keyword(result.as_ref())
+        } else {
+            token
+        };
+        Token::new(&self.interner, token, result.as_ref(), start_location)
     }
 
     ///Returns the next token but if it is not an '}' it will attempt to insert a '}' automatically
@@ -509,19 +871,36 @@ impl<Stream: Iterator<Item = char>> Lexer<Stream> {
     ///Scans the character stream for the next token
     ///Return EOF token if the token stream has ehas ended
     fn next_indent_token(&mut self, newline: &mut bool) -> Token {
-        let mut c = ' ';
-        //Skip all whitespace before the token
-        while c.is_whitespace() {
-            if let Some(x) = self.read_char() {
-                c = x;
-                if self.location.column == 0 {
-                    //newline detected
-                    *newline = true;
+        if self.error.is_some() {
+            return Token::eof();
+        }
+        let start_row = self.location.row;
+        //Skip all whitespace and comments before the token
+        let c = loop {
+            match self.read_char() {
+                Some(x) if x.is_whitespace() => (),
+                Some('-') if self.is_line_comment() => {
+                    while !matches!(self.peek_char(), Some('\n' | '\r') | None) {
+                        self.read_char();
+                    }
                 }
-            } else {
-                return Token::eof();
+                Some('{') if self.peek_char() == Some('-') => {
+                    if let Err(token) = self.skip_block_comment(self.location) {
+                        return token;
+                    }
+                }
+                Some(x) => break Some(x),
+                None => break None,
             }
+        };
+        if self.location.row != start_row {
+            //newline detected
+            *newline = true;
         }
+        let c = match c {
+            Some(c) => c,
+            None => return Token::eof(),
+        };
         let start_location = self.location;
 
         //Decide how to tokenize depending on what the first char is
@@ -543,6 +922,7 @@ impl<Stream: Iterator<Item = char>> Lexer<Stream> {
                 "::" => TYPEDECL,
                 "=>" => CONTEXTARROW,
                 "|" => PIPE,
+                ".." => DOTDOT,
                 _ => OPERATOR,
             };
             return Token::new(&self.interner, tok, result.as_ref(), start_location);
@@ -551,35 +931,19 @@ impl<Stream: Iterator<Item = char>> Lexer<Stream> {
         } else if c.is_alphabetic() || c == '_' {
             return self.scan_identifier(c, start_location);
         } else if c == '`' {
-            let x = self.read_char().expect("Unexpected end of input");
-            assert!(x.is_alphanumeric() || x == '_', "Parse error on '{:?}'", x);
-            let mut token = self.scan_identifier(x, start_location);
-            let end_tick = self.read_char();
-            match end_tick {
-                Some('`') => (),
-                Some(x) => panic!("Parse error on '{:?}'", x),
-                None => panic!("Unexpected end of input"),
+            let mut token = match self.read_char() {
+                Some(x) if x.is_alphabetic() || x == '_' => self.scan_identifier(x, start_location),
+                _ => return self.error_token(start_location, "Expected a name after '`'".into()),
+            };
+            if self.read_char() != Some('`') {
+                return self.error_token(start_location, "Expected a closing '`'".into());
             }
             token.token = OPERATOR;
             return token;
         } else if c == '"' {
-            let mut string = String::new();
-            loop {
-                match self.read_char() {
-                    Some('"') => {
-                        return Token::new(&self.interner, STRING, string.as_ref(), start_location)
-                    }
-                    Some(x) => string.push(x),
-                    None => panic!("Unexpected EOF"),
-                }
-            }
+            return self.scan_string(start_location);
         } else if c == '\'' {
-            let x = self.read_char().expect("Unexpected EOF");
-            if self.read_char() != Some('\'') {
-                panic!("Multi char character")
-            }
-            //FIXME: Slow
-            return Token::new(&self.interner, CHAR, &x.to_string(), start_location);
+            return self.scan_char(start_location);
         }
         let tok = match c {
             ';' => SEMICOLON,
@@ -591,7 +955,7 @@ impl<Stream: Iterator<Item = char>> Lexer<Stream> {
             '}' => RBRACE,
             ',' => COMMA,
             '\\' => LAMBDA,
-            _ => EOF,
+            _ => return self.error_token(start_location, format!("Unexpected character {:?}", c)),
         };
         //FIXME: Slow
         Token::new(&self.interner, tok, c.to_string().as_ref(), start_location)
@@ -629,4 +993,135 @@ in test"
         assert_eq!(*lexer.next(), Token::new_(OPERATOR, "+"));
         assert_eq!(*lexer.next(), Token::new_(NUMBER, "3"));
     }
+
+    #[test]
+    fn arithmetic_sequence() {
+        let mut lexer = Lexer::new("[1..10] [1.5..]".chars());
+
+        assert_eq!(*lexer.next(), Token::new_(LBRACKET, "["));
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "1"));
+        assert_eq!(*lexer.next(), Token::new_(DOTDOT, ".."));
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "10"));
+        assert_eq!(*lexer.next(), Token::new_(RBRACKET, "]"));
+        assert_eq!(*lexer.next(), Token::new_(LBRACKET, "["));
+        assert_eq!(*lexer.next(), Token::new_(FLOAT, "1.5"));
+        assert_eq!(*lexer.next(), Token::new_(DOTDOT, ".."));
+        assert_eq!(*lexer.next(), Token::new_(RBRACKET, "]"));
+    }
+
+    #[test]
+    fn qualified_names() {
+        let mut lexer = Lexer::new("Data.Map.lookup M.Just M.+ Just . f [False..]".chars());
+
+        assert_eq!(*lexer.next(), Token::new_(NAME, "Data.Map.lookup"));
+        assert_eq!(*lexer.next(), Token::new_(NAME, "M.Just"));
+        assert_eq!(*lexer.next(), Token::new_(OPERATOR, "M.+"));
+        assert_eq!(*lexer.next(), Token::new_(NAME, "Just"));
+        assert_eq!(*lexer.next(), Token::new_(OPERATOR, "."));
+        assert_eq!(*lexer.next(), Token::new_(NAME, "f"));
+        assert_eq!(*lexer.next(), Token::new_(LBRACKET, "["));
+        assert_eq!(*lexer.next(), Token::new_(NAME, "False"));
+        assert_eq!(*lexer.next(), Token::new_(DOTDOT, ".."));
+
+        assert_eq!(
+            split_qualified("Data.Map.lookup"),
+            Some(("Data.Map", "lookup"))
+        );
+        assert_eq!(split_qualified("M.+"), Some(("M", "+")));
+        assert_eq!(split_qualified("."), None);
+        assert_eq!(split_qualified("lookup"), None);
+    }
+
+    #[test]
+    fn comments() {
+        let mut lexer = Lexer::new("test -- comment\n{- block {- nested -} -} 2 --> x ---".chars());
+
+        assert_eq!(*lexer.next(), Token::new_(NAME, "test"));
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "2"));
+        assert_eq!(lexer.current().location.row, 1);
+        assert_eq!(*lexer.next(), Token::new_(OPERATOR, "-->"));
+        assert_eq!(*lexer.next(), Token::new_(NAME, "x"));
+        assert_eq!(lexer.next().token, EOF);
+        assert!(lexer.error().is_none());
+    }
+
+    #[test]
+    fn pragmas() {
+        let mut lexer =
+            Lexer::new("{-# LANGUAGE NoMonomorphismRestriction #-}\n{- # -} test {-#-}".chars());
+        assert_eq!(*lexer.next(), Token::new_(NAME, "test"));
+        assert_eq!(lexer.next().token, EOF);
+        assert_eq!(lexer.pragmas(), ["LANGUAGE NoMonomorphismRestriction"]);
+    }
+
+    #[test]
+    fn escapes() {
+        let mut lexer = Lexer::new(
+            r#""a\n\t\"\65\x41\o101\&1\SOH\SO\^A\DEL\    \b" '\n' '\'' '\x41' '"'"#.chars(),
+        );
+
+        assert_eq!(
+            *lexer.next(),
+            Token::new_(STRING, "a\n\t\"AAA1\x01\x0E\x01\x7Fb")
+        );
+        assert_eq!(*lexer.next(), Token::new_(CHAR, "\n"));
+        assert_eq!(*lexer.next(), Token::new_(CHAR, "'"));
+        assert_eq!(*lexer.next(), Token::new_(CHAR, "A"));
+        assert_eq!(*lexer.next(), Token::new_(CHAR, "\""));
+        assert!(lexer.error().is_none());
+    }
+
+    #[test]
+    fn number_followed_by_dot() {
+        let mut lexer = Lexer::new("[1..] f 1.g 1.".chars());
+
+        assert_eq!(*lexer.next(), Token::new_(LBRACKET, "["));
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "1"));
+        assert_eq!(*lexer.next(), Token::new_(DOTDOT, ".."));
+        assert_eq!(*lexer.next(), Token::new_(RBRACKET, "]"));
+        assert_eq!(*lexer.next(), Token::new_(NAME, "f"));
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "1"));
+        assert_eq!(*lexer.next(), Token::new_(OPERATOR, "."));
+        assert_eq!(*lexer.next(), Token::new_(NAME, "g"));
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "1"));
+        assert_eq!(*lexer.next(), Token::new_(OPERATOR, "."));
+    }
+
+    #[test]
+    fn numeric_literals() {
+        let mut lexer = Lexer::new("0x1F 0o17 0b101 1.5e-3 2E10 1e+2 2e x-1 0x".chars());
+
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "31"));
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "15"));
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "5"));
+        assert_eq!(*lexer.next(), Token::new_(FLOAT, "1.5e-3"));
+        assert_eq!(*lexer.next(), Token::new_(FLOAT, "2E10"));
+        assert_eq!(*lexer.next(), Token::new_(FLOAT, "1e+2"));
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "2"));
+        assert_eq!(*lexer.next(), Token::new_(NAME, "e"));
+        assert_eq!(*lexer.next(), Token::new_(NAME, "x"));
+        assert_eq!(*lexer.next(), Token::new_(OPERATOR, "-"));
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "1"));
+        assert_eq!(*lexer.next(), Token::new_(NUMBER, "0"));
+        assert_eq!(*lexer.next(), Token::new_(NAME, "x"));
+    }
+
+    #[test]
+    fn lexical_errors() {
+        fn error(input: &str) -> Located<String> {
+            let mut lexer = Lexer::new(input.chars());
+            while lexer.next().token != EOF {}
+            lexer.error().expect("Expected a lexical error").clone()
+        }
+        let err = error("x = \"abc\\qd\"");
+        assert_eq!((err.location.row, err.location.column), (0, 9));
+        let err = error("x = 1\ny = \"abc");
+        assert_eq!((err.location.row, err.location.column), (1, 5));
+        let err = error("x {- {- -} ");
+        assert_eq!((err.location.row, err.location.column), (0, 3));
+        let err = error("'ab'");
+        assert_eq!((err.location.row, err.location.column), (0, 1));
+        let err = error("99999999999999999999999");
+        assert_eq!(err.node, "Integer literal `99999999999999999999999` is too large");
+    }
 }
@@ -9,7 +9,10 @@ extern crate test;
 #[cfg(not(test))]
 use getopts::Options;
 #[cfg(not(test))]
-use vm::execute_main_module;
+use {
+    search_path::SearchPath,
+    vm::execute_main_module,
+};
 
 macro_rules! write_core_expr(
     ($e:expr, $f:expr, $($p:pat),*) => ({
@@ -41,6 +44,8 @@ mod builtins;
 mod compiler;
 mod core;
 mod deriving;
+mod diagnostics;
+mod exhaustive;
 mod graph;
 mod infix;
 mod interner;
@@ -52,14 +57,22 @@ mod renamer;
 #[cfg(not(test))]
 mod repl;
 mod scoped_map;
+mod search_path;
 mod typecheck;
 mod types;
+mod unlit;
 mod vm;
 
 #[cfg(not(test))]
 fn main() {
     let mut opts = Options::new();
-    opts.optflag("i", "interactive", "Starts the REPL");
+    opts.optflag("", "interactive", "Starts the REPL");
+    opts.optmulti(
+        "i",
+        "include",
+        "Adds a directory to the module search path",
+        "DIR",
+    );
     opts.optflag("h", "help", "Print help");
 
     let matches = {
@@ -72,8 +85,13 @@ fn main() {
         return;
     }
 
-    if matches.opt_present("i") {
-        repl::start();
+    let mut search_path = SearchPath::new();
+    for dir in matches.opt_strs("i") {
+        search_path.add_root(dir);
+    }
+
+    if matches.opt_present("interactive") {
+        repl::start(&search_path);
         return;
     }
 
@@ -83,8 +101,19 @@ fn main() {
     }
 
     let modulename = &matches.free[0];
-    match execute_main_module(modulename.as_ref()).unwrap() {
-        Some(x) => println!("{:?}", x),
-        None => println!("Error running module {}", modulename),
+    match execute_main_module(&search_path, modulename.as_ref()) {
+        Ok((result, warnings)) => {
+            for warning in warnings {
+                eprintln!("{}", warning);
+            }
+            match result {
+                Some(x) => println!("{:?}", x),
+                None => println!("Error running module {}", modulename),
+            }
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
     }
 }
@@ -24,14 +24,52 @@ use self::Expr::*;
 #[derive(Clone, Debug)]
 pub struct Module<Ident = InternedStr> {
     pub name: Ident,
+    //None if the module has no export list, in which case every top level declaration is exported
+    pub exports: Option<Vec<Located<Export<Ident>>>>,
     pub imports: Vec<Import<Ident>>,
     pub bindings: Vec<Binding<Ident>>,
     pub type_declarations: Vec<TypeDeclaration<Ident>>,
     pub classes: Vec<Class<Ident>>,
     pub instances: Vec<Instance<Ident>>,
+    pub deriving_instances: Vec<DerivingInstance<Ident>>,
     pub data_definitions: Vec<DataDefinition<Ident>>,
     pub newtypes: Vec<Newtype<Ident>>,
+    pub type_synonyms: Vec<TypeSynonym<Ident>>,
     pub fixity_declarations: Vec<FixityDeclaration<Ident>>,
+    //The types listed in the `default` declaration of the module, None if there is no declaration
+    pub default_types: Option<Vec<Type<Ident>>>,
+    //The language extensions enabled by `LANGUAGE` pragmas
+    pub extensions: Vec<InternedStr>,
+}
+
+///An entry in the export list of a module
+#[derive(Clone, Debug, PartialEq)]
+pub enum Export<Ident = InternedStr> {
+    ///A value, a class or a type without any of its constructors
+    Name(Ident),
+    ///`T(..)` exports the type or class `T` along with all its constructors, fields or methods
+    All(Ident),
+    ///`T(A, B)` exports the type or class `T` along with the listed constructors, fields or methods
+    With(Ident, Vec<Ident>),
+    ///`module M` exports everything which is in scope both as `name` and as `M.name`
+    Module(InternedStr),
+}
+
+impl<Ident> Export<Ident> {
+    ///Applies `f` to every name in the entry
+    pub fn map<F, Ident2>(self, mut f: F) -> Export<Ident2>
+    where
+        F: FnMut(Ident) -> Ident2,
+    {
+        match self {
+            Self::Name(name) => Export::Name(f(name)),
+            Self::All(name) => Export::All(f(name)),
+            Self::With(name, names) => {
+                Export::With(f(name), names.into_iter().map(&mut f).collect())
+            }
+            Self::Module(module) => Export::Module(module),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -39,24 +77,58 @@ pub struct Import<Ident> {
     pub module: InternedStr,
     //None if 'import Name'
     //Some(names) if 'import Name (names)'
-    pub imports: Option<Vec<Ident>>,
+    //The entries of import lists have the same form as in export lists, except for `module M`
+    pub imports: Option<Vec<Located<Export<Ident>>>>,
+    ///The names listed in 'import Name hiding (names)'
+    pub hiding: Vec<Located<Export<Ident>>>,
+    ///True if 'import qualified Name', in which case the names are only in scope with a qualifier
+    pub qualified: bool,
+    ///The qualifier given by 'import Name as Alias'
+    pub alias: Option<InternedStr>,
+    ///The location of the `import` keyword
+    pub location: Location,
+}
+
+impl<Ident> Import<Ident> {
+    ///Returns the qualifier which the imported names can be referred to with
+    pub fn qualifier(&self) -> InternedStr {
+        self.alias.unwrap_or(self.module)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Class<Ident = InternedStr> {
     pub constraints: Vec<Constraint<Ident>>,
     pub name: Ident,
-    pub variable: TypeVariable,
+    ///The parameters of the class, `a` and `b` in `class Convert a b`
+    pub variables: Vec<TypeVariable>,
     pub declarations: Vec<TypeDeclaration<Ident>>,
     pub bindings: Vec<Binding<Ident>>,
+    ///The location of the `class` keyword
+    pub location: Location,
 }
 
 #[derive(Clone, Debug)]
 pub struct Instance<Ident = InternedStr> {
     pub bindings: Vec<Binding<Ident>>,
     pub constraints: Vec<Constraint<Ident>>,
-    pub typ: Type<Ident>,
+    ///The types the instance is defined for, one for each parameter of the class
+    pub types: Vec<Type<Ident>>,
+    pub classname: Ident,
+    ///The location of the `instance` keyword
+    pub location: Location,
+}
+
+///An instance whose functions are generated from the definition of the type, either from a
+///standalone `deriving instance Show a => Show (Tree a)` declaration or from the deriving
+///clause of a newtype
+#[derive(Clone, Debug)]
+pub struct DerivingInstance<Ident = InternedStr> {
+    pub constraints: Vec<Constraint<Ident>>,
+    pub types: Vec<Type<Ident>>,
     pub classname: Ident,
+    ///The location of the declaration which the instance is derived from
+    pub location: Location,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -74,6 +146,8 @@ pub struct Constructor<Ident = InternedStr> {
     pub typ: Qualified<Type<Ident>, Ident>,
     pub tag: isize,
     pub arity: isize,
+    ///The names of the fields if the constructor was declared with record syntax
+    pub fields: Vec<Ident>,
 }
 
 #[derive(PartialEq, Clone, Debug)]
@@ -82,6 +156,8 @@ pub struct DataDefinition<Ident = InternedStr> {
     pub typ: Qualified<Type<Ident>, Ident>,
     pub parameters: HashMap<InternedStr, isize>,
     pub deriving: Vec<Ident>,
+    ///The location of the `data` keyword
+    pub location: Location,
 }
 
 #[derive(PartialEq, Clone, Debug)]
@@ -90,6 +166,18 @@ pub struct Newtype<Ident = InternedStr> {
     pub constructor_name: Ident,
     pub constructor_type: Qualified<Type<Ident>, Ident>,
     pub deriving: Vec<Ident>,
+    ///The location of the `newtype` keyword
+    pub location: Location,
+}
+
+///A type synonym such as `type String = [Char]` or `type Pair a = (a, a)`
+#[derive(PartialEq, Clone, Debug)]
+pub struct TypeSynonym<Ident = InternedStr> {
+    pub name: Ident,
+    pub parameters: Vec<TypeVariable>,
+    pub typ: Type<Ident>,
+    ///The location of the `type` keyword
+    pub location: Location,
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -110,6 +198,8 @@ pub struct FixityDeclaration<Ident = InternedStr> {
 pub struct TypeDeclaration<Ident = InternedStr> {
     pub typ: Qualified<Type<Ident>, Ident>,
     pub name: Ident,
+    ///The location of the declared name
+    pub location: Location,
 }
 impl<T: fmt::Display + AsRef<str>> fmt::Display for TypeDeclaration<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -160,11 +250,22 @@ pub struct Alternative<Ident = InternedStr> {
     pub where_bindings: Option<Vec<Binding<Ident>>>,
 }
 
-#[derive(Clone, Debug, PartialOrd, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Pattern<Ident = InternedStr> {
     Number(isize),
+    Fractional(f64),
+    Char(char),
+    String(InternedStr),
     Identifier(Ident),
     Constructor(Ident, Vec<Pattern<Ident>>),
+    ///C { field = pattern, .. }
+    Record(Ident, Vec<(Ident, Pattern<Ident>)>),
+    ///name@pattern
+    As(Ident, Box<Pattern<Ident>>),
+    ///~pattern
+    Irrefutable(Box<Pattern<Ident>>),
+    ///!pattern
+    Bang(Box<Pattern<Ident>>),
     WildCard,
 }
 
@@ -176,7 +277,7 @@ pub enum Match<Ident = InternedStr> {
 impl<Ident> Match<Ident> {
     pub fn location<'a>(&'a self) -> &'a Location {
         match *self {
-            Self::Guards(ref gs) => &gs[0].predicate.location,
+            Self::Guards(ref gs) => gs[0].location(),
             Self::Simple(ref e) => &e.location,
         }
     }
@@ -184,9 +285,23 @@ impl<Ident> Match<Ident> {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Guard<Ident = InternedStr> {
-    pub predicate: TypedExpr<Ident>,
+    ///The qualifiers which must all succeed for the guard to be taken,
+    ///`| Just y <- lookup k m, let z = y + 1, z > 0 = z`
+    pub qualifiers: Vec<Qualifier<Ident>>,
     pub expression: TypedExpr<Ident>,
 }
+impl<Ident> Guard<Ident> {
+    pub fn location(&self) -> &Location {
+        match self.qualifiers.first() {
+            Some(Qualifier::Generator(pattern, _)) => &pattern.location,
+            Some(Qualifier::Guard(e)) => &e.location,
+            Some(Qualifier::Let(bindings)) if !bindings.is_empty() => {
+                bindings[0].matches.location()
+            }
+            _ => &self.expression.location,
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum DoBinding<Ident = InternedStr> {
@@ -195,6 +310,15 @@ pub enum DoBinding<Ident = InternedStr> {
     DoExpr(TypedExpr<Ident>),
 }
 
+///A qualifier in a list comprehension such as [e | p <- xs, let y = f p, g y]
+///or in a guard such as | Just y <- lookup k m, let z = y + 1, z > 0
+#[derive(Clone, Debug, PartialEq)]
+pub enum Qualifier<Ident = InternedStr> {
+    Generator(Located<Pattern<Ident>>, TypedExpr<Ident>),
+    Guard(TypedExpr<Ident>),
+    Let(Vec<Binding<Ident>>),
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum LiteralData {
     Integral(isize),
@@ -219,6 +343,25 @@ pub enum Expr<Ident = InternedStr> {
     Do(Vec<DoBinding<Ident>>, Box<TypedExpr<Ident>>),
     TypeSig(Box<TypedExpr<Ident>>, Qualified<Type<Ident>, Ident>),
     Paren(Box<TypedExpr<Ident>>),
+    ListComprehension(Box<TypedExpr<Ident>>, Vec<Qualifier<Ident>>),
+    ///[from..], [from, then..], [from..to] and [from, then..to]
+    ArithmeticSequence(
+        Box<TypedExpr<Ident>>,
+        Option<Box<TypedExpr<Ident>>>,
+        Option<Box<TypedExpr<Ident>>>,
+    ),
+    ///C { field = expr, .. }
+    Record(Ident, Vec<(Ident, TypedExpr<Ident>)>),
+    ///expr { field = expr, .. }
+    RecordUpdate(Box<TypedExpr<Ident>>, Vec<(Ident, TypedExpr<Ident>)>),
+    ///(expr op)
+    LeftSection(Box<TypedExpr<Ident>>, Ident),
+    ///(op expr)
+    RightSection(Ident, Box<TypedExpr<Ident>>),
+    ///(expr,) and (, expr), the missing elements are the arguments of the resulting function
+    TupleSection(Vec<Option<TypedExpr<Ident>>>),
+    ///`_` or `_name`, a placeholder whose expected type is reported by the typechecker
+    Hole(InternedStr),
 }
 impl<T: fmt::Display + AsRef<str>> fmt::Display for Binding<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -250,15 +393,76 @@ impl<T: fmt::Display + AsRef<str>> fmt::Display for Expr<T> {
             OpApply(ref lhs, ref op, ref rhs) => write!(f, "({} {} {})", lhs, op, rhs),
             TypeSig(ref expr, ref typ) => write!(f, "{} {}", expr, typ),
             Paren(ref expr) => write!(f, "({})", expr),
+            ListComprehension(ref expr, ref qualifiers) => {
+                write!(f, "[{} |", expr)?;
+                for (i, qualifier) in qualifiers.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, " {}", qualifier)?;
+                }
+                write!(f, "]")
+            }
+            ArithmeticSequence(ref from, ref then, ref to) => {
+                write!(f, "[{}", from)?;
+                if let Some(ref then) = *then {
+                    write!(f, ", {}", then)?;
+                }
+                write!(f, "..")?;
+                if let Some(ref to) = *to {
+                    write!(f, "{}", to)?;
+                }
+                write!(f, "]")
+            }
+            Record(ref name, ref fields) => {
+                write!(f, "{} ", name)?;
+                write_fields(f, fields)
+            }
+            RecordUpdate(ref expr, ref fields) => {
+                write!(f, "{} ", expr)?;
+                write_fields(f, fields)
+            }
+            LeftSection(ref expr, ref op) => write!(f, "({} {})", expr, op),
+            RightSection(ref op, ref expr) => write!(f, "({} {})", op, expr),
+            TupleSection(ref elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    if let Some(ref e) = *element {
+                        write!(f, "{}", e)?;
+                    }
+                }
+                write!(f, ")")
+            }
+            Hole(ref name) => write!(f, "{}", name),
             _ => Ok(()),
         }
     }
 }
+
+fn write_fields<Ident: fmt::Display, T: fmt::Display>(
+    f: &mut fmt::Formatter,
+    fields: &[(Ident, T)],
+) -> fmt::Result {
+    write!(f, "{{")?;
+    for (i, &(ref name, ref value)) in fields.iter().enumerate() {
+        if i != 0 {
+            write!(f, ",")?;
+        }
+        write!(f, " {} = {}", name, value)?;
+    }
+    write!(f, " }}")
+}
 impl<T: fmt::Display + AsRef<str>> fmt::Display for Pattern<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             &Self::Identifier(ref s) => write!(f, "{}", s),
             &Self::Number(ref i) => write!(f, "{}", i),
+            &Self::Fractional(ref v) => write!(f, "{}", v),
+            &Self::Char(ref c) => write!(f, "'{}'", c),
+            &Self::String(ref s) => write!(f, "\"{}\"", *s),
             &Self::Constructor(ref name, ref patterns) => {
                 write!(f, "({} ", name)?;
                 for p in patterns.iter() {
@@ -266,6 +470,13 @@ impl<T: fmt::Display + AsRef<str>> fmt::Display for Pattern<T> {
                 }
                 write!(f, ")")
             }
+            &Self::Record(ref name, ref fields) => {
+                write!(f, "{} ", name)?;
+                write_fields(f, fields)
+            }
+            &Self::As(ref name, ref pattern) => write!(f, "{}@{}", name, pattern),
+            &Self::Irrefutable(ref pattern) => write!(f, "~{}", pattern),
+            &Self::Bang(ref pattern) => write!(f, "!{}", pattern),
             &Self::WildCard => write!(f, "_"),
         }
     }
@@ -282,13 +493,32 @@ impl<T: fmt::Display + AsRef<str>> fmt::Display for Match<T> {
             Self::Simple(ref e) => write!(f, "{}", *e),
             Self::Guards(ref gs) => {
                 for g in gs.iter() {
-                    write!(f, "| {} -> {}\n", g.predicate, g.expression)?;
+                    write!(f, "|")?;
+                    for (i, q) in g.qualifiers.iter().enumerate() {
+                        write!(f, "{} {}", if i == 0 { "" } else { "," }, q)?;
+                    }
+                    writeln!(f, " -> {}", g.expression)?;
                 }
                 Ok(())
             }
         }
     }
 }
+impl<T: fmt::Display + AsRef<str>> fmt::Display for Qualifier<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Generator(ref p, ref e) => write!(f, "{} <- {}", p.node, e),
+            Self::Guard(ref e) => write!(f, "{}", e),
+            Self::Let(ref bindings) => {
+                write!(f, "let {{ ")?;
+                for bind in bindings.iter() {
+                    write!(f, "{}; ", bind)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
 impl fmt::Display for LiteralData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -334,7 +564,19 @@ pub fn walk_module<Ident, V: Visitor<Ident>>(visitor: &mut V, module: &Module<Id
 pub fn walk_binding<Ident, V: Visitor<Ident>>(visitor: &mut V, binding: &Binding<Ident>) {
     match binding.matches {
         Match::Simple(ref e) => visitor.visit_expr(e),
-        _ => panic!(),
+        Match::Guards(ref gs) => {
+            for g in gs.iter() {
+                for q in g.qualifiers.iter() {
+                    walk_qualifier(visitor, q);
+                }
+                visitor.visit_expr(&g.expression);
+            }
+        }
+    }
+    if let Some(ref bindings) = binding.where_bindings {
+        for bind in bindings.iter() {
+            visitor.visit_binding(bind);
+        }
     }
 }
 
@@ -385,7 +627,54 @@ pub fn walk_expr<Ident, V: Visitor<Ident>>(visitor: &mut V, expr: &TypedExpr<Ide
         }
         &TypeSig(ref expr, _) => visitor.visit_expr(expr),
         &Paren(ref expr) => visitor.visit_expr(expr),
-        &Literal(..) | &Identifier(..) => (),
+        &ListComprehension(ref expr, ref qualifiers) => {
+            for qualifier in qualifiers.iter() {
+                walk_qualifier(visitor, qualifier);
+            }
+            visitor.visit_expr(expr);
+        }
+        &ArithmeticSequence(ref from, ref then, ref to) => {
+            visitor.visit_expr(from);
+            if let Some(ref then) = *then {
+                visitor.visit_expr(then);
+            }
+            if let Some(ref to) = *to {
+                visitor.visit_expr(to);
+            }
+        }
+        &Record(_, ref fields) => {
+            for &(_, ref e) in fields.iter() {
+                visitor.visit_expr(e);
+            }
+        }
+        &RecordUpdate(ref expr, ref fields) => {
+            visitor.visit_expr(expr);
+            for &(_, ref e) in fields.iter() {
+                visitor.visit_expr(e);
+            }
+        }
+        &LeftSection(ref expr, _) | &RightSection(_, ref expr) => visitor.visit_expr(expr),
+        &TupleSection(ref elements) => {
+            for e in elements.iter().flatten() {
+                visitor.visit_expr(e);
+            }
+        }
+        &Literal(..) | &Identifier(..) | &Hole(..) => (),
+    }
+}
+
+pub fn walk_qualifier<Ident, V: Visitor<Ident>>(visitor: &mut V, qualifier: &Qualifier<Ident>) {
+    match *qualifier {
+        Qualifier::Generator(ref pattern, ref e) => {
+            visitor.visit_pattern(&pattern.node);
+            visitor.visit_expr(e);
+        }
+        Qualifier::Guard(ref e) => visitor.visit_expr(e),
+        Qualifier::Let(ref bs) => {
+            for b in bs.iter() {
+                visitor.visit_binding(b);
+            }
+        }
     }
 }
 
@@ -395,7 +684,9 @@ pub fn walk_alternative<Ident, V: Visitor<Ident>>(visitor: &mut V, alt: &Alterna
         Match::Simple(ref e) => visitor.visit_expr(e),
         Match::Guards(ref gs) => {
             for g in gs.iter() {
-                visitor.visit_expr(&g.predicate);
+                for q in g.qualifiers.iter() {
+                    walk_qualifier(visitor, q);
+                }
                 visitor.visit_expr(&g.expression);
             }
         }
@@ -411,10 +702,21 @@ pub fn walk_alternative<Ident, V: Visitor<Ident>>(visitor: &mut V, alt: &Alterna
 }
 
 pub fn walk_pattern<Ident, V: Visitor<Ident>>(visitor: &mut V, pattern: &Pattern<Ident>) {
-    if let &Pattern::Constructor(_, ref ps) = pattern {
-        for p in ps.iter() {
-            visitor.visit_pattern(p);
+    match pattern {
+        &Pattern::Constructor(_, ref ps) => {
+            for p in ps.iter() {
+                visitor.visit_pattern(p);
+            }
         }
+        &Pattern::Record(_, ref fields) => {
+            for &(_, ref p) in fields.iter() {
+                visitor.visit_pattern(p);
+            }
+        }
+        &Pattern::As(_, ref p) | &Pattern::Irrefutable(ref p) | &Pattern::Bang(ref p) => {
+            visitor.visit_pattern(p)
+        }
+        _ => (),
     }
 }
 
@@ -457,7 +759,9 @@ pub fn walk_binding_mut<Ident, V: MutVisitor<Ident>>(
         Match::Simple(ref mut e) => visitor.visit_expr(e),
         Match::Guards(ref mut gs) => {
             for g in gs.iter_mut() {
-                visitor.visit_expr(&mut g.predicate);
+                for q in g.qualifiers.iter_mut() {
+                    walk_qualifier_mut(visitor, q);
+                }
                 visitor.visit_expr(&mut g.expression);
             }
         }
@@ -511,7 +815,57 @@ pub fn walk_expr_mut<Ident, V: MutVisitor<Ident>>(visitor: &mut V, expr: &mut Ty
         }
         TypeSig(ref mut expr, _) => visitor.visit_expr(expr),
         Paren(ref mut expr) => visitor.visit_expr(expr),
-        Literal(..) | Identifier(..) => (),
+        ListComprehension(ref mut expr, ref mut qualifiers) => {
+            for qualifier in qualifiers.iter_mut() {
+                walk_qualifier_mut(visitor, qualifier);
+            }
+            visitor.visit_expr(expr);
+        }
+        ArithmeticSequence(ref mut from, ref mut then, ref mut to) => {
+            visitor.visit_expr(from);
+            if let Some(ref mut then) = *then {
+                visitor.visit_expr(then);
+            }
+            if let Some(ref mut to) = *to {
+                visitor.visit_expr(to);
+            }
+        }
+        Record(_, ref mut fields) => {
+            for &mut (_, ref mut e) in fields.iter_mut() {
+                visitor.visit_expr(e);
+            }
+        }
+        RecordUpdate(ref mut expr, ref mut fields) => {
+            visitor.visit_expr(expr);
+            for &mut (_, ref mut e) in fields.iter_mut() {
+                visitor.visit_expr(e);
+            }
+        }
+        LeftSection(ref mut expr, _) | RightSection(_, ref mut expr) => visitor.visit_expr(expr),
+        TupleSection(ref mut elements) => {
+            for e in elements.iter_mut().flatten() {
+                visitor.visit_expr(e);
+            }
+        }
+        Literal(..) | Identifier(..) | Hole(..) => (),
+    }
+}
+
+pub fn walk_qualifier_mut<Ident, V: MutVisitor<Ident>>(
+    visitor: &mut V,
+    qualifier: &mut Qualifier<Ident>,
+) {
+    match *qualifier {
+        Qualifier::Generator(ref mut pattern, ref mut e) => {
+            visitor.visit_pattern(&mut pattern.node);
+            visitor.visit_expr(e);
+        }
+        Qualifier::Guard(ref mut e) => visitor.visit_expr(e),
+        Qualifier::Let(ref mut bs) => {
+            for b in bs.iter_mut() {
+                visitor.visit_binding(b);
+            }
+        }
     }
 }
 
@@ -524,7 +878,9 @@ pub fn walk_alternative_mut<Ident, V: MutVisitor<Ident>>(
         Match::Simple(ref mut e) => visitor.visit_expr(e),
         Match::Guards(ref mut gs) => {
             for g in gs.iter_mut() {
-                visitor.visit_expr(&mut g.predicate);
+                for q in g.qualifiers.iter_mut() {
+                    walk_qualifier_mut(visitor, q);
+                }
                 visitor.visit_expr(&mut g.expression);
             }
         }
@@ -540,10 +896,21 @@ pub fn walk_pattern_mut<Ident, V: MutVisitor<Ident>>(
     visitor: &mut V,
     pattern: &mut Pattern<Ident>,
 ) {
-    if let Pattern::Constructor(_, ref mut ps) = pattern {
-        for p in ps.iter_mut() {
-            visitor.visit_pattern(p);
+    match pattern {
+        Pattern::Constructor(_, ref mut ps) => {
+            for p in ps.iter_mut() {
+                visitor.visit_pattern(p);
+            }
+        }
+        Pattern::Record(_, ref mut fields) => {
+            for &mut (_, ref mut p) in fields.iter_mut() {
+                visitor.visit_pattern(p);
+            }
+        }
+        Pattern::As(_, ref mut p) | Pattern::Irrefutable(ref mut p) | Pattern::Bang(ref mut p) => {
+            visitor.visit_pattern(p)
         }
+        _ => (),
     }
 }
 
@@ -588,3 +955,13 @@ pub fn encode_binding_identifier(
     let buffer = ["#", &instancename, &bindingname].join("");
     intern(buffer.as_ref())
 }
+
+///Returns the name which identifies an instance for `types` in the names of its bindings.
+///Instances of multi parameter classes separate the names of each type with a ','
+pub fn instance_name<Id: fmt::Display + AsRef<str>>(types: &[Type<Id>]) -> InternedStr {
+    let names: Vec<&str> = types
+        .iter()
+        .map(|typ| crate::lexer::unqualified(extract_applied_type(typ).ctor().name.as_ref()))
+        .collect();
+    intern(&names.join(","))
+}
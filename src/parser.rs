@@ -1,5 +1,11 @@
 use {
     crate::{
+        diagnostics::{
+            Diagnostic,
+            SourceFile,
+            Span,
+            ToDiagnostics,
+        },
         interner::*,
         lexer::{
             TokenEnum::*,
@@ -10,6 +16,11 @@ use {
             LiteralData::*,
             *,
         },
+        search_path::{
+            read_file,
+            SearchPath,
+        },
+        vm::VMError,
     },
     std::{
         collections::{
@@ -18,12 +29,7 @@ use {
         },
         error,
         fmt,
-        fs::File,
-        io::{
-            self,
-            Read,
-        },
-        mem::swap,
+        io,
         str::FromStr,
     },
 };
@@ -37,6 +43,9 @@ use {
 ///it can continue parsing without having to move the lexer's position.
 pub struct Parser<Iter: Iterator<Item = char>> {
     lexer: Lexer<Iter>,
+    ///The errors of the items in blocks which were skipped so that the rest of the block could
+    ///still be parsed
+    errors: Vec<SyntaxError>,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -46,22 +55,14 @@ enum Error {
 }
 
 #[derive(Debug, PartialEq)]
-pub struct ParseError(Located<Error>);
-
-pub type ParseResult<T> = Result<T, ParseError>;
-
-impl From<io::Error> for ParseError {
-    fn from(io_error: io::Error) -> ParseError {
-        ParseError(Located {
-            location: Location::eof(),
-            node: Error::Message(io_error.to_string()),
-        })
-    }
+struct SyntaxError {
+    span: Span,
+    error: Error,
 }
 
-impl fmt::Display for ParseError {
+impl fmt::Display for SyntaxError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.0.node {
+        match self.error {
             Error::UnexpectedToken(unexpected, expected) => {
                 write!(
                     f,
@@ -74,12 +75,79 @@ impl fmt::Display for ParseError {
     }
 }
 
+///The syntax errors found while parsing, which always contains at least one error
+#[derive(Debug, PartialEq)]
+pub struct ParseError(Vec<SyntaxError>);
+
+pub type ParseResult<T> = Result<T, ParseError>;
+
+impl ParseError {
+    fn new(span: Span, error: Error) -> ParseError {
+        ParseError(vec![SyntaxError { span, error }])
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(io_error: io::Error) -> ParseError {
+        ParseError::new(Span::unknown(), Error::Message(io_error.to_string()))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
 impl error::Error for ParseError {
     fn description(&self) -> &str {
         "parse error"
     }
 }
 
+impl ToDiagnostics for ParseError {
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.0
+            .iter()
+            .map(|error| Diagnostic::error(error.span, error.to_string()))
+            .collect()
+    }
+}
+
+///Adds the errors of `error` which have not already been reported
+fn add_errors(errors: &mut Vec<SyntaxError>, error: ParseError) {
+    for error in error.0 {
+        if !errors.contains(&error) {
+            errors.push(error);
+        }
+    }
+}
+
+///Returns the span of the source code which `token` was scanned from
+fn token_span(token: &Token) -> Span {
+    let length = match token.token {
+        //Tokens inserted by the layout algorithm do not appear in the source
+        INDENTLEVEL => 0,
+        STRING | CHAR => token.value.chars().count() as isize + 2,
+        _ => token.value.chars().count() as isize,
+    };
+    let start = token.location;
+    Span::new(
+        start,
+        Location {
+            column: start.column + length,
+            absolute: start.absolute + length,
+            ..start
+        },
+    )
+}
+
 enum BindOrTypeDecl {
     Binding(Binding),
     TypeDecl(TypeDeclaration),
@@ -122,6 +190,7 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
     pub fn new(iterator: Iter) -> Self {
         Self {
             lexer: Lexer::new(iterator),
+            errors: vec![],
         }
     }
 
@@ -134,29 +203,68 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
     }
 
     fn error<T>(&self, message: ::std::string::String) -> ParseResult<T> {
-        Err(ParseError(Located {
-            location: self.lexer.current().location,
-            node: Error::Message(message),
-        }))
+        if let Some(error) = self.lexer_error() {
+            return Err(error);
+        }
+        Err(ParseError::new(
+            token_span(self.lexer.current()),
+            Error::Message(message),
+        ))
     }
     fn unexpected_token(&self, expected: &'static [TokenEnum], actual: TokenEnum) -> ParseError {
-        ParseError(Located {
-            location: self.lexer.current().location,
-            node: Error::UnexpectedToken(expected, actual),
+        if let Some(error) = self.lexer_error() {
+            return error;
+        }
+        ParseError::new(
+            token_span(self.lexer.current()),
+            Error::UnexpectedToken(expected, actual),
+        )
+    }
+    ///Returns the error of the lexer if it failed to scan the input.
+    ///Lexical errors take precedence as any later errors are caused by the lexer stopping
+    fn lexer_error(&self) -> Option<ParseError> {
+        self.lexer.error().map(|error| {
+            ParseError::new(
+                Span::from(error.location),
+                Error::Message(error.node.clone()),
+            )
         })
     }
 
     pub fn module(&mut self) -> ParseResult<Module> {
-        let modulename = match self.lexer.module_next().token {
+        match self.partial_module()? {
+            (module, None) => Ok(module),
+            (_, Some(errors)) => Err(errors),
+        }
+    }
+
+    ///Parses a module, recovering from syntax errors in top level declarations by skipping to the
+    ///start of the next declaration.
+    ///Returns a module containing every declaration which could be parsed along with all the
+    ///errors which were found, so that later passes can still inspect the rest of the module
+    pub fn partial_module(&mut self) -> ParseResult<(Module, Option<ParseError>)> {
+        let (modulename, exports) = match self.lexer.module_next().token {
             MODULE => {
                 let modulename = expect!(self, NAME).value.clone();
+                let exports = if self.lexer.peek().token == LPARENS {
+                    self.lexer.next();
+                    let exports = if self.lexer.peek().token == RPARENS {
+                        vec![]
+                    } else {
+                        self.sep_by_1(|this| this.export(), COMMA)?
+                    };
+                    expect!(self, RPARENS);
+                    Some(exports)
+                } else {
+                    None
+                };
                 expect!(self, WHERE);
                 expect!(self, LBRACE);
-                modulename
+                (modulename, exports)
             }
             LBRACE => {
                 //No module declaration was found so default to Main
-                intern("Main")
+                (intern("Main"), None)
             }
             _ => unexpected!(self, [LBRACE]),
         };
@@ -175,64 +283,246 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
         let mut classes = vec![];
         let mut bindings = vec![];
         let mut instances = vec![];
+        let mut deriving_instances = vec![];
         let mut type_declarations = vec![];
         let mut data_definitions = vec![];
         let mut newtypes = vec![];
+        let mut type_synonyms = vec![];
         let mut fixity_declarations = vec![];
+        let mut default_types = None;
         loop {
             //Do a lookahead to see what the next top level binding is
             let token = self.lexer.peek().token;
+            if token == RBRACE || token == EOF {
+                break;
+            }
+            let start = self.lexer.position();
 
-            match token {
-                NAME | LPARENS => match self.binding_or_type_declaration()? {
+            let result = match token {
+                NAME | LPARENS => self.binding_or_type_declaration().map(|decl| match decl {
                     BindOrTypeDecl::Binding(bind) => bindings.push(bind),
                     BindOrTypeDecl::TypeDecl(decl) => type_declarations.push(decl),
-                },
-                CLASS => classes.push(self.class()?),
-                INSTANCE => instances.push(self.instance()?),
-                DATA => data_definitions.push(self.data_definition()?),
-                NEWTYPE => newtypes.push(self.newtype()?),
-                INFIXL | INFIXR | INFIX => fixity_declarations.push(self.fixity_declaration()?),
+                }),
+                CLASS => self.class().map(|class| classes.push(class)),
+                INSTANCE => self.instance().map(|instance| instances.push(instance)),
+                DERIVING => self
+                    .deriving_instance()
+                    .map(|instance| deriving_instances.push(instance)),
+                DATA => self.data_definition().map(|data| data_definitions.push(data)),
+                NEWTYPE => self.newtype().map(|newtype| newtypes.push(newtype)),
+                TYPE => self.type_synonym().map(|synonym| type_synonyms.push(synonym)),
+                INFIXL | INFIXR | INFIX => self
+                    .fixity_declaration()
+                    .map(|fixity| fixity_declarations.push(fixity)),
+                DEFAULT => self.default_declaration().and_then(|types| {
+                    if default_types.is_some() {
+                        return self.error("Multiple default declarations".to_string());
+                    }
+                    default_types = Some(types);
+                    Ok(())
+                }),
                 _ => {
                     self.lexer.next();
-                    break;
+                    static EXPECTED: &[TokenEnum] = &[
+                        NAME, LPARENS, CLASS, INSTANCE, DERIVING, DATA, NEWTYPE, TYPE, INFIXL,
+                        INFIXR, INFIX, DEFAULT,
+                    ];
+                    Err(self.unexpected_token(EXPECTED, token))
+                }
+            };
+            //A declaration must end where the next one starts or at the end of the module
+            let result = result.and_then(|()| match self.lexer.peek().token {
+                SEMICOLON | RBRACE | EOF => Ok(()),
+                actual => {
+                    self.lexer.next();
+                    static EXPECTED: &[TokenEnum] = &[SEMICOLON, RBRACE];
+                    Err(self.unexpected_token(EXPECTED, actual))
                 }
+            });
+            if let Err(error) = result {
+                add_errors(&mut self.errors, error);
+                self.skip_declaration(start);
             }
 
-            let semicolon = self.lexer.next();
-            debug!("More bindings? {:?}", semicolon.token);
-            if semicolon.token != SEMICOLON {
+            debug!("More bindings? {:?}", self.lexer.peek().token);
+            if self.lexer.peek().token != SEMICOLON {
                 break;
             }
+            self.lexer.next();
         }
 
-        self.lexer.backtrack();
-        expect!(self, RBRACE);
-        expect!(self, EOF);
+        if let Err(error) = self.module_end() {
+            add_errors(&mut self.errors, error);
+        }
+        if let Some(error) = self.lexer_error() {
+            add_errors(&mut self.errors, error);
+        }
 
-        Ok(Module {
+        for data in data_definitions.iter() {
+            bindings.extend(field_selectors(data));
+        }
+        let extensions = self
+            .lexer
+            .pragmas()
+            .iter()
+            .filter_map(|pragma| pragma.strip_prefix("LANGUAGE"))
+            .flat_map(|names| names.split(','))
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(intern)
+            .collect();
+
+        let module = Module {
             name: modulename,
+            exports,
             imports,
             bindings,
             type_declarations,
             classes,
             instances,
+            deriving_instances,
             data_definitions,
             newtypes,
+            type_synonyms,
             fixity_declarations,
-        })
+            default_types,
+            extensions,
+        };
+        let errors = if self.errors.is_empty() {
+            None
+        } else {
+            Some(ParseError(::std::mem::take(&mut self.errors)))
+        };
+        Ok((module, errors))
+    }
+
+    fn module_end(&mut self) -> ParseResult<()> {
+        expect!(self, RBRACE);
+        expect!(self, EOF);
+        Ok(())
+    }
+
+    ///Moves the lexer from `start` past the top level declaration which failed to parse.
+    ///The layout algorithm closes every block of the declaration before the semicolon which
+    ///starts the next declaration, so the lexer stops before the first semicolon or '}' which is
+    ///not inside a block
+    fn skip_declaration(&mut self, start: usize) {
+        self.lexer.reset(start);
+        let mut depth = 0;
+        loop {
+            match self.lexer.next().token {
+                LBRACE => depth += 1,
+                RBRACE if depth > 0 => depth -= 1,
+                SEMICOLON | RBRACE if depth == 0 => break,
+                EOF => break,
+                _ => (),
+            }
+        }
+        self.lexer.backtrack();
+    }
+
+    ///Parses the items of a block separated by semicolons, such as the bindings of a `where` or
+    ///the alternatives of a `case`.
+    ///An item which fails to parse is skipped so that the rest of the block is still parsed, its
+    ///errors are reported once the module has been parsed
+    fn block_items<T>(&mut self, mut f: impl FnMut(&mut Self) -> ParseResult<T>) -> Vec<T> {
+        let mut items = vec![];
+        loop {
+            let start = self.lexer.position();
+            match f(self) {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    add_errors(&mut self.errors, error);
+                    self.skip_block_item(start);
+                }
+            }
+            if self.lexer.peek().token != SEMICOLON {
+                break;
+            }
+            self.lexer.next();
+        }
+        items
+    }
+
+    ///Moves the lexer from `start` past the item of a block which failed to parse, stopping before
+    ///the semicolon or '}' which ends it. An implicit block of a `let` is only closed when `in`
+    ///is parsed so `in` ends the item as well
+    fn skip_block_item(&mut self, start: usize) {
+        self.lexer.reset(start);
+        let mut depth = 0;
+        loop {
+            match self.lexer.next().token {
+                LBRACE => depth += 1,
+                RBRACE if depth > 0 => depth -= 1,
+                SEMICOLON | RBRACE | IN if depth == 0 => break,
+                EOF => break,
+                _ => (),
+            }
+        }
+        self.lexer.backtrack();
+    }
+
+    fn export(&mut self) -> ParseResult<Located<Export>> {
+        if self.lexer.peek().token == MODULE {
+            let location = self.lexer.next().location;
+            let node = Export::Module(expect!(self, NAME).value);
+            return Ok(Located { location, node });
+        }
+        self.import_item()
+    }
+
+    ///Parses an entry of an import list, `name`, `(op)`, `T(..)` or `T(A, b)`
+    fn import_item(&mut self) -> ParseResult<Located<Export>> {
+        let location = self.lexer.peek().location;
+        let node = self.import_item_()?;
+        Ok(Located { location, node })
+    }
+
+    fn import_item_(&mut self) -> ParseResult<Export> {
+        match self.lexer.next().token {
+            LPARENS => {
+                let op = expect!(self, OPERATOR).value;
+                expect!(self, RPARENS);
+                Ok(Export::Name(op))
+            }
+            NAME => {
+                let name = self.lexer.current().value;
+                if self.lexer.peek().token != LPARENS {
+                    return Ok(Export::Name(name));
+                }
+                self.lexer.next();
+                let export = match self.lexer.peek().token {
+                    DOTDOT => {
+                        self.lexer.next();
+                        Export::All(name)
+                    }
+                    RPARENS => Export::With(name, vec![]),
+                    _ => Export::With(name, self.sep_by_1(|this| this.import_name(), COMMA)?),
+                };
+                expect!(self, RPARENS);
+                Ok(export)
+            }
+            _ => unexpected!(self, [NAME, LPARENS]),
+        }
     }
 
     fn import(&mut self) -> ParseResult<Import<InternedStr>> {
-        expect!(self, IMPORT);
+        let location = expect!(self, IMPORT).location;
+        let qualified = self.import_keyword("qualified");
         let module_name = expect!(self, NAME).value;
-        let imports = if self.lexer.peek().token == LPARENS {
-            self.lexer.next();
+        let alias = if self.import_keyword("as") {
+            Some(expect!(self, NAME).value)
+        } else {
+            None
+        };
+        let is_hiding = self.import_keyword("hiding");
+        let imports = if is_hiding || self.lexer.peek().token == LPARENS {
+            expect!(self, LPARENS);
             let x = if self.lexer.peek().token == RPARENS {
                 self.lexer.next();
                 vec![]
             } else {
-                let imports = self.sep_by_1(|this| Ok(expect!(this, NAME).value), COMMA)?;
+                let imports = self.sep_by_1(|this| this.import_item(), COMMA)?;
                 expect!(self, RPARENS);
                 imports
             };
@@ -240,15 +530,59 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
         } else {
             None
         };
+        let (imports, hiding) = if is_hiding {
+            (None, imports.unwrap_or_default())
+        } else {
+            (imports, vec![])
+        };
         Ok(Import {
             module: module_name,
             imports,
+            hiding,
+            qualified,
+            alias,
+            location,
         })
     }
 
+    ///Consumes the next token if it is `name`.
+    ///`qualified`, `as` and `hiding` are only keywords inside an import declaration
+    fn import_keyword(&mut self, name: &str) -> bool {
+        let token = self.lexer.peek();
+        if token.token == NAME && token.value.as_ref() == name {
+            self.lexer.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    ///Parses a name in an import list, either an identifier or an operator in parentheses
+    fn import_name(&mut self) -> ParseResult<InternedStr> {
+        if self.lexer.peek().token == LPARENS {
+            self.lexer.next();
+            let op = expect!(self, OPERATOR).value;
+            expect!(self, RPARENS);
+            Ok(op)
+        } else {
+            Ok(expect!(self, NAME).value)
+        }
+    }
+
     fn class(&mut self) -> ParseResult<Class> {
-        expect!(self, CLASS);
+        let location = expect!(self, CLASS).location;
         let (constraints, typ) = self.constrained_type()?;
+        let (classname, arguments) = match split_class_head(typ) {
+            Some(head) => head,
+            None => return self.error("Parse error in class declaration header".to_string()),
+        };
+        let mut variables = vec![];
+        for argument in arguments {
+            match argument {
+                Type::Variable(var) => variables.push(var),
+                _ => return self.error("Parse error in class declaration header".to_string()),
+            }
+        }
 
         expect!(self, WHERE);
         expect!(self, LBRACE);
@@ -260,17 +594,7 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                 BindOrTypeDecl::Binding(mut bind) => {
                     //Bindings need to have their name altered to distinguish them from
                     //the declarations name
-
-                    let Type::Application(ref op, _) = typ else {
-                        return self.error(
-                            "The name of the class must start with an uppercase letter".to_string(),
-                        );
-                    };
-
-                    let Type::Constructor(ref ctor) = **op else {
-                        return self.error("Expected type operator".to_string());
-                    };
-                    bind.name = encode_binding_identifier(ctor.name, bind.name);
+                    bind.name = encode_binding_identifier(classname, bind.name);
                     bindings.push(bind)
                 }
                 BindOrTypeDecl::TypeDecl(decl) => declarations.push(decl),
@@ -279,66 +603,99 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
 
         expect!(self, RBRACE);
 
-        let Type::Application(l, r) = typ else {
-            return self.error("Parse error in class declaration header".to_string());
-        };
-        let (Type::Constructor(classname), Type::Variable(var)) = (*l, *r) else {
-            return self.error("Parse error in class declaration header".to_string());
-        };
         Ok(Class {
             constraints,
-            name: classname.name,
-            variable: var,
+            name: classname,
+            variables,
             declarations,
             bindings,
+            location,
         })
     }
 
     fn instance(&mut self) -> ParseResult<Instance> {
+        let location = expect!(self, INSTANCE).location;
+
+        let (constraints, instance_type) = self.constrained_type()?;
+        let (classname, types) = match split_class_head(instance_type) {
+            Some(head) => head,
+            None => return self.error("Expected type operator".to_string()),
+        };
+        if types.iter().any(|typ| !matches!(*extract_applied_type(typ), Type::Constructor(_))) {
+            return self.error("TypeVariable in instance".to_string());
+        }
+        expect!(self, WHERE);
+        expect!(self, LBRACE);
+
+        let mut bindings = self.sep_by_1(|this| this.binding(), SEMICOLON)?;
+        let type_name = instance_name(&types);
+        for bind in bindings.iter_mut() {
+            bind.name = encode_binding_identifier(type_name, bind.name);
+        }
+
+        expect!(self, RBRACE);
+        Ok(Instance {
+            types,
+            classname,
+            bindings,
+            constraints,
+            location,
+        })
+    }
+
+    ///Parses a standalone deriving declaration
+    ///deriving instance Show a => Show (Tree a)
+    fn deriving_instance(&mut self) -> ParseResult<DerivingInstance> {
+        let location = expect!(self, DERIVING).location;
         expect!(self, INSTANCE);
 
         let (constraints, instance_type) = self.constrained_type()?;
-        match instance_type {
-            Type::Application(op, arg) => {
-                let classname = match *op {
-                    Type::Constructor(TypeConstructor {
-                        name: classname, ..
-                    }) => classname,
-                    _ => return self.error("Expected type operator".to_string()),
-                };
-                expect!(self, WHERE);
-                expect!(self, LBRACE);
+        let (classname, types) = match split_class_head(instance_type) {
+            Some(head) => head,
+            None => return self.error("Expected type operator".to_string()),
+        };
+        if types.iter().any(|typ| !matches!(*extract_applied_type(typ), Type::Constructor(_))) {
+            return self.error("TypeVariable in instance".to_string());
+        }
+        Ok(DerivingInstance {
+            constraints,
+            types,
+            classname,
+            location,
+        })
+    }
 
-                let mut bindings = self.sep_by_1(|this| this.binding(), SEMICOLON)?;
-                {
-                    let inner_type = extract_applied_type(&arg);
-                    for bind in bindings.iter_mut() {
-                        bind.name = encode_binding_identifier(inner_type.ctor().name, bind.name);
-                    }
-                }
+    ///Parses the list of types which ambiguous numeric types default to, `default (Int, Double)`
+    fn default_declaration(&mut self) -> ParseResult<Vec<Type>> {
+        expect!(self, DEFAULT);
+        expect!(self, LPARENS);
+        let types = if self.lexer.peek().token == RPARENS {
+            vec![]
+        } else {
+            self.sep_by_1(|this| this.parse_type(), COMMA)?
+        };
+        expect!(self, RPARENS);
+        Ok(types)
+    }
 
-                expect!(self, RBRACE);
-                Ok(Instance {
-                    typ: *arg,
-                    classname,
-                    bindings,
-                    constraints,
-                })
-            }
-            _ => return self.error("TypeVariable in instance".to_string()),
+    ///Parses an expression which is not part of a module, such as the input of the REPL.
+    ///Fails if any block of the expression had an error
+    pub fn complete_expression(&mut self) -> ParseResult<TypedExpr> {
+        let expr = self.expression_()?;
+        if !self.errors.is_empty() {
+            return Err(ParseError(::std::mem::take(&mut self.errors)));
         }
+        Ok(expr)
     }
 
     pub fn expression_(&mut self) -> ParseResult<TypedExpr> {
         match self.expression()? {
+            Some(_) if self.lexer.error().is_some() => Err(self.lexer_error().unwrap()),
             Some(expr) => Ok(expr),
-            None => Err(ParseError(Located {
-                location: self.lexer.current().location,
-                node: Error::Message(format!(
-                    "Failed to parse expression at {:?}",
-                    self.lexer.current().location
-                )),
-            })),
+            None => self.error(format!(
+                "Failed to parse expression at {:?}",
+                self.lexer.current().location
+            )),
         }
     }
 
@@ -370,8 +727,28 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
     }
 
     fn list(&mut self) -> ParseResult<TypedExpr> {
+        let location = self.lexer.current().location;
         let mut expressions = vec![];
         while let Some(expr) = self.expression()? {
+            match self.lexer.peek().token {
+                PIPE if expressions.is_empty() => {
+                    self.lexer.next();
+                    let qualifiers = self.sep_by_1(|this| this.qualifier(RBRACKET), COMMA)?;
+                    expect!(self, RBRACKET);
+                    return Ok(TypedExpr::with_location(
+                        ListComprehension(expr.into(), qualifiers),
+                        location,
+                    ));
+                }
+                DOTDOT if expressions.len() <= 1 => {
+                    let (from, then) = match expressions.pop() {
+                        Some(from) => (from, Some(expr)),
+                        None => (expr, None),
+                    };
+                    return self.arithmetic_sequence(location, from, then);
+                }
+                _ => (),
+            }
             expressions.push(expr);
             let comma = self.lexer.next().token;
             if comma != COMMA {
@@ -395,6 +772,48 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
     }
 
     fn sub_expression(&mut self) -> ParseResult<Option<TypedExpr>> {
+        let mut expr = match self.atomic_expression()? {
+            Some(expr) => expr,
+            None => return Ok(None),
+        };
+        //Record construction and update binds tighter than function application
+        while self.lexer.peek().token == LBRACE {
+            let location = expr.location;
+            let fields = self.record_fields(|this| this.expression_())?;
+            expr = match expr.expr {
+                Identifier(name) if is_constructor(name) => {
+                    TypedExpr::with_location(Record(name, fields), location)
+                }
+                _ => TypedExpr::with_location(RecordUpdate(expr.into(), fields), location),
+            };
+        }
+        Ok(Some(expr))
+    }
+
+    ///Parses the fields in a record construction, update or pattern
+    ///{ field1 = x, field2 = y }
+    fn record_fields<T, F>(&mut self, mut field: F) -> ParseResult<Vec<(InternedStr, T)>>
+    where
+        F: FnMut(&mut Parser<Iter>) -> ParseResult<T>,
+    {
+        expect!(self, LBRACE);
+        if self.lexer.peek().token == RBRACE {
+            self.lexer.next();
+            return Ok(vec![]);
+        }
+        let fields = self.sep_by_1(
+            |this| {
+                let name = expect!(this, NAME).value;
+                expect!(this, EQUALSSIGN);
+                field(this).map(|value| (name, value))
+            },
+            COMMA,
+        )?;
+        expect!(self, RBRACE);
+        Ok(fields)
+    }
+
+    fn atomic_expression(&mut self) -> ParseResult<Option<TypedExpr>> {
         let token = self.lexer.next().token;
         debug!("Begin SubExpr {:?}", self.lexer.current());
         Ok(match token {
@@ -404,15 +823,7 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                     self.lexer.next();
                     Some(TypedExpr::with_location(Identifier(intern("()")), location))
                 } else {
-                    let mut expressions = self.sep_by_1(|this| this.expression_(), COMMA)?;
-                    expect!(self, RPARENS);
-                    Some(if expressions.len() == 1 {
-                        let expr = expressions.pop().unwrap();
-                        let loc = expr.location;
-                        TypedExpr::with_location(Paren(expr.into()), loc)
-                    } else {
-                        new_tuple(expressions)
-                    })
+                    Some(self.parenthesized_expression(location)?)
                 }
             }
             LBRACKET => Some(self.list()?),
@@ -429,7 +840,7 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
 
                 expect!(self, OF);
                 expect!(self, LBRACE);
-                let alts = self.sep_by_1(|this| this.alternative(), SEMICOLON)?;
+                let alts = self.block_items(|this| this.alternative());
                 expect!(self, RBRACE);
                 expr.map(|e| TypedExpr::with_location(Case(e.into(), alts), location))
             }
@@ -463,13 +874,13 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                 expect!(self, RBRACE);
 
                 let Some(expr) = bindings.pop() else {
-                    return Err(ParseError(Located {
-                        location: self.lexer.current().location,
-                        node: Error::Message(format!(
+                    return Err(ParseError::new(
+                        token_span(self.lexer.current()),
+                        Error::Message(format!(
                             "{:?}: Parse error: Empty do",
                             self.lexer.current().location
                         )),
-                    }));
+                    ));
                 };
 
                 let DoBinding::DoExpr(expr) = expr else {
@@ -525,6 +936,55 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
         })
     }
 
+    ///Parses what follows an opening parenthesis, which is either a parenthesized expression, a
+    ///tuple, an operator, a tuple constructor or a section
+    fn parenthesized_expression(&mut self, location: Location) -> ParseResult<TypedExpr> {
+        if self.lexer.next().token == OPERATOR {
+            let op = self.lexer.current().value;
+            if self.lexer.peek().token == RPARENS {
+                self.lexer.next();
+                return Ok(TypedExpr::with_location(Identifier(op), location));
+            }
+            //(- x) is a negation and not a section
+            if op != intern("-") {
+                let expr = self.expression_()?;
+                expect!(self, RPARENS);
+                return Ok(TypedExpr::with_location(
+                    RightSection(op, expr.into()),
+                    location,
+                ));
+            }
+        }
+        self.lexer.backtrack();
+        let mut elements = self.sep_by_1(|this| this.expression(), COMMA)?;
+        if elements.len() == 1 {
+            let expr = match elements.pop().unwrap() {
+                Some(expr) => expr,
+                None => return self.error("Expected an expression in parentheses".to_string()),
+            };
+            //binary_expression leaves any operator which is directly followed by ')'
+            if self.lexer.next().token == OPERATOR {
+                let op = self.lexer.current().value;
+                expect!(self, RPARENS);
+                return Ok(TypedExpr::with_location(
+                    LeftSection(expr.into(), op),
+                    location,
+                ));
+            }
+            self.lexer.backtrack();
+            expect!(self, RPARENS);
+            let loc = expr.location;
+            return Ok(TypedExpr::with_location(Paren(expr.into()), loc));
+        }
+        expect!(self, RPARENS);
+        Ok(if elements.iter().all(|e| e.is_some()) {
+            new_tuple(elements.into_iter().map(|e| e.unwrap()).collect())
+        } else {
+            //`(,)` is a section as well so that it can be partially applied like `(, e)`
+            TypedExpr::with_location(TupleSection(elements), location)
+        })
+    }
+
     fn do_binding(&mut self) -> ParseResult<DoBinding> {
         if self.lexer.next().token == LET {
             return self.let_bindings().map(DoBinding::DoLet);
@@ -549,12 +1009,7 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                     self.lexer.next(); //Skip <-
                     return self.expression_().map(move |e| DoBinding::DoBind(p, e));
                 }
-                EOF => {
-                    return Err(ParseError(Located {
-                        location: self.lexer.current().location,
-                        node: Error::Message("Unexpected EOF".to_string()),
-                    }))
-                }
+                EOF => return self.error("Unexpected EOF".to_string()),
                 _ => {
                     debug!("Lookahead {:?}", self.lexer.current());
                 }
@@ -562,10 +1017,65 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
         }
     }
 
+    fn arithmetic_sequence(
+        &mut self,
+        location: Location,
+        from: TypedExpr,
+        then: Option<TypedExpr>,
+    ) -> ParseResult<TypedExpr> {
+        expect!(self, DOTDOT);
+        let to = self.expression()?;
+        expect!(self, RBRACKET);
+        Ok(TypedExpr::with_location(
+            ArithmeticSequence(from.into(), then.map(Box::new), to.map(Box::new)),
+            location,
+        ))
+    }
+
+    ///Parses a qualifier of a list comprehension or guard, which ends at a comma or `end_token`
+    fn qualifier(&mut self, end_token: TokenEnum) -> ParseResult<Qualifier> {
+        if self.lexer.next().token == LET {
+            return self.let_bindings().map(Qualifier::Let);
+        }
+        self.lexer.backtrack();
+        //Look for a '<-' before the end of the qualifier to see if it is a generator
+        let mut lookahead = 0;
+        let mut depth = 0;
+        loop {
+            lookahead += 1;
+            match self.lexer.next().token {
+                token if token == end_token && depth == 0 => {
+                    for _ in 0..lookahead {
+                        self.lexer.backtrack();
+                    }
+                    return self.expression_().map(Qualifier::Guard);
+                }
+                LPARENS | LBRACKET => depth += 1,
+                RPARENS | RBRACKET if depth > 0 => depth -= 1,
+                COMMA if depth > 0 => (),
+                COMMA | RPARENS | RBRACKET | SEMICOLON | RBRACE | EOF => {
+                    for _ in 0..lookahead {
+                        self.lexer.backtrack();
+                    }
+                    return self.expression_().map(Qualifier::Guard);
+                }
+                LARROW if depth == 0 => {
+                    for _ in 0..lookahead {
+                        self.lexer.backtrack();
+                    }
+                    let p = self.located_pattern()?;
+                    expect!(self, LARROW);
+                    return self.expression_().map(move |e| Qualifier::Generator(p, e));
+                }
+                _ => (),
+            }
+        }
+    }
+
     fn let_bindings(&mut self) -> ParseResult<Vec<Binding>> {
         expect!(self, LBRACE);
 
-        let binds = self.sep_by_1(|this| this.binding(), SEMICOLON)?;
+        let binds = self.block_items(|this| this.binding());
         self.lexer.next_end();
         Ok(binds)
     }
@@ -595,6 +1105,11 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
         };
         let op = self.lexer.current().value;
         let loc = self.lexer.current().location;
+        if lhs.is_some() && self.lexer.peek().token == RPARENS {
+            //The operator of a left section, (expr op)
+            self.lexer.backtrack();
+            return Ok(lhs);
+        }
         let rhs = self.application()?;
         let rhs = self.binary_expression(rhs)?;
         Ok(match (lhs, rhs) {
@@ -604,11 +1119,10 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                     loc,
                 ))
             }
-            (Some(lhs), None) => {
-                let name = TypedExpr::with_location(Identifier(op), loc);
-                Some(TypedExpr::with_location(
-                    Apply(name.into(), lhs.into()),
-                    loc,
+            (Some(_), None) => {
+                return self.error(format!(
+                    "Expected an expression after the operator {}, sections must be enclosed in parentheses",
+                    op
                 ))
             }
             (None, Some(rhs)) => {
@@ -617,15 +1131,10 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                     let args = vec![rhs];
                     Some(make_application(name, args.into_iter()))
                 } else {
-                    let name = TypedExpr::with_location(Identifier(intern("negate")), loc);
-                    let args = vec![TypedExpr::with_location(Identifier(intern("#")), loc), rhs];
-                    let mut apply = make_application(name, args.into_iter());
-                    apply.location = loc;
-                    let params = vec![intern("#")];
-                    Some(make_lambda(
-                        params.into_iter().map(|a| Pattern::Identifier(a)),
-                        apply,
-                    ))
+                    return self.error(format!(
+                        "Expected an expression before the operator {}, sections must be enclosed in parentheses",
+                        op
+                    ));
                 }
             }
             (None, None) => return Ok(None),
@@ -650,7 +1159,21 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
     }
 
     fn constructor(&mut self, data_def: &DataDefinition) -> ParseResult<Constructor> {
-        let name = expect!(self, NAME).value.clone();
+        if let Some(constructor) = self.infix_constructor(data_def)? {
+            return Ok(constructor);
+        }
+        let name = if self.lexer.peek().token == LPARENS {
+            //Operator constructor used as a prefix function, (:+) Int Int
+            expect!(self, LPARENS);
+            let name = expect!(self, OPERATOR).value;
+            expect!(self, RPARENS);
+            name
+        } else {
+            expect!(self, NAME).value.clone()
+        };
+        if self.lexer.peek().token == LBRACE {
+            return self.record_constructor(name, data_def);
+        }
         let mut arity = 0;
         let typ = self.constructor_type(&mut arity, data_def)?;
         self.lexer.backtrack();
@@ -659,6 +1182,110 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
             typ: qualified(vec![], typ),
             tag: 0,
             arity,
+            fields: vec![],
+        })
+    }
+
+    ///Parses a constructor declared with an infix operator
+    ///Type1 :+ Type2
+    ///Returns None, without consuming any tokens, if the constructor is not infix
+    fn infix_constructor(
+        &mut self,
+        data_def: &DataDefinition,
+    ) -> ParseResult<Option<Constructor>> {
+        let mut can_backtrack = true;
+        let token = self.lexer.next().token;
+        let lhs = match token {
+            NAME => {
+                let value = self.lexer.current().value;
+                if value.chars().next().expect("char at 0").is_lowercase() {
+                    Type::new_var(value)
+                } else {
+                    Type::new_op(value, vec![])
+                }
+            }
+            LPARENS if self.lexer.peek().token != OPERATOR => {
+                let typ = self.parse_type()?;
+                expect!(self, RPARENS);
+                can_backtrack = false;
+                typ
+            }
+            _ => {
+                self.lexer.backtrack();
+                return Ok(None);
+            }
+        };
+        let is_infix = {
+            let token = self.lexer.peek();
+            token.token == OPERATOR && is_constructor_operator(token.value)
+        };
+        if !is_infix {
+            if !can_backtrack {
+                return self.error(
+                    "Expected a constructor operator after the parenthesized type".to_string(),
+                );
+            }
+            self.lexer.backtrack();
+            return Ok(None);
+        }
+        let name = self.lexer.next().value;
+        let rhs = match self.sub_type()? {
+            Some(typ) => typ,
+            None => {
+                return self.error(format!(
+                    "Expected a type after the constructor operator `{}`",
+                    name
+                ))
+            }
+        };
+        let typ = function_type_(lhs, function_type_(rhs, data_def.typ.value.clone()));
+        Ok(Some(Constructor {
+            name,
+            typ: qualified(vec![], typ),
+            tag: 0,
+            arity: 2,
+            fields: vec![],
+        }))
+    }
+
+    ///Parses the fields of a constructor declared with record syntax
+    ///C { field1, field2 :: Type, field3 :: Type }
+    fn record_constructor(
+        &mut self,
+        name: InternedStr,
+        data_def: &DataDefinition,
+    ) -> ParseResult<Constructor> {
+        expect!(self, LBRACE);
+        let mut fields = vec![];
+        let mut field_types = vec![];
+        if self.lexer.peek().token != RBRACE {
+            loop {
+                let names = self.sep_by_1(|this| Ok(expect!(this, NAME).value), COMMA)?;
+                expect!(self, TYPEDECL);
+                let typ = self.parse_type()?;
+                for field in names.into_iter() {
+                    fields.push(field);
+                    field_types.push(typ.clone());
+                }
+                if self.lexer.next().token != COMMA {
+                    self.lexer.backtrack();
+                    break;
+                }
+            }
+        }
+        expect!(self, RBRACE);
+        let typ = field_types
+            .into_iter()
+            .rev()
+            .fold(data_def.typ.value.clone(), |result, arg| {
+                function_type_(arg, result)
+            });
+        Ok(Constructor {
+            name,
+            typ: qualified(vec![], typ),
+            tag: 0,
+            arity: fields.len() as isize,
+            fields,
         })
     }
 
@@ -757,7 +1384,7 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
         if token == PIPE {
             self.sep_by_1(
                 |this| {
-                    let p = this.expression_()?;
+                    let qualifiers = this.sep_by_1(|this| this.qualifier(end_token), COMMA)?;
                     if this.lexer.next().token != end_token {
                         this.lexer.backtrack();
                         return Err(this.unexpected_token(
@@ -766,7 +1393,7 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                         ));
                     }
                     this.expression_().map(move |e| Guard {
-                        predicate: p,
+                        qualifiers,
                         expression: e,
                     })
                 },
@@ -785,11 +1412,17 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
     where
         F: FnOnce(&mut Parser<Iter>) -> ParseResult<Vec<Pattern>>,
     {
-        let c = name.chars().next().expect("char at 0");
-        if c.is_uppercase() || name == intern(":") {
+        let c = unqualified(&name).chars().next().expect("char at 0");
+        if c.is_uppercase() && self.lexer.peek().token == LBRACE {
+            self.record_fields(|this| this.pattern())
+                .map(|fields| Pattern::Record(name, fields))
+        } else if c.is_uppercase() || name == intern(":") {
             args(self).map(|ps| Pattern::Constructor(name, ps))
         } else if c == '_' {
             Ok(Pattern::WildCard)
+        } else if self.lexer.peek().token == OPERATOR && self.lexer.peek().value == intern("@") {
+            self.lexer.next();
+            Ok(Pattern::As(name, Box::new(self.pattern_argument()?)))
         } else {
             Ok(Pattern::Identifier(name))
         }
@@ -798,33 +1431,51 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
     fn pattern_arguments(&mut self) -> ParseResult<Vec<Pattern>> {
         let mut parameters = vec![];
         loop {
-            let token = self.lexer.next().token;
-            match token {
-                NAME => {
-                    let name = self.lexer.current().value;
-                    let p = self.make_pattern(name, |_| Ok(vec![]))?;
-                    parameters.push(p);
-                }
-                NUMBER => parameters.push(Pattern::Number(
-                    FromStr::from_str(self.lexer.current().value.as_ref()).unwrap(),
-                )),
-                LPARENS => {
-                    self.lexer.backtrack();
-                    parameters.push(self.pattern()?);
-                }
-                LBRACKET => {
-                    expect!(self, RBRACKET);
-                    parameters.push(Pattern::Constructor(intern("[]"), vec![]));
-                }
-                _ => {
-                    break;
-                }
+            let token = self.lexer.peek();
+            let is_argument = match token.token {
+                NAME | NUMBER | FLOAT | STRING | CHAR | LPARENS | LBRACKET => true,
+                OPERATOR => token.value == intern("~") || token.value == intern("!"),
+                _ => false,
+            };
+            if !is_argument {
+                break;
             }
+            parameters.push(self.pattern_argument()?);
         }
-        self.lexer.backtrack();
         Ok(parameters)
     }
 
+    ///Parses a pattern which does not need parentheses when it is the argument of a function or
+    ///constructor
+    fn pattern_argument(&mut self) -> ParseResult<Pattern> {
+        let token = self.lexer.next().token;
+        let value = self.lexer.current().value;
+        let pattern = match token {
+            NAME => self.make_pattern(value, |_| Ok(vec![]))?,
+            NUMBER => Pattern::Number(FromStr::from_str(value.as_ref()).unwrap()),
+            FLOAT => Pattern::Fractional(FromStr::from_str(value.as_ref()).unwrap()),
+            STRING => Pattern::String(value),
+            CHAR => Pattern::Char(value.chars().next().expect("char at 0")),
+            OPERATOR if value == intern("~") => {
+                Pattern::Irrefutable(Box::new(self.pattern_argument()?))
+            }
+            OPERATOR if value == intern("!") => Pattern::Bang(Box::new(self.pattern_argument()?)),
+            LPARENS => {
+                self.lexer.backtrack();
+                self.pattern()?
+            }
+            LBRACKET => {
+                expect!(self, RBRACKET);
+                Pattern::Constructor(intern("[]"), vec![])
+            }
+            _ => unexpected!(
+                self,
+                [NAME, NUMBER, FLOAT, STRING, CHAR, OPERATOR, LPARENS, LBRACKET]
+            ),
+        };
+        Ok(pattern)
+    }
+
     fn located_pattern(&mut self) -> ParseResult<Located<Pattern>> {
         let location = self.lexer.next().location;
         self.lexer.backtrack();
@@ -843,7 +1494,6 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                 Pattern::Constructor(intern("[]"), vec![])
             }
             NAME => self.make_pattern(name, |this| this.pattern_arguments())?,
-            NUMBER => Pattern::Number(FromStr::from_str(name.as_ref()).unwrap()),
             LPARENS => {
                 if self.lexer.peek().token == RPARENS {
                     self.lexer.next();
@@ -861,10 +1511,13 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                     }
                 }
             }
-            _ => unexpected!(self, [LBRACKET, NAME, NUMBER, LPARENS]),
+            _ => {
+                self.lexer.backtrack();
+                self.pattern_argument()?
+            }
         };
         self.lexer.next();
-        if self.lexer.current().token == OPERATOR && self.lexer.current().value.as_ref() == ":" {
+        if self.lexer.current().token == OPERATOR && is_constructor_operator(self.lexer.current().value) {
             Ok(Pattern::Constructor(
                 self.lexer.current().value,
                 vec![pat, self.pattern()?],
@@ -877,8 +1530,10 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
 
     fn type_declaration(&mut self) -> ParseResult<TypeDeclaration> {
         let mut name;
+        let location;
         {
             let name_token = self.lexer.next().token;
+            location = self.lexer.current().location;
             name = self.lexer.current().value.clone();
             if name_token == LPARENS {
                 //Parse a name within parentheses
@@ -900,12 +1555,21 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                 constraints,
                 value: typ,
             },
+            location,
         })
     }
 
     fn constrained_type(&mut self) -> ParseResult<(Vec<Constraint>, Type)> {
         debug!("Parse constrained type");
-        let mut maybe_constraints = if self.lexer.next().token == LPARENS {
+        //The variables of a signature are quantified implicitly so an outermost `forall` such as
+        //in `forall a. Show a => a -> String` changes nothing
+        let token = self.lexer.next();
+        if token.token == NAME && token.value == intern("forall") {
+            self.forall_variables()?;
+        } else {
+            self.lexer.backtrack();
+        }
+        let maybe_constraints = if self.lexer.next().token == LPARENS {
             if self.lexer.peek().token == RPARENS {
                 self.lexer.next();
                 vec![]
@@ -920,23 +1584,50 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
         };
         debug!("{:?}", maybe_constraints);
         //If there is => arrow we proceed to parse the type
-        let typ = match self.lexer.next().token {
-            CONTEXTARROW => self.parse_type(),
+        match self.lexer.next().token {
+            CONTEXTARROW => {
+                let constraints = self.make_constraints(maybe_constraints)?;
+                Ok((constraints, self.parse_type()?))
+            }
             ARROW => {
                 self.lexer.backtrack();
-                let mut args = vec![];
-                swap(&mut args, &mut maybe_constraints);
-                self.parse_return_type(make_tuple_type(args))
+                Ok((vec![], self.parse_return_type(make_tuple_type(maybe_constraints))?))
             }
             _ => {
                 //If no => was found, translate the constraint list into a type
                 self.lexer.backtrack();
-                let mut args = vec![];
-                swap(&mut args, &mut maybe_constraints);
-                Ok(make_tuple_type(args))
+                Ok((vec![], make_tuple_type(maybe_constraints)))
             }
-        };
-        Ok((make_constraints(maybe_constraints), typ?))
+        }
+    }
+
+    ///Converts the types parsed before a `=>` into constraints, each of which must be a class
+    ///applied to type variables
+    fn make_constraints(&self, types: Vec<Type>) -> ParseResult<Vec<Constraint>> {
+        let mut constraints = vec![];
+        for typ in types {
+            let (class, arguments) = match split_class_head(typ) {
+                Some(head) => head,
+                None => {
+                    let message = "Expected a class applied to types in the context";
+                    return self.error(message.to_string());
+                }
+            };
+            let mut variables = vec![];
+            for arg in arguments {
+                match arg {
+                    Type::Variable(var) => variables.push(var),
+                    _ => {
+                        return self.error(format!(
+                            "Expected the arguments of {} in the context to be type variables",
+                            class.as_ref()
+                        ))
+                    }
+                }
+            }
+            constraints.push(Constraint { class, variables });
+        }
+        Ok(constraints)
     }
 
     fn constructor_type(
@@ -974,7 +1665,7 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
     }
 
     fn data_definition(&mut self) -> ParseResult<DataDefinition> {
-        expect!(self, DATA);
+        let location = expect!(self, DATA).location;
 
         let mut definition =
             DataDefinition {
@@ -982,6 +1673,7 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                 typ: qualified(vec![], "a".into()),
                 parameters: HashMap::new(),
                 deriving: vec![],
+                location,
             };
         definition.typ.value = self.data_lhs()?;
         expect!(self, EQUALSSIGN);
@@ -1000,7 +1692,7 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
 
     fn newtype(&mut self) -> ParseResult<Newtype> {
         debug!("Parsing newtype");
-        expect!(self, NEWTYPE);
+        let location = expect!(self, NEWTYPE).location;
         let typ = self.data_lhs()?;
         expect!(self, EQUALSSIGN);
         let name = expect!(self, NAME).value;
@@ -1014,6 +1706,25 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
             constructor_name: name,
             constructor_type: qualified(vec![], function_type_(arg_type, typ)),
             deriving: self.deriving()?,
+            location,
+        })
+    }
+
+    fn type_synonym(&mut self) -> ParseResult<TypeSynonym> {
+        debug!("Parsing type synonym");
+        let location = expect!(self, TYPE).location;
+        let name = expect!(self, NAME).value;
+        let mut parameters = vec![];
+        while self.lexer.next().token == NAME {
+            parameters.push(TypeVariable::new(self.lexer.current().value));
+        }
+        self.lexer.backtrack();
+        expect!(self, EQUALSSIGN);
+        Ok(TypeSynonym {
+            name,
+            parameters,
+            typ: self.parse_type()?,
+            location,
         })
     }
 
@@ -1033,6 +1744,9 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
 
     fn deriving(&mut self) -> ParseResult<Vec<InternedStr>> {
         if self.lexer.next().token == DERIVING {
+            if self.lexer.peek().token == NAME {
+                return Ok(vec![self.lexer.next().value]);
+            }
             expect!(self, LPARENS);
             let vec = self.sep_by_1(|this| Ok(expect!(this, NAME).value), COMMA)?;
             expect!(self, RPARENS);
@@ -1053,13 +1767,9 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
     fn sub_type(&mut self) -> ParseResult<Option<Type>> {
         let token = (*self.lexer.next()).clone();
         let t = match token.token {
-            LBRACKET => {
-                self.lexer.backtrack();
-                Some(self.parse_type()?)
-            }
-            LPARENS => {
+            LBRACKET | LPARENS => {
                 self.lexer.backtrack();
-                Some(self.parse_type()?)
+                Some(self.bracketed_type()?)
             }
             NAME => {
                 if token
@@ -1083,24 +1793,87 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
     }
 
     fn parse_type(&mut self) -> ParseResult<Type> {
+        let token = (*self.lexer.next()).clone();
+        let this_type = match token.token {
+            LBRACKET | LPARENS => {
+                self.lexer.backtrack();
+                self.bracketed_type()?
+            }
+            //The body of a `forall` extends as far to the right as possible
+            NAME if token.value == intern("forall") => {
+                let variables = self.forall_variables()?;
+                //Only the outermost type of a signature can have a context since the arguments
+                //of a function are never passed instance dictionaries
+                let location = self.lexer.peek().location;
+                let (constraints, typ) = self.constrained_type()?;
+                if !constraints.is_empty() {
+                    return Err(ParseError::new(
+                        Span::from(location),
+                        Error::Message(
+                            "A forall type which is not the outermost type can't have a context"
+                                .to_string(),
+                        ),
+                    ));
+                }
+                return Ok(Type::Forall(variables, typ.into()));
+            }
+            NAME => {
+                let mut type_arguments = vec![];
+
+                while let Some(typ) = self.sub_type()? {
+                    type_arguments.push(typ);
+                }
+
+                if token
+                    .value
+                    .chars()
+                    .next()
+                    .expect("char at 0")
+                    .is_uppercase()
+                {
+                    Type::new_op(token.value, type_arguments)
+                } else {
+                    Type::new_var_args(token.value, type_arguments)
+                }
+            }
+            _ => unexpected!(self, [LBRACKET, LPARENS, NAME]),
+        };
+        self.parse_return_type(this_type)
+    }
+
+    ///Parses the variables bound by a `forall` and the `.` which ends them
+    fn forall_variables(&mut self) -> ParseResult<Vec<TypeVariable>> {
+        let mut variables = vec![];
+        while self.lexer.next().token == NAME {
+            variables.push(TypeVariable::new(self.lexer.current().value));
+        }
+        let token = self.lexer.current();
+        if token.token != OPERATOR || token.value != intern(".") || variables.is_empty() {
+            unexpected!(self, [NAME, OPERATOR]);
+        }
+        Ok(variables)
+    }
+
+    ///Parses a list, tuple or parenthesized type.
+    ///Any arrow after the closing bracket is left for the caller so that a bracketed type can
+    ///be the argument of a type application such as `Maybe (a -> b) -> c`
+    fn bracketed_type(&mut self) -> ParseResult<Type> {
         let token = (*self.lexer.next()).clone();
         match token.token {
             LBRACKET => {
                 if self.lexer.next().token == RBRACKET {
-                    let list = Type::new_op_kind(intern("[]"), vec![], Kind::new(2));
-                    self.parse_return_type(list)
+                    Ok(Type::new_op_kind(intern("[]"), vec![], Kind::new(2)))
                 } else {
                     self.lexer.backtrack();
                     let t = self.parse_type()?;
                     expect!(self, RBRACKET);
-                    let list = list_type(t);
-                    self.parse_return_type(list)
+                    Ok(list_type(t))
                 }
             }
             LPARENS => {
                 if self.lexer.peek().token == RPARENS {
                     self.lexer.next();
-                    self.parse_return_type(Type::new_op(intern("()"), vec![]))
+                    Ok(Type::new_op(intern("()"), vec![]))
                 } else {
                     let t = self.parse_type()?;
                     match self.lexer.next().token {
@@ -1109,37 +1882,16 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
                                 self.sep_by_1(|this| this.parse_type(), COMMA)?;
                             tuple_args.insert(0, t);
                             expect!(self, RPARENS);
-
-                            self.parse_return_type(make_tuple_type(tuple_args))
+                            Ok(make_tuple_type(tuple_args))
                         }
-                        RPARENS => self.parse_return_type(t),
+                        RPARENS => Ok(t),
                         _ => {
                             unexpected!(self, [COMMA, RPARENS])
                         }
                     }
                 }
             }
-            NAME => {
-                let mut type_arguments = vec![];
-
-                while let Some(typ) = self.sub_type()? {
-                    type_arguments.push(typ);
-                }
-
-                let this_type = if token
-                    .value
-                    .chars()
-                    .next()
-                    .expect("char at 0")
-                    .is_uppercase()
-                {
-                    Type::new_op(token.value, type_arguments)
-                } else {
-                    Type::new_var_args(token.value, type_arguments)
-                };
-                self.parse_return_type(this_type)
-            }
-            _ => unexpected!(self, [LBRACKET, LPARENS, NAME]),
+            _ => unexpected!(self, [LBRACKET, LPARENS]),
         }
     }
 
@@ -1177,17 +1929,69 @@ impl<Iter: Iterator<Item = char>> Parser<Iter> {
     }
 } //end impl Parser
 
-fn make_constraints(types: Vec<Type>) -> Vec<Constraint> {
-    types
-        .into_iter()
-        .map(|typ| match typ {
-            Type::Application(lhs, rhs) => Constraint {
-                class: lhs.ctor().name.clone(),
-                variables: vec![rhs.var().clone()],
-            },
-            _ => panic!("Parse error in constraint, non applied type"),
-        })
-        .collect()
+fn is_constructor(name: InternedStr) -> bool {
+    unqualified(&name)
+        .chars()
+        .next()
+        .expect("char at 0")
+        .is_uppercase()
+}
+
+///Operators which start with ':' are constructors, such as ':' and ':+'
+fn is_constructor_operator(name: InternedStr) -> bool {
+    name.starts_with(':')
+}
+
+///Creates a selector function for each field declared in a data definition
+///field (C _ x _) = x
+fn field_selectors(data: &DataDefinition) -> Vec<Binding> {
+    let mut fields: Vec<InternedStr> = vec![];
+    for field in data.constructors.iter().flat_map(|ctor| ctor.fields.iter()) {
+        if !fields.contains(field) {
+            fields.push(*field);
+        }
+    }
+    let var = intern("x");
+    let mut bindings = vec![];
+    for field in fields.into_iter() {
+        for ctor in data.constructors.iter() {
+            if let Some(index) = ctor.fields.iter().position(|f| *f == field) {
+                let arguments = (0..ctor.fields.len())
+                    .map(|i| {
+                        if i == index {
+                            Pattern::Identifier(var)
+                        } else {
+                            Pattern::WildCard
+                        }
+                    })
+                    .collect();
+                bindings.push(Binding {
+                    name: field,
+                    arguments: vec![Pattern::Constructor(ctor.name, arguments)],
+                    matches: Match::Simple(TypedExpr::new(Identifier(var))),
+                    where_bindings: None,
+                    typ: Default::default(),
+                });
+            }
+        }
+    }
+    bindings
+}
+
+///Splits the head of a class, instance or constraint such as `Convert a b` into the name of
+///the class and its arguments
+fn split_class_head(typ: Type) -> Option<(InternedStr, Vec<Type>)> {
+    let mut arguments = vec![];
+    let mut head = typ;
+    while let Type::Application(lhs, rhs) = head {
+        arguments.push(*rhs);
+        head = *lhs;
+    }
+    arguments.reverse();
+    match head {
+        Type::Constructor(ref op) if !arguments.is_empty() => Some((op.name, arguments)),
+        _ => None,
+    }
 }
 
 fn make_application<I: Iterator<Item = TypedExpr>>(f: TypedExpr, args: I) -> TypedExpr {
@@ -1225,54 +2029,67 @@ fn make_tuple_type(mut types: Vec<Type>) -> Type {
     }
 }
 
-pub fn parse_string(contents: &str) -> ParseResult<Vec<Module>> {
+pub fn parse_string(contents: &str) -> Result<Vec<(Module, SourceFile)>, VMError> {
     let mut modules = vec![];
     let mut visited = HashSet::new();
-    parse_modules_(&mut visited, &mut modules, "<input>", contents)?;
+    let source = SourceFile {
+        path: "<input>".to_string(),
+        contents: contents.to_string(),
+    };
+    parse_modules_(&SearchPath::new(), &mut visited, &mut modules, "<input>", source)?;
     Ok(modules)
 }
 
-///Parses a module and all its imports
+///Parses a module and all its imports, looking up each module in `search_path`
+///Each module is returned with the file it was parsed from and any error is returned with the
+///source of the module it was found in.
 ///If the modules contain a cyclic dependency fail is called.
-pub fn parse_modules(modulename: &str) -> ParseResult<Vec<Module>> {
+pub fn parse_modules(
+    search_path: &SearchPath,
+    modulename: &str,
+) -> Result<Vec<(Module, SourceFile)>, VMError> {
     let mut modules = vec![];
     let mut visited = HashSet::new();
-    let contents = get_contents(modulename)?;
-    parse_modules_(&mut visited, &mut modules, modulename, contents.as_ref())?;
+    let source = read_module_source(search_path, modulename)?;
+    parse_modules_(search_path, &mut visited, &mut modules, modulename, source)?;
     Ok(modules)
 }
 
-fn get_contents(modulename: &str) -> io::Result<::std::string::String> {
-    let mut filename = ::std::string::String::from(modulename);
-    filename.push_str(".hs");
-    let mut file = File::open(&filename)?;
-    let mut contents = ::std::string::String::new();
-    file.read_to_string(&mut contents)?;
-    Ok(contents)
+fn read_module_source(search_path: &SearchPath, modulename: &str) -> io::Result<SourceFile> {
+    let path = search_path.find_module(modulename)?;
+    Ok(SourceFile {
+        contents: read_file(&path)?,
+        path: path.to_string_lossy().into_owned(),
+    })
 }
 
 fn parse_modules_(
+    search_path: &SearchPath,
     visited: &mut HashSet<InternedStr>,
-    modules: &mut Vec<Module>,
+    modules: &mut Vec<(Module, SourceFile)>,
     modulename: &str,
-    contents: &str,
-) -> ParseResult<()> {
-    let mut parser = Parser::new(contents.chars());
-    let module = parser.module()?;
+    source: SourceFile,
+) -> Result<(), VMError> {
+    let mut parser = Parser::new(source.contents.chars());
+    let with_source =
+        |error: ParseError| VMError::from(error).with_source(&source.path, &source.contents);
+    let module = parser.module().map_err(with_source)?;
     let interned_name = intern(modulename);
     visited.insert(interned_name);
     for import in module.imports.iter() {
         if visited.contains(&import.module) {
-            return parser.error("Cyclic dependency in modules".to_string());
-        } else if modules.iter().all(|m| m.name != import.module) {
+            return parser
+                .error("Cyclic dependency in modules".to_string())
+                .map_err(with_source);
+        } else if modules.iter().all(|(m, _)| m.name != import.module) {
             //parse the module if it is not parsed
             let import_module = import.module.as_ref();
-            let contents_next = get_contents(import_module)?;
-            parse_modules_(visited, modules, import_module, contents_next.as_ref())?;
+            let source_next = read_module_source(search_path, import_module)?;
+            parse_modules_(search_path, visited, modules, import_module, source_next)?;
         }
     }
     visited.remove(&interned_name);
-    modules.push(module);
+    modules.push((module, source));
     Ok(())
 }
 
@@ -1410,6 +2227,20 @@ in test - 2"
         assert_eq!(type_decl.typ.value, f);
     }
     #[test]
+    fn parse_bracketed_type_argument() {
+        let mut parser = Parser::new(r"test :: Maybe (a -> b) -> Maybe [a] -> b".chars());
+        let type_decl = parser.type_declaration().unwrap();
+        let a = &Type::new_var("a".into());
+        let b = &Type::new_var("b".into());
+        let maybe = |t| Type::new_op("Maybe".into(), vec![t]);
+        let f = function_type(
+            &maybe(function_type(a, b)),
+            &function_type(&maybe(list_type(a.clone())), b),
+        );
+
+        assert_eq!(type_decl.typ.value, f);
+    }
+    #[test]
     fn parse_data() {
         let mut parser = Parser::new(r"data Bool = True | False".chars());
         let data = parser.data_definition().unwrap();
@@ -1421,12 +2252,14 @@ in test - 2"
                 tag: 0,
                 arity: 0,
                 typ: b.clone(),
+                fields: vec![],
             };
         let f = Constructor {
             name: intern("False"),
             tag: 1,
             arity: 0,
             typ: b.clone(),
+            fields: vec![],
         };
         assert_eq!(data.typ, b);
         assert_eq!(data.constructors[0], t);
@@ -1447,18 +2280,51 @@ in test - 2"
                 vec![],
                 function_type(&"a".into(), &function_type(&list, &list)),
             ),
+            fields: vec![],
         };
         let nil = Constructor {
             name: intern("Nil"),
             tag: 1,
             arity: 0,
             typ: qualified(vec![], list.clone()),
+            fields: vec![],
         };
         assert_eq!(data.typ.value, list);
         assert_eq!(data.constructors[0], cons);
         assert_eq!(data.constructors[1], nil);
     }
 
+    #[test]
+    fn parse_data_infix_constructor() {
+        let mut parser = Parser::new(r"data Complex a = a :+ (Maybe a) | (:*) Int a".chars());
+        let data = parser.data_definition().unwrap();
+
+        let complex = Type::new_op(intern("Complex"), vec!["a".into()]);
+        let maybe = Type::new_op(intern("Maybe"), vec!["a".into()]);
+        let plus = Constructor {
+            name: intern(":+"),
+            tag: 0,
+            arity: 2,
+            typ: qualified(
+                vec![],
+                function_type(&"a".into(), &function_type(&maybe, &complex)),
+            ),
+            fields: vec![],
+        };
+        let times = Constructor {
+            name: intern(":*"),
+            tag: 1,
+            arity: 2,
+            typ: qualified(
+                vec![],
+                function_type(&int_type(), &function_type(&"a".into(), &complex)),
+            ),
+            fields: vec![],
+        };
+        assert_eq!(data.constructors[0], plus);
+        assert_eq!(data.constructors[1], times);
+    }
+
     #[test]
     fn parse_tuple() {
         let mut parser = Parser::new(r"(1, x)".chars());
@@ -1470,6 +2336,26 @@ in test - 2"
         );
     }
 
+    #[test]
+    fn parse_sections() {
+        let mut parser =
+            Parser::new(r"((+ 1), (subtract 1 .), (`div` 2), (,) 1, (1,), (- x), (+))".chars());
+        let expr = parser.expression_().unwrap();
+        let sections = vec![
+            RightSection(intern("+"), number(1).into()),
+            LeftSection(apply(identifier("subtract"), number(1)).into(), intern(".")),
+            RightSection(intern("div"), number(2).into()),
+            Apply(TypedExpr::new(TupleSection(vec![None, None])).into(), number(1).into()),
+            TupleSection(vec![Some(number(1)), None]),
+            Paren(apply(identifier("negate"), identifier("x")).into()),
+            Identifier(intern("+")),
+        ];
+        assert_eq!(
+            expr,
+            new_tuple(sections.into_iter().map(TypedExpr::new).collect())
+        );
+    }
+
     #[test]
     fn parse_unit() {
         let mut parser = Parser::new(
@@ -1534,7 +2420,37 @@ instance Eq a => Eq [a] where
         assert_eq!(module.classes[0].declarations[1].name, intern("/="));
         assert_eq!(module.instances[0].classname, intern("Eq"));
         assert_eq!(module.instances[0].constraints[0].class, intern("Eq"));
-        assert_eq!(module.instances[0].typ, list_type("a".into()));
+        assert_eq!(module.instances[0].types, vec![list_type("a".into())]);
+    }
+
+    #[test]
+    fn parse_multi_parameter_class() {
+        let mut parser = Parser::new(
+            r"class Convert a b where
+    convert :: a -> b
+
+instance Convert Int Double where
+    convert x = primIntToDouble x
+
+f :: Convert a b => a -> b
+f x = convert x"
+                .chars(),
+        );
+        let module = parser.module().unwrap();
+
+        let a = TypeVariable::new("a".into());
+        let b = TypeVariable::new("b".into());
+        assert_eq!(module.classes[0].name, intern("Convert"));
+        assert_eq!(module.classes[0].variables, vec![a.clone(), b.clone()]);
+        assert_eq!(module.instances[0].classname, intern("Convert"));
+        assert_eq!(
+            module.instances[0].types,
+            vec![Type::new_op(intern("Int"), vec![]), Type::new_op(intern("Double"), vec![])]
+        );
+        assert_eq!(module.instances[0].bindings[0].name, intern("#Int,Doubleconvert"));
+        let constraint = &module.type_declarations[0].typ.constraints[0];
+        assert_eq!(constraint.class, intern("Convert"));
+        assert_eq!(constraint.variables, vec![a, b]);
     }
     #[test]
     fn parse_super_class() {
@@ -1550,7 +2466,7 @@ instance Eq a => Eq [a] where
         let cls = &module.classes[0];
         let a = TypeVariable::new("a".into());
         assert_eq!(cls.name, intern("Ord"));
-        assert_eq!(cls.variable, a);
+        assert_eq!(cls.variables, vec![a.clone()]);
         assert_eq!(cls.constraints[0].class, intern("Eq"));
         assert_eq!(cls.constraints[0].variables[0], a);
     }
@@ -1596,12 +2512,91 @@ instance Eq a => Eq [a] where
         );
     }
 
+    #[test]
+    fn parse_extended_patterns() {
+        let mut parser = Parser::new(r#"f all@(x:_) ~(a, b) !acc 'c' "ab" 1.5 = x"#.chars());
+        let binding = parser.binding().unwrap();
+        let cons = Pattern::Constructor(
+            intern(":"),
+            vec![Pattern::Identifier(intern("x")), Pattern::WildCard],
+        );
+        let pair = Pattern::Constructor(
+            intern("(,)"),
+            vec![Pattern::Identifier(intern("a")), Pattern::Identifier(intern("b"))],
+        );
+        assert_eq!(
+            binding.arguments,
+            vec![
+                Pattern::As(intern("all"), Box::new(cons)),
+                Pattern::Irrefutable(Box::new(pair)),
+                Pattern::Bang(Box::new(Pattern::Identifier(intern("acc")))),
+                Pattern::Char('c'),
+                Pattern::String(intern("ab")),
+                Pattern::Fractional(1.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_list_comprehension() {
+        let mut parser = Parser::new(r"[f x y | (x, _) <- xs, let y = x, p y]".chars());
+        let expr = parser.expression_().unwrap();
+        let pattern = Pattern::Constructor(
+            intern("(,)"),
+            vec![Pattern::Identifier(intern("x")), Pattern::WildCard],
+        );
+        let qualifiers = vec![
+            Qualifier::Generator(
+                Located {
+                    location: Location::eof(),
+                    node: pattern,
+                },
+                identifier("xs"),
+            ),
+            Qualifier::Let(vec![Binding {
+                arguments: vec![],
+                name: intern("y"),
+                typ: Default::default(),
+                matches: Match::Simple(identifier("x")),
+                where_bindings: None,
+            }]),
+            Qualifier::Guard(apply(identifier("p"), identifier("y"))),
+        ];
+        let body = apply(apply(identifier("f"), identifier("x")), identifier("y"));
+        assert_eq!(
+            expr,
+            TypedExpr::new(ListComprehension(body.into(), qualifiers))
+        );
+    }
+
+    #[test]
+    fn parse_arithmetic_sequence() {
+        let mut parser = Parser::new(r"([x..], [1, 3..], [1..n], [1, 3..n])".chars());
+        let expr = parser.expression_().unwrap();
+        let sequences = vec![
+            ArithmeticSequence(identifier("x").into(), None, None),
+            ArithmeticSequence(number(1).into(), Some(number(3).into()), None),
+            ArithmeticSequence(number(1).into(), None, Some(identifier("n").into())),
+            ArithmeticSequence(
+                number(1).into(),
+                Some(number(3).into()),
+                Some(identifier("n").into()),
+            ),
+        ];
+        assert_eq!(
+            expr,
+            new_tuple(sequences.into_iter().map(TypedExpr::new).collect())
+        );
+    }
+
     #[test]
     fn parse_imports() {
         let mut parser = Parser::new(
             r"import Hello
 import World ()
 import Prelude (id, sum)
+import Data (T(..), U(A, b), (+++))
+import Other hiding (V(..))
 
 "
             .chars(),
@@ -1614,17 +2609,54 @@ import Prelude (id, sum)
         assert_eq!(module.imports[1].imports, Some(vec![]));
         assert_eq!(module.imports[2].module.as_ref(), "Prelude");
         assert_eq!(
-            module.imports[2].imports,
-            Some(vec![intern("id"), intern("sum")])
+            export_nodes(module.imports[2].imports.as_ref().unwrap()),
+            vec![Export::Name(intern("id")), Export::Name(intern("sum"))]
+        );
+        assert_eq!(
+            export_nodes(module.imports[3].imports.as_ref().unwrap()),
+            vec![
+                Export::All(intern("T")),
+                Export::With(intern("U"), vec![intern("A"), intern("b")]),
+                Export::Name(intern("+++")),
+            ]
         );
+        assert_eq!(module.imports[3].imports.as_ref().unwrap()[1].location.column, 21);
+        assert_eq!(export_nodes(&module.imports[4].hiding), vec![Export::All(intern("V"))]);
+    }
+    fn export_nodes(items: &[Located<Export>]) -> Vec<Export> {
+        items.iter().map(|item| item.node.clone()).collect()
     }
+
     #[test]
     fn parse_module_imports() {
-        let modules = parse_modules("Test").unwrap();
+        let modules = parse_modules(&SearchPath::new(), "Test").unwrap();
+
+        assert_eq!(modules[0].0.name.as_ref(), "Prelude");
+        assert_eq!(modules[1].0.name.as_ref(), "Test");
+        assert_eq!(modules[1].0.imports[0].module.as_ref(), "Prelude");
+        assert!(modules[1].1.path.ends_with("Test.hs"));
+    }
 
-        assert_eq!(modules[0].name.as_ref(), "Prelude");
-        assert_eq!(modules[1].name.as_ref(), "Test");
-        assert_eq!(modules[1].imports[0].module.as_ref(), "Prelude");
+    #[test]
+    fn parse_module_exports() {
+        let mut parser = Parser::new(
+            r"module Foo (bar, (+++), T(..), C(method), U(), module Prelude) where
+bar = 1
+"
+            .chars(),
+        );
+        let module = parser.module().unwrap();
+        assert_eq!(
+            export_nodes(module.exports.as_ref().unwrap()),
+            vec![
+                Export::Name(intern("bar")),
+                Export::Name(intern("+++")),
+                Export::All(intern("T")),
+                Export::With(intern("C"), vec![intern("method")]),
+                Export::With(intern("U"), vec![]),
+                Export::Module(intern("Prelude")),
+            ]
+        );
     }
 
     #[test]
@@ -1644,11 +2676,11 @@ test x
             typ: <_>::default(),
             matches: Match::Guards(vec![
                 Guard {
-                    predicate: identifier("x"),
+                    qualifiers: vec![Qualifier::Guard(identifier("x"))],
                     expression: number(1),
                 },
                 Guard {
-                    predicate: identifier("otherwise"),
+                    qualifiers: vec![Qualifier::Guard(identifier("otherwise"))],
                     expression: number(0),
                 },
             ]),
@@ -1657,6 +2689,50 @@ test x
         assert_eq!(binding, b2);
     }
 
+    #[test]
+    fn parse_pattern_guards() {
+        let mut parser = Parser::new(
+            r"
+test k m
+    | Just y <- lookup k m, let z = y, p z = z
+    | otherwise = 0
+"
+            .chars(),
+        );
+        let binding = parser.binding().unwrap();
+        let pattern = Pattern::Constructor(intern("Just"), vec![Pattern::Identifier(intern("y"))]);
+        let qualifiers = vec![
+            Qualifier::Generator(
+                Located {
+                    location: Location::eof(),
+                    node: pattern,
+                },
+                apply(apply(identifier("lookup"), identifier("k")), identifier("m")),
+            ),
+            Qualifier::Let(vec![Binding {
+                arguments: vec![],
+                name: intern("z"),
+                typ: Default::default(),
+                matches: Match::Simple(identifier("y")),
+                where_bindings: None,
+            }]),
+            Qualifier::Guard(apply(identifier("p"), identifier("z"))),
+        ];
+        assert_eq!(
+            binding.matches,
+            Match::Guards(vec![
+                Guard {
+                    qualifiers,
+                    expression: identifier("z"),
+                },
+                Guard {
+                    qualifiers: vec![Qualifier::Guard(identifier("otherwise"))],
+                    expression: number(0),
+                },
+            ])
+        );
+    }
+
     #[test]
     fn parse_fixity() {
         let mut parser = Parser::new(
@@ -1708,6 +2784,66 @@ dummy = 1
         assert_eq!(data.deriving, [intern("Eq"), intern("Debug")]);
     }
 
+    #[test]
+    fn deriving_single_class() {
+        let mut parser = Parser::new(
+            r"data Test = A | B deriving Show
+
+dummy = 1
+"
+            .chars(),
+        );
+        let module = parser.module().unwrap();
+        assert_eq!(module.data_definitions[0].deriving, [intern("Show")]);
+    }
+
+    #[test]
+    fn deriving_instance() {
+        let mut parser = Parser::new(
+            r"deriving instance Show a => Show (Tree a)
+
+dummy = 1
+"
+            .chars(),
+        );
+        let module = parser.module().unwrap();
+        let instance = &module.deriving_instances[0];
+        assert_eq!(instance.classname, intern("Show"));
+        assert_eq!(
+            instance.types,
+            [Type::new_op(intern("Tree"), vec![Type::new_var(intern("a"))])]
+        );
+        assert_eq!(
+            instance.constraints,
+            [Constraint {
+                class: intern("Show"),
+                variables: vec![TypeVariable::new(intern("a"))]
+            }]
+        );
+    }
+
+    #[test]
+    fn default_declaration() {
+        let mut parser = Parser::new(
+            r"default (Integer, Double)
+
+dummy = 1
+"
+            .chars(),
+        );
+        let module = parser.module().unwrap();
+        assert_eq!(
+            module.default_types,
+            Some(vec![
+                Type::new_op(intern("Integer"), vec![]),
+                Type::new_op(intern("Double"), vec![])
+            ])
+        );
+
+        let mut parser = Parser::new("default ()\ndefault (Int)".chars());
+        assert!(parser.module().is_err());
+    }
+
     #[test]
     fn test_if_else() {
         let mut parser = Parser::new(
@@ -1795,6 +2931,248 @@ newtype IntPair a = IntPair (a, Int)
         );
     }
 
+    #[test]
+    fn parse_type_synonym() {
+        let s = r"
+type Pair a = (a, a)
+";
+        let module = Parser::new(s.chars()).module().unwrap();
+        let a: Type<_> = "a".into();
+        let synonym = &module.type_synonyms[0];
+        assert_eq!(synonym.name, intern("Pair"));
+        assert_eq!(synonym.parameters, vec![TypeVariable::new(intern("a"))]);
+        assert_eq!(synonym.typ, Type::new_op(intern("(,)"), vec![a.clone(), a]));
+    }
+
+    #[test]
+    fn parse_forall() {
+        let s = r"
+applyToBoth :: (forall a. a -> a) -> (Int, Char) -> (Int, Char)
+";
+        let module = Parser::new(s.chars()).module().unwrap();
+        let a: Type<_> = "a".into();
+        let pair = Type::new_op(intern("(,)"), vec![int_type(), char_type()]);
+        let identity = Type::Forall(
+            vec![a.var().clone()],
+            Box::new(function_type(&a, &a)),
+        );
+        assert_eq!(
+            module.type_declarations[0].typ.value,
+            function_type(&identity, &function_type(&pair, &pair))
+        );
+    }
+
+    #[test]
+    fn nested_forall_context() {
+        let s = r"
+showBoth :: (forall a. Show a => a -> [Char]) -> (Int, Char) -> [Char]
+";
+        let ParseError(errors) = Parser::new(s.chars()).module().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let error = &errors[0];
+        assert_eq!((error.span.start.row, error.span.start.column), (1, 24));
+        assert_eq!(
+            error.error,
+            Error::Message(
+                "A forall type which is not the outermost type can't have a context".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn parse_record() {
+        let s = r"
+data Point = Point { x, y :: Int, label :: [Char] }
+
+move p = p { x = x p + 1 }
+
+origin = Point { x = 0, y = 0, label = [] }
+
+getX (Point { x = a }) = a
+";
+        let module = Parser::new(s.chars()).module().unwrap();
+        let ctor = &module.data_definitions[0].constructors[0];
+        assert_eq!(ctor.fields, vec![intern("x"), intern("y"), intern("label")]);
+        assert_eq!(ctor.arity, 3);
+        assert_eq!(
+            ctor.typ.value,
+            function_type_(
+                int_type(),
+                function_type_(
+                    int_type(),
+                    function_type_(list_type(char_type()), Type::new_op(intern("Point"), vec![]))
+                )
+            )
+        );
+        let binding = |name: &str| {
+            module
+                .bindings
+                .iter()
+                .find(|b| b.name == intern(name))
+                .unwrap_or_else(|| panic!("Missing binding {}", name))
+        };
+        match binding("move").matches {
+            Match::Simple(ref e) => match e.expr {
+                Expr::RecordUpdate(_, ref fields) => assert_eq!(fields[0].0, intern("x")),
+                _ => panic!("Expected record update, found {}", e),
+            },
+            _ => panic!(),
+        }
+        match binding("origin").matches {
+            Match::Simple(ref e) => match e.expr {
+                Expr::Record(ref name, ref fields) => {
+                    assert_eq!(*name, intern("Point"));
+                    assert_eq!(fields.len(), 3);
+                }
+                _ => panic!("Expected record construction, found {}", e),
+            },
+            _ => panic!(),
+        }
+        assert_eq!(
+            binding("getX").arguments,
+            vec![Pattern::Record(
+                intern("Point"),
+                vec![(intern("x"), Pattern::Identifier(intern("a")))]
+            )]
+        );
+        assert_eq!(binding("label").arguments.len(), 1);
+    }
+
+    #[test]
+    fn parse_literals_and_comments() {
+        let mut parser = Parser::new(
+            r#"
+-- A comment
+{- A {- nested -} comment -}
+a = "a\tb\&c"
+b = '\x41' -- A comment after a binding
+c = 0xFF
+d = 2.5e2
+"#
+            .chars(),
+        );
+        let module = parser.module().unwrap();
+
+        let matches: Vec<_> = module.bindings.iter().map(|b| b.matches.clone()).collect();
+        assert_eq!(
+            matches,
+            vec![
+                Match::Simple(TypedExpr::new(Literal(String(intern("a\tbc"))))),
+                Match::Simple(TypedExpr::new(Literal(Char('A')))),
+                Match::Simple(number(255)),
+                Match::Simple(rational(250.)),
+            ]
+        );
+    }
+
+    #[test]
+    fn recover_from_errors() {
+        let mut parser = Parser::new(
+            r"module Main where
+f x = (x + 1
+
+g :: Int -> Int
+g y = case y of
+    0 -> 1
+    _ -> = 2
+
+data T = A | B )
+
+h = let z = 1 in z
+"
+            .chars(),
+        );
+        let (module, errors) = parser.partial_module().unwrap();
+        let ParseError(errors) = errors.expect("Expected syntax errors");
+        let rows: Vec<_> = errors.iter().map(|error| error.span.start.row).collect();
+        assert_eq!(rows, [1, 6, 8]);
+        assert_eq!(module.type_declarations.len(), 1);
+        //Only the alternative of `g` which failed to parse is skipped
+        let names: Vec<_> = module.bindings.iter().map(|bind| bind.name).collect();
+        assert_eq!(names, [intern("g"), intern("h")]);
+        assert_eq!(module.data_definitions.len(), 1);
+    }
+
+    #[test]
+    fn recover_from_errors_in_blocks() {
+        let mut parser = Parser::new(
+            r"f x = y + z
+  where
+    y = (x +
+    z = let w = ] in w
+    v = x
+
+g = case 1 of
+    1 -> )
+    _ -> let a = 1 in a
+"
+            .chars(),
+        );
+        let (module, errors) = parser.partial_module().unwrap();
+        let ParseError(errors) = errors.expect("Expected syntax errors");
+        let rows: Vec<_> = errors.iter().map(|error| error.span.start.row).collect();
+        assert_eq!(rows, [2, 3, 7]);
+        let names: Vec<_> = module.bindings.iter().map(|bind| bind.name).collect();
+        assert_eq!(names, [intern("f"), intern("g")]);
+        let where_bindings = module.bindings[0].where_bindings.as_ref().unwrap();
+        let names: Vec<_> = where_bindings.iter().map(|bind| bind.name).collect();
+        assert_eq!(names, [intern("z"), intern("v")]);
+        match module.bindings[1].matches {
+            Match::Simple(ref expr) => match expr.expr {
+                Case(_, ref alts) => assert_eq!(alts.len(), 1),
+                _ => panic!("Expected a case expression"),
+            },
+            _ => panic!("Expected a simple binding"),
+        }
+    }
+
+    #[test]
+    fn unexpected_top_level_token() {
+        let mut parser = Parser::new("test = 1
+) = 2
+main = test
+".chars());
+        let (module, errors) = parser.partial_module().unwrap();
+        let ParseError(errors) = errors.unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].error,
+            Error::UnexpectedToken(
+                &[
+                    NAME, LPARENS, CLASS, INSTANCE, DERIVING, DATA, NEWTYPE, TYPE, INFIXL, INFIXR,
+                    INFIX, DEFAULT
+                ],
+                RPARENS
+            )
+        );
+        assert_eq!(module.bindings.len(), 2);
+    }
+
+    #[test]
+    fn lexical_error_location() {
+        let mut parser = Parser::new("test = 1\nmain = \"a\\zb\"".chars());
+        let ParseError(errors) = parser.module().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let error = &errors[0];
+        assert_eq!((error.span.start.row, error.span.start.column), (1, 10));
+        assert_eq!(error.error, Error::Message("Invalid escape `\\z`".to_string()));
+    }
+
+    #[test]
+    fn constraint_on_non_variable() {
+        let mut parser = Parser::new("class (Foo [a]) => Bar a where\n    bar :: a".chars());
+        let ParseError(errors) = parser.module().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let error = &errors[0];
+        assert_eq!((error.span.start.row, error.span.start.column), (0, 17));
+        assert_eq!(
+            error.error,
+            Error::Message(
+                "Expected the arguments of Foo in the context to be type variables".to_string()
+            )
+        );
+    }
+
     #[test]
     fn parse_prelude() {
         let path = &Path::new("Prelude.hs");
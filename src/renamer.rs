@@ -1,11 +1,27 @@
 use {
     crate::{
+        builtins,
+        diagnostics::{
+            Diagnostic,
+            Span,
+            ToDiagnostics,
+        },
         interner::*,
-        lexer::Located,
+        lexer::{
+            split_qualified,
+            unqualified,
+            Located,
+            Location,
+        },
         module::*,
         scoped_map::ScopedMap,
+        types::extract_applied_type,
     },
     std::{
+        collections::{
+            HashMap,
+            HashSet,
+        },
         error,
         fmt,
     },
@@ -77,6 +93,10 @@ impl<T> Errors<T> {
         !self.errors.is_empty()
     }
 
+    pub fn iter(&self) -> ::std::slice::Iter<'_, T> {
+        self.errors.iter()
+    }
+
     pub fn into_result<V>(&mut self, value: V) -> Result<V, Errors<T>> {
         if self.has_errors() {
             Err(::std::mem::replace(self, Errors::new()))
@@ -94,14 +114,14 @@ impl<T: fmt::Display> Errors<T> {
             pass
         )?;
         for error in self.errors.iter() {
-            write!(f, "{}", error)?;
+            write!(f, "\n{}", error)?;
         }
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct RenamerError(Errors<Error>);
+pub struct RenamerError(Errors<Located<Error>>);
 
 impl fmt::Display for RenamerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -115,10 +135,24 @@ impl error::Error for RenamerError {
     }
 }
 
+impl ToDiagnostics for RenamerError {
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.0
+            .iter()
+            .map(|error| Diagnostic::error(Span::from(error.location), error.node.to_string()))
+            .collect()
+    }
+}
+
 #[derive(Debug)]
 enum Error {
     MultipleDefinitions(InternedStr),
     UndefinedModule(InternedStr),
+    UndefinedQualifier(InternedStr),
+    UndefinedName(InternedStr),
+    Ambiguous(InternedStr, Vec<String>),
+    UndefinedExport(InternedStr),
+    NotExported(InternedStr, InternedStr),
 }
 
 impl fmt::Display for Error {
@@ -126,6 +160,16 @@ impl fmt::Display for Error {
         match *self {
             Self::MultipleDefinitions(s) => write!(f, "{} is defined multiple times", s),
             Self::UndefinedModule(s) => write!(f, "Module {} is not defined", s),
+            Self::UndefinedQualifier(s) => write!(f, "No module named {} is imported", s),
+            Self::UndefinedName(s) => write!(f, "{} is not in scope", s),
+            Self::Ambiguous(s, ref candidates) => write!(
+                f,
+                "Ambiguous occurrence {}, it could refer to {}",
+                s,
+                candidates.join(", ")
+            ),
+            Self::UndefinedExport(s) => write!(f, "{} is exported but is not in scope", s),
+            Self::NotExported(module, s) => write!(f, "Module {} does not export {}", module, s),
         }
     }
 }
@@ -173,52 +217,89 @@ impl NameSupply {
 struct Renamer {
     ///Mapping of strings into the unique name
     uniques: ScopedMap<InternedStr, Name>,
+    ///Mapping of qualified strings such as `M.lookup` into the global they refer to
+    qualified: HashMap<InternedStr, Name>,
+    ///The qualifiers which are in scope in the current module
+    qualifiers: HashSet<InternedStr>,
+    ///Strings which were imported from more than one module, along with every global they can refer to
+    ambiguous: HashMap<InternedStr, Vec<Name>>,
+    ///The names of the modules which have been renamed, indexed by their uid
+    module_names: HashMap<usize, InternedStr>,
+    ///The names which each renamed module exports, indexed by the uid of the module
+    exports: HashMap<usize, Vec<Name>>,
+    ///The types and classes which each renamed module exports along with the constructors, fields
+    ///and methods which are exported with them, indexed by the uid of the module
+    exported_types: HashMap<usize, HashMap<InternedStr, Vec<Name>>>,
+    ///The constructors and fields of each type and the methods of each class which are in scope in
+    ///the current module, which can be exported with `T(..)`
+    subordinates: HashMap<InternedStr, Vec<Name>>,
     name_supply: NameSupply,
+    ///True when renaming an expression or module without the modules it imports, in which case
+    ///every name which is not in scope is assumed to be a global of the Prelude
+    implicit_prelude: bool,
+    ///The location of the declaration, expression or pattern which is being renamed
+    location: Location,
     ///All errors found while renaming are stored here
-    errors: Errors<Error>,
+    errors: Errors<Located<Error>>,
+}
+
+///Returns the top level names of a module which other modules can import along with the location
+///of the declaration of each name
+fn global_names<T: Eq + Copy>(module: &Module<T>) -> Vec<(T, Location)> {
+    module
+        .data_definitions
+        .iter()
+        .flat_map(|data| data.constructors.iter().map(move |ctor| (ctor.name, data.location)))
+        .chain(
+            module
+                .newtypes
+                .iter()
+                .map(|newtype| (newtype.constructor_name, newtype.location)),
+        )
+        .chain(module.classes.iter().flat_map(|class| {
+            Some((class.name, class.location))
+                .into_iter()
+                .chain(class.declarations.iter().map(|decl| (decl.name, decl.location)))
+                .chain(
+                    binding_groups(&class.bindings)
+                        .map(|binds| (binds[0].name, *binds[0].matches.location())),
+                )
+        }))
+        .chain(
+            binding_groups(module.bindings.as_ref())
+                .map(|binds| (binds[0].name, *binds[0].matches.location())),
+        )
+        .collect()
+}
+
+fn qualify(qualifier: InternedStr, name: InternedStr) -> InternedStr {
+    intern(&[qualifier.as_ref(), ".", name.as_ref()].concat())
 }
 
 impl Renamer {
     fn new() -> Self {
         Self {
             uniques: ScopedMap::new(),
+            qualified: HashMap::new(),
+            qualifiers: HashSet::new(),
+            ambiguous: HashMap::new(),
+            module_names: HashMap::new(),
+            exports: HashMap::new(),
+            exported_types: HashMap::new(),
+            subordinates: HashMap::new(),
             name_supply: NameSupply::new(),
+            implicit_prelude: false,
+            location: Location::eof(),
             errors: Errors::new(),
         }
     }
 
-    fn import_globals<T: Eq + Copy>(
-        &mut self,
-        module: &Module<T>,
-        str_fn: &mut dyn FnMut(T) -> InternedStr,
-        uid: usize,
-    ) {
-        let names = module
-            .data_definitions
-            .iter()
-            .flat_map(|data| data.constructors.iter().map(|ctor| ctor.name))
-            .chain(
-                module
-                    .newtypes
-                    .iter()
-                    .map(|newtype| newtype.constructor_name),
-            )
-            .chain(module.classes.iter().flat_map(|class| {
-                Some(class.name)
-                    .into_iter()
-                    .chain(class.declarations.iter().map(|decl| decl.name))
-                    .chain(binding_groups(&class.bindings).map(|binds| binds[0].name))
-            }))
-            .chain(binding_groups(module.bindings.as_ref()).map(|binds| binds[0].name));
-        for name in names {
-            self.declare_global(str_fn(name), uid);
-        }
-        for instance in module.instances.iter() {
-            let class_uid = self.get_name(str_fn(instance.classname)).uid;
-            for binds in binding_groups(instance.bindings.as_ref()) {
-                self.declare_global(str_fn(binds[0].name), class_uid);
-            }
-        }
+    ///Reports `error` at the location of the node which is being renamed
+    fn error(&mut self, error: Error) {
+        self.errors.insert(Located {
+            location: self.location,
+            node: error,
+        });
     }
 
     ///Puts the globals of `module_env` into the current scope of the renamer.
@@ -230,28 +311,323 @@ impl Renamer {
         module: &Module<InternedStr>,
         uid: usize,
     ) {
-        self.import_globals(module, &mut |name| name, uid);
+        self.qualified.clear();
+        self.qualifiers.clear();
+        self.ambiguous.clear();
+        self.qualifiers.insert(module.name);
+        let mut globals = vec![];
+        for (name, location) in global_names(module) {
+            self.location = location;
+            let global = self.declare_global(name, uid);
+            self.import_name(qualify(module.name, name), global);
+            globals.push(global);
+        }
+        self.subordinates.clear();
+        self.insert_subordinates(module);
+        let declared_types = self.subordinates.clone();
+        //The types brought into scope by each import along with the qualifier of the import
+        let mut imported_types = vec![];
         for import in module.imports.iter() {
+            self.location = import.location;
             let imported_module = module_env.iter().find(|m| m.name.name == import.module);
             let imported_module = match imported_module {
                 Some(x) => x,
                 None => {
-                    self.errors.insert(Error::UndefinedModule(import.module));
+                    self.error(Error::UndefinedModule(import.module));
                     continue;
                 }
             };
             let uid = imported_module.name.uid;
-            if let Some(ref imports) = import.imports {
-                for &imported_str in imports.iter() {
-                    self.declare_global(imported_str, uid);
+            let (exported, exported_types) =
+                match (self.exports.get(&uid), self.exported_types.get(&uid)) {
+                    (Some(exported), Some(exported_types)) => {
+                        (exported.clone(), exported_types.clone())
+                    }
+                    _ => {
+                        self.error(Error::UndefinedModule(import.module));
+                        continue;
+                    }
+                };
+            let (names, types) = match import.imports {
+                Some(ref imports) => {
+                    let mut names = vec![];
+                    let mut types = vec![];
+                    for item in imports.iter() {
+                        self.resolve_import(
+                            import.module,
+                            item,
+                            &exported,
+                            &exported_types,
+                            &mut names,
+                            &mut types,
+                        );
+                    }
+                    (names, types)
                 }
-            } else {
                 //Import everything
-                self.import_globals(
-                    imported_module,
-                    &mut |name| name.name,
-                    imported_module.name.uid,
-                )
+                None => (exported.clone(), exported_types.clone().into_iter().collect()),
+            };
+            let mut hidden = vec![];
+            for item in import.hiding.iter() {
+                self.resolve_import(
+                    import.module,
+                    item,
+                    &exported,
+                    &exported_types,
+                    &mut hidden,
+                    &mut vec![],
+                );
+            }
+            let qualifier = import.qualifier();
+            self.qualifiers.insert(qualifier);
+            for name in names {
+                if hidden.contains(&name) {
+                    continue;
+                }
+                if !import.qualified {
+                    self.import_name(name.name, name);
+                }
+                self.import_name(qualify(qualifier, name.name), name);
+            }
+            for (typ, subordinates) in types {
+                let in_scope = self.subordinates.entry(typ).or_default();
+                for name in subordinates {
+                    if !hidden.contains(&name) && !in_scope.contains(&name) {
+                        in_scope.push(name);
+                    }
+                }
+                imported_types.push((qualifier, typ));
+            }
+            //Instances are always imported
+            for instance in imported_module.instances.iter() {
+                for binds in binding_groups(instance.bindings.as_ref()) {
+                    self.import_name(binds[0].name.name, binds[0].name);
+                }
+            }
+        }
+        for instance in module.instances.iter() {
+            self.location = instance.location;
+            let class_uid = self.get_name(instance.classname).uid;
+            for binds in binding_groups(instance.bindings.as_ref()) {
+                self.declare_global(binds[0].name, class_uid);
+            }
+        }
+        let (exports, exported_types) =
+            self.export_names(module, globals, declared_types, &imported_types);
+        self.exports.insert(uid, exports);
+        self.exported_types.insert(uid, exported_types);
+    }
+
+    ///Resolves an entry in the import or hiding list of an import of `module`.
+    ///The globals the entry refers to are added to `names` and the types and classes to `types`
+    ///along with their constructors, fields or methods which are listed
+    fn resolve_import(
+        &mut self,
+        module: InternedStr,
+        item: &Located<Export<InternedStr>>,
+        exported: &[Name],
+        exported_types: &HashMap<InternedStr, Vec<Name>>,
+        names: &mut Vec<Name>,
+        types: &mut Vec<(InternedStr, Vec<Name>)>,
+    ) {
+        self.location = item.location;
+        match item.node {
+            Export::Name(name) => {
+                let global = exported.iter().find(|global| global.name == name);
+                if let Some(&global) = global {
+                    names.push(global);
+                }
+                if exported_types.contains_key(&name) {
+                    types.push((name, vec![]));
+                } else if global.is_none() {
+                    self.error(Error::NotExported(module, name));
+                }
+            }
+            Export::All(name) | Export::With(name, _) => {
+                let subordinates = match exported_types.get(&name) {
+                    Some(subordinates) => subordinates,
+                    None => {
+                        self.error(Error::NotExported(module, name));
+                        return;
+                    }
+                };
+                //Classes are renamed so the class is imported as well as its methods
+                if let Some(&global) = exported.iter().find(|global| global.name == name) {
+                    names.push(global);
+                }
+                let subordinates: Vec<Name> = match item.node {
+                    Export::With(_, ref listed) => listed
+                        .iter()
+                        .filter_map(|&sub| {
+                            let global = subordinates.iter().find(|global| global.name == sub);
+                            if global.is_none() {
+                                self.error(Error::NotExported(module, sub));
+                            }
+                            global.cloned()
+                        })
+                        .collect(),
+                    _ => subordinates.clone(),
+                };
+                names.extend(subordinates.iter().cloned());
+                types.push((name, subordinates));
+            }
+            //Import lists can't contain `module M`
+            Export::Module(_) => (),
+        }
+    }
+
+    ///Records the constructors and fields of the types and the methods of the classes declared
+    ///in `module`
+    fn insert_subordinates(&mut self, module: &Module<InternedStr>) {
+        for data in module.data_definitions.iter() {
+            let names = data
+                .constructors
+                .iter()
+                .flat_map(|ctor| {
+                    Some(ctor.name)
+                        .into_iter()
+                        .chain(ctor.fields.iter().cloned())
+                })
+                .map(|name| self.get_defined_name(name))
+                .collect();
+            let typ = extract_applied_type(&data.typ.value).ctor().name;
+            self.subordinates.insert(typ, names);
+        }
+        for newtype in module.newtypes.iter() {
+            let names = vec![self.get_defined_name(newtype.constructor_name)];
+            let typ = extract_applied_type(&newtype.typ.value).ctor().name;
+            self.subordinates.insert(typ, names);
+        }
+        for synonym in module.type_synonyms.iter() {
+            self.subordinates.insert(synonym.name, vec![]);
+        }
+        for class in module.classes.iter() {
+            let names = class
+                .declarations
+                .iter()
+                .map(|decl| self.get_defined_name(decl.name))
+                .collect();
+            self.subordinates.insert(class.name, names);
+        }
+    }
+
+    ///Returns the global which `name` refers to, if any
+    fn find_global(&self, name: InternedStr) -> Option<Name> {
+        if split_qualified(&name).is_some() {
+            self.qualified.get(&name).cloned()
+        } else {
+            self.uniques.find(&name).cloned()
+        }
+    }
+
+    ///Resolves the export list of `module` into the globals which importing modules can refer to
+    ///and the types and classes along with their exported constructors, fields and methods.
+    ///`globals` and `declared_types` are the globals and types declared in `module` itself and
+    ///`imported_types` are the types brought into scope by each qualifier
+    fn export_names(
+        &mut self,
+        module: &Module<InternedStr>,
+        globals: Vec<Name>,
+        declared_types: HashMap<InternedStr, Vec<Name>>,
+        imported_types: &[(InternedStr, InternedStr)],
+    ) -> (Vec<Name>, HashMap<InternedStr, Vec<Name>>) {
+        let exports = match module.exports {
+            Some(ref exports) => exports,
+            None => return (globals, declared_types),
+        };
+        let mut names = vec![];
+        let mut types: HashMap<InternedStr, Vec<Name>> = HashMap::new();
+        for export in exports.iter() {
+            self.location = export.location;
+            match export.node {
+                Export::Name(name) => {
+                    let typ = intern(unqualified(&name));
+                    if self.subordinates.contains_key(&typ) {
+                        types.entry(typ).or_default();
+                    }
+                    if self.find_global(name).is_some() {
+                        names.push(self.get_name(name));
+                    } else if !self.subordinates.contains_key(&typ) {
+                        self.error(Error::UndefinedExport(name));
+                    }
+                }
+                Export::All(name) | Export::With(name, _) => {
+                    let typ = intern(unqualified(&name));
+                    let subordinates = match self.subordinates.get(&typ) {
+                        Some(subordinates) => subordinates.clone(),
+                        None => {
+                            self.error(Error::UndefinedExport(name));
+                            continue;
+                        }
+                    };
+                    //Classes are exported as well but types are never renamed
+                    if self.find_global(name).is_some() {
+                        names.push(self.get_name(name));
+                    }
+                    let exported_subordinates = types.entry(typ).or_default();
+                    match export.node {
+                        Export::With(_, ref listed) => {
+                            for &sub in listed.iter() {
+                                match subordinates.iter().find(|global| global.name == sub) {
+                                    Some(&global) => {
+                                        names.push(global);
+                                        exported_subordinates.push(global);
+                                    }
+                                    None => self.error(Error::UndefinedExport(sub)),
+                                }
+                            }
+                        }
+                        _ => {
+                            names.extend(subordinates.iter().cloned());
+                            exported_subordinates.extend(subordinates);
+                        }
+                    }
+                }
+                Export::Module(m) if m == module.name => {
+                    names.extend(globals.iter().cloned());
+                    types.extend(declared_types.clone());
+                }
+                Export::Module(m) => {
+                    if !self.qualifiers.contains(&m) {
+                        self.error(Error::UndefinedExport(m));
+                        continue;
+                    }
+                    for &(qualifier, typ) in imported_types.iter() {
+                        if qualifier == m {
+                            types.insert(typ, self.subordinates[&typ].clone());
+                        }
+                    }
+                    //Only the names which are in scope both qualified and unqualified are exported
+                    for (key, global) in self.qualified.iter() {
+                        if let Some((qualifier, name)) = split_qualified(key) {
+                            if qualifier == m.as_ref()
+                                && self.uniques.find(&intern(name)) == Some(global)
+                            {
+                                names.push(*global);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        (names, types)
+    }
+
+    ///Brings the imported global `name` into scope as `key`.
+    ///If `key` already refers to a different global, `key` becomes ambiguous
+    fn import_name(&mut self, key: InternedStr, name: Name) {
+        let existing = if split_qualified(&key).is_some() {
+            *self.qualified.entry(key).or_insert(name)
+        } else if self.uniques.in_current_scope(&key) {
+            *self.uniques.find(&key).unwrap()
+        } else {
+            self.uniques.insert(key, name);
+            name
+        };
+        if existing != name {
+            let candidates = self.ambiguous.entry(key).or_insert_with(|| vec![existing]);
+            if !candidates.contains(&name) {
+                candidates.push(name);
             }
         }
     }
@@ -280,6 +656,7 @@ impl Renamer {
                 let n = self.uniques.find(&name).map(|u| u.clone()).unwrap_or_else(
                     || unreachable!("Variable {} should already have been defined", name)
                 );
+                self.location = *matches.location();
                 self.uniques.enter_scope();
                 let b = Binding {
                     name: n,
@@ -304,15 +681,20 @@ impl Renamer {
             typ,
             location,
         } = input_expr;
+        self.location = location;
         let e = match expr {
             Literal(l) => Literal(l),
+            //An identifier starting with an underscore which is not in scope is a typed hole
+            Identifier(i) if i.as_ref().starts_with('_') && self.uniques.find(&i).is_none() => {
+                Hole(i)
+            }
             Identifier(i) => Identifier(self.get_name(i)),
             Apply(func, arg) => Apply(self.rename(*func).into(), self.rename(*arg).into()),
-            OpApply(lhs, op, rhs) => OpApply(
-                self.rename(*lhs).into(),
-                self.get_name(op),
-                self.rename(*rhs).into(),
-            ),
+            OpApply(lhs, op, rhs) => {
+                let lhs = self.rename(*lhs);
+                self.location = location;
+                OpApply(lhs.into(), self.get_name(op), self.rename(*rhs).into())
+            }
             Lambda(arg, body) => {
                 self.uniques.enter_scope();
                 let l = Lambda(self.rename_pattern(arg), self.rename(*body).into());
@@ -340,6 +722,7 @@ impl Renamer {
                             where_bindings,
                         } = alt;
                         self.uniques.enter_scope();
+                        self.location = loc;
                         let a = Alternative {
                             pattern: Located {
                                 location: loc,
@@ -368,6 +751,7 @@ impl Renamer {
                         DoLet(bs) => DoLet(self.rename_bindings(bs, false)),
                         DoBind(pattern, expr) => {
                             let Located { location, node } = pattern;
+                            self.location = location;
                             let loc = Located {
                                 location,
                                 node: self.rename_pattern(node),
@@ -378,11 +762,54 @@ impl Renamer {
                     .collect();
                 Do(bs, Box::new(self.rename(*expr)))
             }
-            TypeSig(expr, sig) => TypeSig(
-                Box::new(self.rename(*expr)),
-                self.rename_qualified_type(sig),
-            ),
+            TypeSig(expr, sig) => {
+                let expr = self.rename(*expr);
+                self.location = location;
+                TypeSig(Box::new(expr), self.rename_qualified_type(sig))
+            }
             Paren(expr) => Paren(Box::new(self.rename(*expr))),
+            ListComprehension(expr, qualifiers) => {
+                let scopes = qualifiers.len();
+                let qs = self.rename_qualifiers(qualifiers);
+                let e = ListComprehension(Box::new(self.rename(*expr)), qs);
+                for _ in 0..scopes {
+                    self.uniques.exit_scope();
+                }
+                e
+            }
+            ArithmeticSequence(from, then, to) => ArithmeticSequence(
+                Box::new(self.rename(*from)),
+                then.map(|e| Box::new(self.rename(*e))),
+                to.map(|e| Box::new(self.rename(*e))),
+            ),
+            Record(name, fields) => {
+                let name = self.get_name(name);
+                let fs = fields
+                    .into_iter()
+                    .map(|(field, e)| (self.get_field_name(field), self.rename(e)))
+                    .collect();
+                Record(name, fs)
+            }
+            RecordUpdate(expr, fields) => {
+                let fs = fields
+                    .into_iter()
+                    .map(|(field, e)| (self.get_field_name(field), self.rename(e)))
+                    .collect();
+                RecordUpdate(Box::new(self.rename(*expr)), fs)
+            }
+            LeftSection(lhs, op) => {
+                let lhs = self.rename(*lhs);
+                self.location = location;
+                LeftSection(lhs.into(), self.get_name(op))
+            }
+            RightSection(op, rhs) => RightSection(self.get_name(op), self.rename(*rhs).into()),
+            TupleSection(elements) => TupleSection(
+                elements
+                    .into_iter()
+                    .map(|e| e.map(|e| self.rename(e)))
+                    .collect(),
+            ),
+            Hole(name) => Hole(name),
         };
         let mut t = TypedExpr::with_location(e, location);
         t.typ = self.rename_type(typ);
@@ -392,24 +819,143 @@ impl Renamer {
     fn rename_pattern(&mut self, pattern: Pattern<InternedStr>) -> Pattern<Name> {
         match pattern {
             Pattern::Number(i) => Pattern::Number(i),
+            Pattern::Fractional(f) => Pattern::Fractional(f),
+            Pattern::Char(c) => Pattern::Char(c),
+            Pattern::String(s) => Pattern::String(s),
             Pattern::Constructor(s, ps) => {
                 let ps2: Vec<Pattern<Name>> =
                     ps.into_iter().map(|p| self.rename_pattern(p)).collect();
                 Pattern::Constructor(self.get_name(s), ps2)
             }
+            Pattern::Record(s, fields) => {
+                let fs: Vec<(Name, Pattern<Name>)> = fields
+                    .into_iter()
+                    .map(|(field, p)| (self.get_field_name(field), self.rename_pattern(p)))
+                    .collect();
+                Pattern::Record(self.get_name(s), fs)
+            }
             Pattern::Identifier(s) => Pattern::Identifier(self.make_unique(s)),
+            Pattern::As(s, p) => {
+                let name = self.make_unique(s);
+                Pattern::As(name, Box::new(self.rename_pattern(*p)))
+            }
+            Pattern::Irrefutable(p) => Pattern::Irrefutable(Box::new(self.rename_pattern(*p))),
+            Pattern::Bang(p) => Pattern::Bang(Box::new(self.rename_pattern(*p))),
             Pattern::WildCard => Pattern::WildCard,
         }
     }
+    ///Field names always refer to the global selector, even if a local variable shadows it
+    fn get_field_name(&mut self, name: InternedStr) -> Name {
+        if split_qualified(&name).is_some() {
+            return self.get_name(name);
+        }
+        Name {
+            name,
+            uid: self
+                .uniques
+                .find_outermost(&name)
+                .map(|n| n.uid)
+                .unwrap_or(0),
+        }
+    }
     ///Turns the string into the Name which is currently in scope
-    ///If the name was not found it is assumed to be global
-    fn get_name(&self, name: InternedStr) -> Name {
+    ///Reports an error if the name is not in scope or if it refers to more than one imported
+    ///global
+    fn get_name(&mut self, name: InternedStr) -> Name {
+        self.lookup_name(name, true)
+    }
+    ///Turns the name of a type into the Name which is currently in scope.
+    ///Types are not declared in the scope of the renamer so a type which is not found is
+    ///assumed to be global
+    fn get_type_name(&mut self, name: InternedStr) -> Name {
+        self.lookup_name(name, false)
+    }
+    fn lookup_name(&mut self, name: InternedStr, report_undefined: bool) -> Name {
+        let found = match split_qualified(&name) {
+            Some((qualifier, unqualified)) => match self.qualified.get(&name) {
+                Some(&global) => global,
+                None => {
+                    let qualifier = intern(qualifier);
+                    if !self.qualifiers.contains(&qualifier) {
+                        self.error(Error::UndefinedQualifier(qualifier));
+                    } else if report_undefined {
+                        self.error(Error::UndefinedName(name));
+                    }
+                    return Name {
+                        name: intern(unqualified),
+                        uid: 0,
+                    };
+                }
+            },
+            None => {
+                if report_undefined
+                    && !self.implicit_prelude
+                    && self.uniques.find(&name).is_none()
+                    && !builtins::is_primitive(name.as_ref())
+                {
+                    self.error(Error::UndefinedName(name));
+                }
+                self.get_defined_name(name)
+            }
+        };
+        if let Some(candidates) = self.ambiguous.get(&name) {
+            if candidates.contains(&found) {
+                let candidates = candidates
+                    .iter()
+                    .map(|candidate| {
+                        let module = self
+                            .module_names
+                            .get(&candidate.uid)
+                            .map_or("Prelude", |module| module.as_ref());
+                        [module, ".", candidate.name.as_ref()].concat()
+                    })
+                    .collect();
+                self.error(Error::Ambiguous(name, candidates));
+            }
+        }
+        found
+    }
+    ///Looks up the name a declaration refers to, declarations are never ambiguous
+    fn get_defined_name(&self, name: InternedStr) -> Name {
         Name {
             name,
             uid: self.uniques.find(&name).map(|n| n.uid).unwrap_or(0), // 0 -> Primitive
         }
     }
 
+    ///Renames the qualifiers of a list comprehension or guard.
+    ///Each qualifier can shadow the variables bound by the qualifiers before it so every
+    ///qualifier enters a scope of its own which the caller must exit
+    fn rename_qualifiers(
+        &mut self,
+        qualifiers: Vec<Qualifier<InternedStr>>,
+    ) -> Vec<Qualifier<Name>> {
+        qualifiers
+            .into_iter()
+            .map(|qualifier| match qualifier {
+                Qualifier::Generator(pattern, expr) => {
+                    let expr = self.rename(expr);
+                    self.uniques.enter_scope();
+                    let Located { location, node } = pattern;
+                    self.location = location;
+                    let loc = Located {
+                        location,
+                        node: self.rename_pattern(node),
+                    };
+                    Qualifier::Generator(loc, expr)
+                }
+                Qualifier::Guard(expr) => {
+                    self.uniques.enter_scope();
+                    Qualifier::Guard(self.rename(expr))
+                }
+                Qualifier::Let(bs) => {
+                    self.uniques.enter_scope();
+                    Qualifier::Let(self.rename_bindings(bs, false))
+                }
+            })
+            .collect()
+    }
+
     fn rename_matches(&mut self, matches: Match<InternedStr>) -> Match<Name> {
         match matches {
             Match::Simple(e) => Match::Simple(self.rename(e)),
@@ -417,11 +963,19 @@ impl Renamer {
                 gs.into_iter()
                     .map(
                         |Guard {
-                             predicate: p,
+                             qualifiers,
                              expression: e,
-                         }| Guard {
-                            predicate: self.rename(p),
-                            expression: self.rename(e),
+                         }| {
+                            let scopes = qualifiers.len();
+                            let qualifiers = self.rename_qualifiers(qualifiers);
+                            let expression = self.rename(e);
+                            for _ in 0..scopes {
+                                self.uniques.exit_scope();
+                            }
+                            Guard {
+                                qualifiers,
+                                expression,
+                            }
                         },
                     )
                     .collect(),
@@ -459,19 +1013,72 @@ impl Renamer {
     ) -> Vec<TypeDeclaration<Name>> {
         let decls2: Vec<TypeDeclaration<Name>> = decls
             .into_iter()
-            .map(|decl| TypeDeclaration {
-                name: self.get_name(decl.name),
-                typ: self.rename_qualified_type(decl.typ),
+            .map(|decl| {
+                self.location = decl.location;
+                TypeDeclaration {
+                    name: self.get_defined_name(decl.name),
+                    typ: self.rename_qualified_type(decl.typ),
+                    location: decl.location,
+                }
             })
             .collect();
         decls2
     }
 
+    ///Reports the derived instances which are also defined by an instance declaration or by
+    ///another deriving clause. Instance declarations which are defined multiple times are found
+    ///when their bindings are renamed
+    fn check_derived_instances(
+        &mut self,
+        data_definitions: &[DataDefinition<Name>],
+        newtypes: &[Newtype<Name>],
+        instances: &[Instance<Name>],
+        deriving_instances: &[DerivingInstance<Name>],
+    ) {
+        fn type_name(types: &[Type<Name>]) -> Option<InternedStr> {
+            match types {
+                [typ] => match *extract_applied_type(typ) {
+                    Type::Constructor(ref ctor) => Some(ctor.name.name),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        let mut defined: Vec<(Name, InternedStr)> = instances
+            .iter()
+            .filter_map(|instance| type_name(&instance.types).map(|typ| (instance.classname, typ)))
+            .collect();
+        let data_deriving = data_definitions.iter().flat_map(|data| {
+            let typ = extract_applied_type(&data.typ.value).ctor().name.name;
+            data.deriving.iter().map(move |class| (*class, typ, data.location))
+        });
+        let newtype_deriving = newtypes.iter().flat_map(|newtype| {
+            let typ = extract_applied_type(&newtype.typ.value).ctor().name;
+            newtype.deriving.iter().map(move |class| (*class, typ, newtype.location))
+        });
+        let standalone_deriving = deriving_instances.iter().filter_map(|instance| {
+            type_name(&instance.types).map(|typ| (instance.classname, typ, instance.location))
+        });
+        let derived: Vec<_> = data_deriving
+            .chain(newtype_deriving)
+            .chain(standalone_deriving)
+            .collect();
+        for (class, typ, location) in derived {
+            if defined.contains(&(class, typ)) {
+                self.location = location;
+                let instance = format!("instance {} {}", class.name.as_ref(), typ.as_ref());
+                self.error(Error::MultipleDefinitions(intern(&instance)));
+            } else {
+                defined.push((class, typ));
+            }
+        }
+    }
+
     ///Introduces a new Name to the current scope.
     ///If the name was already declared in the current scope an error is added
     fn make_unique(&mut self, name: InternedStr) -> Name {
         if self.uniques.in_current_scope(&name) {
-            self.errors.insert(Error::MultipleDefinitions(name));
+            self.error(Error::MultipleDefinitions(name));
             self.uniques.find(&name).map(|x| x.clone()).unwrap()
         } else {
             let u = self.name_supply.from_interned(name.clone());
@@ -487,18 +1094,22 @@ impl Renamer {
     }
 
     fn rename_type(&mut self, typ: Type<InternedStr>) -> Type<Name> {
-        typ.map(|s| self.get_name(s))
+        typ.map(|s| self.get_type_name(s))
     }
 }
 
 pub fn rename_expr(expr: TypedExpr<InternedStr>) -> Result<TypedExpr<Name>, RenamerError> {
     let mut renamer = Renamer::new();
+    renamer.implicit_prelude = true;
     let expr = renamer.rename(expr);
     renamer.errors.into_result(expr).map_err(RenamerError)
 }
 
+///Renames a module without the modules it imports, the names which are not in scope are assumed
+///to be globals of the Prelude
 pub fn rename_module(module: Module<InternedStr>) -> Result<Module<Name>, RenamerError> {
     let mut renamer = Renamer::new();
+    renamer.implicit_prelude = true;
     let m = rename_module_(&mut renamer, &[], module);
     renamer.errors.into_result(m).map_err(RenamerError)
 }
@@ -512,10 +1123,12 @@ fn rename_module_(
         renamer.uniques.find_mut(&name.name).unwrap().uid = 0;
         name.uid = 0;
     }
+    renamer.module_names.insert(name.uid, name.name);
     renamer.uniques.enter_scope();
     renamer.insert_globals(module_env, &module, name.uid);
     let Module {
         name: _,
+        exports,
         imports,
         classes,
         data_definitions,
@@ -523,19 +1136,41 @@ fn rename_module_(
         type_declarations,
         bindings,
         instances,
+        deriving_instances,
+        type_synonyms,
         fixity_declarations,
+        default_types,
+        extensions,
     } = module;
 
+    let exports2 = exports.map(|exports| {
+        exports
+            .into_iter()
+            .map(|export| Located {
+                location: export.location,
+                node: export.node.map(|name| renamer.get_defined_name(name)),
+            })
+            .collect()
+    });
+
     let imports2: Vec<Import<Name>> = imports
         .into_iter()
         .map(|import| {
-            let imports = import.imports.as_ref().map(|x| {
-                let is: Vec<Name> = x.iter().map(|&x| renamer.get_name(x)).collect();
-                is
-            });
+            let mut rename_item = |item: Located<Export<InternedStr>>| Located {
+                location: item.location,
+                node: item.node.map(|name| renamer.get_defined_name(name)),
+            };
+            let imports = import
+                .imports
+                .map(|x| x.into_iter().map(&mut rename_item).collect());
+            let hiding = import.hiding.into_iter().map(&mut rename_item).collect();
             Import {
                 module: import.module,
                 imports,
+                hiding,
+                qualified: import.qualified,
+                alias: import.alias,
+                location: import.location,
             }
         })
         .collect();
@@ -548,7 +1183,9 @@ fn rename_module_(
                 typ,
                 parameters,
                 deriving,
+                location,
             } = data;
+            renamer.location = location;
             let c: Vec<Constructor<Name>> = constructors
                 .into_iter()
                 .map(|ctor| {
@@ -557,12 +1194,17 @@ fn rename_module_(
                         typ,
                         tag,
                         arity,
+                        fields,
                     } = ctor;
                     Constructor {
-                        name: renamer.get_name(name),
+                        name: renamer.get_defined_name(name),
                         typ: renamer.rename_qualified_type(typ),
                         tag,
                         arity,
+                        fields: fields
+                            .into_iter()
+                            .map(|f| renamer.get_defined_name(f))
+                            .collect(),
                     }
                 })
                 .collect();
@@ -573,6 +1215,7 @@ fn rename_module_(
                 parameters,
                 constructors: c,
                 deriving: d,
+                location,
             }
         })
         .collect();
@@ -585,13 +1228,35 @@ fn rename_module_(
                 constructor_name,
                 constructor_type,
                 deriving,
+                location,
             } = newtype;
+            renamer.location = location;
             let deriving2: Vec<Name> = deriving.into_iter().map(|s| renamer.get_name(s)).collect();
             Newtype {
                 typ,
-                constructor_name: renamer.get_name(constructor_name),
+                constructor_name: renamer.get_defined_name(constructor_name),
                 constructor_type: renamer.rename_qualified_type(constructor_type),
                 deriving: deriving2,
+                location,
+            }
+        })
+        .collect();
+
+    let type_synonyms2: Vec<TypeSynonym<Name>> = type_synonyms
+        .into_iter()
+        .map(|synonym| {
+            let TypeSynonym {
+                name,
+                parameters,
+                typ,
+                location,
+            } = synonym;
+            renamer.location = location;
+            TypeSynonym {
+                name: renamer.get_type_name(name),
+                parameters,
+                typ: renamer.rename_type(typ),
+                location,
             }
         })
         .collect();
@@ -602,9 +1267,11 @@ fn rename_module_(
             let Instance {
                 bindings,
                 constraints,
-                typ,
+                types,
                 classname,
+                location,
             } = instance;
+            renamer.location = location;
             let constraints2: Vec<Constraint<Name>> = constraints
                 .into_iter()
                 .map(|Constraint { class, variables }| Constraint {
@@ -612,11 +1279,46 @@ fn rename_module_(
                     variables,
                 })
                 .collect();
+            let types = types
+                .into_iter()
+                .map(|typ| renamer.rename_type(typ))
+                .collect();
+            let classname = renamer.get_name(classname);
             Instance {
                 bindings: renamer.rename_bindings(bindings, true),
                 constraints: constraints2,
-                typ: renamer.rename_type(typ),
+                types,
+                classname,
+                location,
+            }
+        })
+        .collect();
+
+    let deriving_instances2: Vec<DerivingInstance<Name>> = deriving_instances
+        .into_iter()
+        .map(|instance| {
+            let DerivingInstance {
+                constraints,
+                types,
+                classname,
+                location,
+            } = instance;
+            renamer.location = location;
+            let constraints2: Vec<Constraint<Name>> = constraints
+                .into_iter()
+                .map(|Constraint { class, variables }| Constraint {
+                    class: renamer.get_name(class),
+                    variables,
+                })
+                .collect();
+            DerivingInstance {
+                constraints: constraints2,
+                types: types
+                    .into_iter()
+                    .map(|typ| renamer.rename_type(typ))
+                    .collect(),
                 classname: renamer.get_name(classname),
+                location,
             }
         })
         .collect();
@@ -627,10 +1329,12 @@ fn rename_module_(
             let Class {
                 constraints,
                 name,
-                variable,
+                variables,
                 declarations,
                 bindings,
+                location,
             } = class;
+            renamer.location = location;
             let constraints2: Vec<Constraint<Name>> = constraints
                 .into_iter()
                 .map(|Constraint { class, variables }| Constraint {
@@ -640,10 +1344,11 @@ fn rename_module_(
                 .collect();
             Class {
                 constraints: constraints2,
-                name: renamer.get_name(name),
-                variable,
+                name: renamer.get_defined_name(name),
+                variables,
                 declarations: renamer.rename_type_declarations(declarations),
                 bindings: renamer.rename_bindings(bindings, true),
+                location,
             }
         })
         .collect();
@@ -658,7 +1363,10 @@ fn rename_module_(
                  precedence,
                  operators,
              }| {
-                let ops: Vec<Name> = operators.into_iter().map(|s| renamer.get_name(s)).collect();
+                let ops: Vec<Name> = operators
+                    .into_iter()
+                    .map(|s| renamer.get_defined_name(s))
+                    .collect();
                 FixityDeclaration {
                     assoc,
                     precedence,
@@ -667,18 +1375,35 @@ fn rename_module_(
             },
         )
         .collect();
+    let default_types2 = default_types.map(|types| {
+        types
+            .into_iter()
+            .map(|typ| renamer.rename_type(typ))
+            .collect()
+    });
     let decls2 = renamer.rename_type_declarations(type_declarations);
+    renamer.check_derived_instances(
+        &data_definitions2,
+        &newtypes2,
+        &instances2,
+        &deriving_instances2,
+    );
     renamer.uniques.exit_scope();
     Module {
         name,
+        exports: exports2,
         imports: imports2,
         classes: classes2,
         data_definitions: data_definitions2,
         type_declarations: decls2,
         bindings: bindings2,
         instances: instances2,
+        deriving_instances: deriving_instances2,
         newtypes: newtypes2,
+        type_synonyms: type_synonyms2,
         fixity_declarations: fixity_declarations2,
+        default_types: default_types2,
+        extensions,
     }
 }
 
@@ -691,16 +1416,22 @@ pub fn prelude_name(s: &str) -> Name {
 
 ///Renames a vector of modules.
 ///If any errors are encounterd while renaming, an error message is output and fail is called
+///Renames `modules` where each module comes after the modules it imports.
+///Renaming stops at the first module which has errors, the errors are returned along with the
+///index of that module.
 pub fn rename_modules(
     modules: Vec<Module<InternedStr>>,
-) -> Result<Vec<Module<Name>>, RenamerError> {
+) -> Result<Vec<Module<Name>>, (usize, RenamerError)> {
     let mut renamer = Renamer::new();
     let mut ms = vec![];
     for module in modules.into_iter() {
         let m = rename_module_(&mut renamer, ms.as_ref(), module);
+        if let Err(errors) = renamer.errors.into_result(()) {
+            return Err((ms.len(), RenamerError(errors)));
+        }
         ms.push(m);
     }
-    renamer.errors.into_result(ms).map_err(RenamerError)
+    Ok(ms)
 }
 
 pub mod typ {
@@ -796,8 +1527,11 @@ pub mod tests {
     use {
         super::Name,
         crate::{
+            diagnostics::ToDiagnostics,
             interner::InternedStr,
             module::{
+                Expr,
+                Match,
                 Module,
                 TypedExpr,
             },
@@ -814,6 +1548,11 @@ pub mod tests {
     pub fn rename_expr(expr: TypedExpr<InternedStr>) -> TypedExpr<Name> {
         super::rename_expr(expr).unwrap()
     }
+    ///Parses `source` and the modules it imports and renames all of them
+    pub fn rename_string(source: &str) -> Vec<Module<Name>> {
+        let modules = parse_string(source).unwrap();
+        rename_modules(modules.into_iter().map(|(module, _)| module).collect())
+    }
 
     #[test]
     #[should_panic]
@@ -832,8 +1571,7 @@ main = 2"
         let file = r"
 import Prelude (id)
 main = id";
-        let modules = parse_string(file).unwrap();
-        rename_modules(modules);
+        rename_string(file);
     }
     #[test]
     #[should_panic]
@@ -847,4 +1585,243 @@ main = id"
         let module = parser.module().unwrap();
         rename_modules(vec![module]);
     }
+
+    fn parse_modules(sources: &[&str]) -> Vec<Module<InternedStr>> {
+        sources
+            .iter()
+            .map(|source| Parser::new(source.chars()).module().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn qualified_import() {
+        let modules = parse_modules(&[
+            "module A where\nlookup x = x",
+            "import qualified A as M\nmain = M.lookup 1",
+        ]);
+        let modules = rename_modules(modules);
+        let a = modules[0].bindings[0].name;
+        match modules[1].bindings[0].matches {
+            Match::Simple(TypedExpr {
+                expr: Expr::Apply(ref func, _),
+                ..
+            }) => assert_eq!(func.expr, Expr::Identifier(a)),
+            ref matches => panic!("Unexpected match {:?}", matches),
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn ambiguous_import() {
+        let modules = parse_modules(&[
+            "module A where\nf x = x",
+            "module B where\nf x = x",
+            "import A\nimport B\nmain = f 1",
+        ]);
+        rename_modules(modules);
+    }
+    #[test]
+    fn hiding_import() {
+        let modules = parse_modules(&[
+            "module A where\nf x = x",
+            "module B where\nf x = x",
+            "import A\nimport B hiding (f)\nmain = f 1",
+        ]);
+        let modules = rename_modules(modules);
+        let a = modules[0].bindings[0].name;
+        assert_ne!(a, modules[1].bindings[0].name);
+        match modules[2].bindings[0].matches {
+            Match::Simple(TypedExpr {
+                expr: Expr::Apply(ref func, _),
+                ..
+            }) => assert_eq!(func.expr, Expr::Identifier(a)),
+            ref matches => panic!("Unexpected match {:?}", matches),
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn import_not_exported() {
+        let modules = parse_modules(&[
+            "module A (f) where\nf x = x\ng x = x",
+            "import A (g)\nmain = g 1",
+        ]);
+        rename_modules(modules);
+    }
+    #[test]
+    #[should_panic]
+    fn undefined_export() {
+        let modules = parse_modules(&["module A (h) where\nf x = x"]);
+        rename_modules(modules);
+    }
+    #[test]
+    fn reexport() {
+        let modules = parse_modules(&[
+            "module A where\nf x = x",
+            "module B (module A, T(..)) where\nimport A\ndata T = C Int",
+            "import B\nmain = f (C 1)",
+        ]);
+        let modules = rename_modules(modules);
+        let f = modules[0].bindings[0].name;
+        let c = modules[1].data_definitions[0].constructors[0].name;
+        match modules[2].bindings[0].matches {
+            Match::Simple(TypedExpr {
+                expr: Expr::Apply(ref func, ref arg),
+                ..
+            }) => {
+                assert_eq!(func.expr, Expr::Identifier(f));
+                match arg.expr {
+                    Expr::Paren(ref e) => match e.expr {
+                        Expr::Apply(ref ctor, _) => assert_eq!(ctor.expr, Expr::Identifier(c)),
+                        ref e => panic!("Unexpected expression {:?}", e),
+                    },
+                    ref e => panic!("Unexpected expression {:?}", e),
+                }
+            }
+            ref matches => panic!("Unexpected match {:?}", matches),
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn import_type_not_exported() {
+        let modules = parse_modules(&[
+            "module A1 where\ndata Nope = Nope",
+            "module A2 (f) where\nf x = x",
+            "import A1\nimport A2 (Nope)\nmain = 1",
+        ]);
+        rename_modules(modules);
+    }
+    #[test]
+    fn import_subordinates() {
+        let modules = parse_modules(&[
+            "module A (T(..), U(C), f) where\ndata T = A | B\ndata U = C | D\nf x = x",
+            "import A (T(..), U(C), f)\nmain = f A B C",
+        ]);
+        let modules = rename_modules(modules);
+        let constructors = &modules[0].data_definitions[0].constructors;
+        let c = modules[0].data_definitions[1].constructors[0].name;
+        match modules[1].bindings[0].matches {
+            Match::Simple(ref e) => {
+                let mut args = vec![];
+                let mut expr = e;
+                while let Expr::Apply(ref func, ref arg) = expr.expr {
+                    args.push(arg.expr.clone());
+                    expr = func;
+                }
+                assert_eq!(
+                    args,
+                    vec![
+                        Expr::Identifier(c),
+                        Expr::Identifier(constructors[1].name),
+                        Expr::Identifier(constructors[0].name),
+                    ]
+                );
+            }
+            ref matches => panic!("Unexpected match {:?}", matches),
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn import_subordinate_not_exported() {
+        let modules = parse_modules(&[
+            "module A (U(C)) where\ndata U = C | D",
+            "import A (U(D))\nmain = D",
+        ]);
+        rename_modules(modules);
+    }
+    #[test]
+    #[should_panic]
+    fn undefined_qualifier() {
+        let modules = parse_modules(&["module A where\nf x = x", "import A as M\nmain = N.f 1"]);
+        rename_modules(modules);
+    }
+    #[test]
+    fn error_locations() {
+        let modules = parse_modules(&[
+            "module A (U(C)) where\ndata U = C | D",
+            "import A (U(D))\nmain = N.f 1",
+        ]);
+        let errors = match super::rename_modules(modules) {
+            Ok(_) => panic!("Expected renaming to fail"),
+            Err((_, errors)) => errors.diagnostics(),
+        };
+        let locations: Vec<_> = errors
+            .iter()
+            .map(|error| (error.span.start.row, error.span.start.column))
+            .collect();
+        assert_eq!(locations, vec![(0, 11), (1, 8)]);
+
+        let modules = parse_modules(&["module B (x, missing) where\nx = 1"]);
+        let errors = match super::rename_modules(modules) {
+            Ok(_) => panic!("Expected renaming to fail"),
+            Err((_, errors)) => errors.diagnostics(),
+        };
+        assert_eq!(errors[0].message, r#""missing" is exported but is not in scope"#);
+        assert_eq!((errors[0].span.start.row, errors[0].span.start.column), (0, 14));
+    }
+    ///Renames `modules` and returns the errors of the renamer
+    fn module_errors(modules: Vec<Module<InternedStr>>) -> Vec<String> {
+        match super::rename_modules(modules) {
+            Ok(_) => vec![],
+            Err((_, errors)) => errors.0.iter().map(|error| error.node.to_string()).collect(),
+        }
+    }
+    ///Renames `source` and the modules it imports and returns the errors of the renamer
+    fn rename_errors(source: &str) -> Vec<String> {
+        let modules = parse_string(source).unwrap();
+        module_errors(modules.into_iter().map(|(module, _)| module).collect())
+    }
+    #[test]
+    fn hidden_prelude_name() {
+        let expected = vec![r#""length" is not in scope"#.to_string()];
+        assert_eq!(
+            rename_errors("import Prelude hiding (length)\nmain = length [1]"),
+            expected
+        );
+        assert_eq!(rename_errors("import Prelude (map)\nmain = length [1]"), expected);
+        assert_eq!(
+            rename_errors("import qualified Prelude as P\nmain = length [1]"),
+            expected
+        );
+        assert_eq!(
+            rename_errors("import Prelude hiding (length)\nmain = Prelude.length [1]"),
+            vec![r#""Prelude.length" is not in scope"#.to_string()]
+        );
+        assert_eq!(
+            rename_errors("import Prelude (map)\nmain = map id []"),
+            vec![r#""id" is not in scope"#.to_string()]
+        );
+    }
+    #[test]
+    fn abstract_type_constructor() {
+        let modules = parse_modules(&[
+            "module Data.Mp (Hidden) where\ndata Hidden = H Int",
+            "import Data.Mp\nf :: Hidden -> Int\nf (H x) = x",
+        ]);
+        assert_eq!(module_errors(modules), vec![r#""H" is not in scope"#.to_string()]);
+    }
+    #[test]
+    fn prelude_names_are_not_reexported() {
+        let modules = parse_modules(&[
+            "module Prelude where\nlength xs = 0",
+            "module Other where\nimport Prelude\nother = length []",
+            "import Other\nmain = length [other]",
+        ]);
+        assert_eq!(module_errors(modules), vec![r#""length" is not in scope"#.to_string()]);
+    }
+    #[test]
+    fn derived_instance_defined_multiple_times() {
+        let errors = |declarations: &str| {
+            rename_errors(&format!("import Prelude\ndata Q = Q{}", declarations))
+        };
+        let expected = vec![r#""instance Eq Q" is defined multiple times"#.to_string()];
+        assert_eq!(
+            errors("\ninstance Eq Q where\n    (==) x y = True\nderiving instance Eq Q"),
+            expected
+        );
+        assert_eq!(errors(" deriving Eq\nderiving instance Eq Q"), expected);
+        assert_eq!(errors("\nderiving instance Eq Q\nderiving instance Eq Q"), expected);
+        assert_eq!(
+            errors("\nderiving instance Eq Q\nderiving instance Ord Q"),
+            Vec::<String>::new()
+        );
+    }
 }
@@ -15,6 +15,7 @@ use crate::{
         rename_expr,
         Name,
     },
+    search_path::SearchPath,
     typecheck::*,
     vm::*,
 };
@@ -31,13 +32,20 @@ fn is_io(typ: &Type<Name>) -> bool {
 
 ///Compiles an expression into an assembly
 fn compile_expr(prelude: &Assembly, expr_str: &str) -> Result<Assembly, VMError> {
+    compile_expr_(prelude, expr_str).map_err(|error| error.with_source("<interactive>", expr_str))
+}
+
+fn compile_expr_(prelude: &Assembly, expr_str: &str) -> Result<Assembly, VMError> {
     let mut parser = Parser::new(expr_str.chars());
-    let expr = parser.expression_().unwrap();
-    let mut expr = rename_expr(expr).unwrap();
+    let expr = parser.complete_expression()?;
+    let mut expr = rename_expr(expr)?;
 
     let mut type_env = TypeEnvironment::new();
     type_env.add_types(prelude as &dyn DataTypes);
-    type_env.typecheck_expr(&mut expr).unwrap();
+    type_env.typecheck_expr(&mut expr)?;
+    for warning in type_env.warnings() {
+        eprintln!("{}", warning.clone().with_source("<interactive>", expr_str));
+    }
     let temp_module = Module::from_expr(translate_expr(expr));
     let m = do_lambda_lift(temp_module);
 
@@ -76,21 +84,27 @@ fn find_main(assembly: &Assembly) -> (Vec<Instruction>, Qualified<Type<Name>, Na
         .expect("Expected main function")
 }
 
-pub fn run_and_print_expr(expr_str: &str) {
-    let prelude = compile_file("Prelude.hs").unwrap();
+pub fn run_and_print_expr(search_path: &SearchPath, expr_str: &str) {
+    let prelude = compile_file(search_path, "Prelude.hs").unwrap();
     let mut vm = VM::new();
     vm.add_assembly(prelude);
-    let assembly = compile_expr(vm.get_assembly(0), expr_str.as_ref()).unwrap();
+    let assembly = match compile_expr(vm.get_assembly(0), expr_str.as_ref()) {
+        Ok(assembly) => assembly,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
     let (instructions, type_decl) = find_main(&assembly);
     let assembly_index = vm.add_assembly(assembly);
     let result = vm.evaluate(&instructions, assembly_index); //TODO 0 is not necessarily correct
     println!("{:?}  {}", result, type_decl);
 }
 
-///Starts the REPL
-pub fn start() {
+///Starts the REPL, loading the Prelude from `search_path`
+pub fn start(search_path: &SearchPath) {
     let mut vm = VM::new();
-    match compile_file("Prelude.hs") {
+    match compile_file(search_path, "Prelude.hs") {
         Ok(prelude) => {
             vm.add_assembly(prelude);
         }
@@ -84,6 +84,12 @@ where
         self.map.get(k).and_then(|x| x.last())
     }
 
+    ///Returns a reference to the first inserted value corresponding to the key,
+    ///ignoring any values which shadow it
+    pub fn find_outermost<'a>(&'a self, k: &K) -> Option<&'a V> {
+        self.map.get(k).and_then(|x| x.first())
+    }
+
     ///Returns the number of elements in the container.
     ///Shadowed elements are not counted
     pub fn len(&self) -> usize {
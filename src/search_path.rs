@@ -0,0 +1,189 @@
+use {
+    crate::unlit::unlit,
+    std::{
+        env,
+        fs::File,
+        io::{
+            self,
+            Read,
+        },
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+};
+
+///The environment variable which overrides the directory of the standard library
+const STANDARD_LIBRARY_VARIABLE: &str = "HASKELL_COMPILER_LIB";
+
+///The checkout the compiler was built from, which contains the standard library (the Prelude)
+///when the compiler is run from its build directory
+const BUILD_STANDARD_LIBRARY: &str = env!("CARGO_MANIFEST_DIR");
+
+///The directories which modules and source files are looked up in.
+///Roots are searched in order: the current directory, every root added with `add_root`
+///and lastly the bundled standard library
+#[derive(Clone, Debug)]
+pub struct SearchPath {
+    roots: Vec<PathBuf>,
+}
+
+impl SearchPath {
+    ///Creates a search path containing the current directory and the standard library
+    pub fn new() -> Self {
+        Self {
+            roots: vec![PathBuf::from("."), standard_library()],
+        }
+    }
+
+    ///Adds a directory which is searched before the standard library
+    pub fn add_root<P: Into<PathBuf>>(&mut self, root: P) {
+        let index = self.roots.len() - 1;
+        self.roots.insert(index, root.into());
+    }
+
+    ///Returns the path of the file which defines `module`.
+    ///A hierarchical module such as `Data.Maybe` is looked up as `Data/Maybe.hs`, or as the
+    ///literate `Data/Maybe.lhs` if a root does not contain `Data/Maybe.hs`
+    pub fn find_module(&self, module: &str) -> io::Result<PathBuf> {
+        let path = module_path(module);
+        let literate_path = path.with_extension("lhs");
+        self.roots
+            .iter()
+            .flat_map(|root| [root.join(&path), root.join(&literate_path)])
+            .find(|path| path.is_file())
+            .ok_or_else(|| self.not_found(&path))
+    }
+
+    ///Returns the first root which contains `filename` joined with `filename`
+    pub fn find_file(&self, filename: &Path) -> io::Result<PathBuf> {
+        self.roots
+            .iter()
+            .map(|root| root.join(filename))
+            .find(|path| path.is_file())
+            .ok_or_else(|| self.not_found(filename))
+    }
+
+    fn not_found(&self, filename: &Path) -> io::Error {
+        let roots: Vec<_> = self
+            .roots
+            .iter()
+            .map(|root| root.display().to_string())
+            .collect();
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "Could not find {} in the search path: {}",
+                filename.display(),
+                roots.join(", ")
+            ),
+        )
+    }
+
+    ///Reads the source code of `module`
+    pub fn read_module(&self, module: &str) -> io::Result<String> {
+        read_file(&self.find_module(module)?)
+    }
+}
+
+///Reads the source code in `path`, removing the commentary if it is a literate (`.lhs`) file
+pub fn read_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    if path.extension().is_some_and(|extension| extension == "lhs") {
+        contents = unlit(&contents);
+    }
+    Ok(contents)
+}
+
+///Returns the directory which contains the standard library. This is the directory named by the
+///`HASKELL_COMPILER_LIB` environment variable if it is set, otherwise the directory of the
+///executable or `../lib/haskell-compiler` relative to it if they contain the Prelude and lastly
+///the checkout the compiler was built from
+fn standard_library() -> PathBuf {
+    let executable = env::current_exe().ok();
+    locate_standard_library(env::var_os(STANDARD_LIBRARY_VARIABLE).map(PathBuf::from), executable)
+}
+
+fn locate_standard_library(variable: Option<PathBuf>, executable: Option<PathBuf>) -> PathBuf {
+    if let Some(root) = variable {
+        return root;
+    }
+    executable
+        .as_ref()
+        .and_then(|executable| executable.parent())
+        .and_then(|directory| {
+            [directory.to_path_buf(), directory.join("../lib/haskell-compiler")]
+                .into_iter()
+                .find(|root| root.join("Prelude.hs").is_file())
+        })
+        .unwrap_or_else(|| PathBuf::from(BUILD_STANDARD_LIBRARY))
+}
+
+///Turns a module name into the relative path of the file which defines it
+fn module_path(module: &str) -> PathBuf {
+    let mut path: PathBuf = module.split('.').collect();
+    path.set_extension("hs");
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hierarchical_module_path() {
+        assert_eq!(module_path("Prelude"), Path::new("Prelude.hs"));
+        assert_eq!(module_path("Data.Maybe"), Path::new("Data/Maybe.hs"));
+    }
+
+    #[test]
+    fn find_standard_library() {
+        let mut search_path = SearchPath::new();
+        search_path.add_root("does-not-exist");
+        let path = search_path.find_module("Prelude").unwrap();
+        assert!(path.ends_with("Prelude.hs"));
+        assert!(search_path.find_module("Data.Missing").is_err());
+    }
+
+    #[test]
+    fn standard_library_location() {
+        let installed = std::env::temp_dir().join("search_path_standard_library_location");
+        std::fs::create_dir_all(installed.join("bin")).unwrap();
+        std::fs::create_dir_all(installed.join("lib/haskell-compiler")).unwrap();
+        std::fs::write(installed.join("lib/haskell-compiler/Prelude.hs"), "").unwrap();
+        let executable = installed.join("bin/haskell-compiler");
+
+        assert_eq!(
+            locate_standard_library(Some(PathBuf::from("lib")), Some(executable.clone())),
+            Path::new("lib")
+        );
+        assert_eq!(
+            locate_standard_library(None, Some(executable)),
+            installed.join("bin/../lib/haskell-compiler")
+        );
+        assert_eq!(
+            locate_standard_library(None, Some(installed.join("haskell-compiler"))),
+            Path::new(BUILD_STANDARD_LIBRARY)
+        );
+        assert_eq!(locate_standard_library(None, None), Path::new(BUILD_STANDARD_LIBRARY));
+    }
+
+    #[test]
+    fn find_literate_module() {
+        let root = std::env::temp_dir().join("search_path_find_literate_module");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("Literate.lhs"), "Commentary\n> main = 1\n").unwrap();
+        let mut search_path = SearchPath::new();
+        search_path.add_root(&root);
+
+        let path = search_path.find_module("Literate").unwrap();
+        assert!(path.ends_with("Literate.lhs"));
+        assert_eq!(
+            search_path.read_module("Literate").unwrap(),
+            "          \n  main = 1\n"
+        );
+    }
+}
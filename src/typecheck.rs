@@ -1,6 +1,22 @@
 use {
     crate::{
         builtins::builtins,
+        deriving::{
+            check_deriving,
+            derived_constraints,
+            derived_instance_type,
+            is_newtype_deriving,
+            newtype_data_definition,
+            newtype_type,
+            newtype_wrapped_type,
+        },
+        diagnostics::{
+            Diagnostic,
+            Severity,
+            SourceFile,
+            Span,
+            ToDiagnostics,
+        },
         graph::{
             strongly_connected_components,
             Graph,
@@ -14,6 +30,8 @@ use {
             *,
         },
         renamer::*,
+        search_path::SearchPath,
+        vm::VMError,
     },
     std::{
         collections::{
@@ -36,22 +54,32 @@ pub trait Types {
         name: Name,
     ) -> Option<(
         &'a [Constraint<Name>],
-        &'a TypeVariable,
+        &'a [TypeVariable],
         &'a [TypeDeclaration<Name>],
     )>;
-    fn has_instance(&self, classname: Name, typ: &TcType) -> bool {
-        self.find_instance(classname, typ).is_some()
+    fn has_instance(&self, classname: Name, types: &[TcType]) -> bool {
+        self.find_instance(classname, types).is_some()
     }
+    ///Finds the instance of `classname` for `types`, one type for each parameter of the class
     fn find_instance<'a>(
         &'a self,
         classname: Name,
-        typ: &TcType,
-    ) -> Option<(&'a [Constraint<Name>], &'a TcType)>;
+        types: &[TcType],
+    ) -> Option<(&'a [Constraint<Name>], &'a [TcType])>;
+    ///Calls `f` with the name and type of every binding, class method and constructor
+    fn each_global(&self, f: &mut dyn FnMut(&Name, &Qualified<TcType, Name>));
 }
 
 ///A trait which also allows for lookup of data types
 pub trait DataTypes: Types {
     fn find_data_type<'a>(&'a self, name: Name) -> Option<&'a DataDefinition<Name>>;
+    fn find_newtype<'a>(&'a self, name: Name) -> Option<&'a Newtype<Name>>;
+    fn find_type_synonym<'a>(&'a self, name: Name) -> Option<&'a TypeSynonym<Name>>;
+    ///Returns the kind of the type constructor `name`
+    fn find_type_kind(&self, name: Name) -> Option<Kind> {
+        self.find_data_type(name)
+            .map(|data| extract_applied_type(&data.typ.value).kind().clone())
+    }
 }
 
 impl Types for Module<Name> {
@@ -87,7 +115,7 @@ impl Types for Module<Name> {
         name: Name,
     ) -> Option<(
         &'a [Constraint<Name>],
-        &'a TypeVariable,
+        &'a [TypeVariable],
         &'a [TypeDeclaration<Name>],
     )> {
         self.classes
@@ -96,7 +124,7 @@ impl Types for Module<Name> {
             .map(|class| {
                 (
                     class.constraints.as_ref(),
-                    &class.variable,
+                    class.variables.as_ref(),
                     class.declarations.as_ref(),
                 )
             })
@@ -105,18 +133,35 @@ impl Types for Module<Name> {
     fn find_instance<'a>(
         &'a self,
         classname: Name,
-        typ: &TcType,
-    ) -> Option<(&'a [Constraint<Name>], &'a TcType)> {
+        types: &[TcType],
+    ) -> Option<(&'a [Constraint<Name>], &'a [TcType])> {
         for instance in self.instances.iter() {
-            if classname == instance.classname
-                && extract_applied_type(&instance.typ) == extract_applied_type(typ)
-            {
-                //test name
-                return Some((instance.constraints.as_ref(), &instance.typ));
+            if classname == instance.classname && same_instance_heads(&instance.types, types) {
+                return Some((instance.constraints.as_ref(), instance.types.as_ref()));
+            }
+        }
+        for instance in self.deriving_instances.iter() {
+            if classname == instance.classname && same_instance_heads(&instance.types, types) {
+                return Some((instance.constraints.as_ref(), instance.types.as_ref()));
             }
         }
         None
     }
+
+    fn each_global(&self, f: &mut dyn FnMut(&Name, &Qualified<TcType, Name>)) {
+        for bind in self.bindings.iter() {
+            f(&bind.name, &bind.typ);
+        }
+        for decl in self.classes.iter().flat_map(|class| class.declarations.iter()) {
+            f(&decl.name, &decl.typ);
+        }
+        for ctor in self.data_definitions.iter().flat_map(|data| data.constructors.iter()) {
+            f(&ctor.name, &ctor.typ);
+        }
+        for newtype in self.newtypes.iter() {
+            f(&newtype.constructor_name, &newtype.constructor_type);
+        }
+    }
 }
 
 impl DataTypes for Module<Name> {
@@ -128,6 +173,48 @@ impl DataTypes for Module<Name> {
         }
         None
     }
+    fn find_newtype<'a>(&'a self, name: Name) -> Option<&'a Newtype<Name>> {
+        self.newtypes
+            .iter()
+            .find(|newtype| extract_applied_type(newtype_type(newtype)).ctor().name == name)
+    }
+    fn find_type_synonym<'a>(&'a self, name: Name) -> Option<&'a TypeSynonym<Name>> {
+        self.type_synonyms
+            .iter()
+            .find(|synonym| synonym.name == name)
+    }
+    fn find_type_kind(&self, name: Name) -> Option<Kind> {
+        self.find_data_type(name)
+            .map(|data| extract_applied_type(&data.typ.value).kind().clone())
+            .or_else(|| {
+                self.newtypes.iter().find_map(|newtype| {
+                    let typ = get_returntype(&newtype.constructor_type.value);
+                    let head = extract_applied_type(&typ);
+                    if head.ctor().name == name {
+                        Some(head.kind().clone())
+                    } else {
+                        None
+                    }
+                })
+            })
+    }
+}
+
+///A typed hole (`_` or `_name`) which is reported once the types of its binding group are known
+struct TypedHole {
+    name: InternedStr,
+    location: Location,
+    typ: TcType,
+    ///The local bindings which were in scope at the hole
+    locals: Vec<(Name, TcType)>,
+}
+
+///Describes the expression which caused a constraint on a type variable, such as the literal or
+///the use of an overloaded function
+#[derive(Clone, Debug)]
+struct ConstraintOrigin {
+    description: ::std::string::String,
+    location: Location,
 }
 
 ///The TypeEnvironment stores most data which is needed as typechecking is performed.
@@ -142,13 +229,50 @@ pub struct TypeEnvironment<'a> {
     local_types: HashMap<Name, Qualified<TcType, Name>>,
     ///Stores the constraints for each typevariable since the typevariables cannot themselves store this.
     constraints: HashMap<TypeVariable, Vec<Name>>,
+    ///The expressions which caused each of the constraints in `constraints`, used to explain
+    ///where a missing instance was required
+    constraint_origins: HashMap<TypeVariable, Vec<(Name, ConstraintOrigin)>>,
     ///Stores data about the instances which are available.
     ///1: Any constraints for the type which the instance is for
     ///2: The name of the class
     ///3: The Type which the instance is defined for
-    instances: Vec<(Vec<Constraint<Name>>, Name, TcType)>,
+    instances: Vec<(Vec<Constraint<Name>>, Name, Vec<TcType>)>,
     classes: Vec<(Vec<Constraint<Name>>, Name)>,
+    ///Constraints of classes with several parameters such as `Convert a b`.
+    ///Since `constraints` can only store constraints on a single variable these are kept until
+    ///their types are known, at which point the instance is looked up.
+    ///Each constraint is stored with the location of the expression which required it
+    multi_constraints: Vec<(Name, Vec<TcType>, Location)>,
     data_definitions: Vec<DataDefinition<Name>>,
+    newtypes: Vec<Newtype<Name>>,
+    type_synonyms: Vec<TypeSynonym<Name>>,
+    ///The inferred kinds of the data types and newtypes declared in the checked modules
+    type_kinds: HashMap<Name, Kind>,
+    ///The types which ambiguous numeric type variables default to, in order of preference.
+    ///Set from the `default` declaration of the module being checked
+    default_types: Option<Vec<TcType>>,
+    ///Type variables which have been generalized in the type of a local binding.
+    ///Their constraints are kept until the enclosing global binding is checked but they are
+    ///not ambiguous and must not be defaulted
+    generalized_variables: Vec<TypeVariable>,
+    ///Whether the monomorphism restriction applies to the checked module, disabled by the
+    ///`NoMonomorphismRestriction` extension
+    monomorphism_restriction: bool,
+    ///Constrained type variables of top level bindings which were not generalized because of the
+    ///monomorphism restriction. Their constraints are kept between binding groups so that later
+    ///uses of the bindings can determine their types, any which are left are defaulted once the
+    ///whole module has been checked
+    monomorphic_variables: Vec<TypeVariable>,
+    ///The types which the variables in `monomorphic_variables` were bound to
+    monomorphic_types: Substitution,
+    ///The typed holes found in the binding groups which are being checked
+    holes: Vec<TypedHole>,
+    ///The variables which replaced the wildcards (`_`) of partial type signatures, for each
+    ///binding in the groups which are being checked
+    wildcards: Vec<(Name, Vec<TcType>)>,
+    ///The locations of the type signatures which were given to the checked bindings
+    signature_locations: HashMap<Name, Location>,
+    warnings: Vec<Diagnostic>,
     ///The current age for newly created variables.
     ///Age is used to determine whether variables need to be quantified or not.
     variable_age: isize,
@@ -170,6 +294,12 @@ impl error::Error for TypeError {
     }
 }
 
+impl ToDiagnostics for TypeError {
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.0.iter().map(|error| error.diagnostic()).collect()
+    }
+}
+
 ///A Substitution is a mapping from typevariables to types.
 #[derive(Clone)]
 pub struct Substitution {
@@ -294,6 +424,12 @@ impl<'a> TypeEnvironment<'a> {
             "primDoubleToInt",
             typ::function_type_(typ::double_type(), typ::int_type()),
         );
+        let char_compare = typ::function_type_(
+            typ::char_type(),
+            typ::function_type_(typ::char_type(), typ::bool_type()),
+        );
+        insert_to(&mut globals, "primCharEQ", char_compare.clone());
+        insert_to(&mut globals, "primCharLE", char_compare);
         let var = Type::Generic(TypeVariable::new_var_kind("a".into(), Kind::Star.clone()));
 
         for (name, typ) in builtins().into_iter() {
@@ -316,9 +452,25 @@ impl<'a> TypeEnvironment<'a> {
             named_types: globals,
             local_types: HashMap::new(),
             constraints: HashMap::new(),
+            constraint_origins: HashMap::new(),
             instances: vec![],
             classes: vec![],
+            multi_constraints: vec![],
             data_definitions: vec![],
+            newtypes: vec![],
+            type_synonyms: vec![],
+            type_kinds: HashMap::new(),
+            default_types: None,
+            generalized_variables: vec![],
+            monomorphism_restriction: true,
+            monomorphic_variables: vec![],
+            monomorphic_types: Substitution {
+                subs: HashMap::new(),
+            },
+            holes: vec![],
+            wildcards: vec![],
+            signature_locations: HashMap::new(),
+            warnings: vec![],
             variable_age: 0,
             errors: Errors::new(),
         }
@@ -328,6 +480,11 @@ impl<'a> TypeEnvironment<'a> {
         self.assemblies.push(types);
     }
 
+    ///Returns the warnings which have been found while typechecking
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
     ///Typechecks a module
     ///If the typecheck is successful the types in the module are updated with the new types.
     ///If any errors were found while typechecking panic! is called.
@@ -337,6 +494,13 @@ impl<'a> TypeEnvironment<'a> {
     }
     pub fn typecheck_module2(&mut self, module: &mut Module<Name>) {
         let start_var_age = self.variable_age + 1;
+        self.monomorphism_restriction = !module
+            .extensions
+            .contains(&intern("NoMonomorphismRestriction"));
+        for synonym in module.type_synonyms.iter_mut() {
+            self.add_type_synonym(synonym);
+        }
+        self.infer_kinds(module);
         for data_def in module.data_definitions.iter_mut() {
             for constructor in data_def.constructors.iter_mut() {
                 let mut typ = constructor.typ.clone();
@@ -345,29 +509,30 @@ impl<'a> TypeEnvironment<'a> {
             }
             self.data_definitions.push(data_def.clone());
         }
-        for newtype in module.newtypes.iter() {
+        for data_def in module.data_definitions.iter() {
+            for &class in data_def.deriving.iter() {
+                if let Err(reason) = check_deriving(class, data_def) {
+                    self.errors.insert(TypeErrorInfo {
+                        location: data_def.location,
+                        lhs: data_def.typ.value.clone(),
+                        rhs: data_def.typ.value.clone(),
+                        error: Error::CannotDerive(class, reason),
+                    });
+                }
+            }
+        }
+        for newtype in module.newtypes.iter_mut() {
             let mut typ = newtype.constructor_type.clone();
             quantify(0, &mut typ);
             self.named_types
                 .insert(newtype.constructor_name.clone(), typ);
+            self.newtypes.push(newtype.clone());
         }
         for class in module.classes.iter_mut() {
-            //Instantiate a new variable and replace all occurances of the class variable with this
-            let mut var_kind = None;
             for type_decl in class.declarations.iter_mut() {
-                var_kind = match find_kind(&class.variable, var_kind, &type_decl.typ.value) {
-                    Ok(k) => k,
-                    Err(msg) => panic!("{:?}", msg),
-                };
-                //If we found the variable, update it immediatly since the kind of th variable
-                //matters when looking for constraints, etc
-                if let Some(ref k) = var_kind {
-                    class.variable.kind.clone_from(k);
-                };
-
                 let c = Constraint {
                     class: class.name.clone(),
-                    variables: vec![class.variable.clone()],
+                    variables: class.variables.clone(),
                 };
                 {
                     //Workaround to add the class's constraints directyly to the declaration
@@ -391,13 +556,14 @@ impl<'a> TypeEnvironment<'a> {
                         || panic!("Could not find {:?} in class {:?}", binding.name, classname)
                     );
                 binding.typ = decl.typ.clone();
+                self.signature_locations.insert(binding.name, decl.location);
                 {
                     let mut context = vec![];
                     swap(&mut context, &mut binding.typ.constraints);
                     let mut vec_context: Vec<Constraint<Name>> = context.into_iter().collect();
                     let c = Constraint {
                         class: class.name.clone(),
-                        variables: vec![class.variable.clone()],
+                        variables: class.variables.clone(),
                     };
                     vec_context.push(c);
                     binding.typ.constraints = vec_context;
@@ -406,16 +572,15 @@ impl<'a> TypeEnvironment<'a> {
             self.classes
                 .push((class.constraints.clone(), class.name.clone()));
         }
-        let data_definitions = module.data_definitions.clone();
         for instance in module.instances.iter_mut() {
-            let (_, class_var, class_decls) = module
+            let (_, class_vars, class_decls) = module
                 .classes
                 .iter()
                 .find(|class| class.name == instance.classname)
                 .map(|class| {
                     (
                         class.constraints.as_ref(),
-                        &class.variable,
+                        class.variables.as_ref(),
                         class.declarations.as_ref(),
                     )
                 })
@@ -426,30 +591,28 @@ impl<'a> TypeEnvironment<'a> {
                         .next()
                 })
                 .unwrap_or_else(|| panic!("Could not find class {:?}", instance.classname));
-            //Update the kind of the type for the instance to be the same as the class kind (since we have no proper kind inference
-            match instance.typ {
-                Type::Constructor(ref mut op) => {
-                    let maybe_data = self
-                        .assemblies
-                        .iter()
-                        .filter_map(|a| a.find_data_type(op.name))
-                        .next();
-                    op.kind = maybe_data
-                        .or_else(|| {
-                            data_definitions.iter().find(|data| {
-                                op.name == extract_applied_type(&data.typ.value).ctor().name
-                            })
-                        })
-                        .map(|data| extract_applied_type(&data.typ.value).kind().clone())
-                        .unwrap_or_else(|| {
-                            if intern("[]") == op.name {
-                                Kind::Function(Kind::Star.into(), Kind::Star.into())
-                            } else {
-                                Kind::Star
-                            }
-                        });
-                }
-                _ => (),
+            if class_vars.len() != instance.types.len() {
+                let head = Type::new_op(instance.classname, instance.types.clone());
+                self.errors.insert(TypeErrorInfo {
+                    location: instance.location,
+                    lhs: head.clone(),
+                    rhs: head,
+                    error: Error::InstanceArity(
+                        instance.classname,
+                        class_vars.len(),
+                        instance.types.len(),
+                    ),
+                });
+                continue;
+            }
+            let mut kinds_match = true;
+            for (class_var, typ) in class_vars.iter().zip(instance.types.iter_mut()) {
+                self.expand_type_synonyms(&instance.location, typ);
+                kinds_match = self.check_instance_kind(&instance.location, &class_var.kind, typ)
+                    && kinds_match;
+            }
+            if !kinds_match {
+                continue;
             }
             for binding in instance.bindings.iter_mut() {
                 let classname = &instance.classname;
@@ -460,8 +623,17 @@ impl<'a> TypeEnvironment<'a> {
                         || panic!("Could not find {:?} in class {:?}", binding.name, classname)
                     );
                 binding.typ = decl.typ.clone();
-                replace_var(&mut binding.typ.value, class_var, &instance.typ);
-                self.freshen_qualified_type(&mut binding.typ, HashMap::new());
+                self.signature_locations.insert(binding.name, instance.location);
+                for (class_var, typ) in class_vars.iter().zip(instance.types.iter()) {
+                    replace_var(&mut binding.typ.value, class_var, typ);
+                }
+                //The class variables have been replaced so constraints on them no longer apply
+                binding.typ.constraints.retain(|constraint| {
+                    !constraint
+                        .variables
+                        .iter()
+                        .any(|var| class_vars.contains(var))
+                });
                 {
                     let mut context = vec![];
                     swap(&mut context, &mut binding.typ.constraints);
@@ -471,6 +643,9 @@ impl<'a> TypeEnvironment<'a> {
                     }
                     binding.typ.constraints = vec_context;
                 }
+                //Freshen the context of the instance together with the type so that the
+                //constraints refer to the variables of the type
+                self.freshen_qualified_type(&mut binding.typ, HashMap::new());
             }
             {
                 let mut missing_super_classes = self
@@ -478,7 +653,15 @@ impl<'a> TypeEnvironment<'a> {
                     .unwrap_or_else(|| panic!("Error: Missing class {:?}", instance.classname))
                     .iter() //Make sure we have an instance for all of the constraints
                     .filter(|constraint| {
-                        self.has_instance(constraint.class, &instance.typ, &mut vec![])
+                        let types: Vec<TcType> = constraint
+                            .variables
+                            .iter()
+                            .map(|var| {
+                                let index = class_vars.iter().position(|v| v.id == var.id);
+                                instance.types[index.unwrap_or(0)].clone()
+                            })
+                            .collect();
+                        self.has_instances(constraint.class, &types, &mut vec![])
                             .is_err()
                     })
                     .peekable();
@@ -490,15 +673,17 @@ impl<'a> TypeEnvironment<'a> {
                         buffer.push_str(constraint.class.as_ref());
                     }
                     panic!("The type {:?} does not have all necessary super class instances required for {:?}.\n Missing: {:?}",
-                        instance.typ, instance.classname, buffer);
+                        instance.types, instance.classname, buffer);
                 }
             }
             self.instances.push((
                 instance.constraints.clone(),
                 instance.classname.clone(),
-                instance.typ.clone(),
+                instance.types.clone(),
             ));
         }
+        self.typecheck_deriving_instances(module);
+        self.add_default_types(module);
 
         for type_decl in module.type_declarations.iter_mut() {
             match module
@@ -508,6 +693,7 @@ impl<'a> TypeEnvironment<'a> {
             {
                 Some(bind) => {
                     bind.typ = type_decl.typ.clone();
+                    self.signature_locations.insert(bind.name, type_decl.location);
                 }
                 None => panic!(
                     "Error: Type declaration for '{:?}' has no binding",
@@ -522,6 +708,7 @@ impl<'a> TypeEnvironment<'a> {
             };
             self.typecheck_global_bindings(start_var_age, &mut subs, module);
         }
+        self.default_monomorphic_variables(module);
     }
 
     ///Typechecks an expression.
@@ -535,9 +722,304 @@ impl<'a> TypeEnvironment<'a> {
         let mut typ = self.typecheck(expr, &mut subs);
         unify_location(self, &mut subs, &expr.location, &mut typ, &mut expr.typ);
         self.substitute(&mut subs, expr);
+        self.report_holes(&subs);
+        //The expression is not generalized so every constrained variable, including those in its
+        //type, must be defaulted
+        let defaults = self.find_defaults(&subs, &[]);
+        let mut visitor = DefaultVisitor::new(self, &defaults);
+        visitor.visit_expr(expr);
+        let ambiguous = visitor.ambiguous;
+        self.report_ambiguous(ambiguous);
         self.errors.into_result(()).map_err(TypeError)
     }
 
+    ///Sets the types which ambiguous type variables default to while checking `module`
+    fn add_default_types(&mut self, module: &Module<Name>) {
+        self.default_types = module.default_types.clone().map(|mut types| {
+            for typ in types.iter_mut() {
+                self.expand_type_synonyms(&Location::eof(), typ);
+                let num = prelude_name("Num");
+                if self.has_instance(num, typ, &mut vec![]).is_err()
+                    && !is_primitive_instance(num, typ)
+                {
+                    self.errors.insert(TypeErrorInfo {
+                        location: Location::eof(),
+                        lhs: typ.clone(),
+                        rhs: typ.clone(),
+                        error: Error::InvalidDefault(typ.clone()),
+                    });
+                }
+            }
+            types
+        });
+    }
+
+    ///Finds the constrained type variables which do not appear in any of `types` and chooses a
+    ///type for each of them from the default types of the module.
+    ///Following Haskell 2010 a variable is only defaulted if at least one of its classes is
+    ///numeric and all of its classes are defined in the Prelude. The defaulted variables are
+    ///returned as a substitution while variables which could not be defaulted are mapped to
+    ///themselves
+    fn find_defaults(&self, subs: &Substitution, types: &[TcType]) -> Substitution {
+        let mut defaults = Substitution {
+            subs: HashMap::new(),
+        };
+        for (var, classes) in self.constraints.iter() {
+            //The variable of a local binding restricted by the monomorphism restriction may be
+            //bound to itself, in which case it must still be defaulted
+            let bound = subs
+                .subs
+                .get(var)
+                .is_some_and(|typ| !matches!(*typ, Type::Variable(ref v) if v == var));
+            if bound
+                || self.generalized_variables.iter().any(|v| v.id == var.id)
+                || self.monomorphic_variables.iter().any(|v| v.id == var.id)
+                || types.iter().any(|typ| occurs(var, typ))
+            {
+                continue;
+            }
+            let default = self
+                .default_type(classes)
+                .unwrap_or_else(|| Type::Variable(var.clone()));
+            defaults.subs.insert(var.clone(), default);
+        }
+        defaults
+    }
+
+    ///Returns the first of the default types which is an instance of all of `classes`
+    fn default_type(&self, classes: &[Name]) -> Option<TcType> {
+        let defaultable = classes.iter().any(|class| is_numeric_class(*class))
+            && classes.iter().all(|class| class.uid == 0);
+        if !defaultable {
+            return None;
+        }
+        let standard_defaults = [typ::int_type(), typ::double_type()];
+        let candidates = match self.default_types {
+            Some(ref types) => &types[..],
+            None => &standard_defaults[..],
+        };
+        candidates
+            .iter()
+            .find(|candidate| {
+                classes.iter().all(|class| {
+                    self.has_instance(*class, candidate, &mut vec![]).is_ok()
+                        || is_primitive_instance(*class, candidate)
+                })
+            })
+            .cloned()
+    }
+
+    ///Records the types which the variables of bindings restricted by the monomorphism
+    ///restriction have been bound to in the binding group which has just been checked
+    fn resolve_monomorphic_variables(&mut self, subs: &Substitution) {
+        let mut resolved = false;
+        let mut i = 0;
+        while i < self.monomorphic_variables.len() {
+            let var = &self.monomorphic_variables[i];
+            let typ = match subs.subs.get(var) {
+                //The type of a binding without a signature may be bound to itself
+                Some(typ) if !matches!(*typ, Type::Variable(ref v) if v == var) => typ.clone(),
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let var = self.monomorphic_variables.swap_remove(i);
+            //Any constrained variables in the new type are now shared with the binding
+            let constraints = &self.constraints;
+            let monomorphic = &mut self.monomorphic_variables;
+            each_type(
+                &typ,
+                |var| {
+                    if constraints.contains_key(var) && !monomorphic.contains(var) {
+                        monomorphic.push(var.clone());
+                    }
+                },
+                |_| (),
+            );
+            self.monomorphic_types.subs.insert(var, typ);
+            resolved = true;
+        }
+        if resolved {
+            for typ in self.named_types.values_mut() {
+                replace(&mut self.constraints, &mut typ.value, &self.monomorphic_types);
+            }
+        }
+    }
+
+    ///Defaults the variables of the top level bindings restricted by the monomorphism restriction
+    ///which were not determined by the rest of the module, and updates the types in `module`
+    fn default_monomorphic_variables(&mut self, module: &mut Module<Name>) {
+        let mut subs = Substitution {
+            subs: HashMap::new(),
+        };
+        swap(&mut subs, &mut self.monomorphic_types);
+        let variables = ::std::mem::take(&mut self.monomorphic_variables);
+        for var in variables {
+            let default = self
+                .constraints
+                .get(&var)
+                .and_then(|classes| self.default_type(classes))
+                .unwrap_or_else(|| Type::Variable(var.clone()));
+            subs.subs.insert(var, default);
+        }
+        if subs.subs.is_empty() {
+            return;
+        }
+        //Variables may have been bound to the variables of other restricted bindings
+        for _ in 0..subs.subs.len() {
+            let previous = Substitution {
+                subs: subs.subs.clone(),
+            };
+            for typ in subs.subs.values_mut() {
+                replace(&mut self.constraints, typ, &previous);
+            }
+        }
+        for typ in self.named_types.values_mut() {
+            replace(&mut self.constraints, &mut typ.value, &subs);
+        }
+        let mut visitor = DefaultVisitor::new(self, &subs);
+        visitor.visit_module(module);
+        let ambiguous = visitor.ambiguous;
+        self.report_ambiguous(ambiguous);
+        self.constraints.clear();
+        self.constraint_origins.clear();
+    }
+
+    ///Warns that `bind` would have been generalized over `constraints` if it were not for the
+    ///monomorphism restriction
+    fn warn_restricted(
+        &mut self,
+        bind: &Binding<Name>,
+        constraints: &[Constraint<Name>],
+        restricted_vars: &[TypeVariable],
+    ) {
+        let mut printer = TypePrinter::new();
+        let constraints: Vec<_> = constraints
+            .iter()
+            .filter(|constraint| {
+                constraint
+                    .variables
+                    .iter()
+                    .all(|var| restricted_vars.contains(var))
+            })
+            .map(|constraint| printer.print_constraint(constraint))
+            .collect();
+        if constraints.is_empty() {
+            return;
+        }
+        self.warnings.push(
+            Diagnostic::new(
+                Severity::Warning,
+                Span::from(*bind.matches.location()),
+                format!(
+                    "The monomorphism restriction prevents {} from being generalized over ({})",
+                    bind.name.name.as_ref(),
+                    constraints.join(", ")
+                ),
+            )
+            .with_note(
+                "add a type signature or enable NoMonomorphismRestriction to make it polymorphic"
+                    .to_string(),
+            ),
+        );
+    }
+
+    ///Reports an error for each of the variables which could not be defaulted
+    fn report_ambiguous(&mut self, ambiguous: Vec<(TypeVariable, Location)>) {
+        for (var, location) in ambiguous {
+            let classes = self.constraints.get(&var).cloned().unwrap_or_default();
+            let typ = Type::Variable(var.clone());
+            self.errors.insert(TypeErrorInfo {
+                location,
+                lhs: typ.clone(),
+                rhs: typ,
+                error: Error::AmbiguousType(var, classes),
+            });
+        }
+    }
+
+    ///Reports an error for each typed hole with its type, the local bindings which were in scope
+    ///and the globals which could be used in its place
+    fn report_holes(&mut self, subs: &Substitution) {
+        for mut hole in ::std::mem::take(&mut self.holes) {
+            replace(&mut self.constraints, &mut hole.typ, subs);
+            for &mut (_, ref mut typ) in hole.locals.iter_mut() {
+                replace(&mut self.constraints, typ, subs);
+            }
+            let constraints = self.find_constraints(&hole.typ);
+            let fits = self.find_hole_fits(&hole.typ);
+            self.errors.insert(TypeErrorInfo {
+                location: hole.location,
+                lhs: hole.typ.clone(),
+                rhs: hole.typ.clone(),
+                error: Error::Hole(hole.name, qualified(constraints, hole.typ), hole.locals, fits),
+            });
+        }
+    }
+
+    ///Returns the globals whose types can be instantiated to `typ`, sorted by name
+    fn find_hole_fits(&self, typ: &TcType) -> Vec<Name> {
+        //Instance methods can't be referred to and primitives are only meant to be used by the
+        //Prelude
+        let visible = |name: &Name| {
+            let name = name.name.as_ref();
+            !name.starts_with('#') && !name.starts_with("prim")
+        };
+        let mut fits = vec![];
+        for (name, global) in self.named_types.iter() {
+            if visible(name) && self.fits_hole(global, typ) {
+                fits.push(*name);
+            }
+        }
+        for types in self.assemblies.iter() {
+            types.each_global(&mut |name, global| {
+                let mut global = global.clone();
+                quantify(0, &mut global);
+                if visible(name) && self.fits_hole(&global, typ) {
+                    fits.push(*name);
+                }
+            });
+        }
+        fits.sort_by(|l, r| l.name.as_ref().cmp(r.name.as_ref()));
+        fits.dedup();
+        fits
+    }
+
+    ///Returns true if a global of type `global` could be used where `expected` is expected.
+    ///The variables in `expected` are not known so the constraints on them must already be
+    ///required by the hole for the global to fit
+    fn fits_hole(&self, global: &Qualified<TcType, Name>, expected: &TcType) -> bool {
+        let mut mapping = HashMap::new();
+        if !instantiates(&mut mapping, &global.value, expected) {
+            return false;
+        }
+        global.constraints.iter().all(|constraint| {
+            let types: Vec<_> = constraint
+                .variables
+                .iter()
+                .map(|var| {
+                    mapping
+                        .get(var)
+                        .cloned()
+                        .unwrap_or_else(|| Type::Variable(var.clone()))
+                })
+                .collect();
+            match types[..] {
+                [Type::Variable(ref var)] => self.constraints.get(var).is_some_and(|classes| {
+                    classes.iter().any(|class| {
+                        *class == constraint.class
+                            || self.exists_as_super_class(*class, constraint.class)
+                    })
+                }),
+                [ref typ] if is_primitive_instance(constraint.class, typ) => true,
+                _ => self
+                    .has_instances(constraint.class, &types, &mut vec![])
+                    .is_ok(),
+            }
+        })
+    }
+
     pub fn typecheck_module_(&mut self, module: &mut Module<Name>) {
         self.typecheck_module(module).unwrap()
     }
@@ -569,129 +1051,638 @@ impl<'a> TypeEnvironment<'a> {
             },
             |_| (),
         );
+        for &(class, ref types, _) in self.multi_constraints.iter() {
+            let variables: Option<Vec<TypeVariable>> = types
+                .iter()
+                .map(|typ| match *typ {
+                    Type::Variable(ref var) => Some(var.clone()),
+                    _ => None,
+                })
+                .collect();
+            if let Some(variables) = variables {
+                if variables.iter().any(|var| occurs(var, typ)) {
+                    let constraint = Constraint { class, variables };
+                    if !result.contains(&constraint) {
+                        result.push(constraint);
+                    }
+                }
+            }
+        }
         result
     }
-    fn find_data_definition(&self, name: Name) -> Option<&DataDefinition<Name>> {
-        self.data_definitions
+    fn find_newtype(&self, name: Name) -> Option<&Newtype<Name>> {
+        self.newtypes
             .iter()
-            .find(|data| extract_applied_type(&data.typ.value).ctor().name == name)
-            .or_else(|| {
-                self.assemblies
-                    .iter()
-                    .filter_map(|a| a.find_data_type(name))
-                    .next()
-            })
-    }
-
-    fn freshen_qualified_type(
-        &mut self,
-        typ: &mut Qualified<TcType, Name>,
-        mut mapping: HashMap<TypeVariable, TcType>,
-    ) {
-        for constraint in typ.constraints.iter_mut() {
-            let old = constraint.variables[0].clone();
-            let new = match mapping.entry(old.clone()) {
-                Entry::Vacant(entry) => entry.insert(self.new_var_kind(old.kind.clone())),
-                Entry::Occupied(entry) => entry.into_mut(),
-            };
-            constraint.variables[0] = new.var().clone();
-        }
-        let mut subs = Substitution { subs: mapping };
-        freshen_all(self, &mut subs, &mut typ.value);
-    }
-    fn apply_locals(&mut self, subs: &Substitution) {
-        for (_, typ) in self.local_types.iter_mut() {
-            replace(&mut self.constraints, &mut typ.value, subs);
-        }
+            .find(|newtype| extract_applied_type(newtype_type(newtype)).ctor().name == name)
+            .or_else(|| self.assemblies.iter().filter_map(|a| a.find_newtype(name)).next())
     }
 
-    ///Walks through an expression and applies the substitution on each of its types
-    fn substitute(&mut self, subs: &Substitution, expr: &mut TypedExpr<Name>) {
-        struct SubVisitor<'a: 'b, 'b, 'c> {
-            env: &'b mut TypeEnvironment<'a>,
-            subs: &'c Substitution,
+    ///Checks the standalone deriving declarations and the deriving clauses of the newtypes in
+    ///`module`. Each derived instance is stored in `module.deriving_instances` with the type and
+    ///constraints which its generated functions are defined for
+    fn typecheck_deriving_instances(&mut self, module: &mut Module<Name>) {
+        let mut instances = vec![];
+        for newtype in module.newtypes.iter() {
+            let typ = newtype_type(newtype);
+            for &class in newtype.deriving.iter() {
+                let name = extract_applied_type(typ).ctor().name;
+                match self.derived_instance(module, class, name) {
+                    Ok((constraints, instance_type)) => instances.push(DerivingInstance {
+                        constraints,
+                        types: vec![instance_type],
+                        classname: class,
+                        location: newtype.location,
+                    }),
+                    Err(reason) => self.errors.insert(TypeErrorInfo {
+                        location: newtype.location,
+                        lhs: typ.clone(),
+                        rhs: typ.clone(),
+                        error: Error::CannotDerive(class, reason),
+                    }),
+                }
+            }
         }
-        impl<'a, 'b, 'c> MutVisitor<Name> for SubVisitor<'a, 'b, 'c> {
-            fn visit_expr(&mut self, expr: &mut TypedExpr<Name>) {
-                replace(&mut self.env.constraints, &mut expr.typ, self.subs);
-                walk_expr_mut(self, expr);
+        for instance in module.deriving_instances.iter() {
+            let class = instance.classname;
+            let typ = &instance.types[0];
+            let result = match *extract_applied_type(typ) {
+                _ if instance.types.len() != 1 => {
+                    Err("only classes with a single parameter can be derived".to_string())
+                }
+                Type::Constructor(ref ctor) => self.derived_instance(module, class, ctor.name),
+                _ => Err("it is not a data type or newtype".to_string()),
+            };
+            let result = result.and_then(|(constraints, instance_type)| {
+                check_deriving_context(&instance.constraints, typ, &constraints, &instance_type)?;
+                Ok(DerivingInstance {
+                    constraints,
+                    types: vec![instance_type],
+                    classname: class,
+                    location: instance.location,
+                })
+            });
+            match result {
+                Ok(derived) => instances.push(derived),
+                Err(reason) => self.errors.insert(TypeErrorInfo {
+                    location: instance.location,
+                    lhs: typ.clone(),
+                    rhs: typ.clone(),
+                    error: Error::CannotDerive(class, reason),
+                }),
             }
         }
-        SubVisitor { env: self, subs }.visit_expr(expr);
+        for instance in instances.iter() {
+            self.instances.push((
+                instance.constraints.clone(),
+                instance.classname,
+                instance.types.clone(),
+            ));
+        }
+        module.deriving_instances = instances;
     }
 
-    ///Returns whether the type 'searched_type' has an instance for 'class'
-    ///If no instance was found, return the instance which was missing
-    fn has_instance(
+    ///Returns the constraints and the type of the instance of `class` which is derived for the
+    ///type constructor `name`, or the reason why it can't be derived
+    fn derived_instance(
         &self,
+        module: &Module<Name>,
         class: Name,
-        searched_type: &TcType,
-        new_constraints: &mut Vec<Constraint<Name>>,
-    ) -> Result<(), InternedStr> {
-        match extract_applied_type(searched_type) {
-            &Type::Constructor(ref ctor) => match self.find_data_definition(ctor.name) {
-                Some(data_type) => {
-                    if data_type.deriving.iter().any(|name| *name == class) {
-                        return self.check_instance_constraints(
-                            &[],
-                            &data_type.typ.value,
-                            searched_type,
-                            new_constraints,
-                        );
-                    }
-                }
-                None => (),
-            },
-            _ => (),
+        name: Name,
+    ) -> Result<(Vec<Constraint<Name>>, TcType), ::std::string::String> {
+        if let Some(data) = self.find_data_definition(name) {
+            check_deriving(class, data)?;
+            return Ok((derived_constraints(class, data), derived_instance_type(class, data)));
         }
-        for &(ref constraints, ref name, ref typ) in self.instances.iter() {
-            if class == *name {
-                let result = self.check_instance_constraints(
-                    constraints,
-                    typ,
-                    searched_type,
-                    new_constraints,
-                );
-                if result.is_ok() {
-                    return result;
-                }
+        let newtype = self
+            .find_newtype(name)
+            .ok_or_else(|| "it is not a data type or newtype".to_string())?;
+        let data = newtype_data_definition(newtype);
+        if !is_newtype_deriving(class) {
+            check_deriving(class, &data)?;
+            return Ok((derived_constraints(class, &data), derived_instance_type(class, &data)));
+        }
+        //The instance of the wrapped type is reused
+        let class_vars = module
+            .classes
+            .iter()
+            .find(|c| c.name == class)
+            .map(|c| c.variables.as_ref())
+            .or_else(|| {
+                self.assemblies
+                    .iter()
+                    .filter_map(|a| a.find_class(class))
+                    .next()
+                    .map(|(_, variables, _)| variables)
+            })
+            .ok_or_else(|| format!("{} is not a class", class))?;
+        match *class_vars {
+            [ref var] if var.kind == Kind::Star => (),
+            _ => {
+                return Err(format!(
+                    "only classes with a single parameter of kind {} can be derived for newtypes",
+                    Kind::Star
+                ))
             }
         }
-
-        for types in self.assemblies.iter() {
-            match types.find_instance(class, searched_type) {
-                Some((constraints, unspecialized_type)) => {
-                    return self.check_instance_constraints(
-                        constraints,
-                        unspecialized_type,
-                        searched_type,
-                        new_constraints,
-                    );
-                }
-                None => (),
+        let wrapped = newtype_wrapped_type(newtype);
+        let needs_constraints = match *wrapped {
+            Type::Variable(_) => true,
+            _ => {
+                let mut constraints = vec![];
+                self.has_instance(class, wrapped, &mut constraints)
+                    .map_err(|_| format!("{} does not have an instance of {}", wrapped, class))?;
+                !constraints.is_empty()
             }
+        };
+        if !needs_constraints {
+            return Ok((vec![], data.typ.value.clone()));
         }
-        Err(class.name)
+        //Dictionaries are passed for every type parameter, as for other derived instances
+        let mut parameters = &data.typ.value;
+        while let Type::Application(ref f, ref param) = *parameters {
+            if *param.kind() != Kind::Star {
+                return Err(format!("all of its type parameters must have kind {}", Kind::Star));
+            }
+            parameters = f;
+        }
+        Ok((derived_constraints(class, &data), data.typ.value.clone()))
     }
 
-    fn find_class_constraints(&self, class: Name) -> Option<&[Constraint<Name>]> {
-        self.classes
+    fn find_data_definition(&self, name: Name) -> Option<&DataDefinition<Name>> {
+        self.data_definitions
             .iter()
-            .find(|&&(_, ref name)| *name == class)
-            .map(|x| x.0.as_ref())
+            .find(|data| extract_applied_type(&data.typ.value).ctor().name == name)
             .or_else(|| {
                 self.assemblies
                     .iter()
-                    .filter_map(|types| types.find_class(class)) //Find the class
-                    .next() //next will get us the first class (but should only be one)
-                    .map(|(constraints, _, _)| constraints)
+                    .filter_map(|a| a.find_data_type(name))
+                    .next()
             })
     }
 
-    ///Checks whether 'actual_type' fulfills all the constraints that the instance has.
-    fn check_instance_constraints(
-        &self,
-        constraints: &[Constraint<Name>],
+    ///Returns the field names of the constructor `name`
+    fn constructor_fields(&self, name: Name) -> Vec<Name> {
+        self.find_fresh(&name)
+            .and_then(|typ| {
+                let data_name = extract_applied_type(&get_returntype(&typ.value))
+                    .ctor()
+                    .name;
+                self.find_data_definition(data_name)
+            })
+            .and_then(|data| data.constructors.iter().find(|ctor| ctor.name == name))
+            .map(|ctor| ctor.fields.clone())
+            .unwrap_or_default()
+    }
+
+    ///Finds the data type which declares the field `field`
+    fn find_field_owner(&self, field: Name) -> Option<&DataDefinition<Name>> {
+        self.data_definitions
+            .iter()
+            .find(|data| {
+                data.constructors
+                    .iter()
+                    .any(|ctor| ctor.fields.contains(&field))
+            })
+            .or_else(|| {
+                //Fields from other modules are found through the type of their selector
+                let selector = self.find_fresh(&field)?;
+                let (record_type, _) = try_get_function(&selector.value)?;
+                match *extract_applied_type(record_type) {
+                    Type::Constructor(ref op) => self.find_data_definition(op.name),
+                    _ => None,
+                }
+            })
+    }
+
+    fn find_type_synonym(&self, name: Name) -> Option<&TypeSynonym<Name>> {
+        self.type_synonyms
+            .iter()
+            .find(|synonym| synonym.name == name)
+            .or_else(|| {
+                self.assemblies
+                    .iter()
+                    .filter_map(|a| a.find_type_synonym(name))
+                    .next()
+            })
+    }
+
+    ///Adds a type synonym to the environment, giving each of its parameters the kind
+    ///it is used with in the definition
+    fn add_type_synonym(&mut self, synonym: &mut TypeSynonym<Name>) {
+        for parameter in synonym.parameters.iter_mut() {
+            match find_kind(parameter, None, &synonym.typ) {
+                Ok(Some(kind)) => parameter.kind = kind,
+                Ok(None) => (),
+                Err(_) => self.errors.insert(TypeErrorInfo {
+                    location: synonym.location,
+                    lhs: synonym.typ.clone(),
+                    rhs: synonym.typ.clone(),
+                    error: Error::KindMismatch(
+                        Type::Variable(parameter.clone()),
+                        parameter.kind.clone(),
+                        None,
+                    ),
+                }),
+            }
+        }
+        self.type_synonyms.push(synonym.clone());
+    }
+
+    ///Returns the kind of the type constructor `name` if it is a known data type or newtype
+    fn find_type_kind(&self, name: Name) -> Option<Kind> {
+        self.type_kinds.get(&name).cloned().or_else(|| {
+            self.assemblies
+                .iter()
+                .filter_map(|a| a.find_type_kind(name))
+                .next()
+        })
+    }
+
+    ///Infers the kinds of the data types, newtypes and classes declared in `module` and updates
+    ///the kinds stored in their types.
+    ///Data types and newtypes are inferred together since they may refer to each other.
+    fn infer_kinds(&mut self, module: &mut Module<Name>) {
+        let mut inference = KindInference::new();
+        let mut data_variables = vec![];
+        for data in module.data_definitions.iter_mut() {
+            for constructor in data.constructors.iter_mut() {
+                self.expand_type_synonyms(&data.location, &mut constructor.typ.value);
+            }
+            let mut variables = HashMap::new();
+            let kind = inference.declare(&mut variables, &data.typ.value);
+            let name = extract_applied_type(&data.typ.value).ctor().name;
+            inference.constructors.insert(name, kind);
+            data_variables.push(variables);
+        }
+        let mut newtype_variables = vec![];
+        for newtype in module.newtypes.iter_mut() {
+            self.expand_type_synonyms(&newtype.location, &mut newtype.constructor_type.value);
+            let mut variables = HashMap::new();
+            let typ = get_returntype(&newtype.constructor_type.value);
+            let kind = inference.declare(&mut variables, &typ);
+            inference.constructors.insert(extract_applied_type(&typ).ctor().name, kind);
+            newtype_variables.push(variables);
+        }
+
+        //Every constructor is a function returning the declared type so its kind must be `*`
+        for (data, variables) in module.data_definitions.iter().zip(data_variables.iter_mut()) {
+            for constructor in data.constructors.iter() {
+                self.check_kind(&data.location, &mut inference, variables, &constructor.typ.value);
+            }
+        }
+        for (newtype, variables) in module.newtypes.iter().zip(newtype_variables.iter_mut()) {
+            let typ = &newtype.constructor_type.value;
+            self.check_kind(&newtype.location, &mut inference, variables, typ);
+        }
+
+        for (data, variables) in module.data_definitions.iter_mut().zip(data_variables.iter()) {
+            inference.update_qualified(self, variables, &mut data.typ);
+            for constructor in data.constructors.iter_mut() {
+                inference.update_qualified(self, variables, &mut constructor.typ);
+            }
+        }
+        for (newtype, variables) in module.newtypes.iter_mut().zip(newtype_variables.iter()) {
+            inference.update_qualified(self, variables, &mut newtype.constructor_type);
+            let typ = get_returntype(&newtype.constructor_type.value);
+            let declared = extract_applied_type(&typ).ctor().clone();
+            let constructor_kind = |op: &TypeConstructor<InternedStr>| {
+                if op.name == declared.name.name {
+                    Some(declared.kind.clone())
+                } else {
+                    None
+                }
+            };
+            inference.update(variables, &constructor_kind, &mut newtype.typ.value);
+        }
+        for (name, kind) in inference.constructors.iter() {
+            self.type_kinds.insert(*name, inference.resolve(kind));
+        }
+
+        //The kinds of the data types are now known so each class can be inferred on its own
+        for class in module.classes.iter_mut() {
+            let mut inference = KindInference::new();
+            let class_kinds: Vec<_> = class
+                .variables
+                .iter()
+                .map(|_| inference.new_variable())
+                .collect();
+            let mut decl_variables = vec![];
+            for type_decl in class.declarations.iter_mut() {
+                self.expand_type_synonyms(&type_decl.location, &mut type_decl.typ.value);
+                let mut variables = HashMap::new();
+                for (var, kind) in class.variables.iter().zip(class_kinds.iter()) {
+                    variables.insert(var.id, kind.clone());
+                }
+                let typ = &type_decl.typ.value;
+                self.check_kind(&type_decl.location, &mut inference, &mut variables, typ);
+                decl_variables.push(variables);
+            }
+            for (var, kind) in class.variables.iter_mut().zip(class_kinds.iter()) {
+                var.kind = inference.resolve(kind);
+            }
+            for (type_decl, variables) in class.declarations.iter_mut().zip(decl_variables.iter()) {
+                inference.update_qualified(self, variables, &mut type_decl.typ);
+            }
+        }
+    }
+
+    ///Checks that `typ` has kind `*`, reporting an error otherwise
+    fn check_kind(
+        &mut self,
+        location: &Location,
+        inference: &mut KindInference,
+        variables: &mut HashMap<InternedStr, InferredKind>,
+        typ: &TcType,
+    ) {
+        if let Err(error) = inference.check(self, variables, typ, &InferredKind::Star) {
+            self.errors.insert(TypeErrorInfo {
+                location: *location,
+                lhs: typ.clone(),
+                rhs: typ.clone(),
+                error,
+            });
+        }
+    }
+
+    ///Checks that the type of an instance has the same kind as the class variable and updates
+    ///the kinds in the type. Returns false if the kinds did not match.
+    fn check_instance_kind(&mut self, location: &Location, kind: &Kind, typ: &mut TcType) -> bool {
+        let mut inference = KindInference::new();
+        let mut variables = HashMap::new();
+        let kind = InferredKind::from_kind(kind);
+        match inference.check(self, &mut variables, typ, &kind) {
+            Ok(()) => {
+                let constructor_kind = |op: &TypeConstructor<Name>| self.find_type_kind(op.name);
+                inference.update(&variables, &constructor_kind, typ);
+                true
+            }
+            Err(error) => {
+                self.errors.insert(TypeErrorInfo {
+                    location: *location,
+                    lhs: typ.clone(),
+                    rhs: typ.clone(),
+                    error,
+                });
+                false
+            }
+        }
+    }
+
+    ///Infers the kinds of the type variables in a type signature and checks that the
+    ///signature has kind `*`
+    fn infer_signature_kinds(&mut self, location: &Location, typ: &mut Qualified<TcType, Name>) {
+        let mut inference = KindInference::new();
+        let mut variables = HashMap::new();
+        match inference.check(self, &mut variables, &typ.value, &InferredKind::Star) {
+            Ok(()) => inference.update_qualified(self, &variables, typ),
+            Err(error) => self.errors.insert(TypeErrorInfo {
+                location: location.clone(),
+                lhs: typ.value.clone(),
+                rhs: typ.value.clone(),
+                error,
+            }),
+        }
+    }
+
+    ///Replaces all uses of type synonyms in `typ` with their definitions
+    fn expand_type_synonyms(&mut self, location: &Location, typ: &mut TcType) {
+        match self.expand_synonyms(&mut vec![], typ) {
+            Ok(expanded) => *typ = expanded,
+            Err(error) => self.errors.insert(TypeErrorInfo {
+                location: location.clone(),
+                lhs: typ.clone(),
+                rhs: typ.clone(),
+                error,
+            }),
+        }
+    }
+
+    fn expand_synonyms(&self, expanding: &mut Vec<Name>, typ: &TcType) -> Result<TcType, Error> {
+        if let Type::Forall(ref bound, ref body) = *typ {
+            let body = self.expand_synonyms(expanding, body)?;
+            return Ok(Type::Forall(bound.clone(), body.into()));
+        }
+        let mut arguments = vec![];
+        let mut head = typ;
+        while let Type::Application(ref lhs, ref rhs) = *head {
+            arguments.push(self.expand_synonyms(expanding, rhs)?);
+            head = lhs;
+        }
+        arguments.reverse();
+        let synonym = match *head {
+            Type::Constructor(ref op) => self.find_type_synonym(op.name),
+            _ => None,
+        };
+        let synonym = match synonym {
+            Some(synonym) => synonym,
+            None => {
+                return Ok(arguments
+                    .into_iter()
+                    .fold(head.clone(), |f, arg| Type::Application(f.into(), arg.into())))
+            }
+        };
+        if expanding.contains(&synonym.name) {
+            return Err(Error::RecursiveSynonym(synonym.name));
+        }
+        if arguments.len() < synonym.parameters.len() {
+            return Err(Error::PartiallyAppliedSynonym(
+                synonym.name,
+                synonym.parameters.len(),
+            ));
+        }
+        let rest = arguments.split_off(synonym.parameters.len());
+        for (parameter, argument) in synonym.parameters.iter().zip(arguments.iter_mut()) {
+            self.check_synonym_argument(parameter, argument)?;
+        }
+        let body = substitute_parameters(&synonym.parameters, &arguments, &synonym.typ);
+        expanding.push(synonym.name);
+        let expanded = self.expand_synonyms(expanding, &body);
+        expanding.pop();
+        Ok(apply_type_arguments(expanded?, rest))
+    }
+
+    ///Checks that `argument` has the kind of `parameter`.
+    ///Unapplied types get no kind information from the parser so data types are given the kind of
+    ///their definition and type variables the kind of the parameter instead.
+    fn check_synonym_argument(
+        &self,
+        parameter: &TypeVariable,
+        argument: &mut TcType,
+    ) -> Result<(), Error> {
+        let mut applied = 0;
+        let mut head = &mut *argument;
+        while let Type::Application(ref mut lhs, _) = *head {
+            applied += 1;
+            head = &mut **lhs;
+        }
+        let declared = match *head {
+            Type::Constructor(ref op) => self
+                .find_data_definition(op.name)
+                .map(|data| extract_applied_type(&data.typ.value).kind().clone()),
+            _ => None,
+        };
+        let head_kind = match declared {
+            Some(kind) => kind,
+            None if applied == 0 && is_variable(head) => parameter.kind.clone(),
+            None => head.kind().clone(),
+        };
+        *head.mut_kind() = head_kind.clone();
+        let mut kind = &head_kind;
+        for _ in 0..applied {
+            kind = match *kind {
+                Kind::Function(_, ref result) => result,
+                Kind::Star => {
+                    return Err(Error::KindMismatch(argument.clone(), parameter.kind.clone(), None))
+                }
+            };
+        }
+        if *kind != parameter.kind {
+            return Err(Error::KindMismatch(
+                argument.clone(),
+                parameter.kind.clone(),
+                Some(kind.clone()),
+            ));
+        }
+        Ok(())
+    }
+
+    fn freshen_qualified_type(
+        &mut self,
+        typ: &mut Qualified<TcType, Name>,
+        mut mapping: HashMap<TypeVariable, TcType>,
+    ) {
+        for constraint in typ.constraints.iter_mut() {
+            for var in constraint.variables.iter_mut() {
+                let new = match mapping.entry(var.clone()) {
+                    Entry::Vacant(entry) => entry.insert(self.new_var_kind(var.kind.clone())),
+                    Entry::Occupied(entry) => entry.into_mut(),
+                };
+                *var = new.var().clone();
+            }
+        }
+        let mut subs = Substitution { subs: mapping };
+        freshen_all(self, &mut subs, &mut typ.value);
+    }
+    fn apply_locals(&mut self, subs: &Substitution) {
+        for (_, typ) in self.local_types.iter_mut() {
+            replace(&mut self.constraints, &mut typ.value, subs);
+        }
+    }
+
+    ///Walks through an expression and applies the substitution on each of its types
+    fn substitute(&mut self, subs: &Substitution, expr: &mut TypedExpr<Name>) {
+        SubVisitor { env: self, subs }.visit_expr(expr);
+    }
+
+    ///Returns whether the type 'searched_type' has an instance for 'class'
+    ///If no instance was found, return the instance which was missing
+    fn has_instance(
+        &self,
+        class: Name,
+        searched_type: &TcType,
+        new_constraints: &mut Vec<Constraint<Name>>,
+    ) -> Result<(), InternedStr> {
+        match extract_applied_type(searched_type) {
+            &Type::Constructor(ref ctor) => match self.find_data_definition(ctor.name) {
+                Some(data_type) => {
+                    if data_type.deriving.iter().any(|name| *name == class) {
+                        return self.check_instance_constraints(
+                            &derived_constraints(class, data_type),
+                            &derived_instance_type(class, data_type),
+                            searched_type,
+                            new_constraints,
+                        );
+                    }
+                }
+                None => (),
+            },
+            _ => (),
+        }
+        for &(ref constraints, ref name, ref types) in self.instances.iter() {
+            if class == *name && types.len() == 1 {
+                let result = self.check_instance_constraints(
+                    constraints,
+                    &types[0],
+                    searched_type,
+                    new_constraints,
+                );
+                if result.is_ok() {
+                    return result;
+                }
+            }
+        }
+
+        for types in self.assemblies.iter() {
+            match types.find_instance(class, ::std::slice::from_ref(searched_type)) {
+                Some((constraints, unspecialized_types)) => {
+                    return self.check_instance_constraints(
+                        constraints,
+                        &unspecialized_types[0],
+                        searched_type,
+                        new_constraints,
+                    );
+                }
+                None => (),
+            }
+        }
+        Err(class.name)
+    }
+
+    ///Returns whether `searched_types` has an instance for 'class', where `class` has one
+    ///parameter for each of the types
+    fn has_instances(
+        &self,
+        class: Name,
+        searched_types: &[TcType],
+        new_constraints: &mut Vec<Constraint<Name>>,
+    ) -> Result<(), InternedStr> {
+        if let [ref searched_type] = *searched_types {
+            return self.has_instance(class, searched_type, new_constraints);
+        }
+        let mut check = |constraints: &[Constraint<Name>], types: &[TcType]| {
+            types
+                .iter()
+                .zip(searched_types.iter())
+                .try_for_each(|(typ, searched_type)| {
+                    self.check_instance_constraints(constraints, typ, searched_type, new_constraints)
+                })
+        };
+        for &(ref constraints, ref name, ref types) in self.instances.iter() {
+            if class == *name && types.len() == searched_types.len() {
+                let result = check(constraints, types);
+                if result.is_ok() {
+                    return result;
+                }
+            }
+        }
+        for types in self.assemblies.iter() {
+            if let Some((constraints, unspecialized_types)) =
+                types.find_instance(class, searched_types)
+            {
+                return check(constraints, unspecialized_types);
+            }
+        }
+        Err(class.name)
+    }
+
+    fn find_class_constraints(&self, class: Name) -> Option<&[Constraint<Name>]> {
+        self.classes
+            .iter()
+            .find(|&&(_, ref name)| *name == class)
+            .map(|x| x.0.as_ref())
+            .or_else(|| {
+                self.assemblies
+                    .iter()
+                    .filter_map(|types| types.find_class(class)) //Find the class
+                    .next() //next will get us the first class (but should only be one)
+                    .map(|(constraints, _, _)| constraints)
+            })
+    }
+
+    ///Checks whether 'actual_type' fulfills all the constraints that the instance has.
+    fn check_instance_constraints(
+        &self,
+        constraints: &[Constraint<Name>],
         instance_type: &TcType,
         actual_type: &TcType,
         new_constraints: &mut Vec<Constraint<Name>>,
@@ -752,6 +1743,41 @@ impl<'a> TypeEnvironment<'a> {
         self.new_var_kind(Kind::Star)
     }
 
+    ///Instantiates the type of the identifier `name`.
+    ///If it is not defined an error is reported and a new variable is returned in its place
+    fn fresh_identifier(&mut self, name: &Name, location: &Location) -> TcType {
+        match self.fresh(name, location) {
+            Some(typ) => {
+                let mut variables = vec![];
+                each_type(&typ, |var| variables.push(var.clone()), |_| ());
+                for var in variables {
+                    let classes = self.constraints.get(&var).cloned().unwrap_or_default();
+                    for class in classes {
+                        let origin = ConstraintOrigin {
+                            description: format!("a use of `{}`", name.name.as_ref()),
+                            location: *location,
+                        };
+                        self.insert_constraint_origin(&var, class, origin);
+                    }
+                }
+                typ
+            }
+            None => self.undefined_identifier(name, location),
+        }
+    }
+
+    ///Reports that `name` is not defined and returns a new variable as its type
+    fn undefined_identifier(&mut self, name: &Name, location: &Location) -> TcType {
+        let typ = self.new_var();
+        self.errors.insert(TypeErrorInfo {
+            location: *location,
+            lhs: typ.clone(),
+            rhs: typ.clone(),
+            error: Error::UndefinedIdentifier(*name),
+        });
+        typ
+    }
+
     ///Typechecks a Match
     fn typecheck_match(&mut self, matches: &mut Match<Name>, subs: &mut Substitution) -> TcType {
         match *matches {
@@ -763,6 +1789,8 @@ impl<'a> TypeEnvironment<'a> {
             Match::Guards(ref mut gs) => {
                 let mut typ = None;
                 for guard in gs.iter_mut() {
+                    //The qualifiers bind the variables used by the expression
+                    self.typecheck_qualifiers(subs, &mut guard.qualifiers, false);
                     let mut typ2 = self.typecheck(&mut guard.expression, subs);
                     unify_location(
                         self,
@@ -772,26 +1800,61 @@ impl<'a> TypeEnvironment<'a> {
                         &mut guard.expression.typ,
                     );
                     if let Some(mut typ) = typ {
-                        unify_location(self, subs, &guard.expression.location, &mut typ, &mut typ2)
+                        unify_expected_first(
+                            self,
+                            subs,
+                            &guard.expression.location,
+                            &mut typ,
+                            &mut typ2,
+                        )
                     };
                     typ = Some(typ2);
-                    let mut predicate = self.typecheck(&mut guard.predicate, subs);
-                    unify_location(
-                        self,
-                        subs,
-                        &guard.predicate.location,
-                        &mut predicate,
-                        &mut typ::bool_type(),
-                    );
-                    unify_location(
-                        self,
+                }
+                typ.unwrap()
+            }
+        }
+    }
+
+    ///Typechecks the qualifiers of a list comprehension or guard.
+    ///A generator in a list comprehension matches the elements of a list while a pattern guard
+    ///matches the value of its expression directly
+    fn typecheck_qualifiers(
+        &mut self,
+        subs: &mut Substitution,
+        qualifiers: &mut [Qualifier<Name>],
+        is_list: bool,
+    ) {
+        for qualifier in qualifiers.iter_mut() {
+            match *qualifier {
+                Qualifier::Generator(ref pattern, ref mut e) => {
+                    let mut typ = self.typecheck(e, subs);
+                    let mut element_type = if is_list {
+                        //The element is taken from the list type which was built here as `typ`
+                        //may not be a list if the generator has the wrong type
+                        let element_type = self.new_var();
+                        let mut list = typ::list_type(element_type.clone());
+                        unify_location(self, subs, &e.location, &mut typ, &mut list);
+                        element_type
+                    } else {
+                        typ
+                    };
+                    replace(&mut self.constraints, &mut element_type, subs);
+                    self.typecheck_pattern(
+                        &pattern.location,
                         subs,
-                        &guard.predicate.location,
-                        &mut predicate,
-                        &mut guard.predicate.typ,
+                        &pattern.node,
+                        &mut element_type,
                     );
                 }
-                typ.unwrap()
+                Qualifier::Guard(ref mut e) => {
+                    let mut typ = self.typecheck(e, subs);
+                    unify_location(self, subs, &e.location, &mut typ, &mut typ::bool_type());
+                    unify_location(self, subs, &e.location, &mut typ, &mut e.typ);
+                }
+                Qualifier::Let(ref mut bindings) => {
+                    self.typecheck_local_bindings(subs, &mut BindingsWrapper { value: bindings });
+                    self.apply_locals(subs);
+                }
             }
         }
     }
@@ -806,16 +1869,26 @@ impl<'a> TypeEnvironment<'a> {
             Literal(ref lit) => {
                 match *lit {
                     Integral(_) => {
-                        self.constraints
-                            .insert(expr.typ.var().clone(), vec![prelude_name("Num")]);
+                        let var = expr.typ.var().clone();
+                        let origin = ConstraintOrigin {
+                            description: format!("the literal {}", lit),
+                            location: expr.location,
+                        };
+                        self.insert_constraint(&var, prelude_name("Num"));
+                        self.insert_constraint_origin(&var, prelude_name("Num"), origin);
                         match expr.typ {
                             Type::Variable(ref mut v) => v.kind = Kind::Star.clone(),
                             _ => (),
                         }
                     }
                     Fractional(_) => {
-                        self.constraints
-                            .insert(expr.typ.var().clone(), vec![prelude_name("Fractional")]);
+                        let var = expr.typ.var().clone();
+                        let origin = ConstraintOrigin {
+                            description: format!("the literal {}", lit),
+                            location: expr.location,
+                        };
+                        self.insert_constraint(&var, prelude_name("Fractional"));
+                        self.insert_constraint_origin(&var, prelude_name("Fractional"), origin);
                         match expr.typ {
                             Type::Variable(ref mut v) => v.kind = Kind::Star.clone(),
                             _ => (),
@@ -830,23 +1903,18 @@ impl<'a> TypeEnvironment<'a> {
                 }
                 expr.typ.clone()
             }
-            Identifier(ref name) => match self.fresh(name) {
-                Some(t) => {
-                    debug!("{:?} as {:?}", name, t);
-                    expr.typ = t.clone();
-                    t
-                }
-                None => panic!("Undefined identifier '{:?}' at {:?}", *name, expr.location),
-            },
+            Identifier(ref name) => {
+                let t = self.fresh_identifier(name, &expr.location);
+                debug!("{:?} as {:?}", name, t);
+                expr.typ = t.clone();
+                t
+            }
             Apply(ref mut func, ref mut arg) => {
                 let func_type = self.typecheck(func, subs);
                 self.typecheck_apply(&expr.location, subs, func_type, arg)
             }
             OpApply(ref mut lhs, ref op, ref mut rhs) => {
-                let op_type = match self.fresh(op) {
-                    Some(typ) => typ,
-                    None => panic!("Undefined identifier '{:?}' at {:?}", *op, expr.location),
-                };
+                let op_type = self.fresh_identifier(op, &expr.location);
                 let first = self.typecheck_apply(&expr.location, subs, op_type, lhs);
                 self.typecheck_apply(&expr.location, subs, first, rhs)
             }
@@ -895,7 +1963,13 @@ impl<'a> TypeEnvironment<'a> {
                         None => (),
                     }
                     let mut alt_type = self.typecheck_match(&mut alt.matches, subs);
-                    unify_location(self, subs, &alt.pattern.location, &mut alt0_, &mut alt_type);
+                    unify_expected_first(
+                        self,
+                        subs,
+                        &alt.pattern.location,
+                        &mut alt0_,
+                        &mut alt_type,
+                    );
                 }
                 alt0_
             }
@@ -904,7 +1978,7 @@ impl<'a> TypeEnvironment<'a> {
                 unify_location(self, subs, &expr.location, &mut p, &mut typ::bool_type());
                 let mut t = self.typecheck(if_true, subs);
                 let mut f = self.typecheck(if_false, subs);
-                unify_location(self, subs, &expr.location, &mut t, &mut f);
+                unify_expected_first(self, subs, &if_false.location, &mut t, &mut f);
                 t
             }
             Do(ref mut bindings, ref mut last_expr) => {
@@ -954,6 +2028,8 @@ impl<'a> TypeEnvironment<'a> {
             }
             TypeSig(ref mut expr, ref mut qualified_type) => {
                 let mut typ = self.typecheck(expr, subs);
+                self.expand_type_synonyms(&expr.location, &mut qualified_type.value);
+                self.infer_signature_kinds(&expr.location, qualified_type);
                 self.freshen_qualified_type(qualified_type, HashMap::new());
                 match_or_fail(
                     self,
@@ -965,10 +2041,183 @@ impl<'a> TypeEnvironment<'a> {
                 typ
             }
             Paren(ref mut expr) => self.typecheck(expr, subs),
-        };
-        debug!("{:?}\nas\n{:?}", expr, x);
-        expr.typ = x.clone();
-        x
+            ListComprehension(ref mut body, ref mut qualifiers) => {
+                self.typecheck_qualifiers(subs, qualifiers, true);
+                let body_type = self.typecheck(body, subs);
+                typ::list_type(body_type)
+            }
+            ArithmeticSequence(ref mut from, ref mut then, ref mut to) => {
+                let mut element_type = self.new_var();
+                let var = element_type.var().clone();
+                let origin = ConstraintOrigin {
+                    description: "an arithmetic sequence".to_string(),
+                    location: expr.location,
+                };
+                self.insert_constraint(&var, prelude_name("Enum"));
+                self.insert_constraint_origin(&var, prelude_name("Enum"), origin);
+                let mut from_type = self.typecheck(from, subs);
+                unify_location(self, subs, &from.location, &mut from_type, &mut element_type);
+                for e in then.iter_mut().chain(to.iter_mut()) {
+                    let mut typ = self.typecheck(e, subs);
+                    unify_location(self, subs, &e.location, &mut typ, &mut element_type);
+                }
+                typ::list_type(element_type)
+            }
+            Record(ref name, ref mut fields) => {
+                let mut t = match self.fresh(name, &expr.location) {
+                    Some(t) => t,
+                    None => {
+                        for &mut (_, ref mut value) in fields.iter_mut() {
+                            self.typecheck(value, subs);
+                        }
+                        return self.undefined_identifier(name, &expr.location);
+                    }
+                };
+                let ctor_fields = self.constructor_fields(*name);
+                for &mut (ref field, ref mut value) in fields.iter_mut() {
+                    let mut value_type = self.typecheck(value, subs);
+                    let index = match ctor_fields.iter().position(|f| f == field) {
+                        Some(index) => index,
+                        None => {
+                            self.errors.insert(TypeErrorInfo {
+                                location: expr.location,
+                                lhs: value_type.clone(),
+                                rhs: value_type,
+                                error: Error::MissingField(*name, *field),
+                            });
+                            continue;
+                        }
+                    };
+                    replace(&mut self.constraints, &mut t, subs);
+                    unify_location(
+                        self,
+                        subs,
+                        &value.location,
+                        &mut value_type,
+                        argument_type(&mut t, index),
+                    );
+                }
+                replace(&mut self.constraints, &mut t, subs);
+                get_returntype(&t)
+            }
+            RecordUpdate(ref mut record, ref mut fields) => {
+                let mut typ = self.typecheck(record, subs);
+                let data = match fields.first() {
+                    Some(&(ref field, _)) => match self.find_field_owner(*field).cloned() {
+                        Some(data) => data,
+                        None => {
+                            self.errors.insert(TypeErrorInfo {
+                                location: expr.location,
+                                lhs: typ.clone(),
+                                rhs: typ.clone(),
+                                error: Error::UndefinedField(*field),
+                            });
+                            return typ;
+                        }
+                    },
+                    None => return typ,
+                };
+                if !data.constructors.iter().any(|ctor| {
+                    fields.iter().all(|&(ref field, _)| ctor.fields.contains(field))
+                }) {
+                    self.errors.insert(TypeErrorInfo {
+                        location: expr.location,
+                        lhs: data.typ.value.clone(),
+                        rhs: data.typ.value.clone(),
+                        error: Error::NoConstructorWithFields(
+                            fields.iter().map(|&(field, _)| field).collect(),
+                        ),
+                    });
+                    return typ;
+                }
+                //The data type and its constructors share type variables so they need to be
+                //instantiated with the same substitution
+                let mut fresh_subs = Substitution {
+                    subs: HashMap::new(),
+                };
+                let mut data_type = data.typ.value.clone();
+                freshen_all(self, &mut fresh_subs, &mut data_type);
+                unify_location(self, subs, &record.location, &mut typ, &mut data_type);
+                for &mut (ref field, ref mut value) in fields.iter_mut() {
+                    let ctor = data
+                        .constructors
+                        .iter()
+                        .find(|ctor| ctor.fields.contains(field))
+                        .unwrap();
+                    let index = ctor.fields.iter().position(|f| f == field).unwrap();
+                    let mut ctor_type = ctor.typ.value.clone();
+                    freshen_all(self, &mut fresh_subs, &mut ctor_type);
+                    replace(&mut self.constraints, &mut ctor_type, subs);
+                    let mut value_type = self.typecheck(value, subs);
+                    unify_location(
+                        self,
+                        subs,
+                        &value.location,
+                        &mut value_type,
+                        argument_type(&mut ctor_type, index),
+                    );
+                }
+                replace(&mut self.constraints, &mut typ, subs);
+                typ
+            }
+            LeftSection(ref mut lhs, ref op) => {
+                let op_type = self.fresh_identifier(op, &expr.location);
+                self.typecheck_apply(&expr.location, subs, op_type, lhs)
+            }
+            RightSection(ref op, ref mut rhs) => {
+                let mut op_type = self.fresh_identifier(op, &expr.location);
+                let arg_type = self.new_var();
+                let result_type = self.new_var();
+                let rhs_type = self.typecheck(rhs, subs);
+                let mut section_type = typ::function_type_(
+                    arg_type.clone(),
+                    typ::function_type_(rhs_type, result_type.clone()),
+                );
+                unify_location(self, subs, &expr.location, &mut op_type, &mut section_type);
+                let mut typ = typ::function_type_(arg_type, result_type);
+                replace(&mut self.constraints, &mut typ, subs);
+                typ
+            }
+            TupleSection(ref mut elements) => {
+                let mut arguments = vec![];
+                let mut types = vec![];
+                for element in elements.iter_mut() {
+                    match *element {
+                        Some(ref mut e) => types.push(self.typecheck(e, subs)),
+                        None => {
+                            let var = self.new_var();
+                            arguments.push(var.clone());
+                            types.push(var);
+                        }
+                    }
+                }
+                let tuple = Type::new_op(name(&typ::tuple_name(types.len())), types);
+                let mut typ = arguments
+                    .into_iter()
+                    .rev()
+                    .fold(tuple, |result, arg| typ::function_type_(arg, result));
+                replace(&mut self.constraints, &mut typ, subs);
+                typ
+            }
+            Hole(name) => {
+                let mut locals: Vec<_> = self
+                    .local_types
+                    .iter()
+                    .map(|(name, typ)| (*name, typ.value.clone()))
+                    .collect();
+                locals.sort_by(|l, r| l.0.name.as_ref().cmp(r.0.name.as_ref()));
+                self.holes.push(TypedHole {
+                    name,
+                    location: expr.location,
+                    typ: expr.typ.clone(),
+                    locals,
+                });
+                expr.typ.clone()
+            }
+        };
+        debug!("{:?}\nas\n{:?}", expr, x);
+        expr.typ = x.clone();
+        x
     }
     fn typecheck_apply(
         &mut self,
@@ -977,9 +2226,40 @@ impl<'a> TypeEnvironment<'a> {
         mut func_type: TcType,
         arg: &mut TypedExpr<Name>,
     ) -> TcType {
+        replace(&mut self.constraints, &mut func_type, subs);
+        if let Some((Type::Forall(vars, parameter), result)) = try_get_function(&func_type)
+        {
+            self.typecheck_polymorphic_argument(subs, arg, vars, parameter);
+            return result.clone();
+        }
         let arg_type = self.typecheck(arg, subs);
-        let mut result = typ::function_type_(arg_type, self.new_var());
-        unify_location(self, subs, location, &mut func_type, &mut result);
+        replace(&mut self.constraints, &mut func_type, subs);
+        let mut result = typ::function_type_(arg_type.clone(), self.new_var());
+        if !can_be_function(&func_type) {
+            self.errors.insert(TypeErrorInfo {
+                location: *location,
+                lhs: result,
+                rhs: func_type,
+                error: Error::TooManyArguments,
+            });
+            return self.new_var();
+        }
+        let parameter = try_get_function(&func_type).map(|(parameter, _)| parameter.clone());
+        match unify(self, subs, &mut func_type, &mut result) {
+            Ok(()) => (),
+            //The instance is missing for the type of the argument so only the types of the
+            //argument and the parameter it is passed to are shown, not those of the whole function
+            Err(error @ Error::MissingInstance(..)) => self.errors.insert(TypeErrorInfo {
+                location: arg.location,
+                rhs: parameter.unwrap_or_else(|| arg_type.clone()),
+                lhs: arg_type,
+                error,
+            }),
+            Err(error) => {
+                let error = expected_first_error(&arg.location, &func_type, &result, error);
+                self.errors.insert(error)
+            }
+        }
         match result {
             Type::Application(_, x) => *x,
             _ => panic!(
@@ -988,6 +2268,46 @@ impl<'a> TypeEnvironment<'a> {
             ),
         }
     }
+    ///Checks that the argument `arg` is at least as polymorphic as the higher rank parameter
+    ///`forall vars. parameter` of the function that it is applied to
+    fn typecheck_polymorphic_argument(
+        &mut self,
+        subs: &mut Substitution,
+        arg: &mut TypedExpr<Name>,
+        vars: &[TypeVariable],
+        parameter: &TcType,
+    ) {
+        let scope: Vec<Name> = self.local_types.keys().cloned().collect();
+        let mut arg_type = self.typecheck(arg, subs);
+        let (mut expected, skolems) = self.skolemize(vars, parameter);
+        let errors = self.errors.iter().count();
+        unify_expected_first(self, subs, &arg.location, &mut expected, &mut arg_type);
+        if self.errors.iter().count() != errors {
+            return;
+        }
+        //If a rigid type has been unified with the type of a variable in scope then the argument
+        //only has the expected type for that one type
+        let mut local_types: Vec<TcType> = scope
+            .iter()
+            .filter_map(|name| self.local_types.get(name))
+            .map(|typ| typ.value.clone())
+            .collect();
+        for typ in local_types.iter_mut() {
+            replace(&mut self.constraints, typ, subs);
+        }
+        let escaped = skolems
+            .into_iter()
+            .find(|skolem| local_types.iter().any(|typ| contains_type(typ, skolem)));
+        if let Some(skolem) = escaped {
+            replace(&mut self.constraints, &mut arg_type, subs);
+            self.errors.insert(TypeErrorInfo {
+                location: arg.location,
+                lhs: arg_type,
+                rhs: Type::Forall(vars.to_vec(), Box::new(parameter.clone())),
+                error: Error::NotPolymorphicEnough(skolem),
+            });
+        }
+    }
     ///Typechecks a pattern.
     ///Checks that the pattern has the type 'match_type' and adds all variables in the pattern.
     fn typecheck_pattern(
@@ -1006,8 +2326,20 @@ impl<'a> TypeEnvironment<'a> {
                 let mut typ = typ::int_type();
                 unify_location(self, subs, location, &mut typ, match_type);
             }
+            &Pattern::Fractional(_) => {
+                let mut typ = typ::double_type();
+                unify_location(self, subs, location, &mut typ, match_type);
+            }
+            &Pattern::Char(_) => {
+                let mut typ = typ::char_type();
+                unify_location(self, subs, location, &mut typ, match_type);
+            }
+            &Pattern::String(_) => {
+                let mut typ = typ::list_type(typ::char_type());
+                unify_location(self, subs, location, &mut typ, match_type);
+            }
             &Pattern::Constructor(ref ctorname, ref patterns) => {
-                let mut t = self.fresh(ctorname).unwrap_or_else(|| {
+                let mut t = self.fresh(ctorname, location).unwrap_or_else(|| {
                     panic!(
                         "Undefined constructer '{:?}' when matching pattern",
                         *ctorname
@@ -1020,6 +2352,47 @@ impl<'a> TypeEnvironment<'a> {
                 self.apply_locals(subs);
                 self.pattern_rec(0, location, subs, patterns, &mut t);
             }
+            &Pattern::Record(ref ctorname, ref fields) => {
+                let mut t = self.fresh(ctorname, location).unwrap_or_else(|| {
+                    panic!(
+                        "Undefined constructer '{:?}' when matching pattern",
+                        *ctorname
+                    )
+                });
+                let ctor_fields = self.constructor_fields(*ctorname);
+                let mut data_type = get_returntype(&t);
+
+                unify_location(self, subs, location, &mut data_type, match_type);
+                for &(ref field, ref p) in fields.iter() {
+                    let index = match ctor_fields.iter().position(|f| f == field) {
+                        Some(index) => index,
+                        None => {
+                            //The variables of the pattern are still bound so that their uses
+                            //are not reported as well
+                            let mut field_type = self.new_var();
+                            self.errors.insert(TypeErrorInfo {
+                                location: *location,
+                                lhs: field_type.clone(),
+                                rhs: field_type.clone(),
+                                error: Error::MissingField(*ctorname, *field),
+                            });
+                            self.typecheck_pattern(location, subs, p, &mut field_type);
+                            continue;
+                        }
+                    };
+                    replace(&mut self.constraints, &mut t, subs);
+                    self.apply_locals(subs);
+                    self.typecheck_pattern(location, subs, p, argument_type(&mut t, index));
+                }
+            }
+            &Pattern::As(ref ident, ref pattern) => {
+                self.typecheck_pattern(location, subs, pattern, match_type);
+                self.local_types
+                    .insert(ident.clone(), qualified(vec![], match_type.clone()));
+            }
+            &Pattern::Irrefutable(ref pattern) | &Pattern::Bang(ref pattern) => {
+                self.typecheck_pattern(location, subs, pattern, match_type);
+            }
             &Pattern::WildCard => {}
         }
     }
@@ -1057,6 +2430,22 @@ impl<'a> TypeEnvironment<'a> {
                 Type::Variable(ref var) => Some(var.clone()),
                 _ => None,
             };
+        //The arguments cannot be inferred to have higher rank types so those are taken from the
+        //signature instead
+        if type_var.is_none() {
+            let mut signature = &bindings[0].typ.value;
+            for typ in argument_types.iter_mut() {
+                match try_get_function(signature) {
+                    Some((arg, result)) => {
+                        if let Type::Forall(..) = *arg {
+                            *typ = arg.clone();
+                        }
+                        signature = result;
+                    }
+                    None => break,
+                }
+            }
+        }
         let mut previous_type = None;
         for bind in bindings.iter_mut() {
             assert!(
@@ -1089,11 +2478,17 @@ impl<'a> TypeEnvironment<'a> {
         //HACK, assume that if the type declaration is only a variable it has no type declaration
         //In that case we need to unify that variable to 'typ' to make sure that environment becomes updated
         //Otherwise a type declaration exists and we need to do a match to make sure that the type is not to specialized
-        if type_var.is_none() {
+        //The wildcards of a partial type signature must be inferred so those are unified as well
+        let partial = self
+            .wildcards
+            .iter()
+            .any(|(name, _)| *name == bindings[0].name);
+        let location = *bindings[0].matches.location();
+        if type_var.is_none() && !partial {
             match_or_fail(
                 self,
                 subs,
-                &Location::eof(),
+                &location,
                 &mut final_type,
                 &bindings[0].typ.value,
             );
@@ -1101,7 +2496,7 @@ impl<'a> TypeEnvironment<'a> {
             unify_location(
                 self,
                 subs,
-                &Location::eof(),
+                &location,
                 &mut final_type,
                 &mut bindings[0].typ.value,
             );
@@ -1110,15 +2505,7 @@ impl<'a> TypeEnvironment<'a> {
             subs.subs.insert(var, final_type);
         }
         for bind in bindings.iter_mut() {
-            match bind.matches {
-                Match::Simple(ref mut e) => self.substitute(subs, e),
-                Match::Guards(ref mut gs) => {
-                    for g in gs.iter_mut() {
-                        self.substitute(subs, &mut g.predicate);
-                        self.substitute(subs, &mut g.expression);
-                    }
-                }
-            }
+            SubVisitor { env: self, subs }.visit_binding(bind);
         }
         debug!(
             "End typecheck {:?} :: {:?}",
@@ -1140,12 +2527,55 @@ impl<'a> TypeEnvironment<'a> {
         let groups = strongly_connected_components(&graph);
 
         for group in groups.iter() {
+            //Following the monomorphism restriction a group where every binding is a simple
+            //binding without a type signature may not be generalized over constrained variables
+            let restricted = self.monomorphism_restriction
+                && group.iter().all(|index| {
+                    bindings
+                        .get_mut(graph.get_vertex(*index).value)
+                        .iter()
+                        .all(|bind| {
+                            bind.arguments.is_empty()
+                                && bind.typ.value == Type::<Name>::new_var(intern("a"))
+                        })
+                });
             for index in group.iter() {
                 let bind_index = graph.get_vertex(*index).value;
                 let binds = bindings.get_mut(bind_index);
                 for bind in binds.iter_mut() {
                     if bind.typ.value == Type::<Name>::new_var(intern("a")) {
                         bind.typ.value = self.new_var();
+                    } else {
+                        let location = self
+                            .signature_locations
+                            .get(&bind.name)
+                            .cloned()
+                            .unwrap_or(*bind.matches.location());
+                        self.expand_type_synonyms(&location, &mut bind.typ.value);
+                        self.infer_signature_kinds(&location, &mut bind.typ);
+                        //Each wildcard in a partial type signature is a separate variable which
+                        //is inferred from the binding
+                        let mut wildcards = vec![];
+                        self.instantiate_wildcards(&mut bind.typ.value, &mut wildcards);
+                        //Replace the variables of the signature with new variables so that they
+                        //are old enough to be generalized
+                        let mut mapping: HashMap<_, _> = wildcards
+                            .iter()
+                            .map(|wildcard| (wildcard.var().clone(), wildcard.clone()))
+                            .collect();
+                        if !wildcards.is_empty() {
+                            self.wildcards.push((bind.name, wildcards));
+                        }
+                        each_type(
+                            &bind.typ.value,
+                            |var| {
+                                if !mapping.contains_key(var) {
+                                    mapping.insert(var.clone(), self.new_var_kind(var.kind.clone()));
+                                }
+                            },
+                            |_| (),
+                        );
+                        self.freshen_qualified_type(&mut bind.typ, mapping);
                     }
                 }
                 if is_global {
@@ -1177,11 +2607,26 @@ impl<'a> TypeEnvironment<'a> {
                     self.apply_locals(subs);
                 }
             }
+            self.resolve_multi_constraints(subs);
+            self.report_wildcards(&graph, group, bindings, subs);
+            //Variables instantiated from the types of other global bindings keep their age so
+            //any variable left in the type of a global binding may be generalized
+            let generalize_age = if is_global { 0 } else { start_var_age };
+            if is_global {
+                self.resolve_monomorphic_variables(subs);
+            }
+            let mut group_types = vec![];
+            let mut restricted_vars = vec![];
             for index in group.iter() {
                 let bind_index = graph.get_vertex(*index).value;
                 let binds = bindings.get_mut(bind_index);
-                for constraint in binds[0].typ.constraints.iter() {
-                    self.insert_constraint(&constraint.variables[0], constraint.class.clone());
+                let location = self
+                    .signature_locations
+                    .get(&binds[0].name)
+                    .cloned()
+                    .unwrap_or(*binds[0].matches.location());
+                for constraint in binds[0].typ.constraints.clone().iter() {
+                    self.insert_class_constraint(constraint, &location);
                 }
                 for bind in binds.iter_mut() {
                     {
@@ -1191,18 +2636,165 @@ impl<'a> TypeEnvironment<'a> {
                             self.local_types.get_mut(&bind.name).unwrap()
                         };
                         bind.typ.value = typ.value.clone();
-                        quantify(start_var_age, typ);
+                        let constraints = &self.constraints;
+                        let monomorphic = &self.monomorphic_variables;
+                        each_type(
+                            &typ.value,
+                            |var| {
+                                if var.age >= generalize_age
+                                    && !restricted_vars.contains(var)
+                                    && !monomorphic.contains(var)
+                                    && restricted
+                                    && constraints.contains_key(var)
+                                {
+                                    restricted_vars.push(var.clone());
+                                }
+                            },
+                            |_| (),
+                        );
+                        if !is_global {
+                            let generalized = &mut self.generalized_variables;
+                            each_type(
+                                &typ.value,
+                                |var| {
+                                    if var.age >= start_var_age && !restricted_vars.contains(var) {
+                                        generalized.push(var.clone());
+                                    }
+                                },
+                                |_| (),
+                            );
+                        }
+                        let excluded: Vec<_> = restricted_vars
+                            .iter()
+                            .chain(self.monomorphic_variables.iter())
+                            .cloned()
+                            .collect();
+                        quantify_except(generalize_age, &excluded, typ);
                     }
                     bind.typ.constraints = self.find_constraints(&bind.typ.value);
+                    let (restricted_constraints, constraints) = bind
+                        .typ
+                        .constraints
+                        .drain(..)
+                        .partition(|constraint: &Constraint<Name>| {
+                            constraint.variables.iter().any(|var| {
+                                restricted_vars.contains(var)
+                                    || self.monomorphic_variables.contains(var)
+                            })
+                        });
+                    bind.typ.constraints = constraints;
+                    //The type of a local binding is usually determined by its uses so only top
+                    //level bindings are warned about
+                    if is_global {
+                        self.warn_restricted(bind, &restricted_constraints, &restricted_vars);
+                    }
+                    group_types.push(bind.typ.value.clone());
                 }
                 debug!("End typecheck {:?} :: {:?}", binds[0].name, binds[0].typ);
             }
             if is_global {
+                self.report_holes(subs);
+                let defaults = self.find_defaults(subs, &group_types);
+                if !defaults.subs.is_empty() {
+                    let mut visitor = DefaultVisitor::new(self, &defaults);
+                    for index in group.iter() {
+                        let bind_index = graph.get_vertex(*index).value;
+                        for bind in bindings.get_mut(bind_index).iter_mut() {
+                            visitor.visit_binding(bind);
+                        }
+                    }
+                    let ambiguous = visitor.ambiguous;
+                    self.report_ambiguous(ambiguous);
+                }
+                //Any constraint which does not appear in the type of a binding can't be resolved
+                for (class, types, location) in self.multi_constraints.drain(..) {
+                    let in_type = types.iter().all(|typ| match *typ {
+                        Type::Variable(ref var) => group_types.iter().any(|t| occurs(var, t)),
+                        _ => false,
+                    });
+                    if !in_type {
+                        let constraint = Type::new_op(class, types);
+                        self.errors.insert(TypeErrorInfo {
+                            location,
+                            lhs: constraint.clone(),
+                            rhs: constraint.clone(),
+                            error: Error::AmbiguousMultiInstance(constraint),
+                        });
+                    }
+                }
+                self.monomorphic_variables.extend(restricted_vars);
+                self.resolve_monomorphic_variables(subs);
                 subs.subs.clear();
-                self.constraints.clear();
+                let monomorphic = &self.monomorphic_variables;
+                self.constraints.retain(|var, _| monomorphic.contains(var));
+                self.constraint_origins
+                    .retain(|var, _| monomorphic.contains(var));
+                self.generalized_variables.clear();
             }
         }
     }
+    ///Replaces each wildcard (`_`) in the partial type signature `typ` with a new variable
+    fn instantiate_wildcards(&mut self, typ: &mut TcType, wildcards: &mut Vec<TcType>) {
+        let kind = match *typ {
+            Type::Variable(ref var) if var.id == intern("_") => var.kind.clone(),
+            Type::Application(ref mut lhs, ref mut rhs) => {
+                self.instantiate_wildcards(lhs, wildcards);
+                self.instantiate_wildcards(rhs, wildcards);
+                return;
+            }
+            _ => return,
+        };
+        *typ = self.new_var_kind(kind);
+        wildcards.push(typ.clone());
+    }
+
+    ///Warns about the types which the wildcards in the partial type signatures of `group` were
+    ///inferred to
+    fn report_wildcards(
+        &mut self,
+        graph: &Graph<(usize, usize)>,
+        group: &[VertexIndex],
+        bindings: &mut dyn Bindings,
+        subs: &Substitution,
+    ) {
+        for index in group.iter() {
+            for bind in bindings.get_mut(graph.get_vertex(*index).value).iter() {
+                let position = match self.wildcards.iter().position(|w| w.0 == bind.name) {
+                    Some(position) => position,
+                    None => continue,
+                };
+                let (_, wildcards) = self.wildcards.swap_remove(position);
+                let mut typ = bind.typ.value.clone();
+                replace(&mut self.constraints, &mut typ, subs);
+                let location = self
+                    .signature_locations
+                    .get(&bind.name)
+                    .cloned()
+                    .unwrap_or(*bind.matches.location());
+                for mut wildcard in wildcards {
+                    replace(&mut self.constraints, &mut wildcard, subs);
+                    let mut printer = TypePrinter::new();
+                    let typ = printer.print(&typ);
+                    self.warnings.push(
+                        Diagnostic::new(
+                            Severity::Warning,
+                            Span::from(location),
+                            format!(
+                                "Found type wildcard _ standing for {}",
+                                printer.print(&wildcard)
+                            ),
+                        )
+                        .with_note(format!(
+                            "in the inferred type {} :: {}",
+                            bind.name.name.as_ref(),
+                            typ
+                        )),
+                    );
+                }
+            }
+        }
+    }
+
     ///Typechecks a group of local bindings (such as a let expression)
     fn typecheck_local_bindings(&mut self, subs: &mut Substitution, bindings: &mut dyn Bindings) {
         let var = self.variable_age + 1;
@@ -1229,6 +2821,14 @@ impl<'a> TypeEnvironment<'a> {
                     let v = types.find_type(name).map(|x| x.clone());
                     match v {
                         Some(mut typ) => {
+                            //The ages of the variables come from the environment of the other
+                            //module so reset them to make the variables older than any local ones
+                            reset_age(&mut typ.value);
+                            for constraint in typ.constraints.iter_mut() {
+                                for var in constraint.variables.iter_mut() {
+                                    var.age = 0;
+                                }
+                            }
                             quantify(0, &mut typ);
                             return Some(typ);
                         }
@@ -1238,8 +2838,9 @@ impl<'a> TypeEnvironment<'a> {
                 None
             })
     }
-    ///Instantiates new typevariables for every typevariable in the type found at 'name'
-    fn fresh(&mut self, name: &Name) -> Option<TcType> {
+    ///Instantiates new typevariables for every typevariable in the type found at 'name'.
+    ///`location` is the location of the expression or pattern which refers to `name`
+    fn fresh(&mut self, name: &Name, location: &Location) -> Option<TcType> {
         match self.find_fresh(name) {
             Some(mut typ) => {
                 let mut subs = Substitution {
@@ -1247,14 +2848,134 @@ impl<'a> TypeEnvironment<'a> {
                 };
                 freshen(self, &mut subs, &mut typ);
                 for c in typ.constraints.iter() {
-                    self.insert_constraint(&c.variables[0], c.class.clone());
+                    self.insert_class_constraint(c, location);
                 }
-                Some(typ.value)
+                Some(self.instantiate(typ.value))
             }
             None => None,
         }
     }
 
+    ///Replaces the variables bound by an explicit `forall` at the top of `typ` with new variables.
+    ///Only the arguments of functions with higher rank types are given such types.
+    fn instantiate(&mut self, mut typ: TcType) -> TcType {
+        while let Type::Forall(vars, body) = typ {
+            typ = *body;
+            for var in vars.iter() {
+                let new = self.new_var_kind(var.kind.clone());
+                replace_var(&mut typ, var, &new);
+            }
+        }
+        typ
+    }
+
+    ///Replaces the variables bound by an explicit `forall` with new rigid types which are only equal
+    ///to themselves, so that a type which unifies with the result is at least as polymorphic
+    fn skolemize(&mut self, vars: &[TypeVariable], typ: &TcType) -> (TcType, Vec<TcType>) {
+        let mut typ = typ.clone();
+        let skolems = vars
+            .iter()
+            .map(|var| {
+                self.variable_age += 1;
+                let skolem = Type::Constructor(TypeConstructor {
+                    name: Name {
+                        name: var.id,
+                        uid: self.variable_age as usize,
+                    },
+                    kind: var.kind.clone(),
+                });
+                replace_var(&mut typ, var, &skolem);
+                skolem
+            })
+            .collect();
+        (typ, skolems)
+    }
+
+    ///Replaces the variables bound by two explicit `forall`s with the same rigid types so that the
+    ///bodies can be compared
+    fn skolemize_foralls(
+        &mut self,
+        lhs_vars: &[TypeVariable],
+        lhs: &TcType,
+        rhs_vars: &[TypeVariable],
+        rhs: &TcType,
+    ) -> (TcType, TcType) {
+        let (lhs, skolems) = self.skolemize(lhs_vars, lhs);
+        let mut rhs = rhs.clone();
+        for (var, skolem) in rhs_vars.iter().zip(skolems.iter()) {
+            replace_var(&mut rhs, var, skolem);
+        }
+        (lhs, rhs)
+    }
+
+    ///Adds the constraint `constraint` which was required at `location` to the environment
+    fn insert_class_constraint(&mut self, constraint: &Constraint<Name>, location: &Location) {
+        match *constraint.variables {
+            [ref var] => self.insert_constraint(var, constraint.class),
+            _ => {
+                let types: Vec<TcType> = constraint
+                    .variables
+                    .iter()
+                    .map(|var| Type::Variable(var.clone()))
+                    .collect();
+                self.insert_multi_constraint(constraint.class, types, *location);
+            }
+        }
+    }
+
+    ///Adds the constraint `class types` unless the same constraint has already been added
+    fn insert_multi_constraint(&mut self, class: Name, types: Vec<TcType>, location: Location) {
+        let exists = self
+            .multi_constraints
+            .iter()
+            .any(|(c, ts, _)| *c == class && *ts == types);
+        if !exists {
+            self.multi_constraints.push((class, types, location));
+        }
+    }
+
+    ///Applies `subs` to the types of the constraints with several parameters and checks that an
+    ///instance exists for each constraint whose types are now fully known
+    fn resolve_multi_constraints(&mut self, subs: &Substitution) {
+        let mut multi_constraints = vec![];
+        swap(&mut multi_constraints, &mut self.multi_constraints);
+        for (class, mut types, location) in multi_constraints {
+            for typ in types.iter_mut() {
+                replace(&mut self.constraints, typ, subs);
+            }
+            if types.iter().any(has_type_variables) {
+                self.insert_multi_constraint(class, types, location);
+            } else if self.has_instances(class, &types, &mut vec![]).is_err() {
+                let constraint = Type::new_op(class, types);
+                self.errors.insert(TypeErrorInfo {
+                    location,
+                    lhs: constraint.clone(),
+                    rhs: constraint.clone(),
+                    error: Error::MissingMultiInstance(constraint),
+                });
+            }
+        }
+    }
+
+    ///Records `origin` as the origin of the `class` constraint on `var` unless it already has one
+    fn insert_constraint_origin(&mut self, var: &TypeVariable, class: Name, origin: ConstraintOrigin) {
+        let origins = self.constraint_origins.entry(var.clone()).or_default();
+        if !origins.iter().any(|(c, _)| *c == class) {
+            origins.push((class, origin));
+        }
+    }
+
+    ///Returns the origin of the `class` constraint on `var`, or of any of its constraints if the
+    ///class was required by one of the others
+    fn constraint_origin(&self, var: &TypeVariable, class: InternedStr) -> Option<ConstraintOrigin> {
+        let origins = self.constraint_origins.get(var)?;
+        origins
+            .iter()
+            .find(|(c, _)| c.name == class)
+            .or(origins.first())
+            .map(|(_, origin)| origin.clone())
+    }
+
     fn insert_constraint(&mut self, var: &TypeVariable, classname: Name) {
         let mut constraints = self.constraints.remove(var).unwrap_or(vec![]);
         self.insert_constraint_(&mut constraints, classname);
@@ -1292,18 +3013,36 @@ impl<'a> TypeEnvironment<'a> {
     }
 }
 
-///Searches through a type, comparing it with the type on the identifier, returning all the specialized constraints
+///Searches through a type, comparing it with the type on the identifier, returning all the specialized constraints.
+///Each constraint is returned with the types its variables are specialized to
 pub fn find_specialized_instances(
     typ: &TcType,
     actual_type: &TcType,
     constraints: &[Constraint<Name>],
-) -> Vec<(Name, TcType)> {
+) -> Vec<(Name, Vec<TcType>)> {
     debug!(
         "Finding specialization {:?} => {:?} <-> {:?}",
         constraints, typ, actual_type
     );
-    let mut result = vec![];
-    find_specialized(&mut result, actual_type, typ, constraints);
+    let mut specialized = vec![];
+    find_specialized(&mut specialized, actual_type, typ);
+    //The dictionaries are passed in the same order as the constraints
+    let mut result: Vec<(Name, Vec<TcType>)> = vec![];
+    for c in constraints.iter() {
+        let types: Vec<TcType> = c
+            .variables
+            .iter()
+            .map(|var| {
+                //A variable which does not appear in the type comes from the enclosing binding
+                //of a lifted function and is the same at the call site
+                specialized
+                    .iter()
+                    .find(|&&(ref v, _)| v == var)
+                    .map_or_else(|| Type::Variable(var.clone()), |&(_, ref actual)| actual.clone())
+            })
+            .collect();
+        result.push((c.class.clone(), types));
+    }
     assert!(
         !constraints.is_empty(),
         "Could not find the specialized instance between {:?} <-> {:?}",
@@ -1312,31 +3051,35 @@ pub fn find_specialized_instances(
     );
     result
 }
+///Finds the type each variable in `typ` is specialized to in `actual_type`
 fn find_specialized(
-    result: &mut Vec<(Name, TcType)>,
+    result: &mut Vec<(TypeVariable, TcType)>,
     actual_type: &TcType,
     typ: &TcType,
-    constraints: &[Constraint<Name>],
 ) {
     match (actual_type, typ) {
-        (_, &Type::Variable(ref var)) => {
-            for c in constraints.iter().filter(|c| c.variables[0] == *var) {
-                if result.iter().find(|x| x.0 == c.class) == None {
-                    result.push((c.class.clone(), actual_type.clone()));
-                }
+        (_, &Type::Variable(ref var)) | (_, &Type::Generic(ref var)) => {
+            if result.iter().all(|&(ref v, _)| v != var) {
+                result.push((var.clone(), actual_type.clone()));
             }
         }
         (&Type::Application(ref lhs1, ref rhs1), &Type::Application(ref lhs2, ref rhs2)) => {
-            find_specialized(result, lhs1, lhs2, constraints);
-            find_specialized(result, rhs1, rhs2, constraints);
+            find_specialized(result, lhs1, lhs2);
+            find_specialized(result, rhs1, rhs2);
         }
-        (_, &Type::Generic(ref var)) => {
-            for c in constraints.iter().filter(|c| c.variables[0] == *var) {
-                if result.iter().find(|x| x.0 == c.class) == None {
-                    result.push((c.class.clone(), actual_type.clone()));
-                }
-            }
+        _ => (),
+    }
+}
+
+///Sets the age of every type variable in `typ` to 0
+fn reset_age(typ: &mut TcType) {
+    match *typ {
+        Type::Variable(ref mut var) => var.age = 0,
+        Type::Application(ref mut lhs, ref mut rhs) => {
+            reset_age(lhs);
+            reset_age(rhs);
         }
+        Type::Forall(_, ref mut typ) => reset_age(typ),
         _ => (),
     }
 }
@@ -1344,13 +3087,30 @@ fn find_specialized(
 ///Quantifies all type variables with an age greater that start_var_age
 ///A quantified variable will when it is instantiated have new type variables
 fn quantify(start_var_age: isize, typ: &mut Qualified<TcType, Name>) {
-    fn quantify_(start_var_age: isize, typ: &mut TcType) {
+    quantify_except(start_var_age, &[], typ)
+}
+
+///Quantifies the type like `quantify` but leaves the variables in `excluded` as they are
+fn quantify_except(
+    start_var_age: isize,
+    excluded: &[TypeVariable],
+    typ: &mut Qualified<TcType, Name>,
+) {
+    fn quantify_(start_var_age: isize, excluded: &[TypeVariable], typ: &mut TcType) {
         let x =
             match *typ {
-                Type::Variable(ref id) if id.age >= start_var_age => Some(id.clone()),
+                Type::Variable(ref id) if id.age >= start_var_age && !excluded.contains(id) => {
+                    Some(id.clone())
+                }
                 Type::Application(ref mut lhs, ref mut rhs) => {
-                    quantify_(start_var_age, lhs);
-                    quantify_(start_var_age, rhs);
+                    quantify_(start_var_age, excluded, lhs);
+                    quantify_(start_var_age, excluded, rhs);
+                    None
+                }
+                //The variables bound by the `forall` are already quantified
+                Type::Forall(ref variables, ref mut typ) => {
+                    let excluded: Vec<_> = excluded.iter().chain(variables).cloned().collect();
+                    quantify_(start_var_age, &excluded, typ);
                     None
                 }
                 _ => None,
@@ -1364,7 +3124,7 @@ fn quantify(start_var_age: isize, typ: &mut Qualified<TcType, Name>) {
             //constraint.variables[0] = Type::Generic(constraint.variables[0].clone())
         }
     }
-    quantify_(start_var_age, &mut typ.value);
+    quantify_(start_var_age, excluded, &mut typ.value);
 }
 
 ///Replaces all occurences of 'var' in 'typ' with the the type 'replacement'
@@ -1384,12 +3144,70 @@ pub fn replace_var(typ: &mut TcType, var: &TypeVariable, replacement: &TcType) {
             None
         }
         Type::Generic(_) => panic!("replace_var called on Generic"),
+        Type::Forall(ref variables, ref mut typ) => {
+            if !variables.contains(var) {
+                replace_var(typ, var, replacement);
+            }
+            None
+        }
     };
     if let Some(x) = new {
         *typ = x.clone();
     }
 }
 ///Returns true if the type is a function
+///Returns true if `typ` is a function type or could become one once its variables are known
+fn can_be_function(typ: &TcType) -> bool {
+    let mut head = typ;
+    while let Type::Application(ref lhs, _) = *head {
+        head = lhs;
+    }
+    match *head {
+        Type::Constructor(ref op) => op.name.as_ref() == "->",
+        _ => true,
+    }
+}
+
+///Returns the number of arguments a value of the type can be applied to
+fn function_arity(typ: &TcType) -> usize {
+    match try_get_function(typ) {
+        Some((_, result)) => 1 + function_arity(result),
+        None => 0,
+    }
+}
+
+fn kind_arity(kind: &Kind) -> isize {
+    match *kind {
+        Kind::Function(_, ref result) => 1 + kind_arity(result),
+        Kind::Star => 0,
+    }
+}
+
+///Suggests how to fix a type which has the `actual` kind instead of the `expected` kind if it has
+///the wrong number of type arguments
+fn kind_arity_note(
+    printer: &mut TypePrinter,
+    typ: &TcType,
+    expected: &Kind,
+    actual: &Kind,
+) -> Option<::std::string::String> {
+    let missing = kind_arity(actual) - kind_arity(expected);
+    if missing > 0 {
+        Some(format!(
+            "perhaps {} is missing {} type argument(s)",
+            printer.print(typ),
+            missing
+        ))
+    } else if missing < 0 {
+        Some(format!(
+            "perhaps {} is applied to too many type arguments",
+            printer.print(typ)
+        ))
+    } else {
+        None
+    }
+}
+
 fn is_function(typ: &TcType) -> bool {
     if let Type::Application(ref lhs, _) = typ {
         if let Type::Application(ref lhs, _) = **lhs {
@@ -1401,6 +3219,51 @@ fn is_function(typ: &TcType) -> bool {
     false
 }
 ///Extracts the final return type of a type
+///Checks that the context written in a standalone deriving declaration for `typ` provides the
+///constraints which the derived instance for `instance_type` needs
+fn check_deriving_context(
+    context: &[Constraint<Name>],
+    typ: &TcType,
+    constraints: &[Constraint<Name>],
+    instance_type: &TcType,
+) -> Result<(), ::std::string::String> {
+    //Pairs each variable of the derived instance with the variable written in its place
+    let mut variables: Vec<(&TypeVariable, &TypeVariable)> = vec![];
+    let (mut declared, mut derived) = (typ, instance_type);
+    loop {
+        match (declared, derived) {
+            (&Type::Application(ref lhs, ref arg), &Type::Application(ref derived_lhs, ref param)) => {
+                match (&**arg, &**param) {
+                    (&Type::Variable(ref var), &Type::Variable(ref param))
+                        if variables.iter().all(|&(_, v)| v.id != var.id) =>
+                    {
+                        variables.push((param, var))
+                    }
+                    _ => return Err("its arguments must be distinct type variables".to_string()),
+                }
+                declared = lhs;
+                derived = derived_lhs;
+            }
+            (&Type::Constructor(_), &Type::Constructor(_)) => break,
+            _ => return Err(format!("the instance must be declared for {}", instance_type)),
+        }
+    }
+    for constraint in constraints.iter() {
+        let var = variables
+            .iter()
+            .find(|&&(param, _)| *param == constraint.variables[0])
+            .map(|&(_, var)| var)
+            .expect("constraint on a parameter of the type");
+        if !context
+            .iter()
+            .any(|c| c.class == constraint.class && c.variables[0].id == var.id)
+        {
+            return Err(format!("its context does not contain {} {}", constraint.class, var));
+        }
+    }
+    Ok(())
+}
+
 fn get_returntype(typ: &TcType) -> TcType {
     let Type::Application(_, ref rhs) = typ else {
         return typ.clone();
@@ -1425,6 +3288,12 @@ fn replace(
             replace(constraints, rhs, subs);
             None
         }
+        //The bound variables keep the names from the source while the variables created by the
+        //typechecker are numbered so the substitution never contains a bound variable
+        Type::Forall(_, ref mut typ) => {
+            replace(constraints, typ, subs);
+            None
+        }
         _ => None, //panic!("replace called on Generic")
     };
     if let Some(x) = replaced {
@@ -1437,10 +3306,23 @@ fn occurs(type_var: &TypeVariable, in_type: &TcType) -> bool {
     match in_type {
         &Type::Variable(ref var) => type_var.id == var.id,
         &Type::Application(ref lhs, ref rhs) => occurs(type_var, lhs) || occurs(type_var, rhs),
+        Type::Forall(_, typ) => occurs(type_var, typ),
         _ => false,
     }
 }
 
+///Returns true if `needle` appears anywhere in `typ`
+fn contains_type(typ: &TcType, needle: &TcType) -> bool {
+    typ == needle
+        || match *typ {
+            Type::Application(ref lhs, ref rhs) => {
+                contains_type(lhs, needle) || contains_type(rhs, needle)
+            }
+            Type::Forall(_, ref typ) => contains_type(typ, needle),
+            _ => false,
+        }
+}
+
 ///Freshen creates new type variables at every position where Type::Generic(..) appears.
 fn freshen(env: &mut TypeEnvironment, subs: &mut Substitution, typ: &mut Qualified<TcType, Name>) {
     debug!("Freshen {:?}", typ);
@@ -1457,6 +3339,10 @@ fn freshen(env: &mut TypeEnvironment, subs: &mut Substitution, typ: &mut Qualifi
                 freshen_(env, subs, constraints, rhs);
                 None
             }
+            Type::Forall(_, ref mut typ) => {
+                freshen_(env, subs, constraints, typ);
+                None
+            }
             _ => None,
         };
         if let Some(x) = result {
@@ -1465,8 +3351,10 @@ fn freshen(env: &mut TypeEnvironment, subs: &mut Substitution, typ: &mut Qualifi
     }
     freshen_(env, subs, &*typ.constraints, &mut typ.value);
     for constraint in typ.constraints.iter_mut() {
-        if let Some(new) = subs.subs.get(&constraint.variables[0]) {
-            constraint.variables[0] = new.var().clone();
+        for var in constraint.variables.iter_mut() {
+            if let Some(new) = subs.subs.get(var) {
+                *var = new.var().clone();
+            }
         }
     }
 }
@@ -1479,6 +3367,21 @@ fn freshen_all(env: &mut TypeEnvironment, subs: &mut Substitution, typ: &mut TcT
             freshen_all(env, subs, rhs);
             None
         }
+        //The bound variables are left as they are, shadowing any variables with the same name
+        Type::Forall(ref variables, ref mut typ) => {
+            let shadowed: Vec<_> = variables
+                .iter()
+                .map(|var| (var.clone(), subs.subs.insert(var.clone(), Type::Variable(var.clone()))))
+                .collect();
+            freshen_all(env, subs, typ);
+            for (var, previous) in shadowed {
+                match previous {
+                    Some(previous) => subs.subs.insert(var, previous),
+                    None => subs.subs.remove(&var),
+                };
+            }
+            None
+        }
         _ => None,
     };
     if let Some(x) = result {
@@ -1500,7 +3403,10 @@ fn freshen_var(
         }
         subs.subs.insert(id.clone(), new.clone());
         {
-            let constraints_for_id = constraints.iter().filter(|c| c.variables[0] == *id);
+            //Constraints with several variables are added once the whole type is freshened
+            let constraints_for_id = constraints
+                .iter()
+                .filter(|c| c.variables.len() == 1 && c.variables[0] == *id);
             //Add all the constraints to he environment for the 'new' variable
             for c in constraints_for_id {
                 env.insert_constraint(new.var(), c.class.clone());
@@ -1510,7 +3416,8 @@ fn freshen_var(
     })
 }
 
-///Takes two types and attempts to make them the same type
+///Takes two types and attempts to make them the same type.
+///`lhs` is the actual type of an expression and `rhs` the type it was expected to have
 fn unify_location(
     env: &mut TypeEnvironment,
     subs: &mut Substitution,
@@ -1530,10 +3437,46 @@ fn unify_location(
     }
 }
 
-#[derive(Debug)]
-struct TypeErrorInfo {
-    location: Location,
-    lhs: TcType,
+///Unifies the types like `unify_location` but with the expected type as the first argument to
+///`unify`, which decides which of two type variables is kept when they have the same age
+fn unify_expected_first(
+    env: &mut TypeEnvironment,
+    subs: &mut Substitution,
+    location: &Location,
+    expected: &mut TcType,
+    actual: &mut TcType,
+) {
+    debug!("{:?} Unifying {:?} <-> {:?}", location, *expected, *actual);
+    match unify(env, subs, expected, actual) {
+        Ok(()) => (),
+        Err(error) => env
+            .errors
+            .insert(expected_first_error(location, expected, actual, error)),
+    }
+}
+
+///Creates the error for when `expected` could not be unified with `actual` by `unify`
+fn expected_first_error(
+    location: &Location,
+    expected: &TcType,
+    actual: &TcType,
+    error: Error,
+) -> TypeErrorInfo {
+    TypeErrorInfo {
+        location: *location,
+        lhs: actual.clone(),
+        rhs: expected.clone(),
+        error: match error {
+            Error::UnifyFail(expected, actual) => Error::UnifyFail(actual, expected),
+            error => error,
+        },
+    }
+}
+
+#[derive(Debug)]
+struct TypeErrorInfo {
+    location: Location,
+    lhs: TcType,
     rhs: TcType,
     error: Error,
 }
@@ -1543,25 +3486,334 @@ enum Error {
     UnifyFail(TcType, TcType),
     RecursiveUnification,
     WrongArity(TcType, TcType),
-    MissingInstance(InternedStr, TcType, TypeVariable),
+    MissingInstance(InternedStr, TcType, Option<ConstraintOrigin>),
+    TooManyArguments,
+    ///A rigid type of a higher rank argument which was unified with the type of a variable in scope
+    NotPolymorphicEnough(TcType),
+    PartiallyAppliedSynonym(Name, usize),
+    RecursiveSynonym(Name),
+    ///The type, its expected kind and its actual kind if it is known
+    KindMismatch(TcType, Kind, Option<Kind>),
+    MissingMultiInstance(TcType),
+    ///A class, the number of its parameters and the number of types an instance of it was given
+    InstanceArity(Name, usize, usize),
+    UndefinedIdentifier(Name),
+    ///A constructor and a field which it does not have
+    MissingField(Name, Name),
+    UndefinedField(Name),
+    ///The fields of a record update, no constructor of the data type has all of them
+    NoConstructorWithFields(Vec<Name>),
+    AmbiguousMultiInstance(TcType),
+    CannotDerive(Name, ::std::string::String),
+    AmbiguousType(TypeVariable, Vec<Name>),
+    InvalidDefault(TcType),
+    Hole(InternedStr, Qualified<TcType, Name>, Vec<(Name, TcType)>, Vec<Name>),
+}
+
+impl TypeErrorInfo {
+    fn diagnostic(&self) -> Diagnostic {
+        let span = Span::from(self.location);
+        //Every type in the diagnostic is printed with the same printer so that a type variable
+        //has the same name in the message and in all of the notes
+        let mut printer = TypePrinter::new();
+        match self.error {
+            Error::UnifyFail(ref actual, ref expected) => {
+                let whole_expected = printer.print(&self.rhs);
+                let whole_actual = printer.print(&self.lhs);
+                let mut diagnostic = Diagnostic::error(
+                    span,
+                    format!(
+                        "Couldn't match expected type {} with actual type {}",
+                        printer.print(expected),
+                        printer.print(actual)
+                    ),
+                );
+                if *actual != self.lhs || *expected != self.rhs {
+                    diagnostic = diagnostic
+                        .with_note(format!("expected: {}", whole_expected))
+                        .with_note(format!("  actual: {}", whole_actual));
+                }
+                let (actual_arity, expected_arity) =
+                    (function_arity(actual), function_arity(expected));
+                if actual_arity > expected_arity && !is_variable(expected) {
+                    diagnostic = diagnostic.with_note(format!(
+                        "perhaps a function is missing {} argument(s)",
+                        actual_arity - expected_arity
+                    ));
+                }
+                diagnostic
+            }
+            Error::TooManyArguments => {
+                let expected = printer.print(&self.rhs);
+                Diagnostic::error(
+                    span,
+                    format!(
+                        "Couldn't match expected type {} with actual type {}",
+                        expected,
+                        printer.print(&self.lhs)
+                    ),
+                )
+                .with_note(format!(
+                    "a function is applied to too many arguments, {} does not take an argument",
+                    expected
+                ))
+            }
+            Error::NotPolymorphicEnough(ref skolem) => Diagnostic::error(
+                span,
+                format!(
+                    "Couldn't match expected type {} with actual type {}",
+                    printer.print(&self.rhs),
+                    printer.print(&self.lhs)
+                ),
+            )
+            .with_note(format!(
+                "the argument is not polymorphic enough, the type {} would escape its scope",
+                printer.print(skolem)
+            )),
+            Error::RecursiveUnification => Diagnostic::error(
+                span,
+                format!(
+                    "Cannot construct the infinite type {} ~ {}",
+                    printer.print(&self.lhs),
+                    printer.print(&self.rhs)
+                ),
+            ),
+            Error::WrongArity(ref var, ref typ) => {
+                let mut diagnostic = Diagnostic::error(
+                    span,
+                    format!(
+                        "Expected a type of kind {} but {} has kind {}",
+                        var.kind(),
+                        printer.print(typ),
+                        typ.kind()
+                    ),
+                );
+                if let Some(note) = kind_arity_note(&mut printer, typ, var.kind(), typ.kind()) {
+                    diagnostic = diagnostic.with_note(note);
+                }
+                if self.lhs != self.rhs {
+                    diagnostic = diagnostic
+                        .with_note(format!("expected: {}", printer.print(&self.rhs)))
+                        .with_note(format!("  actual: {}", printer.print(&self.lhs)));
+                }
+                diagnostic
+            }
+            Error::MissingInstance(ref class, ref typ, ref origin) => {
+                let mut diagnostic = Diagnostic::error(
+                    span,
+                    format!(
+                        "No instance for ({} {})",
+                        class.as_ref(),
+                        printer.print_argument(typ)
+                    ),
+                );
+                if let Some(ref origin) = *origin {
+                    diagnostic = diagnostic.with_note(format!(
+                        "arising from {} at {}:{}",
+                        origin.description,
+                        origin.location.row + 1,
+                        origin.location.column
+                    ));
+                }
+                if try_get_function(typ).is_some() {
+                    diagnostic = diagnostic
+                        .with_note("a function may be applied to too few arguments".to_string());
+                }
+                diagnostic
+            }
+            Error::PartiallyAppliedSynonym(ref name, arity) => Diagnostic::error(
+                span,
+                format!("The type synonym {} must be applied to {} arguments", name, arity),
+            )
+            .with_note(format!("in the type {}", printer.print(&self.lhs))),
+            Error::RecursiveSynonym(ref name) => Diagnostic::error(
+                span,
+                format!("The type synonym {} is defined in terms of itself", name),
+            )
+            .with_note(format!("in the type {}", printer.print(&self.lhs))),
+            Error::KindMismatch(ref typ, ref kind, ref actual) => {
+                let message = match *actual {
+                    Some(ref actual) => format!(
+                        "Expected the type {} to have kind {} but it has kind {}",
+                        printer.print(typ),
+                        kind,
+                        actual
+                    ),
+                    None => format!(
+                        "Expected the type {} to have kind {}",
+                        printer.print(typ),
+                        kind
+                    ),
+                };
+                let mut diagnostic = Diagnostic::error(span, message)
+                    .with_note(format!("in the type {}", printer.print(&self.lhs)));
+                if let Some(ref actual) = *actual {
+                    if let Some(note) = kind_arity_note(&mut printer, typ, kind, actual) {
+                        diagnostic = diagnostic.with_note(note);
+                    }
+                }
+                diagnostic
+            }
+            Error::InstanceArity(ref class, expected, actual) => Diagnostic::error(
+                span,
+                format!(
+                    "The class {} expects {} types but the instance has {}",
+                    class.as_ref(),
+                    expected,
+                    actual
+                ),
+            )
+            .with_note(format!("in the instance {}", printer.print(&self.lhs))),
+            Error::MissingMultiInstance(ref constraint) => Diagnostic::error(
+                span,
+                format!("No instance for ({})", printer.print(constraint)),
+            ),
+            Error::UndefinedIdentifier(ref name) => {
+                Diagnostic::error(span, format!("Undefined identifier {}", name.name))
+            }
+            Error::MissingField(ref constructor, ref field) => Diagnostic::error(
+                span,
+                format!(
+                    "The constructor {} does not have the field {}",
+                    constructor.name.as_ref(),
+                    field.name.as_ref()
+                ),
+            ),
+            Error::UndefinedField(ref field) => Diagnostic::error(
+                span,
+                format!("No data type has the field {}", field.name.as_ref()),
+            ),
+            Error::NoConstructorWithFields(ref fields) => {
+                let fields: Vec<&str> = fields.iter().map(|field| field.name.as_ref()).collect();
+                Diagnostic::error(
+                    span,
+                    format!(
+                        "No constructor of {} has all of the fields {}",
+                        printer.print(&self.lhs),
+                        fields.join(", ")
+                    ),
+                )
+            }
+            Error::AmbiguousMultiInstance(ref constraint) => Diagnostic::error(
+                span,
+                format!(
+                    "The types of the constraint {} could not be determined",
+                    printer.print(constraint)
+                ),
+            ),
+            Error::CannotDerive(ref class, ref reason) => Diagnostic::error(
+                span,
+                format!(
+                    "Cannot derive {} for {} since {}",
+                    class.name.as_ref(),
+                    printer.print(&self.lhs),
+                    reason
+                ),
+            ),
+            Error::AmbiguousType(ref var, ref classes) => {
+                let var = printer.variable(var);
+                let constraints: Vec<_> = classes
+                    .iter()
+                    .map(|class| format!("{} {}", class.name.as_ref(), var))
+                    .collect();
+                Diagnostic::error(
+                    span,
+                    format!("The type variable {} is ambiguous", var),
+                )
+                .with_note(format!(
+                    "it is constrained by ({}) and could not be defaulted",
+                    constraints.join(", ")
+                ))
+                .with_note("add a type signature to fix the type".to_string())
+            }
+            Error::InvalidDefault(ref typ) => Diagnostic::error(
+                span,
+                format!("The default type {} is not an instance of Num", printer.print(typ)),
+            ),
+            Error::Hole(ref name, ref typ, ref locals, ref fits) => {
+                let mut diagnostic = Diagnostic::error(
+                    span,
+                    format!(
+                        "Found hole {} with type {}",
+                        name.as_ref(),
+                        printer.print_qualified(typ)
+                    ),
+                );
+                if !locals.is_empty() {
+                    let locals: Vec<_> = locals
+                        .iter()
+                        .map(|(name, typ)| format!("{} :: {}", name.name.as_ref(), printer.print(typ)))
+                        .collect();
+                    diagnostic = diagnostic
+                        .with_note(format!("relevant bindings include {}", locals.join(", ")));
+                }
+                if !fits.is_empty() {
+                    let fits: Vec<_> = fits
+                        .iter()
+                        .take(MAX_HOLE_FITS)
+                        .map(|name| name.name.as_ref().to_string())
+                        .collect();
+                    diagnostic =
+                        diagnostic.with_note(format!("valid hole fits include {}", fits.join(", ")));
+                }
+                diagnostic
+            }
+        }
+    }
 }
 
 impl fmt::Display for TypeErrorInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.error {
-            Error::UnifyFail(ref l, ref r) =>
-                write!(f, "{} Error: Could not unify types\n{}\nand\n{}\nin types\n{}\nand\n{}",
-                    self.location, l, r, self.lhs, self.rhs),
-            Error::RecursiveUnification =>
-                write!(f, "{} Error: Recursive unification between {}\nand\n{}",
-                    self.location, self.lhs, self.rhs),
-            Error::WrongArity(ref l, ref r) =>
-                write!(f, "{} Error: Types do not have the same arity.\n{} <-> {}\n{} <-> {}\n{}\nand\n{}"
-                    , self.location, l, r, l.kind(), r.kind(), self.lhs, self.rhs),
-            Error::MissingInstance(ref class, ref typ, ref id) =>
-                write!(f, "{} Error: The instance {} {} was not found as required by {} when unifying {}\nand\n{}",
-                    self.location, class, typ, id, self.lhs, self.rhs)
+        write!(f, "{}", self.diagnostic())
+    }
+}
+
+///The maximum number of globals which are suggested as fits for a typed hole
+const MAX_HOLE_FITS: usize = 10;
+
+///Returns true if `typ` can be instantiated to `expected` by replacing its generic variables.
+///The variables in `expected` are unknown types so they can't be replaced
+fn instantiates(
+    mapping: &mut HashMap<TypeVariable, TcType>,
+    typ: &TcType,
+    expected: &TcType,
+) -> bool {
+    match (typ, expected) {
+        (Type::Generic(var), _) => match mapping.get(var) {
+            //`==` considers types equal up to the renaming of variables so the types are
+            //compared with `instantiates` as they do not contain any generic variables
+            Some(typ) => instantiates(&mut HashMap::new(), &typ.clone(), expected),
+            None => {
+                mapping.insert(var.clone(), expected.clone());
+                true
+            }
+        },
+        (Type::Variable(l), Type::Variable(r)) => l == r,
+        (Type::Application(l1, r1), Type::Application(l2, r2)) => {
+            instantiates(mapping, l1, l2) && instantiates(mapping, r1, r2)
         }
+        (Type::Constructor(l), Type::Constructor(r)) => l.name == r.name,
+        _ => false,
+    }
+}
+
+///Returns true if `class` is one of the numeric classes which literals can be defaulted for
+fn is_numeric_class(class: Name) -> bool {
+    ["Num", "Fractional", "Integral"]
+        .iter()
+        .any(|name| class == prelude_name(name))
+}
+
+///Returns true if the primitive type `typ` is treated as an instance of `class` even if the
+///instance has not been declared, which is the case for numeric literals without the Prelude
+fn is_primitive_instance(class: Name, typ: &TcType) -> bool {
+    match *typ {
+        Type::Constructor(ref op) if *typ.kind() == Kind::Star => {
+            (class.name == intern("Num")
+                && (op.name == intern("Int") || op.name == intern("Double")))
+                || (class.name == intern("Fractional") && op.name == intern("Double"))
+        }
+        _ => false,
     }
 }
 
@@ -1586,6 +3838,9 @@ fn bind_variable(
                         env.insert_constraint(var2, c.clone());
                     }
                 }
+                for (class, origin) in env.constraint_origins.remove(var).unwrap_or_default() {
+                    env.insert_constraint_origin(var2, class, origin);
+                }
             }
             Ok(())
         }
@@ -1605,28 +3860,12 @@ fn bind_variable(
                         for c in constraints.iter() {
                             let result = env.has_instance(*c, typ, &mut new_constraints);
                             match result {
+                                Err(_) if is_primitive_instance(*c, typ) => (),
                                 Err(missing_instance) => {
-                                    match *typ {
-                                        Type::Constructor(ref op) => {
-                                            if c.name == intern("Num")
-                                                && (op.name == intern("Int")
-                                                    || op.name == intern("Double"))
-                                                && *typ.kind() == Kind::Star
-                                            {
-                                                continue;
-                                            } else if c.name == intern("Fractional")
-                                                && intern("Double") == op.name
-                                                && *typ.kind() == Kind::Star
-                                            {
-                                                continue;
-                                            }
-                                        }
-                                        _ => (),
-                                    }
                                     return Err(Error::MissingInstance(
                                         missing_instance,
                                         typ.clone(),
-                                        var.clone(),
+                                        env.constraint_origin(var, missing_instance),
                                     ));
                                 }
                                 Ok(()) => (),
@@ -1636,7 +3875,14 @@ fn bind_variable(
                     _ => (),
                 }
                 for constraint in new_constraints.into_iter() {
-                    env.insert_constraint(&constraint.variables[0], constraint.class)
+                    env.insert_constraint(&constraint.variables[0], constraint.class);
+                    if let Some(origin) = env.constraint_origin(var, constraint.class.name) {
+                        env.insert_constraint_origin(
+                            &constraint.variables[0],
+                            constraint.class,
+                            origin,
+                        );
+                    }
                 }
                 Ok(())
             }
@@ -1656,11 +3902,20 @@ fn unify(
         (
             &mut Type::Application(ref mut l1, ref mut r1),
             &mut Type::Application(ref mut l2, ref mut r2),
-        ) => unify(env, subs, l1, l2).and_then(|_| {
-            replace(&mut env.constraints, r1, subs);
-            replace(&mut env.constraints, r2, subs);
-            unify(env, subs, r1, r2)
-        }),
+        ) => match unify(env, subs, l1, l2) {
+            //Report the whole applications instead of partially applied constructors such as
+            //`[]` and `(->) a` which would be confusing in an error message
+            Err(Error::UnifyFail(ref l, _)) if *l.kind() != Kind::Star => Err(Error::UnifyFail(
+                Type::Application(l1.clone(), r1.clone()),
+                Type::Application(l2.clone(), r2.clone()),
+            )),
+            Err(error) => Err(error),
+            Ok(()) => {
+                replace(&mut env.constraints, r1, subs);
+                replace(&mut env.constraints, r2, subs);
+                unify(env, subs, r1, r2)
+            }
+        },
         (&mut Type::Variable(ref mut lhs), &mut Type::Variable(ref mut rhs)) => {
             //If both are variables we choose that they younger variable is replaced by the oldest
             //This is because when doing the quantifying, only variables that are created during
@@ -1691,6 +3946,12 @@ fn unify(
                 ))
             }
         }
+        (&mut Type::Forall(ref lhs_vars, ref lhs), &mut Type::Forall(ref rhs_vars, ref rhs))
+            if lhs_vars.len() == rhs_vars.len() =>
+        {
+            let (mut lhs, mut rhs) = env.skolemize_foralls(lhs_vars, lhs, rhs_vars, rhs);
+            unify(env, subs, &mut lhs, &mut rhs)
+        }
         (lhs, rhs) => {
             let x = match lhs {
                 &mut Type::Variable(ref mut var) => bind_variable(env, subs, var, rhs),
@@ -1761,6 +4022,12 @@ fn match_(
                 ))
             }
         }
+        (&mut Type::Forall(ref lhs_vars, ref lhs), Type::Forall(rhs_vars, rhs))
+            if lhs_vars.len() == rhs_vars.len() =>
+        {
+            let (mut lhs, rhs) = env.skolemize_foralls(lhs_vars, lhs, rhs_vars, rhs);
+            match_(env, subs, &mut lhs, &rhs)
+        }
         (lhs, rhs) => {
             let x = match lhs {
                 &mut Type::Variable(ref mut var) => bind_variable(env, subs, var, rhs),
@@ -1772,6 +4039,77 @@ fn match_(
     }
 }
 
+///Replaces the variables which have been defaulted and finds where each ambiguous variable is
+///first used
+struct DefaultVisitor<'a: 'b, 'b, 'c> {
+    env: &'b mut TypeEnvironment<'a>,
+    defaults: &'c Substitution,
+    ambiguous: Vec<(TypeVariable, Location)>,
+}
+impl<'a, 'b, 'c> DefaultVisitor<'a, 'b, 'c> {
+    fn new(env: &'b mut TypeEnvironment<'a>, defaults: &'c Substitution) -> Self {
+        let ambiguous = defaults
+            .subs
+            .iter()
+            .filter(|&(var, typ)| matches!(*typ, Type::Variable(ref v) if v == var))
+            .map(|(var, _)| (var.clone(), Location::eof()))
+            .collect();
+        DefaultVisitor {
+            env,
+            defaults,
+            ambiguous,
+        }
+    }
+}
+impl<'a, 'b, 'c> MutVisitor<Name> for DefaultVisitor<'a, 'b, 'c> {
+    fn visit_expr(&mut self, expr: &mut TypedExpr<Name>) {
+        for &mut (ref var, ref mut location) in self.ambiguous.iter_mut() {
+            if location.row < 0 && occurs(var, &expr.typ) {
+                *location = expr.location;
+            }
+        }
+        replace(&mut self.env.constraints, &mut expr.typ, self.defaults);
+        walk_expr_mut(self, expr);
+    }
+    fn visit_binding(&mut self, binding: &mut Binding<Name>) {
+        replace(&mut self.env.constraints, &mut binding.typ.value, self.defaults);
+        let defaults = self.defaults;
+        binding
+            .typ
+            .constraints
+            .retain(|constraint| !defaults.subs.contains_key(&constraint.variables[0]));
+        if let Some(ref mut bindings) = binding.where_bindings {
+            for bind in bindings.iter_mut() {
+                self.visit_binding(bind);
+            }
+        }
+        walk_binding_mut(self, binding);
+    }
+}
+
+///Replaces the types of the visited expressions with their substituted types
+struct SubVisitor<'a: 'b, 'b, 'c> {
+    env: &'b mut TypeEnvironment<'a>,
+    subs: &'c Substitution,
+}
+impl<'a, 'b, 'c> MutVisitor<Name> for SubVisitor<'a, 'b, 'c> {
+    fn visit_expr(&mut self, expr: &mut TypedExpr<Name>) {
+        replace(&mut self.env.constraints, &mut expr.typ, self.subs);
+        walk_expr_mut(self, expr);
+    }
+    fn visit_binding(&mut self, binding: &mut Binding<Name>) {
+        //Variables of where bindings restricted by the monomorphism restriction are only
+        //determined by the enclosing binding
+        replace(&mut self.env.constraints, &mut binding.typ.value, self.subs);
+        if let Some(ref mut bindings) = binding.where_bindings {
+            for bind in bindings.iter_mut() {
+                self.visit_binding(bind);
+            }
+        }
+        walk_binding_mut(self, binding);
+    }
+}
+
 ///Creates a graph containing a vertex for each binding and edges from every binding to every other
 ///binding that it references
 fn build_graph(bindings: &dyn Bindings) -> Graph<(usize, usize)> {
@@ -1783,15 +4121,7 @@ fn build_graph(bindings: &dyn Bindings) -> Graph<(usize, usize)> {
     });
     bindings.each_binding(&mut |binds, _| {
         for bind in binds.iter() {
-            match bind.matches {
-                Match::Simple(ref e) => add_edges(&mut graph, &map, map[&bind.name], e),
-                Match::Guards(ref gs) => {
-                    for g in gs.iter() {
-                        add_edges(&mut graph, &map, map[&bind.name], &g.predicate);
-                        add_edges(&mut graph, &map, map[&bind.name], &g.expression);
-                    }
-                }
-            }
+            add_edges(&mut graph, &map, map[&bind.name], &bind.matches);
         }
     });
     graph
@@ -1802,7 +4132,7 @@ fn add_edges<T: 'static>(
     graph: &mut Graph<T>,
     map: &HashMap<Name, VertexIndex>,
     function_index: VertexIndex,
-    expr: &TypedExpr<Name>,
+    matches: &Match<Name>,
 ) {
     struct EdgeVisitor<'a, T: 'a> {
         graph: &'a mut Graph<T>,
@@ -1820,12 +4150,22 @@ fn add_edges<T: 'static>(
                 .map(|index| self.graph.connect(self.function_index, *index));
         }
     }
-    EdgeVisitor {
+    let mut visitor = EdgeVisitor {
         graph,
         map,
         function_index,
+    };
+    match *matches {
+        Match::Simple(ref e) => visitor.visit_expr(e),
+        Match::Guards(ref gs) => {
+            for g in gs.iter() {
+                for q in g.qualifiers.iter() {
+                    walk_qualifier(&mut visitor, q);
+                }
+                visitor.visit_expr(&g.expression);
+            }
+        }
     }
-    .visit_expr(expr)
 }
 
 ///Walks through the type and calls the functions on each variable and type constructor
@@ -1848,6 +4188,15 @@ fn each_type_<Id>(
             each_type_(lhs, var_fn, op_fn);
             each_type_(rhs, var_fn, op_fn);
         }
+        Type::Forall(bound, typ) => each_type_(
+            typ,
+            &mut |var| {
+                if !bound.contains(var) {
+                    var_fn(var)
+                }
+            },
+            op_fn,
+        ),
         _ => (),
     }
 }
@@ -1874,10 +4223,352 @@ fn find_kind(
         Type::Application(ref lhs, ref rhs) => {
             find_kind(test, expected, lhs).and_then(|result| find_kind(test, result, rhs))
         }
+        Type::Forall(ref bound, ref typ) if !bound.iter().any(|var| var.id == test.id) => {
+            find_kind(test, expected, typ)
+        }
         _ => Ok(expected),
     }
 }
 
+///A kind which may still contain unknown parts while kinds are being inferred
+#[derive(Clone, Debug)]
+enum InferredKind {
+    Star,
+    Function(Box<InferredKind>, Box<InferredKind>),
+    Variable(usize),
+}
+
+impl InferredKind {
+    fn from_kind(kind: &Kind) -> Self {
+        match *kind {
+            Kind::Star => Self::Star,
+            Kind::Function(ref arg, ref result) => {
+                Self::Function(Self::from_kind(arg).into(), Self::from_kind(result).into())
+            }
+        }
+    }
+}
+
+///Infers the kinds of type constructors and type variables.
+///Each unknown kind is given a kind variable which is then unified with the kinds required by the
+///type applications it appears in. Kind variables which are never constrained default to `*`.
+struct KindInference {
+    ///The kind which each kind variable has been unified with, if any
+    variables: Vec<Option<InferredKind>>,
+    ///The kinds of the type constructors which are currently being declared
+    constructors: HashMap<Name, InferredKind>,
+}
+
+impl KindInference {
+    fn new() -> Self {
+        Self {
+            variables: vec![],
+            constructors: HashMap::new(),
+        }
+    }
+
+    fn new_variable(&mut self) -> InferredKind {
+        self.variables.push(None);
+        InferredKind::Variable(self.variables.len() - 1)
+    }
+
+    ///Returns the kind of a type constructor with the parameters in `typ`, adding a fresh kind
+    ///variable for each parameter to `variables`
+    fn declare(
+        &mut self,
+        variables: &mut HashMap<InternedStr, InferredKind>,
+        typ: &TcType,
+    ) -> InferredKind {
+        let mut kind = InferredKind::Star;
+        let mut head = typ;
+        //The parameters are visited from right to left
+        while let Type::Application(ref lhs, ref rhs) = *head {
+            let argument = self.new_variable();
+            if let Type::Variable(ref var) = **rhs {
+                variables.insert(var.id, argument.clone());
+            }
+            kind = InferredKind::Function(argument.into(), kind.into());
+            head = lhs;
+        }
+        kind
+    }
+
+    ///Follows the bindings of kind variables until an unbound variable or a kind constructor
+    ///is found
+    fn prune(&self, kind: &InferredKind) -> InferredKind {
+        match *kind {
+            InferredKind::Variable(var) => match self.variables[var] {
+                Some(ref bound) => self.prune(bound),
+                None => kind.clone(),
+            },
+            _ => kind.clone(),
+        }
+    }
+
+    fn occurs(&self, var: usize, kind: &InferredKind) -> bool {
+        match self.prune(kind) {
+            InferredKind::Variable(other) => var == other,
+            InferredKind::Function(ref arg, ref result) => {
+                self.occurs(var, arg) || self.occurs(var, result)
+            }
+            InferredKind::Star => false,
+        }
+    }
+
+    fn unify(&mut self, lhs: &InferredKind, rhs: &InferredKind) -> bool {
+        match (self.prune(lhs), self.prune(rhs)) {
+            (InferredKind::Variable(l), InferredKind::Variable(r)) if l == r => true,
+            (InferredKind::Variable(var), kind) | (kind, InferredKind::Variable(var)) => {
+                if self.occurs(var, &kind) {
+                    false
+                } else {
+                    self.variables[var] = Some(kind);
+                    true
+                }
+            }
+            (InferredKind::Star, InferredKind::Star) => true,
+            (InferredKind::Function(l_arg, l_result), InferredKind::Function(r_arg, r_result)) => {
+                self.unify(&l_arg, &r_arg) && self.unify(&l_result, &r_result)
+            }
+            _ => false,
+        }
+    }
+
+    ///Returns the final kind of `kind`, defaulting any unknown parts to `*`
+    fn resolve(&self, kind: &InferredKind) -> Kind {
+        match self.prune(kind) {
+            InferredKind::Variable(_) | InferredKind::Star => Kind::Star,
+            InferredKind::Function(ref arg, ref result) => {
+                Kind::Function(self.resolve(arg).into(), self.resolve(result).into())
+            }
+        }
+    }
+
+    fn constructor_kind(&self, env: &TypeEnvironment, op: &TypeConstructor<Name>) -> InferredKind {
+        self.constructors
+            .get(&op.name)
+            .cloned()
+            .or_else(|| env.find_type_kind(op.name).map(|kind| InferredKind::from_kind(&kind)))
+            .unwrap_or_else(|| InferredKind::from_kind(&op.kind))
+    }
+
+    ///Infers the kind of `typ`, giving any type variables not in `variables` a fresh kind
+    fn infer(
+        &mut self,
+        env: &TypeEnvironment,
+        variables: &mut HashMap<InternedStr, InferredKind>,
+        typ: &TcType,
+    ) -> Result<InferredKind, Error> {
+        match *typ {
+            Type::Variable(ref var) | Type::Generic(ref var) => match variables.get(&var.id) {
+                Some(kind) => Ok(kind.clone()),
+                None => {
+                    let kind = self.new_variable();
+                    variables.insert(var.id, kind.clone());
+                    Ok(kind)
+                }
+            },
+            Type::Constructor(ref op) => Ok(self.constructor_kind(env, op)),
+            Type::Application(ref lhs, ref rhs) => {
+                let function = self.infer(env, variables, lhs)?;
+                let argument = self.infer(env, variables, rhs)?;
+                let result = self.new_variable();
+                let expected =
+                    InferredKind::Function(argument.clone().into(), result.clone().into());
+                if self.unify(&function, &expected) {
+                    Ok(result)
+                } else {
+                    Err(match self.prune(&function) {
+                        InferredKind::Function(ref expected_argument, _) => Error::KindMismatch(
+                            (**rhs).clone(),
+                            self.resolve(expected_argument),
+                            Some(self.resolve(&argument)),
+                        ),
+                        _ => Error::KindMismatch(
+                            (**lhs).clone(),
+                            self.resolve(&expected),
+                            Some(self.resolve(&function)),
+                        ),
+                    })
+                }
+            }
+            //The bound variables shadow any variables with the same name in the enclosing type
+            Type::Forall(ref bound, ref typ) => {
+                for var in bound.iter() {
+                    let kind = self.new_variable();
+                    variables.insert(var.id, kind);
+                }
+                self.check(env, variables, typ, &InferredKind::Star)?;
+                Ok(InferredKind::Star)
+            }
+        }
+    }
+
+    ///Infers the kind of `typ` and checks that it is `kind`
+    fn check(
+        &mut self,
+        env: &TypeEnvironment,
+        variables: &mut HashMap<InternedStr, InferredKind>,
+        typ: &TcType,
+        kind: &InferredKind,
+    ) -> Result<(), Error> {
+        let inferred = self.infer(env, variables, typ)?;
+        if self.unify(&inferred, kind) {
+            Ok(())
+        } else {
+            Err(Error::KindMismatch(
+                typ.clone(),
+                self.resolve(kind),
+                Some(self.resolve(&inferred)),
+            ))
+        }
+    }
+
+    ///Writes the inferred kinds back into `typ`
+    fn update<Id>(
+        &self,
+        variables: &HashMap<InternedStr, InferredKind>,
+        constructor_kind: &dyn Fn(&TypeConstructor<Id>) -> Option<Kind>,
+        typ: &mut Type<Id>,
+    ) {
+        match *typ {
+            Type::Variable(ref mut var) | Type::Generic(ref mut var) => {
+                if let Some(kind) = variables.get(&var.id) {
+                    var.kind = self.resolve(kind);
+                }
+            }
+            Type::Constructor(ref mut op) => {
+                if let Some(kind) = constructor_kind(op) {
+                    op.kind = kind;
+                }
+            }
+            Type::Application(ref mut lhs, ref mut rhs) => {
+                self.update(variables, constructor_kind, lhs);
+                self.update(variables, constructor_kind, rhs);
+            }
+            Type::Forall(ref mut bound, ref mut typ) => {
+                for var in bound.iter_mut() {
+                    if let Some(kind) = variables.get(&var.id) {
+                        var.kind = self.resolve(kind);
+                    }
+                }
+                self.update(variables, constructor_kind, typ);
+            }
+        }
+    }
+
+    ///Writes the inferred kinds back into a type and its constraints
+    fn update_qualified(
+        &self,
+        env: &TypeEnvironment,
+        variables: &HashMap<InternedStr, InferredKind>,
+        typ: &mut Qualified<TcType, Name>,
+    ) {
+        let constructor_kind = |op: &TypeConstructor<Name>| {
+            self.constructors
+                .get(&op.name)
+                .map(|kind| self.resolve(kind))
+                .or_else(|| env.find_type_kind(op.name))
+        };
+        self.update(variables, &constructor_kind, &mut typ.value);
+        for constraint in typ.constraints.iter_mut() {
+            for var in constraint.variables.iter_mut() {
+                if let Some(kind) = variables.get(&var.id) {
+                    var.kind = self.resolve(kind);
+                }
+            }
+        }
+    }
+}
+
+///Returns true if each type has the same type constructor as the type at the same position in
+///the other slice
+pub fn same_instance_heads(lhs: &[TcType], rhs: &[TcType]) -> bool {
+    lhs.len() == rhs.len()
+        && lhs
+            .iter()
+            .zip(rhs.iter())
+            .all(|(l, r)| extract_applied_type(l) == extract_applied_type(r))
+}
+
+fn has_type_variables(typ: &TcType) -> bool {
+    match *typ {
+        Type::Variable(_) | Type::Generic(_) => true,
+        Type::Application(ref lhs, ref rhs) => has_type_variables(lhs) || has_type_variables(rhs),
+        Type::Forall(_, ref typ) => has_type_variables(typ),
+        Type::Constructor(_) => false,
+    }
+}
+
+///Returns the type of the `n`th argument of a function type
+fn argument_type(typ: &mut TcType, n: usize) -> &mut TcType {
+    match *typ {
+        Type::Application(ref mut lhs, ref mut result) => {
+            if n == 0 {
+                match **lhs {
+                    Type::Application(_, ref mut arg) => arg,
+                    _ => panic!("Expected a function type"),
+                }
+            } else {
+                argument_type(result, n - 1)
+            }
+        }
+        _ => panic!("Expected a function type"),
+    }
+}
+
+fn is_variable(typ: &TcType) -> bool {
+    matches!(*typ, Type::Variable(_))
+}
+
+///Replaces each of the type synonym's parameters in `typ` with the corresponding argument
+fn substitute_parameters(parameters: &[TypeVariable], arguments: &[TcType], typ: &TcType) -> TcType {
+    match *typ {
+        Type::Variable(ref var) => parameters
+            .iter()
+            .position(|parameter| parameter.id == var.id)
+            .map(|i| arguments[i].clone())
+            .unwrap_or_else(|| typ.clone()),
+        Type::Application(ref lhs, ref rhs) => Type::Application(
+            substitute_parameters(parameters, arguments, lhs).into(),
+            substitute_parameters(parameters, arguments, rhs).into(),
+        ),
+        Type::Forall(ref bound, ref body) => {
+            let (parameters, arguments): (Vec<_>, Vec<_>) = parameters
+                .iter()
+                .zip(arguments.iter())
+                .filter(|(parameter, _)| bound.iter().all(|var| var.id != parameter.id))
+                .map(|(parameter, argument)| (parameter.clone(), argument.clone()))
+                .unzip();
+            Type::Forall(
+                bound.clone(),
+                substitute_parameters(&parameters, &arguments, body).into(),
+            )
+        }
+        _ => typ.clone(),
+    }
+}
+
+///Applies `typ` to `arguments`, updating the kind of the applied type to accept them
+fn apply_type_arguments(typ: TcType, arguments: Vec<TcType>) -> TcType {
+    if arguments.is_empty() {
+        return typ;
+    }
+    let mut arity = arguments.len() as isize;
+    let mut result = typ;
+    {
+        let mut head = &mut result;
+        while let Type::Application(ref mut lhs, _) = *head {
+            arity += 1;
+            head = &mut **lhs;
+        }
+        *head.mut_kind() = Kind::new(arity + 1);
+    }
+    arguments
+        .into_iter()
+        .fold(result, |f, arg| Type::Application(f.into(), arg.into()))
+}
+
 ///Takes a function type and calls the 'func' with the argument to the function and its
 ///return type.
 ///Returns true if the function was called.
@@ -1938,38 +4629,70 @@ pub fn paren(expr: TypedExpr) -> TypedExpr {
 pub fn typecheck_string(module: &str) -> Result<Vec<Module<Name>>, ::std::string::String> {
     use crate::parser::parse_string;
     parse_string(module)
-        .map_err(|e| format!("{:?}", e))
         .and_then(typecheck_modules_common)
+        .map(|(modules, _)| modules)
+        .map_err(|error| error.to_string())
 }
 
-///Parses a module, renames and typechecks it, as well as all of its imported modules
-pub fn typecheck_module(module: &str) -> Result<Vec<Module<Name>>, ::std::string::String> {
+///Parses a module, renames and typechecks it, as well as all of its imported modules.
+///The warnings found in the modules are returned along with them
+pub fn typecheck_module(
+    search_path: &SearchPath,
+    module: &str,
+) -> Result<(Vec<Module<Name>>, Vec<Diagnostic>), VMError> {
     use crate::parser::parse_modules;
-    parse_modules(module)
-        .map_err(|e| format!("{:?}", e))
-        .and_then(typecheck_modules_common)
+    parse_modules(search_path, module).and_then(typecheck_modules_common)
 }
 
+///Renames and typechecks `modules`, the errors and warnings are returned with the source of the
+///module they were found in
 fn typecheck_modules_common(
-    modules: Vec<Module>,
-) -> Result<Vec<Module<Name>>, ::std::string::String> {
-    use crate::infix::PrecedenceVisitor;
-    let mut modules = rename_modules(modules).map_err(|e| format!("{}", e))?;
-    let mut prec_visitor = PrecedenceVisitor::new();
-    for module in modules.iter_mut() {
+    modules: Vec<(Module, SourceFile)>,
+) -> Result<(Vec<Module<Name>>, Vec<Diagnostic>), VMError> {
+    use crate::{
+        exhaustive::check_module,
+        infix::PrecedenceVisitor,
+    };
+    let (modules, sources): (Vec<_>, Vec<_>) = modules.into_iter().unzip();
+    let with_source = |index: usize, error: VMError| {
+        let source: &SourceFile = &sources[index];
+        error.with_source(&source.path, &source.contents)
+    };
+    let mut modules =
+        rename_modules(modules).map_err(|(index, error)| with_source(index, error.into()))?;
+    let mut prec_visitor = PrecedenceVisitor::new();
+    for (index, module) in modules.iter_mut().enumerate() {
         prec_visitor.visit_module(module);
+        prec_visitor
+            .take_errors(())
+            .map_err(|error| with_source(index, error.into()))?;
     }
-    let result = {
-        let mut env = TypeEnvironment::new();
-        for module in modules.iter_mut() {
-            env.typecheck_module2(module);
-            env.assemblies.push(module);
+    let mut warnings = vec![];
+    let mut env = TypeEnvironment::new();
+    for (index, module) in modules.iter_mut().enumerate() {
+        env.typecheck_module2(module);
+        let source = &sources[index];
+        warnings.extend(
+            env.warnings
+                .drain(..)
+                .map(|warning| warning.with_source(&source.path, &source.contents)),
+        );
+        env.errors
+            .into_result(())
+            .map_err(|errors| with_source(index, TypeError(errors).into()))?;
+        env.assemblies.push(module);
+    }
+    for (module, source) in modules.iter().zip(sources.iter()) {
+        for warning in check_module(module, &modules) {
+            let diagnostic = Diagnostic::new(
+                Severity::Warning,
+                Span::from(warning.location),
+                warning.node.to_string(),
+            );
+            warnings.push(diagnostic.with_source(&source.path, &source.contents));
         }
-        env.errors.into_result(())
-    };
-    result
-        .map(|()| modules)
-        .map_err(|e| format!("{}", TypeError(e)))
+    }
+    Ok((modules, warnings))
 }
 
 #[cfg(test)]
@@ -2190,7 +4913,7 @@ in b"
 instance Test Int where
     test x = 10
 
-main = test 1";
+main = test (1 :: Int)";
 
         let module = do_typecheck(file);
 
@@ -2583,6 +5306,19 @@ test x = Test 2 == Test 1 || J x == N",
         .unwrap();
     }
 
+    #[test]
+    fn qualified_import() {
+        let modules = typecheck_string(
+            r"
+import qualified Prelude as P
+test = P.map (P.+ 1) [1 :: P.Int]
+",
+        )
+        .unwrap();
+        let module = modules.last().unwrap();
+        assert_eq!(module.bindings[0].typ.value, list_type(int_type()));
+    }
+
     #[test]
     fn instance_constraints_propagate() {
         let modules = typecheck_string(
@@ -2606,6 +5342,210 @@ test x y = [x] == [y]
         assert_eq!(un_name(module.bindings[0].typ.clone()), typ);
     }
 
+    #[test]
+    fn default_literal_type() {
+        let modules = typecheck_string(
+            r"
+import Prelude
+default (Double)
+test = 1 == 2
+",
+        )
+        .unwrap();
+        let module = modules.last().unwrap();
+        match module.bindings[0].matches {
+            Match::Simple(TypedExpr {
+                expr: OpApply(ref lhs, _, _),
+                ..
+            }) => assert_eq!(lhs.typ, double_type()),
+            ref matches => panic!("Unexpected match {:?}", matches),
+        }
+    }
+
+    #[test]
+    fn monomorphism_restriction() {
+        let modules = typecheck_string(
+            r"
+import Prelude
+x = 3
+plus = (+)
+",
+        )
+        .unwrap();
+        let module = modules.last().unwrap();
+        assert_eq!(
+            un_name(module.bindings[0].typ.clone()),
+            qualified(vec![], int_type())
+        );
+        assert_eq!(
+            un_name(module.bindings[1].typ.clone()),
+            qualified(
+                vec![],
+                function_type_(int_type(), function_type_(int_type(), int_type()))
+            )
+        );
+    }
+
+    #[test]
+    fn no_monomorphism_restriction() {
+        let modules = typecheck_string(
+            r"
+{-# LANGUAGE NoMonomorphismRestriction #-}
+import Prelude
+plus = (+)
+test = plus (1 :: Int) 2 == 3 && plus 1.5 2.5 == 4.0
+",
+        )
+        .unwrap();
+        let module = modules.last().unwrap();
+        let plus = module
+            .bindings
+            .iter()
+            .find(|bind| bind.name.as_ref() == "plus")
+            .unwrap();
+        assert_eq!(plus.typ.constraints.len(), 1);
+        assert_eq!(plus.typ.constraints[0].class.as_ref(), "Num");
+    }
+
+    #[test]
+    fn monomorphism_restriction_warning() {
+        let prelude = {
+            let path = &Path::new("Prelude.hs");
+            let mut contents = ::std::string::String::new();
+            File::open(path)
+                .and_then(|mut f| f.read_to_string(&mut contents))
+                .unwrap();
+            do_typecheck(contents.as_ref())
+        };
+        let mut parser = Parser::new("test = (+)\ntest2 :: Num a => a -> a\ntest2 = negate".chars());
+        let mut module = rename_module(parser.module().unwrap());
+        let mut env = TypeEnvironment::new();
+        env.add_types(&prelude as &dyn DataTypes);
+        env.typecheck_module_(&mut module);
+        assert_eq!(env.warnings().len(), 1);
+        assert!(env.warnings()[0].message.contains("test"));
+    }
+
+    #[test]
+    fn monomorphism_restriction_no_warning_for_local_bindings() {
+        let prelude = {
+            let path = &Path::new("Prelude.hs");
+            let mut contents = ::std::string::String::new();
+            File::open(path)
+                .and_then(|mut f| f.read_to_string(&mut contents))
+                .unwrap();
+            do_typecheck(contents.as_ref())
+        };
+        let mut parser = Parser::new(
+            "test z = let x = 2 in x + y + z\n    where\n        y = 1".chars(),
+        );
+        let mut module = rename_module(parser.module().unwrap());
+        let mut env = TypeEnvironment::new();
+        env.add_types(&prelude as &dyn DataTypes);
+        env.typecheck_module_(&mut module);
+        assert_eq!(env.warnings().len(), 0);
+    }
+
+    #[test]
+    fn typed_hole() {
+        let error = typecheck_string(
+            r"
+import Prelude
+test :: Int -> [Int] -> Int
+test n xs = let y = n * 2 in _ y (sum xs)
+",
+        )
+        .unwrap_err();
+        assert!(error.contains("Found hole _ with type Int -> Int -> Int"), "{}", error);
+        assert!(error.contains("relevant bindings include n :: Int, xs :: [Int]"), "{}", error);
+        assert!(error.contains(" +,"), "{}", error);
+        assert!(!error.contains("++"), "{}", error);
+    }
+
+    #[test]
+    fn named_typed_hole() {
+        let error = typecheck_string(
+            r"
+import Prelude
+test x = _what (x :: Int)
+",
+        )
+        .unwrap_err();
+        assert!(error.contains("Found hole _what with type Int -> a"), "{}", error);
+    }
+
+    #[test]
+    fn underscore_binding_is_not_a_hole() {
+        typecheck_string(
+            r"
+import Prelude
+_unused = 1 :: Int
+test = _unused + 2
+",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn partial_type_signature() {
+        let modules = typecheck_string(
+            r"
+import Prelude
+test :: _ -> _ -> Int
+test c xs = if c then length xs else 0
+",
+        )
+        .unwrap();
+        let module = modules.last().unwrap();
+        let a = Type::new_var(intern("a"));
+        assert_eq!(
+            un_name(module.bindings[0].typ.clone()),
+            qualified(
+                vec![],
+                function_type_(bool_type(), function_type_(list_type(a), int_type()))
+            )
+        );
+    }
+
+    #[test]
+    fn partial_type_signature_warning() {
+        let prelude = {
+            let path = &Path::new("Prelude.hs");
+            let mut contents = ::std::string::String::new();
+            File::open(path)
+                .and_then(|mut f| f.read_to_string(&mut contents))
+                .unwrap();
+            do_typecheck(contents.as_ref())
+        };
+        let mut parser = Parser::new("test :: _ -> Int\ntest x = x + 1".chars());
+        let mut module = rename_module(parser.module().unwrap());
+        let mut env = TypeEnvironment::new();
+        env.add_types(&prelude as &dyn DataTypes);
+        env.typecheck_module_(&mut module);
+        assert_eq!(env.warnings().len(), 1);
+        assert!(env.warnings()[0].message.contains("Int"));
+    }
+
+    #[test]
+    fn partial_type_signature_warning_location() {
+        let prelude = {
+            let path = &Path::new("Prelude.hs");
+            let mut contents = ::std::string::String::new();
+            File::open(path)
+                .and_then(|mut f| f.read_to_string(&mut contents))
+                .unwrap();
+            do_typecheck(contents.as_ref())
+        };
+        let mut parser = Parser::new("test :: _ -> Int\n\ntest x = x + 1".chars());
+        let mut module = rename_module(parser.module().unwrap());
+        let mut env = TypeEnvironment::new();
+        env.add_types(&prelude as &dyn DataTypes);
+        env.typecheck_module_(&mut module);
+        assert_eq!(env.warnings().len(), 1);
+        //The warning points to the signature which contains the wildcard, not the binding
+        assert_eq!(env.warnings()[0].span.start.row, 0);
+    }
+
     #[test]
     fn newtype() {
         let modules =
@@ -2660,6 +5600,215 @@ test x = do
         do_typecheck_with(file, &[&prelude as &dyn DataTypes]);
     }
 
+    #[test]
+    fn type_synonym() {
+        let module = do_typecheck(
+            r"
+type Pair a = (a, a)
+type IntPair = Pair Int
+
+test :: IntPair -> Pair [Int]
+test (x, y) = ([x], [y])
+",
+        );
+        let pair = |t: Type<InternedStr>| Type::new_op(intern("(,)"), vec![t.clone(), t]);
+        assert_eq!(
+            un_name_type(module.bindings[0].typ.value.clone()),
+            function_type_(pair(int_type()), pair(list_type(int_type())))
+        );
+    }
+
+    #[test]
+    fn record() {
+        let module = do_typecheck(
+            r"
+data Point a = Point { x :: a, y :: a } | Origin
+data Flag = On | Off
+
+test p = case p { x = On } of
+    Point { y = b } -> [b, x p]
+    Origin -> []
+",
+        );
+        let test = module
+            .bindings
+            .iter()
+            .find(|b| b.name.name == intern("test"))
+            .unwrap();
+        let flag = Type::new_op(intern("Flag"), vec![]);
+        let point = Type::new_op(intern("Point"), vec![flag.clone()]);
+        assert_eq!(
+            un_name_type(test.typ.value.clone()),
+            function_type_(point, list_type(flag))
+        );
+    }
+
+    #[test]
+    fn record_unknown_field() {
+        let error = typecheck_string(
+            r"
+import Prelude
+data Point = Point { x :: Int, y :: Int }
+data P = P { px :: Int }
+
+test = Point { z = 1 }
+test2 = P { py = 1 }
+test3 p = p { z = 1 }
+",
+        )
+        .unwrap_err();
+        assert!(error.contains("The constructor Point does not have the field z"), "{}", error);
+        assert!(error.contains("The constructor P does not have the field py"), "{}", error);
+        assert!(error.contains("No data type has the field z"), "{}", error);
+    }
+
+    #[test]
+    fn record_update_fields_of_different_constructors() {
+        let error = typecheck_string(
+            r"
+import Prelude
+data Shape = Circle { radius :: Int } | Rect { width :: Int }
+
+test s = s { radius = 1, width = 2 }
+",
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("No constructor of Shape has all of the fields radius, width"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn type_synonym_partially_applied() {
+        do_typecheck(
+            r"
+type Pair a = (a, a)
+type Apply f a = f a
+
+test :: Apply Pair Int
+test = (1, 2)
+",
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn type_synonym_recursive() {
+        do_typecheck(
+            r"
+type List a = (a, List a)
+
+test :: List Int -> Int
+test (x, _) = x
+",
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn type_synonym_kind_mismatch() {
+        do_typecheck(
+            r"
+type Apply f a = f a
+
+test :: Apply Int Int
+test = 1
+",
+        );
+    }
+
+    #[test]
+    fn kind_inference_data() {
+        let module = do_typecheck(
+            r"
+data Fix f = In (f (Fix f))
+newtype Compose f g a = Compose (f (g a))
+
+out :: Fix f -> f (Fix f)
+out (In x) = x
+",
+        );
+        let unary = Kind::Function(Kind::Star.into(), Kind::Star.into());
+        let fix = extract_applied_type(&module.data_definitions[0].typ.value);
+        assert_eq!(
+            *fix.kind(),
+            Kind::Function(unary.clone().into(), Kind::Star.into())
+        );
+        let compose_type = get_returntype(&module.newtypes[0].constructor_type.value);
+        let compose = extract_applied_type(&compose_type);
+        assert_eq!(
+            *compose.kind(),
+            Kind::Function(
+                unary.clone().into(),
+                Kind::Function(unary.into(), Kind::new(2).into()).into()
+            )
+        );
+    }
+
+    #[test]
+    fn kind_inference_class() {
+        let module = do_typecheck(
+            r"
+data Fix f = In (f (Fix f))
+
+class Unwrap t where
+    unwrap :: t f -> f (t f)
+
+instance Unwrap Fix where
+    unwrap (In x) = x
+",
+        );
+        let class = &module.classes[0];
+        let unary = Kind::Function(Kind::Star.into(), Kind::Star.into());
+        assert_eq!(
+            class.variables[0].kind,
+            Kind::Function(unary.into(), Kind::Star.into())
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn kind_mismatch_data() {
+        do_typecheck(
+            r"
+data Maybe a = Just a | Nothing
+data T = T Maybe
+",
+        );
+    }
+
+    #[test]
+    fn kind_mismatch_location() {
+        let error = typecheck_string(
+            r"
+data Box a = Box a
+type Pair a = (a, a)
+data T = T (Pair Box)
+",
+        )
+        .unwrap_err();
+        assert!(error.contains("--> <input>:4:1"), "{}", error);
+    }
+
+    #[test]
+    #[should_panic]
+    fn kind_mismatch_instance() {
+        do_typecheck(
+            r"
+data Fix f = In (f (Fix f))
+
+class Unwrap t where
+    unwrap :: t f -> f (t f)
+
+instance Unwrap Int where
+    unwrap x = x
+",
+        );
+    }
+
     #[test]
     #[should_panic]
     fn wrong_type() {
@@ -2727,6 +5876,64 @@ test = case 1 :: Int of
         .unwrap();
     }
 
+    #[test]
+    fn deriving_enum_with_fields() {
+        let error = typecheck_string(
+            r"
+import Prelude
+data Test = A Int | B
+    deriving(Enum)
+",
+        )
+        .unwrap_err();
+        assert!(error.contains("Cannot derive Enum for Test"), "{}", error);
+        assert!(error.contains("--> <input>:3:1"), "{}", error);
+    }
+
+    #[test]
+    fn deriving_functor_contravariant_field() {
+        let error = typecheck_string(
+            r"
+import Prelude
+data F b a = F (a -> Int) b
+    deriving(Functor)
+",
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("a can't be mapped over in the field a -> Int"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn deriving_instance_missing_context() {
+        typecheck_string(
+            r"
+import Prelude
+data Box a = Box a
+
+deriving instance Eq (Box a)
+",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn deriving_newtype_missing_instance() {
+        typecheck_string(
+            r"
+import Prelude
+newtype Name = Name (Int -> Int)
+    deriving(Eq)
+",
+        )
+        .unwrap();
+    }
+
     #[test]
     #[should_panic]
     fn newtype_wrong_arg() {
@@ -2742,6 +5949,307 @@ test = IntPair (True, False)
         .unwrap();
     }
 
+    #[test]
+    #[should_panic]
+    fn missing_multi_parameter_instance() {
+        typecheck_string(
+            r"
+import Prelude
+
+class Convert a b where
+    convert :: a -> b
+
+instance Convert Int Double where
+    convert x = primIntToDouble x
+
+test = convert True :: Double
+",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn multi_parameter_constraint_location() {
+        let error = typecheck_string(
+            r"
+import Prelude
+
+class Convert a b where
+    convert :: a -> b
+
+instance Convert Int Double where
+    convert x = primIntToDouble x
+
+test = convert 'a' :: Bool
+",
+        )
+        .unwrap_err();
+        assert!(error.contains("No instance for (Convert Char Bool)"), "{}", error);
+        assert!(error.contains("--> <input>:10:8"), "{}", error);
+    }
+
+    #[test]
+    fn ambiguous_multi_parameter_constraint_location() {
+        let error = typecheck_string(
+            r"
+import Prelude
+
+class Convert a b where
+    convert :: a -> b
+
+test :: Int -> Int
+test x = const x (convert x)
+",
+        )
+        .unwrap_err();
+        assert!(error.contains("Convert Int"), "{}", error);
+        assert!(error.contains("--> <input>:8:19"), "{}", error);
+    }
+
+    #[test]
+    fn instance_with_wrong_number_of_types() {
+        let error = typecheck_string(
+            r"
+import Prelude
+
+class Convert a b where
+    convert :: a -> b
+
+instance Convert Int where
+    convert x = x
+",
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("The class Convert expects 2 types but the instance has 1"),
+            "{}",
+            error
+        );
+        assert!(error.contains("--> <input>:7:1"), "{}", error);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ambiguous_type_variable() {
+        typecheck_string(
+            r#"
+import Prelude
+test = show (read "1")
+"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_default_declaration() {
+        typecheck_string(
+            r"
+import Prelude
+default ()
+test = show (1 + 2)
+",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn default_type_without_num_instance() {
+        typecheck_string(
+            r"
+import Prelude
+default (Bool)
+",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn type_error_expected_and_actual() {
+        let error = typecheck_string(
+            r"
+import Prelude
+test = not 'a'
+",
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("Couldn't match expected type Bool with actual type Char"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn type_error_variable_names() {
+        let error = typecheck_string(
+            r"
+import Prelude
+test = length 'a'
+",
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("Couldn't match expected type [a] with actual type Char"),
+            "{}",
+            error
+        );
+        assert!(error.contains("expected: [a] -> Int"), "{}", error);
+    }
+
+    #[test]
+    fn missing_instance_origin() {
+        let error = typecheck_string(
+            r"
+import Prelude
+test :: Int
+test = 1 + 2.5
+",
+        )
+        .unwrap_err();
+        assert!(error.contains("No instance for (Fractional Int)"), "{}", error);
+        assert!(error.contains("arising from the literal 2.5 at 4:12"), "{}", error);
+    }
+
+    #[test]
+    fn missing_instance_too_few_arguments() {
+        let error = typecheck_string(
+            r"
+import Prelude
+f :: Int -> Int -> Int
+f x y = x + y
+test = f 1 + 2
+",
+        )
+        .unwrap_err();
+        assert!(error.contains("No instance for (Num (Int -> Int))"), "{}", error);
+        assert!(error.contains("applied to too few arguments"), "{}", error);
+        //The types which were unified are not shown as they only contain type variables
+        assert!(!error.contains("expected:"), "{}", error);
+        assert!(!error.contains("a -> a -> a"), "{}", error);
+    }
+
+    #[test]
+    fn too_many_arguments() {
+        let error = typecheck_string(
+            r"
+import Prelude
+f :: Int -> Int
+f x = x
+test = f 1 2
+",
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("Couldn't match expected type Int with actual type a -> b"),
+            "{}",
+            error
+        );
+        assert!(error.contains("applied to too many arguments"), "{}", error);
+    }
+
+    #[test]
+    fn missing_arguments() {
+        let error = typecheck_string(
+            r"
+import Prelude
+test = length (map id)
+",
+        )
+        .unwrap_err();
+        assert!(error.contains("perhaps a function is missing 1 argument(s)"), "{}", error);
+    }
+
+    #[test]
+    fn missing_type_argument() {
+        let error = typecheck_string(
+            r"
+import Prelude
+test :: Maybe -> Int
+test _ = 1
+",
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("Expected the type Maybe to have kind * but it has kind (* -> *)"),
+            "{}",
+            error
+        );
+        assert!(error.contains("perhaps Maybe is missing 1 type argument(s)"), "{}", error);
+    }
+
+    #[test]
+    fn list_comprehension_generator_not_list() {
+        let error = typecheck_string(
+            r"
+import Prelude
+test = [x | x <- True]
+",
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("Couldn't match expected type [a] with actual type Bool"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rank_n_argument() {
+        let modules = typecheck_string(
+            r"
+import Prelude
+applyToBoth :: (forall a. a -> a) -> (Int, Char) -> (Int, Char)
+applyToBoth f (x, y) = (f x, f y)
+test = applyToBoth (\x -> x) (1, 'c')
+",
+        )
+        .unwrap();
+        let module = modules.last().unwrap();
+        let test = module
+            .bindings
+            .iter()
+            .find(|bind| bind.name.as_ref() == "test")
+            .unwrap();
+        assert_eq!(
+            test.typ.value,
+            Type::new_op(intern("(,)"), vec![int_type(), char_type()])
+        );
+    }
+
+    #[test]
+    fn rank_n_argument_not_polymorphic() {
+        let error = typecheck_string(
+            r"
+import Prelude
+applyToBoth :: (forall a. a -> a) -> (Int, Char) -> (Int, Char)
+applyToBoth f (x, y) = (f x, f y)
+test = applyToBoth not (1, 'c')
+",
+        )
+        .unwrap_err();
+        assert!(
+            error.contains("Couldn't match expected type a with actual type Bool"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rank_n_escape() {
+        let error = typecheck_string(
+            r"
+import Prelude
+applyToBoth :: (forall a. a -> a) -> (Int, Char) -> (Int, Char)
+applyToBoth f (x, y) = (f x, f y)
+test y = applyToBoth (\x -> y) (1, 'c')
+",
+        )
+        .unwrap_err();
+        assert!(error.contains("the argument is not polymorphic enough"), "{}", error);
+    }
+
     #[bench]
     fn bench_prelude(b: &mut Bencher) {
         let path = &Path::new("Prelude.hs");
@@ -39,6 +39,9 @@ pub enum Type<Ident = InternedStr> {
     Constructor(TypeConstructor<Ident>),
     Application(Box<Self>, Box<Self>),
     Generic(TypeVariable),
+    ///An explicitly quantified type such as `forall a. a -> a`, which may appear as the
+    ///argument of a function to give it a higher rank type
+    Forall(Vec<TypeVariable>, Box<Self>),
 }
 
 #[derive(Clone, Debug, Default, Hash)]
@@ -169,6 +172,7 @@ impl<Id: fmt::Display + AsRef<str>> Type<Id> {
                 }
             }
             &Self::Generic(ref v) => &v.kind,
+            Self::Forall(_, typ) => typ.kind(),
         }
     }
     ///Returns a mutable reference to the types kind
@@ -181,6 +185,7 @@ impl<Id: fmt::Display + AsRef<str>> Type<Id> {
                 _ => panic!("Type application must have a kind of Kind::Function"),
             },
             Self::Generic(ref mut v) => &mut v.kind,
+            Self::Forall(_, ref mut typ) => typ.mut_kind(),
         }
     }
 }
@@ -207,6 +212,7 @@ impl<Id> Type<Id> {
                 Type::Application(lhs.map_(f).into(), rhs.map_(f).into())
             }
             Self::Generic(v) => Type::Generic(v),
+            Self::Forall(variables, typ) => Type::Forall(variables, typ.map_(f).into()),
         }
     }
 }
@@ -398,6 +404,20 @@ impl<'a, Id: fmt::Display + AsRef<str>> fmt::Display for Prec<'a, Id> {
             Type::Variable(ref var) => write!(f, "{}", *var),
             Type::Constructor(ref op) => write!(f, "{}", *op),
             Type::Generic(ref var) => write!(f, "\\#{}", *var),
+            Type::Forall(ref variables, ref typ) => {
+                if p >= Prec_::Function {
+                    write!(f, "(")?;
+                }
+                write!(f, "forall")?;
+                for var in variables.iter() {
+                    write!(f, " {}", *var)?;
+                }
+                write!(f, ". {}", typ)?;
+                if p >= Prec_::Function {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
             Type::Application(ref lhs, ref rhs) => match try_get_function(t) {
                 Some((arg, result)) => {
                     if p >= Prec_::Function {
@@ -447,6 +467,139 @@ impl<I: fmt::Display> fmt::Display for Constraint<I> {
         Ok(())
     }
 }
+
+///Prints types the way they would be written in source code, for use in error messages.
+///Type variables are named `a`, `b`, `c` ... in the order they are first seen so that the
+///same variable gets the same name in every type printed by one printer.
+#[derive(Default)]
+pub struct TypePrinter {
+    names: HashMap<InternedStr, String>,
+}
+
+impl TypePrinter {
+    pub fn new() -> TypePrinter {
+        TypePrinter::default()
+    }
+
+    ///Creates a printer which prints `variables` with the names they were declared with
+    pub fn with_declared_names(variables: &[TypeVariable]) -> TypePrinter {
+        TypePrinter {
+            names: variables
+                .iter()
+                .map(|var| (var.id, var.id.as_ref().to_string()))
+                .collect(),
+        }
+    }
+
+    pub fn variable(&mut self, var: &TypeVariable) -> String {
+        let count = self.names.len();
+        self.names
+            .entry(var.id)
+            .or_insert_with(|| {
+                let letter = (b'a' + (count % 26) as u8) as char;
+                if count < 26 {
+                    letter.to_string()
+                } else {
+                    format!("{}{}", letter, count / 26)
+                }
+            })
+            .clone()
+    }
+
+    pub fn print<Id: AsRef<str>>(&mut self, typ: &Type<Id>) -> String {
+        self.print_prec(Prec_::Top, typ)
+    }
+
+    ///Prints the type as the argument of a type application, with parentheses if needed
+    pub fn print_argument<Id: AsRef<str>>(&mut self, typ: &Type<Id>) -> String {
+        self.print_prec(Prec_::Constructor, typ)
+    }
+
+    pub fn print_constraint<Id: AsRef<str>>(&mut self, constraint: &Constraint<Id>) -> String {
+        let mut result = constraint.class.as_ref().to_string();
+        for var in constraint.variables.iter() {
+            result.push(' ');
+            result.push_str(&self.variable(var));
+        }
+        result
+    }
+
+    pub fn print_qualified<Id: AsRef<str>>(&mut self, typ: &Qualified<Type<Id>, Id>) -> String {
+        let constraints: Vec<_> = typ
+            .constraints
+            .iter()
+            .map(|constraint| self.print_constraint(constraint))
+            .collect();
+        let value = self.print(&typ.value);
+        match constraints.len() {
+            0 => value,
+            1 => format!("{} => {}", constraints[0], value),
+            _ => format!("({}) => {}", constraints.join(", "), value),
+        }
+    }
+
+    fn print_prec<Id: AsRef<str>>(&mut self, prec: Prec_, typ: &Type<Id>) -> String {
+        match *typ {
+            Type::Variable(ref var) | Type::Generic(ref var) => self.variable(var),
+            Type::Constructor(ref op) => op.name.as_ref().to_string(),
+            Type::Forall(ref variables, ref typ) => {
+                let mut result = "forall".to_string();
+                for var in variables.iter() {
+                    result.push(' ');
+                    result.push_str(&self.variable(var));
+                }
+                result.push_str(". ");
+                result.push_str(&self.print_prec(Prec_::Top, typ));
+                if prec >= Prec_::Function {
+                    format!("({})", result)
+                } else {
+                    result
+                }
+            }
+            Type::Application(..) => {
+                if let Some((arg, result)) = try_get_function(typ) {
+                    let arg = self.print_prec(Prec_::Function, arg);
+                    let result = self.print_prec(Prec_::Top, result);
+                    return if prec >= Prec_::Function {
+                        format!("({} -> {})", arg, result)
+                    } else {
+                        format!("{} -> {}", arg, result)
+                    };
+                }
+                let mut arguments = vec![];
+                let mut head = typ;
+                while let Type::Application(ref lhs, ref rhs) = *head {
+                    arguments.push(&**rhs);
+                    head = lhs;
+                }
+                arguments.reverse();
+                if let Type::Constructor(ref op) = *head {
+                    if op.name.as_ref() == "[]" && arguments.len() == 1 {
+                        return format!("[{}]", self.print_prec(Prec_::Top, arguments[0]));
+                    }
+                    if arguments.len() >= 2 && op.name.as_ref() == tuple_name(arguments.len()) {
+                        let elements: Vec<_> = arguments
+                            .iter()
+                            .map(|arg| self.print_prec(Prec_::Top, arg))
+                            .collect();
+                        return format!("({})", elements.join(", "));
+                    }
+                }
+                let mut result = self.print_prec(Prec_::Constructor, head);
+                for arg in arguments {
+                    result.push(' ');
+                    result.push_str(&self.print_prec(Prec_::Constructor, arg));
+                }
+                if prec >= Prec_::Constructor {
+                    format!("({})", result)
+                } else {
+                    result
+                }
+            }
+        }
+    }
+}
+
 fn type_eq<'a, Id, Id2>(
     mapping: &mut HashMap<&'a TypeVariable, &'a TypeVariable>,
     lhs: &'a Type<Id>,
@@ -461,6 +614,11 @@ where
         (&Type::Application(ref lhs1, ref rhs1), &Type::Application(ref lhs2, ref rhs2)) => {
             type_eq(mapping, lhs1, lhs2) && type_eq(mapping, rhs1, rhs2)
         }
+        (Type::Forall(vars1, typ1), Type::Forall(vars2, typ2)) => {
+            vars1.len() == vars2.len()
+                && vars1.iter().zip(vars2.iter()).all(|(l, r)| var_eq(mapping, l, r))
+                && type_eq(mapping, typ1, typ2)
+        }
         _ => false,
     }
 }
@@ -0,0 +1,57 @@
+///Removes the commentary from a literate Haskell source, leaving only the code.
+///Both bird tracks (`> code`) and `\begin{code}` ... `\end{code}` blocks are supported.
+///Every removed character is replaced by a space so that the row and column of the code
+///are the same as in the literate source
+pub fn unlit(contents: &str) -> String {
+    let mut result = String::with_capacity(contents.len());
+    let mut in_code_block = false;
+    for line in contents.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        if in_code_block {
+            if text.starts_with("\\end{code}") {
+                in_code_block = false;
+                blank(&mut result, text);
+            } else {
+                result.push_str(text);
+            }
+        } else if text.starts_with("\\begin{code}") {
+            in_code_block = true;
+            blank(&mut result, text);
+        } else if let Some(code) = text.strip_prefix('>') {
+            result.push(' ');
+            result.push_str(code);
+        } else {
+            blank(&mut result, text);
+        }
+        result.push_str(&line[text.len()..]);
+    }
+    result
+}
+
+///Pushes a space for each character in `text`
+fn blank(result: &mut String, text: &str) {
+    result.extend(text.chars().map(|_| ' '));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bird_tracks() {
+        let source = "Some commentary\n\n> main = 1\n>   + 2\r\n\nMore commentary";
+        assert_eq!(
+            unlit(source),
+            "               \n\n  main = 1\n    + 2\r\n\n               "
+        );
+    }
+
+    #[test]
+    fn code_blocks() {
+        let source = "Text\n\\begin{code}\nmain = 1\n\\end{code}\n> test = 2\n";
+        assert_eq!(
+            unlit(source),
+            "    \n            \nmain = 1\n          \n  test = 2\n"
+        );
+    }
+}
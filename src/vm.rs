@@ -2,10 +2,19 @@ use {
     crate::{
         compiler::*,
         core::translate::translate_module,
+        diagnostics::{
+            Diagnostic,
+            Span,
+            ToDiagnostics,
+        },
         interner::*,
         lambda_lift::do_lambda_lift,
         parser::Parser,
         renamer::rename_module,
+        search_path::{
+            read_file,
+            SearchPath,
+        },
         typecheck::TypeEnvironment,
         vm::primitive::{
             get_builtin,
@@ -20,11 +29,7 @@ use {
         },
         error::Error,
         fmt,
-        fs::File,
-        io::{
-            self,
-            Read,
-        },
+        io,
         num::Wrapping,
         path::Path,
         rc::Rc,
@@ -249,6 +254,8 @@ impl<'a> VM {
                 DoubleLE => primitive_float(stack, |l, r| constr(l <= r)),
                 DoubleGT => primitive_float(stack, |l, r| constr(l > r)),
                 DoubleGE => primitive_float(stack, |l, r| constr(l >= r)),
+                CharEQ => primitive_char(stack, |l, r| constr(l == r)),
+                CharLE => primitive_char(stack, |l, r| constr(l <= r)),
                 IntToDouble => {
                     let top = stack.pop().unwrap();
                     stack.push(match *top.borrow() {
@@ -485,7 +492,16 @@ impl<'a> VM {
                                             DictionaryEntry::App(index, arg.clone())
                                         ));
                                     }
-                                    _ => panic!(),
+                                    //An instance with several constraints, such as
+                                    //`(Show a, Show b) => Show (a, b)`, takes the dictionaries
+                                    //of all its arguments in order
+                                    DictionaryEntry::App(index, ref applied) => {
+                                        let mut dict = applied.clone();
+                                        dict.entries.extend(arg.entries.iter().cloned());
+                                        new_dict
+                                            .entries
+                                            .push(Rc::new(DictionaryEntry::App(index, dict)));
+                                    }
                                 }
                             }
                         }
@@ -495,8 +511,8 @@ impl<'a> VM {
                 }
                 ConstructDictionary(size) => {
                     let mut new_dict = InstanceDictionary { entries: vec![] };
-                    for _ in 0..size {
-                        let temp = stack.pop().unwrap();
+                    let start = stack.len() - size;
+                    for temp in stack.drain(start..) {
                         let temp = temp.borrow();
                         match *temp {
                             Dictionary(ref d) => {
@@ -561,6 +577,23 @@ where
         ),
     }
 }
+///Exucutes a binary primitive instruction taking two characters
+fn primitive_char<'a, F>(stack: &mut Vec<Node<'a>>, f: F)
+where
+    F: FnOnce(char, char) -> Node_<'a>,
+{
+    let l = stack.pop().unwrap();
+    let r = stack.pop().unwrap();
+    let l = l.borrow();
+    let r = r.borrow();
+    match (&*l, &*r) {
+        (&Char(lhs), &Char(rhs)) => stack.push(Node::new(f(lhs, rhs))),
+        (lhs, rhs) => panic!(
+            "Expected fully evaluted characters in primitive instruction\n LHS: {:?}\nRHS: {:?} ",
+            lhs, rhs
+        ),
+    }
+}
 fn primitive<F>(stack: &mut Vec<Node>, f: F)
 where
     F: FnOnce(isize, isize) -> isize,
@@ -578,6 +611,7 @@ pub enum VMResult {
 
 // TODO: throw this garbage into the macro below
 use crate::{
+    infix::PrecedenceError,
     parser::ParseError,
     renamer::RenamerError,
     typecheck::TypeError,
@@ -589,14 +623,35 @@ macro_rules! vm_error {
     #[derive(Debug)]
     pub enum VMError {
         Io(io::Error),
-        $($post(crate::$pre::$post)),+
+        $($post(crate::$pre::$post)),+,
+        ///The diagnostics of one of the other errors with the source they were found in attached
+        Diagnostics(Vec<Diagnostic>),
     }
 
     impl fmt::Display for VMError {
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
             match *self {
                 Self::Io(ref e) => write!(f, "{}", e),
-                $(Self::$post(ref e) => write!(f, "{}", e)),+
+                $(Self::$post(ref e) => write!(f, "{}", e)),+,
+                Self::Diagnostics(ref diagnostics) => {
+                    for (i, diagnostic) in diagnostics.iter().enumerate() {
+                        if i != 0 {
+                            writeln!(f)?;
+                        }
+                        write!(f, "{}", diagnostic)?;
+                    }
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    impl ToDiagnostics for VMError {
+        fn diagnostics(&self) -> Vec<Diagnostic> {
+            match *self {
+                Self::Io(ref e) => vec![Diagnostic::error(Span::unknown(), e.to_string())],
+                $(Self::$post(ref e) => e.diagnostics()),+,
+                Self::Diagnostics(ref diagnostics) => diagnostics.clone(),
             }
         }
     }
@@ -621,28 +676,44 @@ macro_rules! vm_error {
     })+
     }
 }
-vm_error! { parser::ParseError, renamer::RenamerError, typecheck::TypeError }
+vm_error! {
+    parser::ParseError,
+    renamer::RenamerError,
+    infix::PrecedenceError,
+    typecheck::TypeError
+}
+
+impl VMError {
+    ///Turns the error into diagnostics which display the offending lines of `source`
+    pub fn with_source(self, file: &str, source: &str) -> Self {
+        Self::Diagnostics(
+            self.diagnostics()
+                .into_iter()
+                .map(|diagnostic| diagnostic.with_source(file, source))
+                .collect(),
+        )
+    }
+}
 
 fn compile_iter<T: Iterator<Item = char>>(iterator: T) -> Result<Assembly, VMError> {
     let mut parser = Parser::new(iterator);
-    let module = parser.module().unwrap();
-    let mut module = rename_module(module).unwrap();
+    let module = parser.module()?;
+    let mut module = rename_module(module)?;
 
     let mut typer = TypeEnvironment::new();
-    typer.typecheck_module(&mut module).unwrap();
+    typer.typecheck_module(&mut module)?;
     let core_module = do_lambda_lift(translate_module(module));
 
     let mut compiler = Compiler::new();
     Ok(compiler.compile_module(&core_module))
 }
 
-///Compiles a single file
-pub fn compile_file(filename: &str) -> Result<Assembly, VMError> {
-    let path = &Path::new(filename);
-    let mut file = File::open(path)?;
-    let mut contents = ::std::string::String::new();
-    file.read_to_string(&mut contents)?;
+///Compiles a single file, a relative filename is looked up in `search_path`
+pub fn compile_file(search_path: &SearchPath, filename: &str) -> Result<Assembly, VMError> {
+    let path = search_path.find_file(Path::new(filename))?;
+    let contents = read_file(&path)?;
     compile_iter(contents.chars())
+        .map_err(|error| error.with_source(&path.to_string_lossy(), &contents))
 }
 
 fn extract_result(node: Node_) -> Option<VMResult> {
@@ -673,17 +744,21 @@ fn extract_result(node: Node_) -> Option<VMResult> {
 
 pub fn execute_main_string(module: &str) -> Result<Option<VMResult>, String> {
     let assemblies = compile_string(module)?;
-    execute_main_module_(assemblies)
+    Ok(execute_main_module_(assemblies))
 }
 
 ///Takes a module with a main function and compiles it and all its imported modules
-///and then executes the main function
-pub fn execute_main_module(modulename: &str) -> Result<Option<VMResult>, String> {
-    let assemblies = compile_module(modulename)?;
-    execute_main_module_(assemblies)
+///and then executes the main function. The warnings found while compiling are returned along
+///with the result
+pub fn execute_main_module(
+    search_path: &SearchPath,
+    modulename: &str,
+) -> Result<(Option<VMResult>, Vec<Diagnostic>), VMError> {
+    let (assemblies, warnings) = compile_module(search_path, modulename)?;
+    Ok((execute_main_module_(assemblies), warnings))
 }
 
-fn execute_main_module_(assemblies: Vec<Assembly>) -> Result<Option<VMResult>, String> {
+fn execute_main_module_(assemblies: Vec<Assembly>) -> Option<VMResult> {
     let mut vm = VM::new();
     for assembly in assemblies.into_iter() {
         vm.add_assembly(assembly);
@@ -697,9 +772,9 @@ fn execute_main_module_(assemblies: Vec<Assembly>) -> Result<Option<VMResult>, S
         Some(sc) => {
             assert!(sc.arity == 0);
             let result = vm.evaluate(&*sc.instructions, sc.assembly_id);
-            Ok(extract_result(result))
+            extract_result(result)
         }
-        None => Ok(None),
+        None => None,
     }
 }
 
@@ -875,7 +950,9 @@ mod tests {
 
     use crate::{
         compiler::compile_with_type_env,
+        diagnostics::ToDiagnostics,
         interner::*,
+        search_path::SearchPath,
         typecheck::TypeEnvironment,
         vm::{
             compile_file,
@@ -905,6 +982,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn compile_error_diagnostics() {
+        let source = "test = primIntAdd 1 2\nmain = missing test\n";
+        let error = compile_iter(source.chars())
+            .err()
+            .expect("Expected an undefined identifier")
+            .with_source("Test.hs", source);
+        assert_eq!(
+            error.to_string(),
+            r#"error: Undefined identifier "missing"
+ --> Test.hs:2:8
+  |
+2 | main = missing test
+  |        ^"#
+        );
+
+        let source = "main = primIntAdd (1 2\n";
+        let diagnostics = compile_iter(source.chars())
+            .err()
+            .expect("Expected a parse error")
+            .diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            (diagnostics[0].span.start.row, diagnostics[0].span.start.column),
+            (0, 22)
+        );
+    }
+
     #[test]
     fn test_primitive() {
         assert_eq!(
@@ -920,7 +1025,7 @@ mod tests {
             Some(VMResult::Int(5))
         );
         assert_eq!(
-            execute_main("main = primDoubleDivide 3. 2.".chars()),
+            execute_main("main = primDoubleDivide 3.0 2.0".chars()),
             Some(VMResult::Double(1.5))
         );
         let s = r"data Bool = True | False
@@ -1042,7 +1147,7 @@ main = testAdd True";
 
     #[test]
     fn test_run_prelude() {
-        let prelude = compile_file("Prelude.hs").unwrap();
+        let prelude = compile_file(&SearchPath::new(), "Prelude.hs").unwrap();
         let assembly = {
             let mut type_env = TypeEnvironment::new();
 
@@ -1077,7 +1182,7 @@ main = foldl add 0 [1,2,3,4]",
 
     #[test]
     fn instance_super_class() {
-        let prelude = compile_file("Prelude.hs").unwrap();
+        let prelude = compile_file(&SearchPath::new(), "Prelude.hs").unwrap();
 
         let assembly = {
             let mut type_env = TypeEnvironment::new();
@@ -1111,7 +1216,7 @@ main = foldl add 0 [1,2,3,4]",
 
     #[test]
     fn monad_do() {
-        let prelude = compile_file("Prelude.hs").unwrap();
+        let prelude = compile_file(&SearchPath::new(), "Prelude.hs").unwrap();
 
         let assembly = {
             let mut type_env = TypeEnvironment::new();
@@ -1155,8 +1260,40 @@ main = test (Just 4) (Just 6)",
 
     #[test]
     fn import() {
-        let result = execute_main_module("Test");
-        assert_eq!(result, Ok(Some(VMResult::Int(6))));
+        let (result, _) = execute_main_module(&SearchPath::new(), "Test").unwrap();
+        assert_eq!(result, Some(VMResult::Int(6)));
+    }
+
+    #[test]
+    fn module_error_shows_source() {
+        let root = std::env::temp_dir().join("vm_module_error_shows_source");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("Main.hs"), "import Prelude\nmain :: Int\nmain = True\n").unwrap();
+        let mut search_path = SearchPath::new();
+        search_path.add_root(&root);
+        let error = execute_main_module(&search_path, "Main").unwrap_err().to_string();
+        assert!(error.contains("Main.hs:3:8"), "{}", error);
+        assert!(error.contains("3 | main = True"), "{}", error);
+    }
+
+    #[test]
+    fn module_warning_shows_source() {
+        let root = std::env::temp_dir().join("vm_module_warning_shows_source");
+        std::fs::create_dir_all(&root).unwrap();
+        let source = "import Prelude\nf :: Bool -> Int\nf True = 1\nmain = f True\n";
+        std::fs::write(root.join("Main.hs"), source).unwrap();
+        let mut search_path = SearchPath::new();
+        search_path.add_root(&root);
+        let (result, warnings) = execute_main_module(&search_path, "Main").unwrap();
+        assert_eq!(result, Some(VMResult::Int(1)));
+        let warnings: Vec<_> = warnings
+            .iter()
+            .map(|warning| warning.to_string())
+            .filter(|warning| warning.contains("Main.hs"))
+            .collect();
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].contains("Main.hs:3:10"), "{}", warnings[0]);
+        assert!(warnings[0].contains("3 | f True = 1"), "{}", warnings[0]);
     }
 
     #[test]
@@ -1315,6 +1452,173 @@ main = compare (A 0) (A 2) == LT && compare B (A 123) == GT
         assert_eq!(result, Some(VMResult::Constructor(0, vec![])));
     }
 
+    #[test]
+    fn deriving_show() {
+        let result = execute_main_string(
+            r#"
+import Prelude
+data Shape a = Circle a | Rect { width :: a, height :: Int } | Empty
+    deriving(Show)
+
+infixl 6 :+
+data Complex = Int :+ Int deriving Show
+
+main = show [Circle (Just (-3 :: Int)), Rect { width = Just 2, height = 3 }, Empty]
+        == "[Circle (Just (-3)),Rect {width = Just 2, height = 3},Empty]"
+    && show (Just (1 :+ 2), 3 :+ 4) == "(Just (1 :+ 2),3 :+ 4)"
+"#,
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Constructor(0, vec![])));
+    }
+
+    #[test]
+    fn deriving_read() {
+        let result = execute_main_string(
+            r#"
+import Prelude
+data Shape a = Circle a | Rect { width :: a, height :: Int } | Empty
+    deriving(Eq, Read)
+
+main = case read " ( Circle  4 ) " :: Shape Int of
+    Circle x -> x + height (read "Rect {width = 5, height = 6}" :: Shape Int)
+        + length (read "[Empty,Empty]" :: [Shape Int])
+"#,
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(12)));
+    }
+
+    #[test]
+    fn deriving_enum_bounded() {
+        let result = execute_main_string(
+            r"
+import Prelude
+data Color = Red | Green | Blue
+    deriving(Eq, Enum, Bounded)
+
+main = sum (map fromEnum [minBound .. maxBound :: Color])
+    + fromEnum (succ Red) * 10
+    + length (enumFromThen Blue Green) * 100
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(313)));
+    }
+
+    #[test]
+    fn deriving_functor() {
+        let result = execute_main_string(
+            r"
+import Prelude
+data Tree a = Leaf | Node (Tree a) a ([Tree a])
+    deriving(Functor)
+
+total :: Tree Int -> Int
+total Leaf = 0
+total (Node l x rest) = total l + x + sum (map total rest)
+
+main = total (fmap (* 2) (Node (Node Leaf 1 []) 2 [Node Leaf 3 []]))
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(12)));
+    }
+
+    #[test]
+    fn deriving_newtype() {
+        let result = execute_main_string(
+            r#"
+import Prelude
+newtype Age = Age Int
+    deriving(Eq, Ord, Show, Bounded)
+newtype Wrap a = Wrap [a]
+    deriving(Eq, Functor)
+
+unwrap :: Wrap a -> [a]
+unwrap (Wrap xs) = xs
+
+main = show (Age 3) == "Age 3"
+    && (Age 1 < Age 2)
+    && fmap (+ 1) (Wrap [1 :: Int]) == Wrap [2]
+    && sum (unwrap (Wrap [1, 2 :: Int])) == 3
+"#,
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Constructor(0, vec![])));
+    }
+
+    #[test]
+    fn deriving_standalone() {
+        let result = execute_main_string(
+            r"
+import Prelude
+data Tree a = Leaf | Node (Tree a) a (Tree a)
+
+deriving instance Eq a => Eq (Tree a)
+deriving instance Functor Tree
+
+main = Node Leaf 1 Leaf == fmap (\x -> x - 1) (Node Leaf (2 :: Int) Leaf)
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Constructor(0, vec![])));
+    }
+
+    #[test]
+    fn default_numeric_literals() {
+        let result = execute_main_string(
+            r#"
+import Prelude
+main = show (2 + 3) == "5" && (5 / 2) == 2.5 && show (length [1, 2, 3]) == "3"
+"#,
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Constructor(0, vec![])));
+    }
+
+    #[test]
+    fn default_declaration() {
+        let result = execute_main_string(
+            r#"
+import Prelude
+default (Double, Integer)
+main = (7 / 2) == 3.5 && show (toInteger 7) == "7"
+"#,
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Constructor(0, vec![])));
+    }
+
+    #[test]
+    fn monomorphism_restriction() {
+        let result = execute_main_string(
+            r"
+import Prelude
+x = 3
+plus = (+)
+main = let y = 4 in plus x y * 2
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(14)));
+    }
+
+    #[test]
+    fn unused_restricted_local_binding() {
+        let result = execute_main_string(
+            r"
+import Prelude
+main = 2 + x
+    where
+        x = 1
+        g = 3
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(3)));
+    }
+
     #[test]
     fn instance_eq_list() {
         let result =
@@ -1384,6 +1688,51 @@ main = makeEven (100 * 3)
         );
     }
 
+    #[test]
+    fn record() {
+        let result = execute_main_string(
+            r#"
+import Prelude
+data Config = Config { port :: Int, host :: [Char] }
+
+defaultPort (Config { port = p }) = p
+
+summary :: Config -> Int
+summary c = defaultPort c + port c + length (host c)
+
+main = summary ((Config { host = "localhost", port = 8080 }) { port = 80 })
+"#,
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(169)));
+    }
+
+    #[test]
+    fn type_synonym() {
+        let result = execute_main_string(
+            r"
+import Prelude
+type Pair a = (a, a)
+type Apply f a = f a
+
+swap :: Pair a -> Pair a
+swap (x, y) = (y, x)
+
+greeting :: Apply Maybe String
+greeting = Just (show True)
+
+count :: Maybe String -> Int
+count (Just s) = length s
+count Nothing = 0
+
+main = case swap (1, 2 :: Int) of
+    (x, _) -> x + count greeting
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(6)));
+    }
+
     #[test]
     fn where_bindings() {
         let result = execute_main_string(
@@ -1404,4 +1753,339 @@ main = case list of
         .unwrap();
         assert_eq!(result, Some(VMResult::Int(11)));
     }
+
+    #[test]
+    fn sections() {
+        let result = execute_main_string(
+            r"
+import Prelude
+
+pair :: Int -> (Int, Int)
+pair = (, 10)
+
+main = case pair 3 of
+    (x, y) -> sum (map (subtract 1 . (* 2)) [x, y]) + (`div` 2) 9 + (100 -) 1 + (- 3)
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(124)));
+    }
+
+    #[test]
+    fn partially_applied_constructors() {
+        let result = execute_main_string(
+            r"
+import Prelude
+
+data P = P Int Int
+
+first (P x _) = x
+
+main = fst (pair 2) + snd (swap 4) + first (p 5) + length (map Just [1, 2])
+  where
+    pair = (,) 1
+    swap = (, 3)
+    p = P 6
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(12)));
+    }
+
+    #[test]
+    fn list_comprehension() {
+        let result = execute_main_string(
+            r"
+import Prelude
+
+main = sum [x * y | x <- [1 :: Int, 2, 3], let y = x + 1, x /= 2]
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(14)));
+    }
+
+    #[test]
+    fn list_comprehension_pattern() {
+        let result = execute_main_string(
+            r"
+import Prelude
+
+main = [x | Just x <- [Just 1, Nothing, Just (3 :: Int)]]
+",
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            Some(VMResult::Constructor(
+                1,
+                vec![
+                    VMResult::Int(1),
+                    VMResult::Constructor(
+                        1,
+                        vec![VMResult::Int(3), VMResult::Constructor(0, vec![])]
+                    )
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn arithmetic_sequence() {
+        let result = execute_main_string(
+            r"
+import Prelude
+
+main = sum [1..10 :: Int] + sum [1, 3..9] + sum [10, 8..5] + head (tail [5..])
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(55 + 25 + 24 + 6)));
+    }
+
+    #[test]
+    fn multi_parameter_class() {
+        let result = execute_main_string(
+            r"
+import Prelude
+
+class Convert a b where
+    convert :: a -> b
+
+instance Convert Int Double where
+    convert x = primIntToDouble x
+
+instance Convert Bool Int where
+    convert True = 1
+    convert False = 0
+
+convertAll :: Convert a b => [a] -> [b]
+convertAll xs = map convert xs
+
+main = sum (convertAll [True, False, True] :: [Int]) + primDoubleToInt (convert (2 :: Int) :: Double)
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(4)));
+    }
+
+    #[test]
+    fn polymorphic_signature() {
+        let result = execute_main_string(
+            r"
+import Prelude
+
+size :: [a] -> Int
+size xs = length xs
+
+main = size [True, False] + size [1 :: Int, 2, 3]
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(5)));
+    }
+
+    #[test]
+    fn as_pattern() {
+        let result = execute_main_string(
+            r"
+import Prelude
+
+dup :: [Int] -> [Int]
+dup all@(x:_) = x : all
+dup [] = []
+
+firstTwo :: [Int] -> Int
+firstTwo (x:rest@(y:_)) = x + y + length rest
+firstTwo _ = 0
+
+main = length (dup [1, 2, 3]) + firstTwo [10, 20, 30]
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(4 + 32)));
+    }
+
+    #[test]
+    fn irrefutable_pattern() {
+        let result = execute_main_string(
+            r"
+import Prelude
+
+lazyPair :: (Int, Int) -> Int
+lazyPair ~(a, b) = 1
+
+swap :: (Int, Int) -> (Int, Int)
+swap p = case p of
+    ~(a, b) -> (b, a)
+
+main = lazyPair undefined + fst (swap (2, 3))
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(4)));
+    }
+
+    #[test]
+    fn bang_pattern() {
+        let result = execute_main_string(
+            r"
+import Prelude
+
+sumTo :: Int -> Int -> Int
+sumTo !acc 0 = acc
+sumTo !acc n = sumTo (acc + n) (n - 1)
+
+main = sumTo 0 10
+",
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(55)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn bang_pattern_forces_argument() {
+        execute_main_string(
+            r"
+import Prelude
+
+const1 :: Int -> Int
+const1 !x = 1
+
+main = const1 undefined
+",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn literal_patterns() {
+        let result = execute_main_string(
+            r#"
+import Prelude
+
+vowel :: Char -> Int
+vowel 'a' = 1
+vowel 'e' = 1
+vowel _ = 0
+
+greet :: [Char] -> Int
+greet "hello" = 10
+greet "hi" = 20
+greet _ = 30
+
+half :: Double -> Int
+half 0.5 = 100
+half _ = 200
+
+main = vowel 'e' + vowel 'z' + greet "hi" + greet "hey" + half 0.5 + half 1.5
+"#,
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(1 + 20 + 30 + 100 + 200)));
+    }
+
+    #[test]
+    fn match_mixed_equations() {
+        let result = execute_main_string(
+            r#"
+import Prelude
+
+data T = A | B Int | C Int Int
+
+f :: T -> Int -> Int
+f A 0 = 1
+f (B 1) n = n
+f _ 5 = 50
+f (B x) n | x > n = 100
+f (C x y) _ = x + y
+f t n = 7
+
+main = [f A 0, f (B 1) 3, f A 5, f (B 9) 2, f (C 2 3) 1, f (B 2) 3, f A 3] == [1, 3, 50, 100, 5, 7, 7]
+"#,
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Constructor(0, vec![])));
+    }
+
+    #[test]
+    fn match_nested_patterns() {
+        let result = execute_main_string(
+            r#"
+import Prelude
+
+zipLength :: [Int] -> [Int] -> Int
+zipLength [] _ = 0
+zipLength _ [] = 0
+zipLength (x:xs) (y:ys) = 1 + zipLength xs ys
+
+type Nested = Maybe (Maybe Int)
+
+nested :: Nested -> Int
+nested (Just (Just n)) | n > 10 = n
+nested (Just Nothing) = 1
+nested Nothing = 2
+nested x = 3
+
+main = zipLength [1, 2, 3] [4, 5] == 2
+    && [nested (Just (Just 20)), nested (Just (Just 5)), nested (Just Nothing), nested Nothing] == [20, 3, 1, 2]
+"#,
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Constructor(0, vec![])));
+    }
+
+    #[test]
+    fn pattern_guard_qualifiers() {
+        let result = execute_main_string(
+            r#"
+import Prelude
+
+lookup :: Int -> [(Int, Int)] -> Maybe Int
+lookup k [] = Nothing
+lookup k ((key, value) : rest)
+    | k == key = Just value
+    | otherwise = lookup k rest
+
+classify :: Int -> [(Int, Int)] -> Int
+classify k m
+    | Just y <- lookup k m, y > 10 = y
+    | Just y <- lookup k m, let z = y * 2, z > 4 = z
+    | k > 100 = 1
+classify k m = 0
+
+firstPositive :: [Int] -> Maybe Int
+firstPositive xs = case xs of
+    (x:rest) | x > 0 -> Just x
+             | (y:_) <- rest, y > 0 -> Just y
+    _ -> Nothing
+
+main = [classify 1 [(1, 20)], classify 1 [(1, 3)], classify 1 [(1, 1)], classify 200 [], classify 5 []] == [20, 6, 0, 1, 0]
+    && [firstPositive [1, 2], firstPositive [0, 2], firstPositive [0, 0], firstPositive []] == [Just 1, Just 2, Nothing, Nothing]
+"#,
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Constructor(0, vec![])));
+    }
+
+    #[test]
+    fn rank_n_types() {
+        let result = execute_main_string(
+            r#"
+import Prelude
+
+applyToBoth :: (forall a. a -> a) -> (Int, Char) -> (Int, Char)
+applyToBoth f (x, y) = (f x, f y)
+
+lengths :: (forall a. [a] -> Int) -> ([Int], [Char]) -> Int
+lengths f (xs, ys) = f xs + f ys
+
+main = case applyToBoth id (1, 'c') of
+    (x, _) -> x + lengths length ([1, 2], "abc")
+"#,
+        )
+        .unwrap();
+        assert_eq!(result, Some(VMResult::Int(6)));
+    }
 }